@@ -1,4 +1,6 @@
-use criterion::{black_box, criterion_group, criterion_main, Criterion, BenchmarkId};
+use better_cp::cli::OverwriteMode;
+use better_cp::copy::FileCopier;
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
 use std::fs::{self, File};
 use std::io::Write;
 use std::path::PathBuf;
@@ -106,9 +108,9 @@ fn bench_directory_structure(c: &mut Criterion) {
 
     // Add files at various levels
     for i in 0..5 {
-        create_test_file(&PathBuf::from(src_dir.join(format!("file_{}.bin", i))), 1024 * 1024);
-        create_test_file(&PathBuf::from(src_dir.join(format!("dir1/file_{}.bin", i))), 1024 * 1024);
-        create_test_file(&PathBuf::from(src_dir.join(format!("dir1/dir2/file_{}.bin", i))), 1024 * 1024);
+        create_test_file(&src_dir.join(format!("file_{}.bin", i)), 1024 * 1024);
+        create_test_file(&src_dir.join(format!("dir1/file_{}.bin", i)), 1024 * 1024);
+        create_test_file(&src_dir.join(format!("dir1/dir2/file_{}.bin", i)), 1024 * 1024);
     }
 
     c.bench_function("copy_nested_directory_structure", |b| {
@@ -139,13 +141,42 @@ fn bench_directory_structure(c: &mut Criterion) {
     });
 }
 
+fn bench_pipelined_vs_serial_loop(c: &mut Criterion) {
+    let temp_dir = TempDir::new().unwrap();
+    let src_path = temp_dir.path().join("source_pipeline.bin");
+    let dst_path = temp_dir.path().join("dest_pipeline.bin");
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+
+    create_test_file(&src_path, 256 * 1024 * 1024); // 256 MB, four chunks
+
+    let mut group = c.benchmark_group("copy_loop_256mb");
+    group.sample_size(10);
+    for (name, pipeline) in [("serial", false), ("pipelined", true)] {
+        group.bench_function(name, |b| {
+            b.iter(|| {
+                runtime.block_on(async {
+                    FileCopier::new(src_path.clone(), dst_path.clone(), OverwriteMode::Always, false, false, false)
+                        .with_progress(false)
+                        .with_pipeline(pipeline)
+                        .copy()
+                        .await
+                        .unwrap();
+                });
+                let _ = fs::remove_file(&dst_path);
+            });
+        });
+    }
+    group.finish();
+}
+
 criterion_group!(
     benches,
     bench_small_file_copy,
     bench_medium_file_copy,
     bench_large_file_copy,
     bench_many_small_files,
-    bench_directory_structure
+    bench_directory_structure,
+    bench_pipelined_vs_serial_loop
 );
 
 criterion_main!(benches);
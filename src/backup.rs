@@ -0,0 +1,61 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use crate::cli::BackupMode;
+use crate::error::{Error, Result};
+
+/// Where the existing `target` would be renamed to under `mode`: `file~`
+/// for simple backups, the first free `file.~N~` for numbered ones
+pub fn backup_path(target: &Path, mode: BackupMode) -> Option<PathBuf> {
+    let with_suffix = |suffix: &str| {
+        let mut name = target.as_os_str().to_os_string();
+        name.push(suffix);
+        PathBuf::from(name)
+    };
+
+    match mode {
+        BackupMode::None => None,
+        BackupMode::Simple => Some(with_suffix("~")),
+        BackupMode::Numbered => (1..)
+            .map(|n| with_suffix(&format!(".~{}~", n)))
+            .find(|candidate| fs::symlink_metadata(candidate).is_err()),
+    }
+}
+
+/// Rename an existing `target` out of the way before it is overwritten,
+/// returning where it went. Does nothing for `BackupMode::None`.
+pub fn backup_existing(target: &Path, mode: BackupMode) -> Result<Option<PathBuf>> {
+    let Some(backup) = backup_path(target, mode) else {
+        return Ok(None);
+    };
+    fs::rename(target, &backup).map_err(|e| {
+        Error::Custom(format!(
+            "Failed to back up {} to {}: {}",
+            target.display(),
+            backup.display(),
+            e
+        ))
+    })?;
+    Ok(Some(backup))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_numbered_backups_take_the_next_free_number() {
+        let temp_dir = TempDir::new().unwrap();
+        let target = temp_dir.path().join("file.txt");
+
+        fs::write(&target, b"first").unwrap();
+        let first = backup_existing(&target, BackupMode::Numbered).unwrap().unwrap();
+        fs::write(&target, b"second").unwrap();
+        let second = backup_existing(&target, BackupMode::Numbered).unwrap().unwrap();
+
+        assert_eq!(first, temp_dir.path().join("file.txt.~1~"));
+        assert_eq!(second, temp_dir.path().join("file.txt.~2~"));
+        assert_eq!(fs::read(&second).unwrap(), b"second");
+        assert!(backup_existing(&target, BackupMode::None).unwrap().is_none());
+    }
+}
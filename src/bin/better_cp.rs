@@ -1,180 +1,851 @@
 use clap::Parser;
-use console::style;
-use better_cp::cli::{Cli, Commands, CopyArgs};
-use better_cp::copy::{FileCopier, copy_directory};
-use better_cp::parallel::{ParallelFileCopier, parallel_copy_directory};
-use better_cp::error::Result;
-use std::time::Instant;
+use better_cp::color::{self, style};
+use better_cp::cli::{
+    BackupMode, CheckpointArgs, CleanArgs, Cli, Commands, CopyArgs, OverwriteMode, ReflinkMode, ResumeArgs, SortOrder, TimeStyle, VerifyArgs,
+};
+use better_cp::copy::{
+    DirectoryCopier, FileCopier, copy_from_stdin, copy_symlink, copy_to_stdout, is_stdio,
+    prepare_destination_dir, source_dir_link,
+};
+use better_cp::parallel::{ParallelFileCopier, Parallelism, TreeCopy, parallel_copy_directory};
+use better_cp::error::{Error, Result};
+use better_cp::filter::WalkFilter;
+use better_cp::plan::{file_action, link_action, plan_tree, strip_components, PlannedAction};
+use better_cp::preflight::{check_destination_writable, confirm_impact, Impact};
+use better_cp::json_output::{FileResult, FileResults, FileStatus, OperationResult, PlannedFile};
+use better_cp::manifest::{ChecksumManifest, ExpectedChecksums};
+use better_cp::mirror::extraneous_entries;
+use better_cp::report::{BandwidthReport, RunStats};
+use better_cp::output::{human_output, render_tree, suppress_human_output, Logger, OutputManager};
+use better_cp::interrupt::{self, InterruptFlag};
+use better_cp::options::CopyOptions;
+use better_cp::prompt::{self, set_time_style};
+use better_cp::resume::{find_stale_files, Checkpoint, ResumeIndex, ResumeState};
+use better_cp::verify::{compare_trees, compare_trees_cached, ChecksumCache, DiscrepancyKind};
+use better_cp::transaction::RollbackLog;
+use better_cp::progress::{
+    bandwidth_samples, progress_enabled, record_bandwidth_samples, set_progress_target, set_progress_template,
+    set_refresh_interval, ProgressEvent, ProgressMode, TransferStats,
+};
+use std::io::{self, IsTerminal};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
 
 #[tokio::main]
 async fn main() -> Result<()> {
     let cli = Cli::parse();
+    color::init(cli.color);
+    if let Some(ms) = cli.progress_interval {
+        set_refresh_interval(Duration::from_millis(ms));
+    }
+    if let Some(template) = cli.progress_template() {
+        set_progress_template(&template);
+    }
+    set_progress_target(cli.progress_to);
+    set_time_style(cli.time_style);
+    if cli.command.json() {
+        suppress_human_output();
+    }
 
     match cli.command {
         Commands::Copy(args) => {
-            if let Err(e) = handle_copy(args).await {
-                eprintln!("❌ {}", e.detailed_message());
-                std::process::exit(1);
+            if let Err(e) = handle_copy(*args).await {
+                exit_with(e);
+            }
+        }
+        Commands::Move(_) => {
+            exit_with(Error::Usage("use better-mv for move operations".to_string()));
+        }
+        Commands::Clean(args) => {
+            if let Err(e) = handle_clean(&args) {
+                exit_with(e);
             }
         }
-        Commands::Move(_args) => {
-            eprintln!("Move operation not yet implemented");
+        Commands::Resume(args) => {
+            if let Err(e) = handle_resume(&args).await {
+                exit_with(e);
+            }
+        }
+        Commands::Checkpoint(args) => {
+            if let Err(e) = handle_checkpoint(&args) {
+                exit_with(e);
+            }
         }
+        Commands::Verify(args) => match handle_verify(&args) {
+            Ok(true) => {}
+            // Differences found: the same code as a checksum mismatch
+            Ok(false) => std::process::exit(5),
+            Err(e) => {
+                if args.json {
+                    print_json_done(&CopyRun::default(), Instant::now(), Some(&e));
+                }
+                exit_with(e);
+            }
+        },
     }
 
     Ok(())
 }
 
+/// Report `e` on stderr, unless `--json` already carries it, and exit with its
+/// code. A skip is what was asked for, so only its exit code tells of it.
+fn exit_with(e: Error) -> ! {
+    if human_output() && !e.is_skip() {
+        eprintln!("❌ {}", e.detailed_message());
+    }
+    std::process::exit(e.exit_code());
+}
+
+/// What a copy run got through, for its `--json` done event
+#[derive(Default)]
+struct CopyRun {
+    /// Each source copied, with its stats; skipped sources have none
+    stats: Vec<(PathBuf, TransferStats)>,
+    /// Targets of the sources left alone because the target existed
+    skipped: Vec<PathBuf>,
+    files: Vec<FileResult>,
+}
+
+/// Where the files a copy writes are reported, besides its own stats
+#[derive(Clone)]
+struct Recorders {
+    manifest: Option<ChecksumManifest>,
+    run_stats: Option<RunStats>,
+    /// The files of the source being copied
+    files: FileResults,
+    /// `--log`, appended to as each file is done
+    logger: Option<Logger>,
+}
+
+impl Recorders {
+    /// The same run-wide recorders, with a fresh file list for another source
+    fn for_source(&self) -> Self {
+        Self { files: FileResults::new(), ..self.clone() }
+    }
+}
+
 async fn handle_copy(args: CopyArgs) -> Result<()> {
+    let start = Instant::now();
+    let mut run = CopyRun::default();
+    let result = copy_sources(&args, &mut run).await;
+
+    // --json prints one document whether or not the copy worked: the plan
+    // of a dry run that worked, else the done event, which carries any
+    // failure. A copy to stdout has no room for it.
+    if args.json && !is_stdio(&args.destination) && !(args.dry_run && result.is_ok()) {
+        print_json_done(&run, start, result.as_ref().err());
+    }
+    result
+}
+
+async fn copy_sources(args: &CopyArgs, run: &mut CopyRun) -> Result<()> {
     if args.source.is_empty() {
-        eprintln!("Error: no source specified");
-        return Ok(());
+        return Err(Error::Usage("no source specified".to_string()));
     }
+    // A bad or colliding --rename fails before anything is created
+    args.targets()?;
 
     // Dry-run mode: just show preview
     if args.dry_run {
-        dry_run_preview(&args).await?;
+        dry_run_preview(args).await?;
         return Ok(());
     }
 
+    if !is_stdio(&args.destination) {
+        for target in args.targets()? {
+            check_destination_writable(&target)?;
+        }
+    }
+
+    // Ask before a large overwrite, unless told not to or nobody is there to
+    // answer (--attrs-only rewrites no contents)
+    if !args.yes && !args.attrs_only && !args.json && !args.quiet && io::stdin().is_terminal() && !is_stdio(&args.destination) {
+        let filter = walk_filter(args)?;
+        let mut impact = Impact::default();
+        for (source, target) in args.source.iter().zip(args.targets()?) {
+            if !is_stdio(source) {
+                impact += Impact::of_copy(source, &target, &filter, &args.overwrite_mode())?;
+            }
+        }
+        confirm_impact(impact, &args.confirm_threshold(), "overwrite")?;
+    }
+
+    if args.bandwidth_report {
+        record_bandwidth_samples();
+    }
     let start = Instant::now();
+    let output = OutputManager::new(args.quiet, args.verbose);
+    let progress = if args.json && !args.no_progress {
+        ProgressMode::Json
+    } else {
+        progress_enabled(args.no_progress || output.is_quiet()).into()
+    };
+    let interrupt = interrupt::install_handler()?;
+    let CopyRun { stats, skipped, files } = run;
+    let recorders = Recorders {
+        manifest: match &args.write_manifest {
+            Some(_) => Some(ChecksumManifest::new(manifest_root(args)?)),
+            None => None,
+        },
+        run_stats: args.stats.then(RunStats::new),
+        files: FileResults::new(),
+        logger: Logger::from_args(args.log.as_ref(), args.log_format)?,
+    };
 
     if args.source.len() == 1 {
         // Single source copy (file or directory)
         let source = &args.source[0];
         
-        if source.is_dir() {
-            // Directory copy - use parallel if enabled
-            if args.parallel > 0 {
-                parallel_copy_directory(
-                    source,
-                    &args.destination,
-                    args.parallel,
-                ).await?;
-            } else {
-                copy_directory(
-                    source,
-                    &args.destination,
-                    args.overwrite,
-                    !args.no_verify,
-                ).await?;
-            }
+        if is_stdio(&args.destination) {
+            // stdout carries the data, so there is no summary to print
+            copy_to_stdout(source, args.buffer_size()?).await?;
+            return Ok(());
+        }
+
+        let (target, existed, result) = if is_stdio(source) {
+            let existed = target_exists(&args.destination);
+            let result =
+                copy_from_stdin(&args.destination, args.overwrite_mode(), progress, args.buffer_size()?).await;
+            (args.destination.clone(), existed, result)
         } else {
-            // File copy - use parallel if enabled and file is large enough
-            if args.parallel > 0 {
-                let parallel_copier = ParallelFileCopier::new(
-                    source.clone(),
-                    args.destination.clone(),
-                    args.parallel,
-                    !args.no_verify,
-                );
-                parallel_copier.copy().await?;
-            } else {
-                let copier = FileCopier::new(
-                    source.clone(),
-                    args.destination.clone(),
-                    args.overwrite,
-                    !args.no_verify,
-                    !args.no_resume && args.resume,
-                    args.atomic,
-                );
-                copier.copy().await?;
-            }
+            let target = args.targets()?.remove(0);
+            let existed = target_exists(&target);
+            let result = copy_source(source, &target, args, progress, &interrupt, &recorders).await;
+            (target, existed, result)
+        };
+        files.extend(source_results(
+            source,
+            &target,
+            existed,
+            &result,
+            &recorders.files,
+            recorders.logger.as_ref(),
+        ));
+        match result {
+            Ok(s) => stats.push((source.clone(), s)),
+            Err(e) if e.is_skip() => skipped.push(target),
+            Err(e) => return Err(e),
         }
     } else {
         // Multiple sources copy (to directory)
-        if !args.destination.is_dir() {
-            eprintln!("Error: destination must be a directory for multiple sources");
-            return Ok(());
+        if args.source.iter().any(|s| is_stdio(s)) || is_stdio(&args.destination) {
+            return Err(Error::Usage("'-' (stdin/stdout) can only be used with a single source".to_string()));
         }
 
-        if args.parallel > 0 {
-            // Parallel copy of multiple files
+        prepare_destination_dir(&args.destination)?;
+
+        let mut rollback = args.transactional.then(RollbackLog::new);
+        let mut results = Vec::new();
+        if args.parallel > 0 || args.tree_parallel > 0 {
+            // Parallel copy of multiple sources
             let mut handles = Vec::new();
 
-            for source in &args.source {
-                let target = args.destination.join(
-                    source.file_name()
-                        .ok_or_else(|| better_cp::error::Error::Custom("Invalid source path".to_string()))?
-                );
+            for (source, target) in args.source.iter().zip(args.targets()?) {
+                if let Some(log) = rollback.as_mut() {
+                    log.track(&target);
+                }
 
+                let existed = target_exists(&target);
                 let src = source.clone();
-                let parallel_threads = args.parallel;
-                let verify = !args.no_verify;
-
+                let task_target = target.clone();
+                let task_args = args.clone();
+                let task_interrupt = interrupt.clone();
+                let task_recorders = recorders.for_source();
+                let source_files = task_recorders.files.clone();
                 let handle = tokio::spawn(async move {
-                    if src.is_dir() {
-                        parallel_copy_directory(&src, &target, parallel_threads).await
-                    } else {
-                        let copier = ParallelFileCopier::new(src, target, parallel_threads, verify);
-                        copier.copy().await
-                    }
+                    copy_source(&src, &task_target, &task_args, progress, &task_interrupt, &task_recorders).await
                 });
 
-                handles.push(handle);
+                handles.push((source, target, existed, source_files, handle));
             }
 
             // Wait for all to complete
-            for handle in handles {
-                handle.await.map_err(|e| better_cp::error::Error::Custom(e.to_string()))?
-                    .map_err(|e| better_cp::error::Error::Custom(format!("Copy error: {}", e)))?;
+            for (source, target, existed, source_files, handle) in handles {
+                let result = handle.await.map_err(|e| better_cp::error::Error::Custom(e.to_string()))?;
+                results.push((source, target, existed, source_files, result));
             }
         } else {
             // Sequential copy
-            for source in &args.source {
-                let target = args.destination.join(
-                    source.file_name()
-                        .ok_or_else(|| better_cp::error::Error::Custom("Invalid source path".to_string()))?
-                );
-                
-                if source.is_dir() {
-                    // Recursive directory copy
-                    copy_directory(
-                        source,
-                        &target,
-                        args.overwrite.clone(),
-                        !args.no_verify,
-                    ).await?;
-                } else {
-                    // File copy
-                    let copier = FileCopier::new(
-                        source.clone(),
-                        target,
-                        args.overwrite.clone(),
-                        !args.no_verify,
-                        !args.no_resume && args.resume,
-                        args.atomic,
-                    );
-                    copier.copy().await?;
+            for (source, target) in args.source.iter().zip(args.targets()?) {
+                if let Some(log) = rollback.as_mut() {
+                    log.track(&target);
+                }
+
+                let existed = target_exists(&target);
+                let source_recorders = recorders.for_source();
+                let result = copy_source(source, &target, args, progress, &interrupt, &source_recorders).await;
+                let failed = matches!(&result, Err(e) if !e.is_skip());
+                results.push((source, target, existed, source_recorders.files, result));
+                if failed {
+                    break;
+                }
+            }
+        }
+
+        for (source, target, existed, source_files, result) in results {
+            files.extend(source_results(
+                source,
+                &target,
+                existed,
+                &result,
+                &source_files,
+                recorders.logger.as_ref(),
+            ));
+            match result {
+                Ok(s) => stats.push((source.clone(), s)),
+                Err(e) if e.is_skip() => skipped.push(target),
+                Err(e) => {
+                    if let Some(log) = &rollback {
+                        let removed = log.rollback()?;
+                        if human_output() {
+                            eprintln!("↩ Rolled back {} newly created path(s)", removed);
+                        }
+                    }
+                    return Err(e);
                 }
             }
         }
     }
 
-    // Show completion summary
-    let duration = start.elapsed().as_secs_f64();
-    if !args.quiet {
+    if let (Some(manifest), Some(path)) = (&recorders.manifest, &args.write_manifest) {
+        manifest.write(path)?;
+    }
+
+    // Show completion summary; --json has its done event printed by the caller
+    if !args.json {
+        if output.is_verbose() {
+            for (source, file_stats) in stats.iter() {
+                println!(
+                    "  {}: {} in {:.2}s ({}/s)",
+                    source.display(),
+                    file_stats.transferred_human(),
+                    file_stats.elapsed.as_secs_f64(),
+                    file_stats.speed_human()
+                );
+            }
+        }
+
+        let total = total_stats(stats, start.elapsed());
+        let skipped_count = skipped.len() + total.files_skipped;
         let count = args.source.len();
         let count_str = if count == 1 { "file" } else { "files" };
-        println!(
-            "\n{} {} {} in {:.2}s",
+        let mut extra_str = if skipped_count > 0 {
+            format!(", {} skipped", skipped_count)
+        } else {
+            String::new()
+        };
+        if total.files_deleted > 0 {
+            extra_str.push_str(&format!(", {} deleted", total.files_deleted));
+        }
+        if total.files_touched > 0 {
+            extra_str.push_str(&format!(", {} touched", total.files_touched));
+        }
+        if total.files_linked > 0 {
+            extra_str.push_str(&format!(", {} linked", total.files_linked));
+        }
+        if total.files_failed > 0 {
+            extra_str.push_str(&format!(", {} failed", total.files_failed));
+        }
+        output.summary(&format!(
+            "\n{} {} {}, {} in {:.2}s ({:.2} MB/s{})",
             style("✓").green(),
             count,
             count_str,
-            duration
-        );
+            total.transferred_human(),
+            total.elapsed.as_secs_f64(),
+            total.speed_mbps(),
+            extra_str
+        ));
+        if args.tree && output.is_verbose() {
+            output.summary(&format!("\n{}", render_tree(files).trim_end()));
+        }
+    }
+
+    if let Some(run_stats) = &recorders.run_stats {
+        if !args.json {
+            output.summary(&format!("\n{}", run_stats.report(start.elapsed()).to_string().trim_end()));
+        }
+    }
+    if args.bandwidth_report && !args.json {
+        let report = BandwidthReport::from_samples(bandwidth_samples());
+        output.summary(&format!("\n{}", report.to_string().trim_end()));
+    }
+
+    // --ignore-errors carried on past failed files, but the run still failed
+    let failed: usize = stats.iter().map(|(_, s)| s.files_failed).sum();
+    if failed > 0 {
+        return Err(Error::FilesFailed(failed));
+    }
+    // So does one that kept an existing target under --no-clobber or
+    // --on-conflict, with the skip code (an --update skip is a success)
+    let kept_existing = matches!(args.overwrite_mode(), OverwriteMode::NoClobber | OverwriteMode::Policy(_));
+    if let (Some(target), true) = (skipped.first(), kept_existing) {
+        return Err(Error::Skipped(target.to_string_lossy().to_string()));
+    }
+
+    Ok(())
+}
+
+/// Whether `target` is there before the copy, to tell created from overwritten
+fn target_exists(target: &Path) -> bool {
+    std::fs::symlink_metadata(target).is_ok()
+}
+
+/// Describe how one source fared for the `--json` file list: each file a
+/// directory copy recorded, else (or when the copy failed outside any one
+/// file) the source as a whole, which is appended to the `--log` file
+fn source_results(
+    source: &Path,
+    target: &Path,
+    existed: bool,
+    result: &Result<TransferStats>,
+    recorded: &FileResults,
+    logger: Option<&Logger>,
+) -> Vec<FileResult> {
+    let mut files = recorded.to_vec();
+    let file_failed = files.iter().any(|file| file.status == FileStatus::Failed);
+    if files.is_empty() || (matches!(result, Err(e) if !e.is_skip()) && !file_failed) {
+        files.push(file_result(source, target, existed, result, logger));
+    }
+    files
+}
+
+/// Describe how one source fared as a whole, appending it to the `--log`
+/// file as well
+fn file_result(
+    source: &Path,
+    target: &Path,
+    existed: bool,
+    result: &Result<TransferStats>,
+    logger: Option<&Logger>,
+) -> FileResult {
+    let (source, target) = (source.to_path_buf(), target.to_path_buf());
+    let entry = match result {
+        // A lone file whose target already matched
+        Ok(stats) if stats.files_touched > 0 && !source.is_dir() => FileResult::touched(source, target),
+        Ok(stats) => FileResult::copied(source, target, stats.transferred_bytes).overwriting(existed),
+        Err(e) if e.is_skip() => FileResult::skipped(source, target, e.to_string()),
+        Err(e) => FileResult::failed(source, target, e.to_string()),
+    };
+    if let Some(logger) = logger {
+        logger.record("copy", &entry);
+    }
+    entry
+}
+
+/// Totals over every source copied
+fn total_stats(stats: &[(PathBuf, TransferStats)], elapsed: Duration) -> TransferStats {
+    let stats: Vec<TransferStats> = stats.iter().map(|(_, s)| s.clone()).collect();
+    TransferStats::aggregate(&stats, elapsed)
+}
+
+/// Print the final `--json` event: the per-source results and, when the run
+/// failed, its error
+fn print_json_done(run: &CopyRun, start: Instant, error: Option<&Error>) {
+    let mut total = total_stats(&run.stats, start.elapsed());
+    total.files_skipped += run.skipped.len();
+    let mut done = ProgressEvent::done_with_files(&total, run.files.clone());
+    // Skipped targets are counted, not reported as an error
+    if let Some(e) = error.filter(|e| !e.is_skip()) {
+        done = done.with_error(e.detailed_message());
+    }
+    println!("{}", done.to_json_line());
+}
+
+/// List the interrupted copies on record, or continue the one into `target`
+async fn handle_resume(args: &ResumeArgs) -> Result<()> {
+    let Some(target) = &args.target else {
+        let entries = match ResumeIndex::user() {
+            Some(index) => index.entries()?,
+            None => Vec::new(),
+        };
+        if entries.is_empty() {
+            println!("No interrupted copies to resume");
+        }
+        for entry in entries {
+            println!(
+                "{:>5.1}%  {:>10}/{:<10}  {:>11} ago  {} <- {}",
+                entry.percent_complete(),
+                humansize::format_size(entry.bytes_completed, humansize::BINARY),
+                humansize::format_size(entry.total_size, humansize::BINARY),
+                prompt::format_age(entry.age()),
+                entry.target.display(),
+                entry.source.display()
+            );
+        }
+        return Ok(());
+    };
+
+    let state = ResumeState::load(target)?.ok_or_else(|| {
+        Error::Usage(format!(
+            "No resume state for {}; run `better-cp resume --list` to see what can be resumed",
+            target.display()
+        ))
+    })?;
+    // Asking for this transfer by name is the answer to "resume?"
+    let options = CopyOptions::interactive().on_resume(|_, _, _, _| Ok(true));
+    let stats = FileCopier::new(state.source, state.target, OverwriteMode::Always, true, true, false)
+        .with_progress(progress_enabled(args.no_progress))
+        .with_interrupt(interrupt::install_handler()?)
+        .with_options(options)
+        .copy()
+        .await?;
+
+    println!(
+        "\n{} {} in {:.2}s ({:.2} MB/s)",
+        style("✓").green(),
+        stats.transferred_human(),
+        stats.elapsed.as_secs_f64(),
+        stats.speed_mbps()
+    );
+    Ok(())
+}
+
+/// Print what a resume state file holds, and fail if it couldn't be resumed
+fn handle_checkpoint(args: &CheckpointArgs) -> Result<()> {
+    let checkpoint = Checkpoint::inspect(&args.path)?;
+    let state = &checkpoint.state;
+    let saved = std::fs::metadata(&checkpoint.state_file)
+        .map(|metadata| prompt::modified_time(&metadata))
+        .unwrap_or_else(|_| "unknown".to_string());
+
+    println!("State file: {}", checkpoint.state_file.display());
+    println!("Source:     {}", state.source.display());
+    println!("Target:     {}", state.target.display());
+    println!("Total:      {}", humansize::format_size(state.total_size, humansize::BINARY));
+    println!(
+        "Completed:  {} ({:.1}%)",
+        humansize::format_size(state.bytes_completed(), humansize::BINARY),
+        state.percent_complete()
+    );
+    // A relative --time-style already says how long ago it was
+    match checkpoint.age().filter(|_| prompt::time_style() != TimeStyle::Relative) {
+        Some(age) => println!("Saved:      {} ({} ago)", saved, prompt::format_age(age)),
+        None => println!("Saved:      {}", saved),
+    }
+    if checkpoint.is_coherent() {
+        println!("Coherent:   {}", style("yes").green());
+        return Ok(());
+    }
+    println!("Coherent:   {}", style("no").red());
+    for problem in &checkpoint.problems {
+        println!("  {} {}", style("⚠️").yellow(), problem);
+    }
+    Err(Error::InvalidResumeState)
+}
+
+/// Remove leftovers of interrupted copies under a directory
+fn handle_clean(args: &CleanArgs) -> Result<()> {
+    let stale = find_stale_files(&args.directory)?;
+
+    for path in &stale {
+        if args.dry_run {
+            println!("would remove {}", path.display());
+        } else {
+            let removed = if path.is_dir() {
+                std::fs::remove_dir_all(path)
+            } else {
+                std::fs::remove_file(path)
+            };
+            removed.map_err(better_cp::error::Error::Io)?;
+            println!("removed {}", path.display());
+        }
     }
 
+    let verb = if args.dry_run { "would be removed" } else { "removed" };
+    println!("\n{} {} stale file(s) {}", style("✓").green(), stale.len(), verb);
     Ok(())
 }
 
+/// Compare a copy against its source, returning whether they match
+fn handle_verify(args: &VerifyArgs) -> Result<bool> {
+    let report = match &args.checksum_cache {
+        Some(path) => {
+            let mut cache = ChecksumCache::load(path);
+            let report = compare_trees_cached(&args.source, &args.destination, &mut cache)?;
+            cache.save()?;
+            report
+        }
+        None => compare_trees(&args.source, &args.destination)?,
+    };
+
+    if args.json {
+        println!("{}", serde_json::to_string(&report).unwrap_or_default());
+        return Ok(report.is_match());
+    }
+
+    for discrepancy in &report.discrepancies {
+        let label = match discrepancy.kind {
+            DiscrepancyKind::Mismatch => style("mismatch").red(),
+            DiscrepancyKind::Missing => style("missing").red(),
+            DiscrepancyKind::Extra => style("extra").yellow(),
+        };
+        println!("  {:<8} {}", label, discrepancy.path.display());
+    }
+
+    if report.is_match() {
+        println!("\n{} {} file(s) verified, trees match", style("✓").green(), report.files_checked);
+    } else {
+        println!(
+            "\n{} {} discrepancy(ies) in {} checked file(s)",
+            style("✗").red(),
+            report.discrepancies.len(),
+            report.files_checked
+        );
+    }
+    Ok(report.is_match())
+}
+
+/// Build the directory walk filter from the command line
+fn walk_filter(args: &CopyArgs) -> Result<WalkFilter> {
+    WalkFilter::new()
+        .with_one_file_system(args.one_file_system)
+        .with_follow_symlinks(args.follow_symlinks)
+        .with_max_depth(args.max_depth)
+        .with_sort(args.sort)
+        .with_size_range(args.min_size, args.max_size)
+        .with_newer_than(args.newer_than)
+        .with_includes(&args.include)?
+        .with_excludes(&args.exclude_patterns()?)
+}
+
+/// Directory the `--write-manifest` paths are relative to: the copied tree
+/// itself for a single directory, otherwise the directory files land in
+fn manifest_root(args: &CopyArgs) -> Result<PathBuf> {
+    if let [source] = args.source.as_slice() {
+        let target = args.targets()?.remove(0);
+        if source.is_dir() {
+            return Ok(target);
+        }
+        return Ok(target.parent().map(Path::to_path_buf).unwrap_or_default());
+    }
+    Ok(args.destination.clone())
+}
+
+/// Where `source` points, when it is a symlink to a directory that is
+/// copied as a link (`--no-dereference-args`)
+fn preserved_link(source: &Path, args: &CopyArgs) -> Option<PathBuf> {
+    if !args.no_dereference_args {
+        return None;
+    }
+    source_dir_link(source)
+}
+
+/// Copy one source (file or directory) to its resolved target
+async fn copy_source(
+    source: &Path,
+    target: &Path,
+    args: &CopyArgs,
+    progress: ProgressMode,
+    interrupt: &InterruptFlag,
+    recorders: &Recorders,
+) -> Result<TransferStats> {
+    let Recorders { manifest, run_stats, files, logger } = recorders;
+    // The parallel copiers neither resume, back up, update in place, recreate
+    // special files, record or check checksum manifests, transcode, delete
+    // sources or extraneous targets, keep holes in sparse files, pass over
+    // failed files, leave identical targets be, copy attributes alone, weigh
+    // --on-conflict policies, clone blocks (so --reflink=always needs the
+    // sequential copiers), overlap reads with writes (--pipeline), nor copy
+    // xattrs, ownership or alternate data streams. Picked
+    // automatically (no --parallel flags), they are also kept to fresh
+    // targets, where no overwrite decision or staging directory is involved.
+    let preserve = args.preserve_set()?;
+    let timeout = args.timeout.map(Duration::from_secs);
+    let parallelism =
+        Parallelism::resolve(args.parallel, args.tree_parallel, args.file_parallel, source);
+    let fsync = args.fsync()?;
+    let buffer_size = args.buffer_size()?;
+    // --quiet runs unattended, so nothing may stop to ask; without a
+    // terminal to ask on, a prompt is an error rather than a hang
+    let options = if args.quiet {
+        CopyOptions::unattended()
+    } else if args.json || !io::stdin().is_terminal() {
+        CopyOptions::non_interactive()
+    } else {
+        CopyOptions::interactive()
+    };
+    let verify_source = args.verify_source.as_deref().map(ExpectedChecksums::load).transpose()?;
+    let parallel = parallelism.is_parallel()
+        && (args.explicit_parallel() || (!target.exists() && !args.atomic))
+        && !args.resume
+        && args.backup == BackupMode::None
+        && !args.update_inplace
+        && !args.specials
+        && manifest.is_none()
+        && verify_source.is_none()
+        && args.transcode().is_none()
+        && !args.remove_source_files
+        && !args.delete
+        && args.strip_components == 0
+        && !args.ignore_errors
+        && !args.touch_only
+        && !args.attrs_only
+        && !args.dedup
+        && args.sort == SortOrder::None
+        && !args.is_partial()
+        && args.on_conflict.is_none()
+        && !fsync
+        && !args.preallocate
+        && !args.update
+        && args.chmod.is_none()
+        && !args.sparse
+        && args.reflink != ReflinkMode::Always
+        && !args.pipeline
+        && !preserve.xattr
+        && !preserve.streams
+        && !preserve.ownership
+        && !preserve.links;
+
+    if source.is_dir() && args.is_partial() {
+        return Err(Error::Usage(format!(
+            "--skip and --count copy part of a file, but {} is a directory",
+            source.display()
+        )));
+    }
+
+    if let Some(link_target) = preserved_link(source, args) {
+        copy_symlink(&link_target, target, &args.overwrite_mode())?;
+        let mut stats = TransferStats::aggregate(&[], Duration::ZERO);
+        stats.files_linked = 1;
+        return Ok(stats);
+    }
+
+    if source.is_dir() {
+        // Directory copy - use parallel if enabled (it has no read timeout)
+        if parallel && timeout.is_none() {
+            let mut settings = TreeCopy::new(parallelism);
+            settings.verify = !args.no_verify;
+            settings.preserve = preserve;
+            settings.overwrite_mode = args.overwrite_mode();
+            settings.run_stats = run_stats.clone();
+            settings.file_results = Some(files.clone());
+            settings.logger = logger.clone();
+            settings.case_collision = args.case_collision;
+            settings.interrupt = interrupt.clone();
+            parallel_copy_directory(source, target, progress, &walk_filter(args)?, &settings).await
+        } else {
+            DirectoryCopier::new(
+                source.to_path_buf(),
+                target.to_path_buf(),
+                args.overwrite_mode(),
+                !args.no_verify,
+            )
+            .with_progress(progress)
+            .with_preserve(preserve)
+            .with_filter(walk_filter(args)?)
+            .with_remove_source_files(args.remove_source_files)
+            .with_interrupt(interrupt.clone())
+            .with_atomic(args.atomic)
+            .with_resume(!args.no_resume && args.resume)
+            .with_backup(args.backup)
+            .with_update_inplace(args.update_inplace)
+            .with_specials(args.specials)
+            .with_timeout(timeout)
+            .with_manifest(manifest.clone())
+            .with_verify_source(verify_source.clone())
+            .with_transcode(args.transcode())
+            .with_run_stats(run_stats.clone())
+            .with_file_results(Some(files.clone()))
+            .with_logger(logger.clone())
+            .with_reflink(args.reflink)
+            .with_verify_mode(args.verify)
+            .with_resume_interval(args.resume_interval()?)
+            .with_fsync(fsync)
+            .with_preallocate(args.preallocate)
+            .with_update(args.update)
+            .with_chmod(args.chmod.clone())
+            .with_sparse(args.sparse)
+            .with_delete(args.delete)
+            .with_strip_components(args.strip_components)
+            .with_touch_only(args.touch_only)
+            .with_attrs_only(args.attrs_only)
+            .with_dedup(args.dedup)
+            .with_case_collision(args.case_collision)
+            .with_ignore_errors(args.ignore_errors)
+            .with_buffer_size(buffer_size)
+            .with_pipeline(args.pipeline)
+            .with_options(options)
+            .copy()
+            .await
+        }
+    } else if parallel {
+        // File copy - use parallel if enabled and file is large enough
+        ParallelFileCopier::new(
+            source.to_path_buf(),
+            target.to_path_buf(),
+            parallelism.file,
+            !args.no_verify,
+        )
+        .with_progress(progress)
+        .with_preserve(preserve)
+        .with_timeout(timeout)
+        .with_run_stats(run_stats.clone())
+        .with_interrupt(interrupt.clone())
+        .copy()
+        .await
+    } else {
+        FileCopier::new(
+            source.to_path_buf(),
+            target.to_path_buf(),
+            args.overwrite_mode(),
+            !args.no_verify,
+            !args.no_resume && args.resume,
+            args.atomic,
+        )
+        .with_progress(progress)
+        .with_remove_source_files(args.remove_source_files)
+        .with_interrupt(interrupt.clone())
+        .with_preserve(preserve)
+        .with_backup(args.backup)
+        .with_update_inplace(args.update_inplace)
+        .with_timeout(timeout)
+        .with_manifest(manifest.clone())
+        .with_verify_source(verify_source)
+        .with_transcode(args.transcode())
+        .with_run_stats(run_stats.clone())
+        .with_reflink(args.reflink)
+        .with_verify_mode(args.verify)
+        .with_resume_interval(args.resume_interval()?)
+        .with_fsync(fsync)
+        .with_preallocate(args.preallocate)
+        .with_update(args.update)
+        .with_chmod(args.chmod.clone())
+        .with_sparse(args.sparse)
+        .with_touch_only(args.touch_only)
+        .with_attrs_only(args.attrs_only)
+        .with_range(args.skip.unwrap_or(0), args.count)
+        .with_buffer_size(buffer_size)
+        .with_pipeline(args.pipeline)
+        .with_options(options)
+        .copy()
+        .await
+    }
+}
+
 async fn dry_run_preview(args: &CopyArgs) -> Result<()> {
-    use better_cp::prompt;
     use std::fs;
+
+    // As the copy itself would, a missing source fails the run
+    if let Some(missing) = args.source.iter().find(|source| !is_stdio(source) && fs::symlink_metadata(source).is_err()) {
+        return Err(Error::SourceNotFound(missing.to_string_lossy().to_string()));
+    }
+
+    if args.json {
+        let mut plan = Vec::new();
+        for (source, target) in args.source.iter().zip(args.targets()?) {
+            if !is_stdio(source) {
+                plan.extend(planned_actions(source, &target, args)?);
+            }
+        }
+        let result = OperationResult::planned(args.source.clone(), args.destination.clone(), plan);
+        println!("{}", result.to_json());
+        return Ok(());
+    }
     
     if args.source.len() == 1 {
         let source = &args.source[0];
-        let target = &args.destination;
+        let target = &args.targets()?.remove(0);
         
         if source.is_dir() {
             // Directory preview
@@ -193,6 +864,13 @@ async fn dry_run_preview(args: &CopyArgs) -> Result<()> {
                     } else {
                         println!("  Action: {} (new directory)", style("create").green());
                     }
+
+                    if args.delete {
+                        let filter = walk_filter(args)?.rooted_at(source)?;
+                        for path in extraneous_entries(source, target, &filter, args.transcode())? {
+                            println!("  {} {}", style("would delete").red(), path.display());
+                        }
+                    }
                 }
                 Err(e) => {
                     eprintln!("❌ {}", e.detailed_message());
@@ -203,8 +881,8 @@ async fn dry_run_preview(args: &CopyArgs) -> Result<()> {
             // File preview
             match fs::metadata(source) {
                 Ok(metadata) => {
-                    let target_exists = target.exists();
-                    prompt::preview_operation(source, target, metadata.len(), target_exists);
+                    let tgt_metadata = fs::metadata(target).ok();
+                    prompt::preview_operation(source, target, &metadata, tgt_metadata.as_ref());
                 }
                 Err(e) => {
                     let err = better_cp::error::Error::Io(e);
@@ -213,8 +891,7 @@ async fn dry_run_preview(args: &CopyArgs) -> Result<()> {
                 }
             }
         } else {
-            eprintln!("Error: source is not a file or directory");
-            return Ok(());
+            return Err(Error::Custom(format!("{} is not a file or directory", source.display())));
         }
     } else {
         // Multiple files preview
@@ -237,12 +914,82 @@ async fn dry_run_preview(args: &CopyArgs) -> Result<()> {
         println!("  Total size: {}", humansize::format_size(total_size, humansize::BINARY));
         println!("  Action: {}", style("copy all files").green());
     }
+
+    if args.verbose {
+        println!();
+        for (source, target) in args.source.iter().zip(args.targets()?) {
+            print_planned_actions(source, &target, args)?;
+        }
+    }
     
     println!("\n{}", style("No files were modified (--dry-run)").green());
     
     Ok(())
 }
 
+/// What copying `source` would do to each file, walking and filtering
+/// exactly as the real copy does
+fn planned_actions(source: &Path, target: &Path, args: &CopyArgs) -> Result<Vec<PlannedFile>> {
+    let overwrite_mode = args.overwrite_mode();
+    let mut planned = Vec::new();
+    if let Some(link_target) = preserved_link(source, args) {
+        planned.push(PlannedFile {
+            source: source.to_path_buf(),
+            destination: target.to_path_buf(),
+            action: link_action(Some(&link_target), target, &overwrite_mode),
+            bytes: 0,
+        });
+    } else if source.is_dir() {
+        let filter = walk_filter(args)?.rooted_at(source)?;
+        let plan = plan_tree(source, target, &filter)?;
+        let (plan, too_shallow) = strip_components(plan, target, args.strip_components);
+        for entry in &plan {
+            if let Some(action) = entry.action(&overwrite_mode)? {
+                planned.push(PlannedFile {
+                    source: entry.source.clone(),
+                    destination: entry.target.clone(),
+                    action,
+                    bytes: entry.bytes(),
+                });
+            }
+        }
+        planned.extend(too_shallow.into_iter().map(|entry| PlannedFile {
+            bytes: entry.bytes(),
+            source: entry.source,
+            destination: entry.target,
+            action: PlannedAction::Skip,
+        }));
+    } else {
+        let metadata = std::fs::metadata(source).map_err(better_cp::error::Error::Io)?;
+        planned.push(PlannedFile {
+            source: source.to_path_buf(),
+            destination: target.to_path_buf(),
+            action: file_action(source, &metadata, target, &overwrite_mode)?,
+            bytes: metadata.len(),
+        });
+    }
+    Ok(planned)
+}
+
+/// List what copying `source` would do to each file
+fn print_planned_actions(source: &Path, target: &Path, args: &CopyArgs) -> Result<()> {
+    for file in planned_actions(source, target, args)? {
+        print_planned_action(file.action, &file.source, &file.destination);
+    }
+    Ok(())
+}
+
+fn print_planned_action(action: PlannedAction, source: &Path, target: &Path) {
+    let label = format!("{:<9}", action.to_string());
+    let label = match action {
+        PlannedAction::Create => style(label).green(),
+        PlannedAction::Overwrite => style(label).red(),
+        PlannedAction::Prompt => style(label).yellow(),
+        PlannedAction::Skip | PlannedAction::Excluded => style(label).dim(),
+    };
+    println!("  {} {} -> {}", label, source.display(), target.display());
+}
+
 /// Calculate directory size and file count for dry-run preview
 fn calculate_dir_size(path: &std::path::Path) -> Result<(usize, u64)> {
     use std::fs;
@@ -251,12 +998,12 @@ fn calculate_dir_size(path: &std::path::Path) -> Result<(usize, u64)> {
     let mut total_size = 0;
     
     let entries = fs::read_dir(path)
-        .map_err(|e| better_cp::error::Error::Io(e))?;
+        .map_err(better_cp::error::Error::Io)?;
     
     for entry in entries {
-        let entry = entry.map_err(|e| better_cp::error::Error::Io(e))?;
+        let entry = entry.map_err(better_cp::error::Error::Io)?;
         let metadata = entry.metadata()
-            .map_err(|e| better_cp::error::Error::Io(e))?;
+            .map_err(better_cp::error::Error::Io)?;
         
         if metadata.is_file() {
             file_count += 1;
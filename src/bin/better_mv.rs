@@ -1,33 +1,84 @@
 use clap::Parser;
-use console::style;
+use better_cp::color::{self, style};
 use better_cp::cli::{Cli, Commands, MoveArgs};
-use better_cp::r#move::{FileMover, move_directory};
-use better_cp::error::Result;
-use std::time::Instant;
+use better_cp::copy::{prepare_destination_dir, resolve_target};
+use better_cp::r#move::{move_item_logged, move_many, MoveSettings};
+use better_cp::removal::check_trash_supported;
+use better_cp::options::CopyOptions;
+use better_cp::prompt::{self, set_time_style};
+use better_cp::output::{human_output, suppress_human_output, Logger, OutputManager};
+use better_cp::json_output::{FileResults, OperationResult, OperationSummary};
+use better_cp::preflight::{check_destination_writable, confirm_impact, Impact};
+use better_cp::error::{Error, Result};
+use better_cp::interrupt;
+use better_cp::progress::{progress_enabled, set_progress_target, set_progress_template, set_refresh_interval};
+use std::io::{self, IsTerminal};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
 
 #[tokio::main]
 async fn main() -> Result<()> {
     let cli = Cli::parse();
+    color::init(cli.color);
+    if let Some(ms) = cli.progress_interval {
+        set_refresh_interval(Duration::from_millis(ms));
+    }
+    if let Some(template) = cli.progress_template() {
+        set_progress_template(&template);
+    }
+    set_progress_target(cli.progress_to);
+    set_time_style(cli.time_style);
+    if cli.command.json() {
+        suppress_human_output();
+    }
 
     match cli.command {
         Commands::Move(args) => {
+            let (source, destination, json) = (args.source.clone(), args.destination.clone(), args.json);
             if let Err(e) = handle_move(args).await {
-                eprintln!("❌ {}", e.detailed_message());
-                std::process::exit(1);
+                // A skip is no failure: the result was printed already
+                if json && !e.is_skip() {
+                    let mut failure = OperationResult::failure(source, destination, e.detailed_message());
+                    failure.operation = "move".to_string();
+                    println!("{}", failure.to_json());
+                }
+                exit_with(e);
             }
         }
         Commands::Copy(_) => {
-            eprintln!("Use better-cp for copy operations");
+            exit_with(Error::Usage("use better-cp for copy operations".to_string()));
+        }
+        Commands::Clean(_) => {
+            exit_with(Error::Usage("use better-cp clean to remove leftovers of interrupted copies".to_string()));
+        }
+        Commands::Verify(_) => {
+            exit_with(Error::Usage("use better-cp verify to compare a copy against its source".to_string()));
+        }
+        Commands::Resume(_) => {
+            exit_with(Error::Usage("use better-cp resume to list or continue interrupted copies".to_string()));
+        }
+        Commands::Checkpoint(_) => {
+            exit_with(Error::Usage(
+                "use better-cp checkpoint to inspect an interrupted copy's resume state".to_string(),
+            ));
         }
     }
 
     Ok(())
 }
 
+/// Report `e` on stderr, unless `--json` already carries it, and exit with its
+/// code. A skip is what was asked for, so only its exit code tells of it.
+fn exit_with(e: Error) -> ! {
+    if human_output() && !e.is_skip() {
+        eprintln!("❌ {}", e.detailed_message());
+    }
+    std::process::exit(e.exit_code());
+}
+
 async fn handle_move(args: MoveArgs) -> Result<()> {
     if args.source.is_empty() {
-        eprintln!("Error: no source specified");
-        return Ok(());
+        return Err(Error::Usage("no source specified".to_string()));
     }
 
     // Dry-run mode: just show preview
@@ -36,82 +87,108 @@ async fn handle_move(args: MoveArgs) -> Result<()> {
         return Ok(());
     }
 
+    if args.trash {
+        check_trash_supported()?;
+    }
+    for source in &args.source {
+        check_destination_writable(&resolve_target(source, &args.destination)?)?;
+    }
+
+    // Ask before a large move, unless told not to or nobody is there to answer
+    if !args.yes && !args.json && !args.quiet && io::stdin().is_terminal() {
+        let mut impact = Impact::default();
+        // Missing sources are reported by the move itself
+        for source in args.source.iter().filter(|source| source.exists()) {
+            impact += Impact::of_move(source)?;
+        }
+        confirm_impact(impact, &args.confirm_threshold(), "move")?;
+    }
+
     let start = Instant::now();
+    let output = OutputManager::new(args.quiet, args.verbose);
+    let settings = MoveSettings {
+        overwrite_mode: args.overwrite_mode(),
+        backup: args.backup,
+        trash: args.trash,
+        verbose: output.is_verbose() && !args.json,
+        show_progress: progress_enabled(args.no_progress || args.json || output.is_quiet()),
+        same_device_only: args.same_device_only,
+        interrupt: interrupt::install_handler()?,
+        options: if output.is_quiet() {
+            CopyOptions::unattended()
+        } else if args.json || !io::stdin().is_terminal() {
+            CopyOptions::non_interactive()
+        } else {
+            CopyOptions::interactive()
+        },
+        file_results: args.json.then(FileResults::new),
+    };
+    let mut skipped = Vec::new();
+    let logger = Logger::from_args(args.log.as_ref(), args.log_format)?;
 
     if args.source.len() == 1 {
         // Single source move (file or directory)
         let source = &args.source[0];
+        let target = resolve_target(source, &args.destination)?;
 
-        if source.is_dir() {
-            // Directory move
-            move_directory(
-                source,
-                &args.destination,
-                args.overwrite.clone(),
-                args.verbose,
-            )
-            .await?;
-        } else {
-            // File move
-            let mover = FileMover::new(
-                source.clone(),
-                args.destination.clone(),
-                args.overwrite.clone(),
-                args.verbose,
-            );
-            mover.move_file().await?;
+        match move_item_logged(source, &target, &settings, logger.as_ref()).await {
+            Ok(()) => {}
+            Err(e) if e.is_skip() => skipped.push(target),
+            Err(e) => return Err(e),
         }
     } else {
         // Multiple sources move (to directory)
-        if !args.destination.is_dir() {
-            eprintln!("Error: destination must be a directory for multiple sources");
-            return Ok(());
-        }
+        prepare_destination_dir(&args.destination)?;
 
+        let mut moves = Vec::new();
         for source in &args.source {
-            let target = args.destination.join(
-                source
-                    .file_name()
-                    .ok_or_else(|| better_cp::error::Error::Custom("Invalid source path".to_string()))?,
-            );
-
-            if source.is_dir() {
-                // Recursive directory move
-                move_directory(
-                    source,
-                    &target,
-                    args.overwrite.clone(),
-                    args.verbose,
-                )
-                .await?;
-            } else {
-                // File move
-                let mover = FileMover::new(
-                    source.clone(),
-                    target,
-                    args.overwrite.clone(),
-                    args.verbose,
-                );
-                mover.move_file().await?;
-            }
+            moves.push((source.clone(), resolve_target(source, &args.destination)?));
         }
+
+        skipped = move_many(moves, &settings, args.parallel, logger.as_ref()).await?;
     }
 
     // Show completion summary
     let duration = start.elapsed().as_secs_f64();
-    if !args.quiet {
-        let count = args.source.len();
-        let count_str = if count == 1 { "item" } else { "items" };
-        println!(
-            "\n{} {} {} in {:.2}s",
-            style("✓").green(),
-            count,
-            count_str,
-            duration
-        );
+    if args.json {
+        let summary = OperationSummary {
+            files_skipped: skipped.len(),
+            duration_secs: duration,
+            ..OperationSummary::default()
+        };
+        let files = settings.file_results.as_ref().map(FileResults::to_vec).unwrap_or_default();
+        let mut result =
+            OperationResult::success(args.source.clone(), args.destination.clone(), summary).with_files(files);
+        result.operation = "move".to_string();
+        println!("{}", result.to_json());
+        return skip_outcome(&skipped);
     }
+    let count = args.source.len();
+    let count_str = if count == 1 { "item" } else { "items" };
+    let skipped_str = if !skipped.is_empty() {
+        format!(" ({} skipped)", skipped.len())
+    } else {
+        String::new()
+    };
+    output.summary(&format!(
+        "\n{} {} {} in {:.2}s{}",
+        style("✓").green(),
+        count,
+        count_str,
+        duration,
+        skipped_str
+    ));
 
-    Ok(())
+    skip_outcome(&skipped)
+}
+
+/// A move that left an existing target alone still exits with the skip
+/// code, once everything else has been moved
+fn skip_outcome(skipped: &[PathBuf]) -> Result<()> {
+    match skipped.first() {
+        Some(target) => Err(Error::Skipped(target.to_string_lossy().to_string())),
+        None => Ok(()),
+    }
 }
 
 async fn dry_run_preview(args: &MoveArgs) -> Result<()> {
@@ -119,7 +196,7 @@ async fn dry_run_preview(args: &MoveArgs) -> Result<()> {
 
     if args.source.len() == 1 {
         let source = &args.source[0];
-        let target = &args.destination;
+        let target = &resolve_target(source, &args.destination)?;
 
         if source.is_dir() {
             // Directory preview
@@ -151,7 +228,12 @@ async fn dry_run_preview(args: &MoveArgs) -> Result<()> {
                     let target_exists = target.exists();
 
                     println!("\n{}", style("📋 Dry Run Preview (File Move)").cyan().bold());
-                    println!("  Source: {} ({} bytes)", source.display(), metadata.len());
+                    println!(
+                        "  Source: {} ({} bytes, modified {})",
+                        source.display(),
+                        metadata.len(),
+                        prompt::modified_time(&metadata)
+                    );
                     println!("  Target: {}", target.display());
 
                     if target_exists {
@@ -205,12 +287,12 @@ fn calculate_dir_size(path: &std::path::Path) -> Result<(usize, u64)> {
     let mut total_size = 0;
 
     let entries = fs::read_dir(path)
-        .map_err(|e| better_cp::error::Error::Io(e))?;
+        .map_err(better_cp::error::Error::Io)?;
 
     for entry in entries {
-        let entry = entry.map_err(|e| better_cp::error::Error::Io(e))?;
+        let entry = entry.map_err(better_cp::error::Error::Io)?;
         let metadata = entry.metadata()
-            .map_err(|e| better_cp::error::Error::Io(e))?;
+            .map_err(better_cp::error::Error::Io)?;
 
         if metadata.is_file() {
             file_count += 1;
@@ -0,0 +1,167 @@
+use std::fs;
+use std::path::Path;
+use crate::error::{Error, Result};
+
+/// A `--chmod` mode forced on copied files, like `install -m`: octal
+/// (`644`, `0o600`) or `chmod`-style symbolic clauses (`u+rw,go-w`, `a=rX`).
+///
+/// A clause is who (`u`, `g`, `o`, `a`; none means `a`, with no umask
+/// applied) followed by one or more operations (`+`, `-`, `=`) on `r`, `w`,
+/// `x`, `X` (execute only for directories or files already executable by
+/// someone), `s` and `t`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ChmodSpec {
+    Octal(u32),
+    Symbolic(Vec<Clause>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Clause {
+    /// The bits of the classes the clause is about, special bits included
+    who: u32,
+    actions: Vec<Action>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Action {
+    op: Op,
+    bits: u32,
+    /// `X`: execute, if the file is a directory or executable already
+    conditional_exec: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+    Add,
+    Remove,
+    Set,
+}
+
+impl ChmodSpec {
+    pub fn parse(spec: &str) -> Result<Self> {
+        let invalid = |why: &str| Error::Usage(format!("Invalid --chmod mode '{}': {}", spec, why));
+
+        let digits = spec.strip_prefix("0o").unwrap_or(spec);
+        if !digits.is_empty() && digits.chars().all(|c| c.is_ascii_digit()) {
+            return match u32::from_str_radix(digits, 8) {
+                Ok(mode) if digits.len() <= 4 => Ok(ChmodSpec::Octal(mode)),
+                _ => Err(invalid("expected up to four octal digits")),
+            };
+        }
+
+        let mut clauses = Vec::new();
+        for clause in spec.split(',') {
+            let mut chars = clause.chars().peekable();
+            let mut who = 0;
+            while let Some(&c) = chars.peek() {
+                who |= match c {
+                    'u' => 0o4700,
+                    'g' => 0o2070,
+                    'o' => 0o1007,
+                    'a' => 0o7777,
+                    _ => break,
+                };
+                chars.next();
+            }
+            if who == 0 {
+                who = 0o7777;
+            }
+
+            let mut actions = Vec::new();
+            while let Some(c) = chars.next() {
+                let op = match c {
+                    '+' => Op::Add,
+                    '-' => Op::Remove,
+                    '=' => Op::Set,
+                    c => return Err(invalid(&format!("unexpected '{}'", c))),
+                };
+                let mut action = Action { op, bits: 0, conditional_exec: false };
+                while let Some(&c) = chars.peek() {
+                    match c {
+                        'r' => action.bits |= 0o444,
+                        'w' => action.bits |= 0o222,
+                        'x' => action.bits |= 0o111,
+                        'X' => action.conditional_exec = true,
+                        's' => action.bits |= 0o6000,
+                        't' => action.bits |= 0o1000,
+                        _ => break,
+                    }
+                    chars.next();
+                }
+                actions.push(action);
+            }
+            if actions.is_empty() {
+                return Err(invalid(&format!("'{}' has no +, - or =", clause)));
+            }
+            clauses.push(Clause { who, actions });
+        }
+        Ok(ChmodSpec::Symbolic(clauses))
+    }
+
+    /// The mode a file with `mode` ends up with
+    pub fn mode_for(&self, mode: u32, is_dir: bool) -> u32 {
+        let clauses = match self {
+            ChmodSpec::Octal(octal) => return (mode & !0o7777) | octal,
+            ChmodSpec::Symbolic(clauses) => clauses,
+        };
+
+        let mut mode = mode;
+        for clause in clauses {
+            for action in &clause.actions {
+                let mut bits = action.bits;
+                if action.conditional_exec && (is_dir || mode & 0o111 != 0) {
+                    bits |= 0o111;
+                }
+                let bits = bits & clause.who;
+                mode = match action.op {
+                    Op::Add => mode | bits,
+                    Op::Remove => mode & !bits,
+                    Op::Set => (mode & !clause.who) | bits,
+                };
+            }
+        }
+        mode
+    }
+
+    /// Give `target` the mode this spec makes of its current one
+    #[cfg(unix)]
+    pub fn apply_to(&self, target: &Path) -> Result<()> {
+        use std::os::unix::fs::PermissionsExt;
+
+        let metadata = fs::metadata(target).map_err(Error::Io)?;
+        let mode = self.mode_for(metadata.permissions().mode(), metadata.is_dir());
+        fs::set_permissions(target, fs::Permissions::from_mode(mode)).map_err(Error::Io)
+    }
+
+    /// Only the read-only flag can be set here: set when nobody may write
+    #[cfg(not(unix))]
+    pub fn apply_to(&self, target: &Path) -> Result<()> {
+        let metadata = fs::metadata(target).map_err(Error::Io)?;
+        let current = if metadata.permissions().readonly() { 0o444 } else { 0o666 };
+        let mut permissions = metadata.permissions();
+        permissions.set_readonly(self.mode_for(current, metadata.is_dir()) & 0o222 == 0);
+        fs::set_permissions(target, permissions).map_err(Error::Io)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_octal_and_symbolic_modes() {
+        assert_eq!(ChmodSpec::parse("0o600").unwrap().mode_for(0o100644, false), 0o100600);
+        assert_eq!(ChmodSpec::parse("755").unwrap().mode_for(0o644, false), 0o755);
+        assert_eq!(ChmodSpec::parse("go-rwx").unwrap().mode_for(0o755, false), 0o700);
+        assert_eq!(ChmodSpec::parse("u+rw,go-w").unwrap().mode_for(0o466, false), 0o644);
+        assert_eq!(ChmodSpec::parse("a=rX").unwrap().mode_for(0o700, false), 0o555);
+        assert_eq!(ChmodSpec::parse("a=rX").unwrap().mode_for(0o600, false), 0o444);
+        assert_eq!(ChmodSpec::parse("a=rX").unwrap().mode_for(0o600, true), 0o555);
+        assert_eq!(ChmodSpec::parse("u=rw-w+x").unwrap().mode_for(0o044, false), 0o544);
+        assert_eq!(ChmodSpec::parse("+t").unwrap().mode_for(0o755, true), 0o1755);
+
+        for bad in ["", "999", "07777777", "u", "u*r", "z+r", "u+r,"] {
+            assert!(ChmodSpec::parse(bad).is_err(), "{:?} should not parse", bad);
+        }
+    }
+}
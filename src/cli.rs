@@ -1,5 +1,17 @@
 use clap::{Parser, Subcommand, ValueEnum};
-use std::path::PathBuf;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use crate::error::{Error, Result};
+use crate::config::Config;
+use crate::copy::Transcode;
+use crate::preflight::ConfirmThreshold;
+use crate::filter::parse_reference_time;
+use crate::preserve::PreserveSet;
+use crate::progress::preset_template;
+use crate::rename::{resolve_targets, RenameTemplate};
+use crate::chmod::ChmodSpec;
+use crate::resume::ResumeInterval;
 
 #[derive(Parser, Debug)]
 #[command(name = "better-cp")]
@@ -8,16 +20,83 @@ use std::path::PathBuf;
 pub struct Cli {
     #[command(subcommand)]
     pub command: Commands,
+
+    /// Colorize output: auto|always|never (auto honors NO_COLOR)
+    #[arg(long, value_name = "WHEN", default_value = "auto", global = true)]
+    pub color: ColorChoice,
+
+    /// Redraw progress at most every MS milliseconds [default: 100];
+    /// also paces --json progress events
+    #[arg(long, value_name = "MS", global = true)]
+    pub progress_interval: Option<u64>,
+
+    /// Progress bar layout as an indicatif template (e.g. '{bytes}/{total_bytes} {msg}')
+    /// [default: the ui.progress_style preset: bars|minimal|detailed]
+    #[arg(long, value_name = "TPL", global = true)]
+    pub progress_template: Option<String>,
+
+    /// Stream progress bars are drawn on: stdout|stderr. Keeping them on
+    /// stderr leaves stdout clean for data and JSON
+    #[arg(long, value_name = "STREAM", default_value = "stderr", global = true)]
+    pub progress_to: ProgressTarget,
+
+    /// How prompts and dry-run previews show modification times:
+    /// local|iso|relative|unix
+    #[arg(long, value_name = "STYLE", default_value = "local", global = true)]
+    pub time_style: TimeStyle,
+}
+
+impl Cli {
+    /// `--progress-template`, else the preset named by `ui.progress_style`
+    pub fn progress_template(&self) -> Option<String> {
+        self.progress_template.clone().or_else(|| {
+            let config = Config::load().ok()?;
+            preset_template(&config.ui.progress_style).map(String::from)
+        })
+    }
 }
 
 #[derive(Subcommand, Debug)]
 pub enum Commands {
     /// Copy files with progress and safety
-    Copy(CopyArgs),
+    Copy(Box<CopyArgs>),
     /// Move files with progress and safety
     Move(MoveArgs),
+    /// Remove leftovers (.tmp, .better-cp.partial, .better-cp.state) of interrupted copies
+    Clean(CleanArgs),
+    /// Compare a copy against its source by checksum
+    Verify(VerifyArgs),
+    /// List interrupted copies, or continue one
+    Resume(ResumeArgs),
+    /// Show the saved resume state of an interrupted copy and whether it can resume
+    Checkpoint(CheckpointArgs),
+}
+
+impl Commands {
+    /// Whether the command was asked for `--json`, which then is all it prints
+    pub fn json(&self) -> bool {
+        match self {
+            Commands::Copy(args) => args.json,
+            Commands::Move(args) => args.json,
+            Commands::Verify(args) => args.json,
+            Commands::Clean(_) | Commands::Resume(_) | Commands::Checkpoint(_) => false,
+        }
+    }
 }
 
+/// What a partial copy (`--skip`, `--count`) can't be combined with: they
+/// all work on whole files
+const PARTIAL_CONFLICTS: [&str; 8] = [
+    "resume",
+    "remove_source_files",
+    "compress",
+    "decompress",
+    "update_inplace",
+    "touch_only",
+    "attrs_only",
+    "verify_source",
+];
+
 #[derive(Parser, Debug, Clone)]
 pub struct CopyArgs {
     /// Source file or directory (one or more)
@@ -28,10 +107,50 @@ pub struct CopyArgs {
     #[arg(required = true)]
     pub destination: PathBuf,
 
-    /// Overwrite behavior: never|prompt|always|smart
+    /// Overwrite behavior: never|prompt|always|smart|smart-checksum|no-clobber
     #[arg(long, value_name = "MODE", default_value = "prompt")]
     pub overwrite: OverwriteMode,
 
+    /// Skip existing targets, moving on to the rest, then exit with code 4
+    /// (same as --overwrite=no-clobber)
+    #[arg(short = 'n', long, action = clap::ArgAction::SetTrue)]
+    pub no_clobber: bool,
+
+    /// Only copy when the target is missing or older than the source (like
+    /// `cp -u`); up-to-date targets are skipped without reading the source
+    #[arg(short = 'u', long, action = clap::ArgAction::SetTrue)]
+    pub update: bool,
+
+    /// Give every copied file this mode, octal (`644`) or symbolic
+    /// (`u+rw,go-w`), instead of the source's
+    #[arg(long, value_name = "MODE", value_parser = ChmodSpec::parse)]
+    pub chmod: Option<ChmodSpec>,
+
+    /// With --overwrite=smart, compare checksums when mtimes and sizes match
+    #[arg(long, action = clap::ArgAction::SetTrue)]
+    pub smart_checksum: bool,
+
+    /// Overwrite an existing target only when the source is: newer|larger|newer-or-larger|different-checksum.
+    /// Other targets are skipped. Takes the place of --overwrite
+    #[arg(long, value_name = "POLICY", conflicts_with = "overwrite")]
+    pub on_conflict: Option<ConflictPolicy>,
+
+    /// Rename existing targets before overwriting them: none|simple (file~)|numbered (file.~N~)
+    #[arg(
+        long,
+        value_name = "MODE",
+        num_args = 0..=1,
+        require_equals = true,
+        default_value = "none",
+        default_missing_value = "simple"
+    )]
+    pub backup: BackupMode,
+
+    /// Name each copy in the destination directory from a template:
+    /// {name}, {ext}, {index}, with :lower or :upper (e.g. 'backup_{name}.{ext}')
+    #[arg(long, value_name = "TEMPLATE", value_parser = RenameTemplate::parse)]
+    pub rename: Option<RenameTemplate>,
+
     /// Resume interrupted transfers
     #[arg(long, action = clap::ArgAction::SetTrue)]
     pub resume: bool,
@@ -40,6 +159,11 @@ pub struct CopyArgs {
     #[arg(long, action = clap::ArgAction::SetTrue)]
     pub no_resume: bool,
 
+    /// Save resume state every SIZE and/or TIME (e.g. 16M, 5s, 16M,5s)
+    /// [default: performance.resume_threshold, else 100M,30s]
+    #[arg(long, value_name = "SIZE,TIME", value_parser = ResumeInterval::parse)]
+    pub resume_interval: Option<ResumeInterval>,
+
     /// Verify checksums after transfer
     #[arg(long, value_name = "MODE", default_value = "fast")]
     pub verify: VerifyMode,
@@ -48,19 +172,72 @@ pub struct CopyArgs {
     #[arg(long, action = clap::ArgAction::SetTrue)]
     pub no_verify: bool,
 
-    /// Use atomic operations
+    /// Use atomic operations (temp file, or staging directory for trees)
     #[arg(long, action = clap::ArgAction::SetTrue)]
     pub atomic: bool,
 
-    /// Number of parallel threads (0 = auto)
+    /// Flush each copy to disk before renaming it into place, then its
+    /// directory, so it survives a power loss [default: behavior.fsync]
+    #[arg(long, action = clap::ArgAction::SetTrue)]
+    pub fsync: bool,
+
+    /// Reserve each target's full size before copying into it (fallocate on
+    /// Linux), so it is laid out contiguously and a full disk fails up front.
+    /// Holes would be filled and transcoded sizes aren't known, so it can't
+    /// go with --sparse, --compress or --decompress
+    #[arg(long, action = clap::ArgAction::SetTrue, conflicts_with_all = ["sparse", "compress", "decompress"])]
+    pub preallocate: bool,
+
+    /// Write a SHA256SUMS-style manifest of every copied file (checkable with `sha256sum -c`)
+    #[arg(long, value_name = "FILE")]
+    pub write_manifest: Option<PathBuf>,
+
+    /// Check sources against this SHA256SUMS-style manifest as they are read,
+    /// refusing to copy any that no longer match (paths relative to the manifest)
+    #[arg(long, value_name = "SHA256SUMS")]
+    pub verify_source: Option<PathBuf>,
+
+    /// Abort if reading makes no progress for this many seconds (e.g. a hung NFS mount)
+    #[arg(long, value_name = "SECS")]
+    pub timeout: Option<u64>,
+
+    /// Rewrite only the blocks that differ when the target exists with the same size
+    #[arg(long, action = clap::ArgAction::SetTrue, conflicts_with = "atomic")]
+    pub update_inplace: bool,
+
+    /// Compress each file on the way, writing `<target>.zst`
+    #[arg(long, value_name = "ALGO", conflicts_with_all = ["decompress", "resume", "update_inplace"])]
+    pub compress: Option<Compression>,
+
+    /// Decompress `.zst` files written by --compress, dropping the suffix
+    #[arg(long, action = clap::ArgAction::SetTrue, conflicts_with_all = ["resume", "update_inplace"])]
+    pub decompress: bool,
+
+    /// Number of parallel threads (0 = auto): files at once for directories,
+    /// chunks of the file for a single file
     #[arg(long, value_name = "N", default_value = "0")]
     pub parallel: usize,
 
-    /// Internal buffer size (e.g. 64M, 1G)
-    #[arg(long, value_name = "SIZE", default_value = "64M")]
-    pub buffer: String,
+    /// Threads splitting each file into chunks (0 = as --parallel for a
+    /// single file, unsplit within directories)
+    #[arg(long, value_name = "N", default_value = "0")]
+    pub file_parallel: usize,
+
+    /// Files of a directory copied at once (0 = as --parallel)
+    #[arg(long, value_name = "M", default_value = "0")]
+    pub tree_parallel: usize,
+
+    /// Largest read buffer a file gets (e.g. 64M, 1G); smaller files get
+    /// one their own size [default: performance.buffer_size, else 64M]
+    #[arg(long, value_name = "SIZE", value_parser = Config::parse_size)]
+    pub buffer: Option<u64>,
+
+    /// Read the next chunk while the last one is being written, at the cost
+    /// of a second read buffer per file (helps most on fast storage)
+    #[arg(long, action = clap::ArgAction::SetTrue)]
+    pub pipeline: bool,
 
-    /// Enable sparse file detection
+    /// Keep holes in sparse files, copying only their data extents
     #[arg(long, action = clap::ArgAction::SetTrue)]
     pub sparse: bool,
 
@@ -76,11 +253,74 @@ pub struct CopyArgs {
     #[arg(short, long, action = clap::ArgAction::SetTrue)]
     pub verbose: bool,
 
-    /// Minimal output
-    #[arg(long, action = clap::ArgAction::SetTrue)]
+    /// With --verbose, end with a tree of what was created (+), overwritten (~),
+    /// touched (=), skipped (-) or failed (!)
+    #[arg(long, action = clap::ArgAction::SetTrue, requires = "verbose")]
+    pub tree: bool,
+
+    /// Print nothing but errors: no progress, summary or prompts (--overwrite
+    /// prompt becomes never)
+    #[arg(short, long, action = clap::ArgAction::SetTrue)]
     pub quiet: bool,
 
-    /// Machine-readable JSON output
+    /// Print a detailed report after the run: sizes, extensions, largest file, throughput
+    #[arg(long, action = clap::ArgAction::SetTrue)]
+    pub stats: bool,
+
+    /// After copying a directory, delete destination entries the source doesn't have (mirror)
+    #[arg(long, action = clap::ArgAction::SetTrue)]
+    pub delete: bool,
+
+    /// Drop N leading path components inside a copied directory (like
+    /// `tar --strip-components`); entries with nothing left are skipped
+    #[arg(long, value_name = "N", default_value = "0", conflicts_with = "delete")]
+    pub strip_components: usize,
+
+    /// Leave targets whose contents already match alone, only updating their
+    /// timestamps to the source's (reported as touched)
+    #[arg(long, action = clap::ArgAction::SetTrue)]
+    pub touch_only: bool,
+
+    /// Copy no contents: only apply the preserved attributes (see --preserve)
+    /// to targets that already exist with the source's size
+    #[arg(
+        long,
+        action = clap::ArgAction::SetTrue,
+        conflicts_with_all = ["touch_only", "update_inplace", "compress", "decompress", "remove_source_files"]
+    )]
+    pub attrs_only: bool,
+
+    /// Start copying SIZE bytes into the source file (like dd's skip)
+    #[arg(long, value_name = "SIZE", value_parser = Config::parse_size, conflicts_with_all = PARTIAL_CONFLICTS)]
+    pub skip: Option<u64>,
+
+    /// Copy only SIZE bytes of the source file (like dd's count); with
+    /// --skip, a slice from the middle. Partial copies aren't verified
+    #[arg(long, value_name = "SIZE", value_parser = Config::parse_size, conflicts_with_all = PARTIAL_CONFLICTS)]
+    pub count: Option<u64>,
+
+    /// In directory copies, hard-link a file whose contents match one already
+    /// copied in this run instead of copying it again; the links share one
+    /// set of attributes
+    #[arg(long, action = clap::ArgAction::SetTrue)]
+    pub dedup: bool,
+
+    /// In directory copies, warn about each file that fails and copy the rest,
+    /// exiting with code 7 if any failed
+    #[arg(long, alias = "keep-going", action = clap::ArgAction::SetTrue)]
+    pub ignore_errors: bool,
+
+    /// Do not draw progress bars (implied when stdout is not a terminal)
+    #[arg(long, action = clap::ArgAction::SetTrue)]
+    pub no_progress: bool,
+
+    /// Sample throughput during the copy and print a histogram of MB/s with
+    /// its p50/p95/min/max at the end
+    #[arg(long, action = clap::ArgAction::SetTrue)]
+    pub bandwidth_report: bool,
+
+    /// Machine-readable JSON output: one JSON document on stdout (after any
+    /// progress events), errors included, and nothing else on stdout or stderr
     #[arg(long, action = clap::ArgAction::SetTrue)]
     pub json: bool,
 
@@ -88,35 +328,149 @@ pub struct CopyArgs {
     #[arg(long, value_name = "FILE")]
     pub log: Option<PathBuf>,
 
-    /// Follow symlinks
+    /// Log line format: text|jsonl
+    #[arg(long, value_name = "FORMAT", default_value = "text")]
+    pub log_format: LogFormat,
+
+    /// Follow symlinks in directories (by default links are recreated as links)
     #[arg(long, action = clap::ArgAction::SetTrue)]
     pub follow_symlinks: bool,
 
-    /// Preserve timestamps
-    #[arg(long, action = clap::ArgAction::SetTrue, default_value_t = true)]
-    pub preserve_times: bool,
+    /// Copy the directory a source symlink points to, but keep symlinks
+    /// inside it as links (like cp -H). This is the default
+    #[arg(short = 'H', long, action = clap::ArgAction::SetTrue)]
+    pub dereference_args: bool,
+
+    /// Copy a source that is a symlink to a directory as the symlink itself,
+    /// as cp -r does
+    #[arg(long, action = clap::ArgAction::SetTrue, conflicts_with_all = ["dereference_args", "follow_symlinks"])]
+    pub no_dereference_args: bool,
+
+    /// Recreate FIFOs, sockets and device nodes in directories (skipped with a warning otherwise)
+    #[arg(long, action = clap::ArgAction::SetTrue)]
+    pub specials: bool,
+
+    /// Do not cross filesystem boundaries when copying directories
+    #[arg(short = 'x', long, action = clap::ArgAction::SetTrue)]
+    pub one_file_system: bool,
 
     /// Exclude files matching pattern
     #[arg(long, value_name = "PATTERN")]
     pub exclude: Vec<String>,
 
+    /// Descend at most N directory levels (0 = only the source's own entries)
+    #[arg(long, value_name = "N")]
+    pub max_depth: Option<usize>,
+
+    /// Order each directory's entries are copied in: none|name|size|mtime.
+    /// `none` takes the filesystem's order; the others make logs and
+    /// manifests come out the same on every run
+    #[arg(long, value_name = "KEY", default_value = "none")]
+    pub sort: SortOrder,
+
+    /// What to do when a case-insensitive target (macOS, Windows) would get
+    /// two source names that differ only in case, like File.txt and
+    /// file.txt: warn|error|rename
+    #[arg(long, value_name = "MODE", default_value = "warn")]
+    pub case_collision: CaseCollisionMode,
+
+    /// Skip files smaller than SIZE (e.g. 1K, 10M)
+    #[arg(long, value_name = "SIZE", value_parser = Config::parse_size)]
+    pub min_size: Option<u64>,
+
+    /// Skip files larger than SIZE (e.g. 1K, 10M)
+    #[arg(long, value_name = "SIZE", value_parser = Config::parse_size)]
+    pub max_size: Option<u64>,
+
+    /// Only copy files modified after this path's mtime or date (YYYY-MM-DD)
+    #[arg(long, value_name = "PATH|DATE", value_parser = parse_reference_time)]
+    pub newer_than: Option<SystemTime>,
+
+    /// Read exclude patterns from a file (one per line, # for comments)
+    #[arg(long, value_name = "FILE")]
+    pub exclude_from: Vec<PathBuf>,
+
+    /// Copy files matching pattern even if an exclude pattern matches
+    #[arg(long, value_name = "PATTERN")]
+    pub include: Vec<String>,
+
+    /// Delete each source file after its copy has been verified
+    #[arg(long, action = clap::ArgAction::SetTrue)]
+    pub remove_source_files: bool,
+
+    /// With multiple sources, undo everything this run created if any copy fails
+    #[arg(long, action = clap::ArgAction::SetTrue)]
+    pub transactional: bool,
+
+    /// Attributes to keep, and only these (comma-separated):
+    /// mode,timestamps,ownership,xattr,streams,links,all
+    /// [default: behavior.preserve_permissions and preserve_times, else mode,timestamps]
+    #[arg(long, value_name = "LIST")]
+    pub preserve: Option<PreserveSet>,
+
     /// Interactive mode (prompt for each action)
     #[arg(short, long, action = clap::ArgAction::SetTrue)]
     pub interactive: bool,
+
+    /// Don't ask before large overwrites (see --confirm-files and --confirm-size)
+    #[arg(short = 'y', long, visible_alias = "force", action = clap::ArgAction::SetTrue)]
+    pub yes: bool,
+
+    /// Ask before overwriting more than N existing files
+    #[arg(long, value_name = "N", default_value = "100")]
+    pub confirm_files: usize,
+
+    /// Ask before overwriting more than SIZE of existing files (e.g. 500M, 2G)
+    #[arg(long, value_name = "SIZE", default_value = "1G", value_parser = Config::parse_size)]
+    pub confirm_size: u64,
 }
 
 #[derive(Parser, Debug, Clone)]
 pub struct MoveArgs {
     /// Source file or directory
+    #[arg(required = true)]
     pub source: Vec<PathBuf>,
 
     /// Destination file or directory
     pub destination: PathBuf,
 
-    /// Overwrite behavior: never|prompt|always|smart
+    /// Overwrite behavior: never|prompt|always|smart|smart-checksum|no-clobber
     #[arg(long, value_name = "MODE", default_value = "prompt")]
     pub overwrite: OverwriteMode,
 
+    /// Skip existing targets, moving on to the rest, then exit with code 4
+    /// (same as --overwrite=no-clobber)
+    #[arg(short = 'n', long, action = clap::ArgAction::SetTrue)]
+    pub no_clobber: bool,
+
+    /// With --overwrite=smart, compare checksums when mtimes and sizes match
+    #[arg(long, action = clap::ArgAction::SetTrue)]
+    pub smart_checksum: bool,
+
+    /// Overwrite an existing target only when the source is: newer|larger|newer-or-larger|different-checksum.
+    /// Other targets are skipped. Takes the place of --overwrite
+    #[arg(long, value_name = "POLICY", conflicts_with = "overwrite")]
+    pub on_conflict: Option<ConflictPolicy>,
+
+    /// Rename existing targets before overwriting them: none|simple (file~)|numbered (file.~N~)
+    #[arg(
+        long,
+        value_name = "MODE",
+        num_args = 0..=1,
+        require_equals = true,
+        default_value = "none",
+        default_missing_value = "simple"
+    )]
+    pub backup: BackupMode,
+
+    /// Send replaced targets and moved-away sources to the trash instead of deleting them
+    #[arg(long, action = clap::ArgAction::SetTrue)]
+    pub trash: bool,
+
+    /// Only rename: fail instead of copying and deleting when the target is on another filesystem
+    #[arg(long, action = clap::ArgAction::SetTrue)]
+    pub same_device_only: bool,
+
     /// Number of parallel threads (0 = auto)
     #[arg(long, value_name = "N", default_value = "0")]
     pub parallel: usize,
@@ -129,11 +483,17 @@ pub struct MoveArgs {
     #[arg(short, long, action = clap::ArgAction::SetTrue)]
     pub verbose: bool,
 
-    /// Minimal output
-    #[arg(long, action = clap::ArgAction::SetTrue)]
+    /// Print nothing but errors: no progress, summary or prompts (--overwrite
+    /// prompt becomes never)
+    #[arg(short, long, action = clap::ArgAction::SetTrue)]
     pub quiet: bool,
 
-    /// Machine-readable JSON output
+    /// Do not draw progress bars (implied when stdout is not a terminal)
+    #[arg(long, action = clap::ArgAction::SetTrue)]
+    pub no_progress: bool,
+
+    /// Machine-readable JSON output: one JSON document on stdout (after any
+    /// progress events), errors included, and nothing else on stdout or stderr
     #[arg(long, action = clap::ArgAction::SetTrue)]
     pub json: bool,
 
@@ -141,9 +501,196 @@ pub struct MoveArgs {
     #[arg(long, value_name = "FILE")]
     pub log: Option<PathBuf>,
 
+    /// Log line format: text|jsonl
+    #[arg(long, value_name = "FORMAT", default_value = "text")]
+    pub log_format: LogFormat,
+
     /// Interactive mode
     #[arg(short, long, action = clap::ArgAction::SetTrue)]
     pub interactive: bool,
+
+    /// Don't ask before large moves (see --confirm-files and --confirm-size)
+    #[arg(short = 'y', long, visible_alias = "force", action = clap::ArgAction::SetTrue)]
+    pub yes: bool,
+
+    /// Ask before moving more than N files
+    #[arg(long, value_name = "N", default_value = "100")]
+    pub confirm_files: usize,
+
+    /// Ask before moving more than SIZE of files (e.g. 500M, 2G)
+    #[arg(long, value_name = "SIZE", default_value = "1G", value_parser = Config::parse_size)]
+    pub confirm_size: u64,
+}
+
+impl CopyArgs {
+    /// Effective overwrite mode, with `--no-clobber` taking precedence,
+    /// then `--on-conflict`, `--quiet` turning `prompt` into `never` and
+    /// `--smart-checksum` escalating `smart`
+    pub fn overwrite_mode(&self) -> OverwriteMode {
+        match (&self.overwrite, self.on_conflict) {
+            _ if self.no_clobber => OverwriteMode::NoClobber,
+            (_, Some(policy)) => OverwriteMode::Policy(policy),
+            // Nobody is there to answer
+            (OverwriteMode::Prompt, None) if self.quiet => OverwriteMode::Never,
+            (OverwriteMode::Smart, None) if self.smart_checksum => OverwriteMode::SmartChecksum,
+            (mode, None) => mode.clone(),
+        }
+    }
+
+    /// Size past which the copy asks before overwriting
+    pub fn confirm_threshold(&self) -> ConfirmThreshold {
+        ConfirmThreshold { files: self.confirm_files, bytes: self.confirm_size }
+    }
+
+    /// Where each source goes, renamed by `--rename` if given
+    pub fn targets(&self) -> Result<Vec<PathBuf>> {
+        resolve_targets(&self.source, &self.destination, self.rename.as_ref())
+    }
+
+    /// Whether any of `--parallel`, `--file-parallel` or `--tree-parallel` was given
+    pub fn explicit_parallel(&self) -> bool {
+        self.parallel > 0 || self.file_parallel > 0 || self.tree_parallel > 0
+    }
+
+    /// `--resume-interval`, else `performance.resume_threshold` from the config file
+    pub fn resume_interval(&self) -> Result<ResumeInterval> {
+        match self.resume_interval {
+            Some(interval) => Ok(interval),
+            None => ResumeInterval::parse(&Config::load()?.performance.resume_threshold),
+        }
+    }
+
+    /// Whether `--skip` or `--count` ask for part of a file
+    pub fn is_partial(&self) -> bool {
+        self.skip.is_some() || self.count.is_some()
+    }
+
+    /// `--buffer`, else `performance.buffer_size` from the config file
+    pub fn buffer_size(&self) -> Result<usize> {
+        let size = match self.buffer {
+            Some(size) => size,
+            None => Config::parse_size(&Config::load()?.performance.buffer_size)?,
+        };
+        if size == 0 {
+            return Err(Error::Usage("--buffer must be larger than 0".to_string()));
+        }
+        Ok(size as usize)
+    }
+
+    /// `--fsync`, or `behavior.fsync` from the config file
+    pub fn fsync(&self) -> Result<bool> {
+        Ok(self.fsync || Config::load()?.behavior.fsync)
+    }
+
+    /// How `--compress` or `--decompress` transform file contents, if at all
+    pub fn transcode(&self) -> Option<Transcode> {
+        match self.compress {
+            Some(compression) => Some(Transcode::Compress(compression)),
+            None if self.decompress => Some(Transcode::Decompress(Compression::Zstd)),
+            None => None,
+        }
+    }
+}
+
+impl CopyArgs {
+    /// `--preserve`, else the attributes the config file's `behavior` keeps
+    pub fn preserve_set(&self) -> Result<PreserveSet> {
+        match self.preserve {
+            Some(set) => Ok(set),
+            None => Ok(Config::load()?.behavior.preserve_set()),
+        }
+    }
+
+    /// Inline `--exclude` patterns merged with those from `--exclude-from` files
+    pub fn exclude_patterns(&self) -> Result<Vec<String>> {
+        let mut patterns = self.exclude.clone();
+        for file in &self.exclude_from {
+            patterns.extend(read_pattern_file(file)?);
+        }
+        Ok(patterns)
+    }
+}
+
+/// Read glob patterns from a file, one per line, skipping blanks and `#` comments
+pub fn read_pattern_file(path: &Path) -> Result<Vec<String>> {
+    let content = fs::read_to_string(path)
+        .map_err(|e| Error::Custom(format!("Failed to read pattern file {}: {}", path.display(), e)))?;
+    Ok(content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(String::from)
+        .collect())
+}
+
+impl MoveArgs {
+    /// Effective overwrite mode, with `--no-clobber` taking precedence,
+    /// then `--on-conflict`, `--quiet` turning `prompt` into `never` and
+    /// `--smart-checksum` escalating `smart`
+    pub fn overwrite_mode(&self) -> OverwriteMode {
+        match (&self.overwrite, self.on_conflict) {
+            _ if self.no_clobber => OverwriteMode::NoClobber,
+            (_, Some(policy)) => OverwriteMode::Policy(policy),
+            // Nobody is there to answer
+            (OverwriteMode::Prompt, None) if self.quiet => OverwriteMode::Never,
+            (OverwriteMode::Smart, None) if self.smart_checksum => OverwriteMode::SmartChecksum,
+            (mode, None) => mode.clone(),
+        }
+    }
+
+    /// Size past which the move asks before going ahead
+    pub fn confirm_threshold(&self) -> ConfirmThreshold {
+        ConfirmThreshold { files: self.confirm_files, bytes: self.confirm_size }
+    }
+}
+
+#[derive(Parser, Debug, Clone)]
+pub struct CleanArgs {
+    /// Directory to scan recursively
+    pub directory: PathBuf,
+
+    /// List what would be removed without deleting anything
+    #[arg(long, action = clap::ArgAction::SetTrue)]
+    pub dry_run: bool,
+}
+
+#[derive(Parser, Debug, Clone)]
+pub struct ResumeArgs {
+    /// Target of the interrupted copy to continue
+    #[arg(required_unless_present = "list", conflicts_with = "list")]
+    pub target: Option<PathBuf>,
+
+    /// List every interrupted copy that can be resumed, with how far it got
+    #[arg(long, action = clap::ArgAction::SetTrue)]
+    pub list: bool,
+
+    /// Disable progress bar
+    #[arg(long, action = clap::ArgAction::SetTrue)]
+    pub no_progress: bool,
+}
+
+#[derive(Parser, Debug, Clone)]
+pub struct CheckpointArgs {
+    /// Target of the interrupted copy, or its .better-cp.state file
+    pub path: PathBuf,
+}
+
+#[derive(Parser, Debug, Clone)]
+pub struct VerifyArgs {
+    /// Original file or directory
+    pub source: PathBuf,
+
+    /// Copy to check against it
+    pub destination: PathBuf,
+
+    /// Machine-readable JSON output listing discrepancies
+    #[arg(long, action = clap::ArgAction::SetTrue)]
+    pub json: bool,
+
+    /// Keep checksums in FILE between runs and trust them while a file's
+    /// size and modification time are unchanged
+    #[arg(long, value_name = "FILE")]
+    pub checksum_cache: Option<PathBuf>,
 }
 
 #[derive(Debug, Clone, ValueEnum)]
@@ -157,12 +704,38 @@ pub enum OverwriteMode {
     /// Always overwrite
     #[value(name = "always")]
     Always,
-    /// Overwrite if source is newer
+    /// Overwrite if source is newer, or same age but a different size
     #[value(name = "smart")]
     Smart,
+    /// Like smart, but also overwrite same-age, same-size files whose checksums differ
+    #[value(name = "smart-checksum")]
+    SmartChecksum,
+    /// Skip existing targets and carry on, exiting with code 4
+    #[value(name = "no-clobber")]
+    NoClobber,
+    /// Overwrite only what the policy allows, skipping the rest (`--on-conflict`)
+    #[value(skip)]
+    Policy(ConflictPolicy),
 }
 
-#[derive(Debug, Clone, ValueEnum)]
+/// When `--on-conflict` lets a source replace an existing target
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ConflictPolicy {
+    /// The source was modified more recently
+    #[value(name = "newer")]
+    Newer,
+    /// The source is bigger
+    #[value(name = "larger")]
+    Larger,
+    /// The source is newer, or bigger
+    #[value(name = "newer-or-larger")]
+    NewerOrLarger,
+    /// The contents differ (sizes first, then checksums)
+    #[value(name = "different-checksum")]
+    DifferentChecksum,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
 pub enum VerifyMode {
     /// No verification
     #[value(name = "none")]
@@ -175,7 +748,116 @@ pub enum VerifyMode {
     Full,
 }
 
-#[derive(Debug, Clone, ValueEnum)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum BackupMode {
+    /// Overwrite without keeping the old file
+    #[value(name = "none")]
+    None,
+    /// Keep the old file as `file~`
+    #[value(name = "simple")]
+    Simple,
+    /// Keep every old version as `file.~1~`, `file.~2~`, ...
+    #[value(name = "numbered")]
+    Numbered,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Compression {
+    /// Zstandard, written with a `.zst` suffix
+    #[value(name = "zstd")]
+    Zstd,
+}
+
+impl Compression {
+    /// File name suffix marking compressed files, without the dot
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Compression::Zstd => "zst",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum ColorChoice {
+    /// Color when writing to a terminal and NO_COLOR is unset
+    #[value(name = "auto")]
+    Auto,
+    /// Always emit colors
+    #[value(name = "always")]
+    Always,
+    /// Never emit colors
+    #[value(name = "never")]
+    Never,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ProgressTarget {
+    /// Standard output
+    #[value(name = "stdout")]
+    Stdout,
+    /// Standard error
+    #[value(name = "stderr")]
+    Stderr,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum SortOrder {
+    /// Whatever order the filesystem lists them in
+    #[default]
+    #[value(name = "none")]
+    None,
+    /// By file name
+    #[value(name = "name")]
+    Name,
+    /// Largest first, then by name
+    #[value(name = "size")]
+    Size,
+    /// Most recently modified first, then by name
+    #[value(name = "mtime")]
+    Mtime,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum CaseCollisionMode {
+    /// Warn, and let the later file overwrite the earlier one
+    #[default]
+    #[value(name = "warn")]
+    Warn,
+    /// Refuse to copy the tree
+    #[value(name = "error")]
+    Error,
+    /// Copy the later one as `name (2).ext`
+    #[value(name = "rename")]
+    Rename,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum TimeStyle {
+    /// Local time, e.g. `2024-05-01 14:03:27`
+    #[value(name = "local")]
+    Local,
+    /// RFC 3339 with the local offset, e.g. `2024-05-01T14:03:27+02:00`
+    #[value(name = "iso")]
+    Iso,
+    /// Age, e.g. `3 days ago`
+    #[value(name = "relative")]
+    Relative,
+    /// Seconds since the Unix epoch
+    #[value(name = "unix")]
+    Unix,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum LogFormat {
+    /// `COPY src -> dst (N bytes) OK`
+    #[value(name = "text")]
+    Text,
+    /// One JSON object per operation
+    #[value(name = "jsonl")]
+    Jsonl,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
 pub enum ReflinkMode {
     /// Try reflink, fall back to copy
     #[value(name = "auto")]
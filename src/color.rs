@@ -0,0 +1,66 @@
+use std::sync::atomic::{AtomicU8, Ordering};
+use console::StyledObject;
+use crate::cli::ColorChoice;
+
+const UNDECIDED: u8 = 0;
+const ENABLED: u8 = 1;
+const DISABLED: u8 = 2;
+
+/// Whether styled output carries ANSI colors; left to `console`'s own
+/// terminal detection until [`init`] decides
+static COLORS: AtomicU8 = AtomicU8::new(UNDECIDED);
+
+/// Decide once, at startup, whether output is colored: `--color`, then
+/// `NO_COLOR`, then whether stdout is a terminal
+pub fn init(choice: ColorChoice) {
+    let no_color = std::env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty());
+    set_enabled(should_color(choice, no_color, console::colors_enabled()));
+}
+
+/// Turn colors on or off for everything styled from now on, including the
+/// progress bars drawn by `indicatif`
+pub fn set_enabled(enabled: bool) {
+    COLORS.store(if enabled { ENABLED } else { DISABLED }, Ordering::Relaxed);
+    console::set_colors_enabled(enabled);
+    console::set_colors_enabled_stderr(enabled);
+}
+
+/// Drop-in for `console::style` that honors `--color` and `NO_COLOR`
+pub fn style<D>(val: D) -> StyledObject<D> {
+    let styled = console::style(val);
+    match COLORS.load(Ordering::Relaxed) {
+        ENABLED => styled.force_styling(true),
+        DISABLED => styled.force_styling(false),
+        _ => styled,
+    }
+}
+
+fn should_color(choice: ColorChoice, no_color: bool, is_terminal: bool) -> bool {
+    match choice {
+        ColorChoice::Always => true,
+        ColorChoice::Never => false,
+        ColorChoice::Auto => !no_color && is_terminal,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_color_decision() {
+        assert!(should_color(ColorChoice::Always, true, false));
+        assert!(!should_color(ColorChoice::Never, false, true));
+        assert!(should_color(ColorChoice::Auto, false, true));
+        assert!(!should_color(ColorChoice::Auto, true, true));
+        assert!(!should_color(ColorChoice::Auto, false, false));
+    }
+
+    #[test]
+    fn test_disabled_colors_emit_no_escapes() {
+        set_enabled(false);
+        let plain = format!("{} {}", style("✓").green(), style("Target exists").yellow().bold());
+        assert!(!plain.contains('\x1b'));
+        assert_eq!(plain, "✓ Target exists");
+    }
+}
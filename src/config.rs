@@ -2,10 +2,17 @@ use serde::{Deserialize, Serialize};
 use std::fs;
 use std::path::{Path, PathBuf};
 use crate::error::{Error, Result};
+use crate::preserve::PreserveSet;
+
+/// Schema version of the config files this build reads and writes
+pub const CONFIG_VERSION: u32 = 1;
 
 /// Configuration for better-cp
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Config {
+    /// Schema version the file was written for; files from before the
+    /// field existed are version 0 and get migrated on load
+    pub version: u32,
     pub defaults: Defaults,
     pub behavior: Behavior,
     pub performance: Performance,
@@ -28,6 +35,9 @@ pub struct Behavior {
     pub preserve_times: bool,
     pub preserve_permissions: bool,
     pub atomic: bool,
+    /// Flush copies to disk before reporting them done (`--fsync`)
+    #[serde(default)]
+    pub fsync: bool,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -47,6 +57,7 @@ pub struct UiConfig {
 impl Default for Config {
     fn default() -> Self {
         Self {
+            version: CONFIG_VERSION,
             defaults: Defaults {
                 overwrite: "prompt".to_string(),
                 resume: true,
@@ -60,11 +71,12 @@ impl Default for Config {
                 preserve_times: true,
                 preserve_permissions: true,
                 atomic: true,
+                fsync: false,
             },
             performance: Performance {
                 buffer_size: "64M".to_string(),
                 chunk_size: "100M".to_string(),
-                resume_threshold: "100M".to_string(),
+                resume_threshold: "100M,30s".to_string(),
             },
             ui: UiConfig {
                 color: true,
@@ -75,6 +87,17 @@ impl Default for Config {
     }
 }
 
+impl Behavior {
+    /// The preservation steps these settings turn on
+    pub fn preserve_set(&self) -> PreserveSet {
+        PreserveSet {
+            mode: self.preserve_permissions,
+            timestamps: self.preserve_times,
+            ..PreserveSet::default()
+        }
+    }
+}
+
 impl Config {
     /// Load config from standard locations
     pub fn load() -> Result<Config> {
@@ -99,11 +122,35 @@ impl Config {
         Ok(Self::default())
     }
 
-    /// Load config from specific file
+    /// Load config from specific file. One written for an older schema is
+    /// migrated to the current one; one for a newer schema is an error
+    /// rather than something to half understand.
     pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Config> {
         let content = fs::read_to_string(path)
             .map_err(|e| Error::ConfigError(format!("Failed to read config: {}", e)))?;
-        toml::from_str(&content)
+        let mut table: toml::Table = toml::from_str(&content)
+            .map_err(|e| Error::ConfigError(format!("Failed to parse TOML: {}", e)))?;
+
+        let version = match table.get("version") {
+            None => 0,
+            Some(toml::Value::Integer(version)) if *version >= 0 => *version as u64,
+            Some(other) => {
+                return Err(Error::ConfigError(format!("Invalid schema version: {}", other)));
+            }
+        };
+        if version > CONFIG_VERSION as u64 {
+            return Err(Error::ConfigError(format!(
+                "The config is for schema version {}, but this better-cp only reads up to version {}; \
+                 upgrade better-cp or rewrite the config for version {}",
+                version, CONFIG_VERSION, CONFIG_VERSION
+            )));
+        }
+        if version == 0 {
+            migrate_v0(&mut table);
+        }
+
+        toml::Value::Table(table)
+            .try_into()
             .map_err(|e| Error::ConfigError(format!("Failed to parse TOML: {}", e)))
     }
 
@@ -140,6 +187,19 @@ impl Config {
     }
 }
 
+/// Bring a version 0 config (one without a `version`) to version 1, which
+/// renamed `ui.progress` to `ui.progress_style`
+fn migrate_v0(table: &mut toml::Table) {
+    if let Some(toml::Value::Table(ui)) = table.get_mut("ui") {
+        if !ui.contains_key("progress_style") {
+            if let Some(progress) = ui.remove("progress") {
+                ui.insert("progress_style".to_string(), progress);
+            }
+        }
+    }
+    table.insert("version".to_string(), toml::Value::Integer(1));
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -151,6 +211,35 @@ mod tests {
         assert!(config.behavior.atomic);
     }
 
+    #[test]
+    fn test_config_schema_versions() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join("config.toml");
+
+        // The current layout round-trips with its version
+        Config::default().save(&path).unwrap();
+        assert_eq!(Config::from_file(&path).unwrap().version, CONFIG_VERSION);
+
+        // A versionless legacy config is migrated
+        let legacy = toml::to_string(&Config::default())
+            .unwrap()
+            .replace("version = 1\n", "")
+            .replace("progress_style = \"bars\"", "progress = \"minimal\"");
+        assert!(!legacy.contains("version") && legacy.contains("progress = "));
+        fs::write(&path, legacy).unwrap();
+        let config = Config::from_file(&path).unwrap();
+        assert_eq!(config.version, CONFIG_VERSION);
+        assert_eq!(config.ui.progress_style, "minimal");
+
+        // A config from the future is refused, naming both versions
+        let future = toml::to_string(&Config::default()).unwrap().replace("version = 1", "version = 99");
+        fs::write(&path, future).unwrap();
+        match Config::from_file(&path) {
+            Err(Error::ConfigError(msg)) => assert!(msg.contains("version 99") && msg.contains("version 1"), "{}", msg),
+            other => panic!("expected a ConfigError, got {:?}", other),
+        }
+    }
+
     #[test]
     fn test_parse_size() {
         assert_eq!(Config::parse_size("64M").unwrap(), 64 * 1024 * 1024);
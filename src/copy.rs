@@ -1,14 +1,159 @@
+use std::collections::HashMap;
+use std::ffi::OsStr;
 use std::fs::{self, File, Metadata};
-use std::io::{Read, Write, Seek, SeekFrom};
+use std::io::{self, BufWriter, Read, Write, Seek, SeekFrom};
 use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
 use crate::error::{Error, Result};
-use crate::progress::ProgressTracker;
-use crate::verify::compute_checksum;
-use crate::cli::OverwriteMode;
-use crate::prompt::{self, OverwriteChoice};
-use crate::resume::ResumeState;
+use crate::progress::{ProgressMode, ProgressTracker, TransferStats};
+use crate::verify::{
+    checksum_reader, compute_checksum, first_difference, verify_tree, BlockVerdict, BlockVerifier,
+    StreamingChecksum, VerifyCheckpoint, MMAP_COMPARE_LIMIT,
+};
+use crate::cli::{BackupMode, CaseCollisionMode, Compression, ConflictPolicy, OverwriteMode, ReflinkMode, VerifyMode};
+use crate::output::warn;
+use crate::preflight::is_case_insensitive;
+use crate::backup::backup_existing;
+use crate::removal::remove_path;
+use crate::prompt::OverwriteChoice;
+use crate::options::CopyOptions;
+use crate::durability::{sync_file, sync_parent_dir};
+use crate::resume::{DirResumeState, ResumeInterval, ResumeState};
+use crate::filter::WalkFilter;
+use crate::plan::{
+    file_action, link_action, plan_tree, resolve_case_collisions, strip_components, PlanEntry, PlanKind, PlannedAction,
+};
+use crate::interrupt::{is_interrupted, InterruptFlag};
+use crate::preserve::{apply_metadata, hard_link_key, PreserveSet};
+use crate::watchdog::read_within;
+use crate::manifest::{ChecksumManifest, ExpectedChecksums};
+use crate::report::RunStats;
+use crate::reflink::{data_extents, is_sparse_file, try_reflink};
+use crate::mirror::delete_extraneous;
+use crate::sink::{preallocate, CopySink, LocalFileSink};
+use crate::chmod::ChmodSpec;
+use crate::json_output::{FileResult, FileResults};
+use crate::output::Logger;
 
 const CHUNK_SIZE: usize = 64 * 1024 * 1024; // 64 MB chunks
+const MIN_BUFFER_SIZE: usize = 64 * 1024; // 64 KB, however small the file
+
+/// Zstandard level used by `--compress zstd` (the library default)
+const ZSTD_LEVEL: i32 = 3;
+
+/// How file contents are transformed on their way to the target
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Transcode {
+    Compress(Compression),
+    Decompress(Compression),
+}
+
+impl Transcode {
+    /// Where a copy to `target` is written: the suffix is appended when
+    /// compressing, and dropped (if present) when decompressing
+    pub fn target_path(&self, target: &Path) -> PathBuf {
+        match self {
+            Transcode::Compress(compression) => {
+                let mut name = target.as_os_str().to_os_string();
+                name.push(".");
+                name.push(compression.extension());
+                PathBuf::from(name)
+            }
+            Transcode::Decompress(compression) => {
+                if target.extension() == Some(OsStr::new(compression.extension())) {
+                    target.with_extension("")
+                } else {
+                    target.to_path_buf()
+                }
+            }
+        }
+    }
+
+    /// Stream everything from `reader` into `writer`, compressing or decompressing
+    fn stream<R: Read, W: Write>(&self, mut reader: R, mut writer: W) -> io::Result<()> {
+        match self {
+            Transcode::Compress(Compression::Zstd) => {
+                let mut encoder = zstd::Encoder::new(writer, ZSTD_LEVEL)?;
+                io::copy(&mut reader, &mut encoder)?;
+                encoder.finish()?.flush()
+            }
+            Transcode::Decompress(Compression::Zstd) => {
+                io::copy(&mut zstd::Decoder::new(reader)?, &mut writer)?;
+                writer.flush()
+            }
+        }
+    }
+
+    /// Digests of the uncompressed contents on each side, source first
+    fn plain_digests(&self, source: &Path, target: &Path) -> io::Result<(String, String)> {
+        let decoded = |compression: &Compression, path: &Path| match compression {
+            Compression::Zstd => checksum_reader(zstd::Decoder::new(File::open(path)?)?),
+        };
+        match self {
+            Transcode::Compress(compression) => Ok((compute_checksum(source, None)?, decoded(compression, target)?)),
+            Transcode::Decompress(compression) => Ok((decoded(compression, source)?, compute_checksum(target, None)?)),
+        }
+    }
+}
+
+/// Source reader for transcoded copies: counts bytes into the tracker and
+/// stops once an interrupt is raised
+struct TrackedReader<'a> {
+    file: File,
+    tracker: &'a ProgressTracker,
+    interrupt: &'a InterruptFlag,
+}
+
+impl Read for TrackedReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if is_interrupted(self.interrupt) {
+            return Err(io::Error::other("interrupted"));
+        }
+        let bytes_read = self.file.read(buf)?;
+        self.tracker.add_bytes(bytes_read as u64);
+        Ok(bytes_read)
+    }
+}
+
+/// The reading half of the copy loop: fill each buffer from `src_file` in
+/// turn (starting with `spare`, then whatever comes back on `empty`) and send
+/// it with the byte count read. Stops after the end of the file (a count of
+/// 0) or an error, or once the writer hangs up.
+async fn read_chunks(
+    mut src_file: io::Take<File>,
+    source: PathBuf,
+    timeout: Option<Duration>,
+    mut spare: Vec<Vec<u8>>,
+    mut empty: mpsc::Receiver<Vec<u8>>,
+    filled: mpsc::Sender<Result<(Vec<u8>, usize)>>,
+) {
+    loop {
+        let next = match spare.pop() {
+            Some(buffer) => Some(buffer),
+            None => empty.recv().await,
+        };
+        let Some(mut buffer) = next else {
+            return;
+        };
+        let read = read_within(timeout, &source, move || {
+            let bytes_read = src_file.read(&mut buffer)?;
+            Ok((src_file, buffer, bytes_read))
+        })
+        .await;
+        let (file, buffer, bytes_read) = match read {
+            Ok(read) => read,
+            Err(e) => {
+                filled.send(Err(e)).await.ok();
+                return;
+            }
+        };
+        src_file = file;
+        if filled.send(Ok((buffer, bytes_read))).await.is_err() || bytes_read == 0 {
+            return;
+        }
+    }
+}
 
 /// Copy a single file with progress tracking and resume support
 pub struct FileCopier {
@@ -18,6 +163,33 @@ pub struct FileCopier {
     verify: bool,
     resume: bool,
     atomic: bool,
+    progress: ProgressMode,
+    remove_source_files: bool,
+    interrupt: InterruptFlag,
+    chunk_size: usize,
+    preserve: PreserveSet,
+    backup: BackupMode,
+    trash: bool,
+    update_inplace: bool,
+    timeout: Option<Duration>,
+    manifest: Option<ChecksumManifest>,
+    verify_source: Option<ExpectedChecksums>,
+    transcode: Option<Transcode>,
+    run_stats: Option<RunStats>,
+    reflink: ReflinkMode,
+    verify_mode: VerifyMode,
+    resume_interval: ResumeInterval,
+    fsync: bool,
+    preallocate: bool,
+    update: bool,
+    chmod: Option<ChmodSpec>,
+    sparse: bool,
+    touch_only: bool,
+    attrs_only: bool,
+    skip: u64,
+    count: Option<u64>,
+    pipeline: bool,
+    options: CopyOptions,
 }
 
 impl FileCopier {
@@ -36,11 +208,256 @@ impl FileCopier {
             verify,
             resume,
             atomic,
+            progress: ProgressMode::Bar,
+            remove_source_files: false,
+            interrupt: InterruptFlag::default(),
+            chunk_size: CHUNK_SIZE,
+            preserve: PreserveSet::default(),
+            backup: BackupMode::None,
+            trash: false,
+            update_inplace: false,
+            timeout: None,
+            manifest: None,
+            verify_source: None,
+            transcode: None,
+            run_stats: None,
+            reflink: ReflinkMode::Never,
+            verify_mode: VerifyMode::Fast,
+            resume_interval: ResumeInterval::default(),
+            fsync: false,
+            preallocate: false,
+            update: false,
+            chmod: None,
+            sparse: false,
+            touch_only: false,
+            attrs_only: false,
+            skip: 0,
+            count: None,
+            pipeline: false,
+            options: CopyOptions::interactive(),
+        }
+    }
+
+    /// Choose how progress is reported (a bar by default); accepts a bool
+    pub fn with_progress(mut self, progress: impl Into<ProgressMode>) -> Self {
+        self.progress = progress.into();
+        self
+    }
+
+    /// Delete the source once the copy has been verified (a verified move)
+    pub fn with_remove_source_files(mut self, remove_source_files: bool) -> Self {
+        self.remove_source_files = remove_source_files;
+        self
+    }
+
+    /// Stop between chunks, saving resume state, once this flag is raised
+    pub fn with_interrupt(mut self, interrupt: InterruptFlag) -> Self {
+        self.interrupt = interrupt;
+        self
+    }
+
+    /// Attributes to carry over once the content is written (none by default)
+    pub fn with_preserve(mut self, preserve: PreserveSet) -> Self {
+        self.preserve = preserve;
+        self
+    }
+
+    /// Rename an existing target to a backup before overwriting it
+    pub fn with_backup(mut self, backup: BackupMode) -> Self {
+        self.backup = backup;
+        self
+    }
+
+    /// Send an existing target to the trash before overwriting it, unless
+    /// it was backed up
+    pub fn with_trash(mut self, trash: bool) -> Self {
+        self.trash = trash;
+        self
+    }
+
+    /// Rewrite only the changed blocks of an existing target of the same size
+    pub fn with_update_inplace(mut self, update_inplace: bool) -> Self {
+        self.update_inplace = update_inplace;
+        self
+    }
+
+    /// Fail with `Error::Timeout` when reading one chunk takes longer than this
+    pub fn with_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Record the digest of the copied file in this manifest
+    pub fn with_manifest(mut self, manifest: Option<ChecksumManifest>) -> Self {
+        self.manifest = manifest;
+        self
+    }
+
+    /// Check the source against the digest these checksums list for it, if
+    /// any, as it is read. A source that doesn't match fails the copy with
+    /// `Error::SourceCorrupted` and nothing is left at the target.
+    pub fn with_verify_source(mut self, verify_source: Option<ExpectedChecksums>) -> Self {
+        self.verify_source = verify_source;
+        self
+    }
+
+    /// Count the copied file towards the `--stats` report
+    pub fn with_run_stats(mut self, run_stats: Option<RunStats>) -> Self {
+        self.run_stats = run_stats;
+        self
+    }
+
+    /// Compress or decompress the contents on the way, adding or dropping
+    /// the target's suffix to match. Such copies are never resumed.
+    pub fn with_transcode(mut self, transcode: Option<Transcode>) -> Self {
+        if let Some(transcode) = transcode {
+            self.target = transcode.target_path(&self.target);
+        }
+        self.transcode = transcode;
+        self
+    }
+
+    /// Clone the source's blocks instead of copying its bytes where the
+    /// filesystem allows (never by default). Resumed and transcoded copies
+    /// always copy bytes.
+    pub fn with_reflink(mut self, reflink: ReflinkMode) -> Self {
+        self.reflink = reflink;
+        self
+    }
+
+    /// How thoroughly to verify (`fast` by default). Only `full` re-reads a
+    /// reflinked target, whose blocks are the source's own.
+    pub fn with_verify_mode(mut self, verify_mode: VerifyMode) -> Self {
+        self.verify_mode = verify_mode;
+        self
+    }
+
+    /// How often the resume state is saved while copying (every 100 MiB or
+    /// 30 seconds by default)
+    pub fn with_resume_interval(mut self, resume_interval: ResumeInterval) -> Self {
+        self.resume_interval = resume_interval;
+        self
+    }
+
+    /// Flush the target to disk before it is renamed into place, and its
+    /// directory after, so a finished copy survives a power loss
+    pub fn with_fsync(mut self, fsync: bool) -> Self {
+        self.fsync = fsync;
+        self
+    }
+
+    /// Reserve the target's full size before writing it, so it is laid out
+    /// contiguously and a full disk fails up front
+    pub fn with_preallocate(mut self, preallocate: bool) -> Self {
+        self.preallocate = preallocate;
+        self
+    }
+
+    /// Skip the copy, before the source is opened, when the target exists
+    /// and is at least as new as the source (`cp -u`)
+    pub fn with_update(mut self, update: bool) -> Self {
+        self.update = update;
+        self
+    }
+
+    /// Give the target this mode once copied, overriding a preserved one
+    pub fn with_chmod(mut self, chmod: Option<ChmodSpec>) -> Self {
+        self.chmod = chmod;
+        self
+    }
+
+    /// Keep the holes of a sparse source: only its data extents are read
+    /// and written, and the target is left sparse in the same places
+    pub fn with_sparse(mut self, sparse: bool) -> Self {
+        self.sparse = sparse;
+        self
+    }
+
+    /// When the target already holds exactly the source's bytes, only bring
+    /// over the source's timestamps (and the rest of the preserve set)
+    /// instead of copying; such a file counts in `files_touched`
+    pub fn with_touch_only(mut self, touch_only: bool) -> Self {
+        self.touch_only = touch_only;
+        self
+    }
+
+    /// Copy no bytes: only apply the preserve set to a target that must
+    /// already exist with the source's size (`--attrs-only`). The file
+    /// counts in `files_touched`.
+    pub fn with_attrs_only(mut self, attrs_only: bool) -> Self {
+        self.attrs_only = attrs_only;
+        self
+    }
+
+    /// Copy only `count` bytes (or the rest) starting `skip` bytes into the
+    /// source, to the start of the target, like `dd` (`--skip`, `--count`).
+    /// Such a partial copy is never resumed or verified against the source.
+    pub fn with_range(mut self, skip: u64, count: Option<u64>) -> Self {
+        self.skip = skip;
+        self.count = count;
+        self
+    }
+
+    /// Whether `--skip` or `--count` limit the copy to part of the source
+    fn is_partial(&self) -> bool {
+        self.skip > 0 || self.count.is_some()
+    }
+
+    /// The bytes of a `source_size`-byte source to copy, as (offset, length).
+    /// A range reaching past the end of the source is a usage error.
+    fn byte_range(&self, source_size: u64) -> Result<(u64, u64)> {
+        let count = self.count.unwrap_or(source_size.saturating_sub(self.skip));
+        match self.skip.checked_add(count) {
+            Some(end) if end <= source_size => Ok((self.skip, count)),
+            _ => Err(Error::Usage(format!(
+                "--skip {} --count {} reaches past the end of {} ({} bytes)",
+                self.skip,
+                count,
+                self.source.display(),
+                source_size
+            ))),
         }
     }
 
-    /// Execute the copy operation
-    pub async fn copy(&self) -> Result<()> {
+    /// Read the next chunk while the last one is being written (off by
+    /// default, `--pipeline`). Off, the copy loop strictly alternates reads
+    /// and writes and holds one chunk in memory instead of two.
+    pub fn with_pipeline(mut self, pipeline: bool) -> Self {
+        self.pipeline = pipeline;
+        self
+    }
+
+    /// Hooks for conflicts, resume decisions and progress (terminal prompts by default)
+    pub fn with_options(mut self, options: CopyOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Largest read buffer a copy uses (`--buffer`, 64 MB by default).
+    /// Files smaller than that get a buffer their own size.
+    pub fn with_buffer_size(mut self, buffer_size: usize) -> Self {
+        self.chunk_size = buffer_size;
+        self
+    }
+
+    /// Size of the buffer to copy a `file_size`-byte file through
+    fn buffer_size(&self, file_size: u64) -> usize {
+        buffer_size_for(file_size, self.chunk_size)
+    }
+
+    /// Execute the copy operation, returning the transfer statistics
+    pub async fn copy(&self) -> Result<TransferStats> {
+        if self.remove_source_files && !self.verify {
+            return Err(Error::Usage(
+                "--remove-source-files requires verification; drop --no-verify".to_string(),
+            ));
+        }
+        if self.remove_source_files && self.is_partial() {
+            return Err(Error::Usage(
+                "--remove-source-files can't follow a partial copy (--skip, --count)".to_string(),
+            ));
+        }
+
         // Validate source exists
         let src_metadata = fs::metadata(&self.source)
             .map_err(|_| Error::SourceNotFound(self.source.to_string_lossy().to_string()))?;
@@ -48,11 +465,25 @@ impl FileCopier {
         if !src_metadata.is_file() {
             return Err(Error::Custom("Source is not a file".to_string()));
         }
+        if self.update && self.is_up_to_date(&src_metadata) {
+            return Err(Error::Skipped(self.target.to_string_lossy().to_string()));
+        }
+        if self.attrs_only {
+            return self.copy_attrs(&src_metadata);
+        }
+        if self.is_partial() {
+            let range = self.byte_range(src_metadata.len())?;
+            if self.target.exists() {
+                self.handle_overwrite(&src_metadata)?;
+            }
+            create_parent_dirs(&self.target)?;
+            return self.perform_copy(&src_metadata, range, None).await;
+        }
 
         let total_size = src_metadata.len();
 
         // Check for existing resume state
-        let mut resume_state = if self.resume {
+        let mut resume_state = if self.resume && self.transcode.is_none() {
             ResumeState::load(&self.target)?
         } else {
             None
@@ -60,13 +491,14 @@ impl FileCopier {
 
         // Handle resume validation
         if let Some(ref state) = resume_state {
-            // Validate state is still valid
+            // Validate state is still valid, and was saved for this source
             state.validate()?;
-            
+            state.validate_source(&self.source, total_size)?;
+
             // If resume state exists, ask user if they want to resume
             let bytes_done = state.bytes_completed();
             if bytes_done > 0 && bytes_done < total_size {
-                let should_resume = prompt::prompt_resume(
+                let should_resume = (self.options.on_resume)(
                     &self.source,
                     &self.target,
                     total_size,
@@ -84,292 +516,3535 @@ impl FileCopier {
             }
         }
 
+        // Stopped while verifying: only the verification is left to finish
+        if let Some(state) = &resume_state {
+            let target_len = fs::metadata(&self.target).map(|m| m.len()).ok();
+            if state.bytes_completed() == total_size && target_len == Some(total_size) && !self.is_partial() {
+                return self.resume_verification(state, &src_metadata);
+            }
+        }
+
+        // An identical target isn't rewritten, or asked about
+        if self.touch_only && resume_state.is_none() && self.transcode.is_none() {
+            if let Some(stats) = self.touch_identical(&src_metadata)? {
+                return Ok(stats);
+            }
+        }
+
         // Check if target exists and handle overwrite logic
         if self.target.exists() && resume_state.is_none() {
             self.handle_overwrite(&src_metadata)?;
+
+            // A backup has moved the old target away, leaving nothing to diff against
+            let same_size = fs::metadata(&self.target)
+                .is_ok_and(|metadata| metadata.is_file() && metadata.len() == total_size);
+            if self.update_inplace && !self.atomic && self.transcode.is_none() && same_size {
+                return self.update_in_place(&src_metadata);
+            }
         }
 
-        // Create parent directories if needed
-        if let Some(parent) = self.target.parent() {
-            fs::create_dir_all(parent)
-                .map_err(|e| Error::Custom(format!("Failed to create parent directory: {}", e)))?;
+        if self.atomic {
+            self.handle_leftover_temp(resume_state.as_ref())?;
         }
 
-        // Perform the copy
-        self.perform_copy(&src_metadata, resume_state).await?;
+        create_parent_dirs(&self.target)?;
 
-        Ok(())
+        if let Some(transcode) = self.transcode {
+            return self.transcode_copy(transcode, &src_metadata);
+        }
+
+        if resume_state.is_none() {
+            if let Some(stats) = self.reflink_copy(&src_metadata)? {
+                return Ok(stats);
+            }
+            if self.sparse && is_sparse_file(&self.source)? {
+                return self.sparse_copy(&src_metadata);
+            }
+        }
+
+        // Perform the copy
+        self.perform_copy(&src_metadata, (0, total_size), resume_state).await
     }
 
-    async fn perform_copy(&self, src_metadata: &Metadata, mut resume_state: Option<ResumeState>) -> Result<()> {
-        let total_size = src_metadata.len();
-        let tracker = ProgressTracker::new(total_size, true);
+    /// Finish a copy whose every byte was written before it stopped, most
+    /// likely while verifying: verify it, continuing from any
+    /// `VerifyCheckpoint`, and settle it as `perform_copy` would have
+    fn resume_verification(&self, state: &ResumeState, src_metadata: &Metadata) -> Result<TransferStats> {
+        let tracker = ProgressTracker::with_mode(state.total_size, self.progress);
+        tracker.add_bytes(state.total_size);
 
-        // Use temporary file if atomic mode
+        self.apply_attributes(src_metadata, &self.preserve)?;
+        if let Err(e) = self.verify_and_release_source(false, Some(state.total_size)) {
+            tracker.finish();
+            return Err(e);
+        }
+
+        state.cleanup().ok();
+        let marker = Self::corrupt_marker_path(&self.target);
+        if marker.exists() {
+            fs::remove_file(&marker).ok();
+        }
+
+        tracker.finish();
+        let stats = tracker.get_stats();
+        self.options.report_progress(stats.clone());
+        Ok(stats)
+    }
+
+    /// Stream the source through `transcode` into the target. Progress
+    /// follows the source bytes read, as the output size isn't known up front.
+    fn transcode_copy(&self, transcode: Transcode, src_metadata: &Metadata) -> Result<TransferStats> {
+        self.check_source()?;
+        let tracker = ProgressTracker::with_mode(src_metadata.len(), self.progress);
         let write_target = if self.atomic {
-            let mut temp_path = self.target.clone();
-            temp_path.set_extension("tmp");
-            temp_path
+            atomic_temp_path(&self.target)
         } else {
             self.target.clone()
         };
 
-        // Open files
-        let mut src_file = File::open(&self.source)
-            .map_err(Error::Io)?;
-        
-        // For resume, open in append mode; otherwise create/truncate
-        let mut dst_file = if resume_state.is_some() {
-            File::options()
-                .write(true)
-                .create(true)
-                .truncate(false)
-                .open(&write_target)
-                .map_err(Error::Io)?
-        } else {
-            File::create(&write_target)
-                .map_err(Error::Io)?
+        let reader = TrackedReader {
+            file: File::open(&self.source).map_err(Error::Io)?,
+            tracker: &tracker,
+            interrupt: &self.interrupt,
         };
+        let writer = BufWriter::new(File::create(&write_target).map_err(Error::Io)?);
+        if let Err(e) = transcode.stream(reader, writer) {
+            tracker.finish();
+            // A half-written stream can't be resumed, so don't leave it behind
+            fs::remove_file(&write_target).ok();
+            return Err(if is_interrupted(&self.interrupt) {
+                Error::Interrupted(self.target.to_string_lossy().to_string())
+            } else {
+                Error::Io(e)
+            });
+        }
 
-        // If resuming, seek to the last completed position
-        let mut current_offset: u64 = 0;
-        if let Some(ref state) = resume_state {
-            let bytes_done = state.bytes_completed();
-            src_file.seek(SeekFrom::Start(bytes_done))
-                .map_err(Error::Io)?;
-            dst_file.seek(SeekFrom::End(0))
+        self.sync_written(&write_target)?;
+        if self.atomic {
+            fs::rename(&write_target, &self.target)
                 .map_err(Error::Io)?;
-            current_offset = bytes_done;
-            
-            // Report already-transferred bytes
-            tracker.add_bytes(bytes_done);
         }
+        self.sync_target_dir()?;
 
-        // Create or update resume state
-        if resume_state.is_none() {
-            resume_state = Some(ResumeState::new(
-                self.source.clone(),
-                self.target.clone(),
-                total_size,
-            ));
+        self.apply_attributes(src_metadata, &self.preserve)?;
+        self.verify_and_release_source(false, None)?;
+
+        tracker.finish();
+        let stats = tracker.get_stats();
+        self.options.report_progress(stats.clone());
+        Ok(stats)
+    }
+
+    /// Clone the source into the target as `--reflink` asks, returning
+    /// `None` when a regular copy should run instead
+    fn reflink_copy(&self, src_metadata: &Metadata) -> Result<Option<TransferStats>> {
+        let write_target = if self.atomic {
+            atomic_temp_path(&self.target)
+        } else {
+            self.target.clone()
+        };
+
+        let cloned = try_reflink(&self.source, &write_target, self.reflink).and_then(|cloned| {
+            if cloned {
+                self.check_source()?;
+            }
+            Ok(cloned)
+        });
+        match cloned {
+            Ok(true) => {}
+            Ok(false) => return Ok(None),
+            Err(e) => {
+                // Don't leave the empty file (or the clone of a bad source) behind
+                fs::remove_file(&write_target).ok();
+                return Err(e);
+            }
         }
 
-        // Copy in chunks
-        let mut buffer = vec![0; CHUNK_SIZE];
-        loop {
-            let bytes_read = src_file.read(&mut buffer)
+        self.sync_written(&write_target)?;
+        if self.atomic {
+            fs::rename(&write_target, &self.target)
                 .map_err(Error::Io)?;
+        }
+        self.sync_target_dir()?;
 
-            if bytes_read == 0 {
-                break;
-            }
+        let tracker = ProgressTracker::with_mode(src_metadata.len(), self.progress);
+        tracker.add_logical_bytes(src_metadata.len());
+        tracker.set_completed_reason("reflink");
+
+        self.apply_attributes(src_metadata, &self.preserve)?;
+        self.verify_and_release_source(true, None)?;
+
+        tracker.finish();
+        let stats = tracker.get_stats();
+        self.options.report_progress(stats.clone());
+        Ok(Some(stats))
+    }
+
+    /// Copy only the data extents of a sparse source into a target of the
+    /// same length, so the holes between them stay holes. Such a copy isn't
+    /// resumable; an interrupted one is removed.
+    fn sparse_copy(&self, src_metadata: &Metadata) -> Result<TransferStats> {
+        self.check_source()?;
+        let total_size = src_metadata.len();
+        let tracker = ProgressTracker::with_mode(total_size, self.progress);
+        let write_target = if self.atomic {
+            atomic_temp_path(&self.target)
+        } else {
+            self.target.clone()
+        };
+
+        let mut src_file = File::open(&self.source)
+            .map_err(Error::Io)?;
+        let mut dst_file = File::create(&write_target)
+            .map_err(Error::Io)?;
+        dst_file.set_len(total_size)
+            .map_err(Error::Io)?;
 
-            dst_file.write_all(&buffer[..bytes_read])
+        let mut buffer = vec![0; self.buffer_size(total_size)];
+        let mut offset = 0;
+        let mut holes = 0;
+        for (start, len) in data_extents(&self.source, self.chunk_size)? {
+            // The hole before this extent counts as copied
+            tracker.add_logical_bytes(start - offset);
+            holes += start - offset;
+            src_file.seek(SeekFrom::Start(start))
+                .map_err(Error::Io)?;
+            dst_file.seek(SeekFrom::Start(start))
                 .map_err(Error::Io)?;
 
-            tracker.add_bytes(bytes_read as u64);
-            current_offset += bytes_read as u64;
-
-            // Update resume state periodically (every 100MB)
-            if let Some(ref mut state) = resume_state {
-                if current_offset.is_multiple_of(100 * 1024 * 1024) || bytes_read == 0 {
-                    state.mark_chunk_done(
-                        current_offset.saturating_sub(bytes_read as u64),
-                        bytes_read as u64,
-                        None, // Skip per-chunk checksums for speed
-                    );
-                    state.save().ok(); // Best effort save, don't fail if it fails
+            let mut remaining = len;
+            while remaining > 0 {
+                let chunk = remaining.min(buffer.len() as u64) as usize;
+                src_file.read_exact(&mut buffer[..chunk])
+                    .map_err(Error::Io)?;
+                dst_file.write_all(&buffer[..chunk])
+                    .map_err(Error::Io)?;
+                remaining -= chunk as u64;
+                tracker.add_bytes(chunk as u64);
+                self.options.report_progress(tracker.get_stats());
+
+                if is_interrupted(&self.interrupt) {
+                    tracker.finish();
+                    drop(dst_file);
+                    fs::remove_file(&write_target).ok();
+                    return Err(Error::Interrupted(self.target.to_string_lossy().to_string()));
                 }
             }
+            offset = start + len;
+        }
+        tracker.add_logical_bytes(total_size - offset);
+        holes += total_size - offset;
+        if holes > 0 {
+            tracker.set_completed_reason(format!(
+                "sparse: {} skipped",
+                humansize::format_size(holes, humansize::BINARY)
+            ));
         }
 
+        if self.fsync {
+            sync_file(&dst_file, &write_target)?;
+        }
         drop(src_file);
         drop(dst_file);
 
-        // If atomic, rename temp file to target
         if self.atomic {
             fs::rename(&write_target, &self.target)
                 .map_err(Error::Io)?;
         }
-
-        // Verify checksum if requested
-        if self.verify {
-            self.verify_copy()?;
+        if self.fsync {
+            sync_parent_dir(&self.target)?;
         }
 
-        // Clean up resume state on success
-        if let Some(ref state) = resume_state {
-            state.cleanup().ok();
-        }
+        self.apply_attributes(src_metadata, &self.preserve)?;
+        self.verify_and_release_source(false, None)?;
 
         tracker.finish();
-        Ok(())
+        let stats = tracker.get_stats();
+        self.options.report_progress(stats.clone());
+        Ok(stats)
     }
 
-    fn handle_overwrite(&self, src_metadata: &Metadata) -> Result<()> {
-        match self.overwrite_mode {
-            OverwriteMode::Never => {
-                Err(Error::TargetExists(self.target.to_string_lossy().to_string()))
-            }
-            OverwriteMode::Always => Ok(()),
-            OverwriteMode::Prompt => {
-                let tgt_metadata = fs::metadata(&self.target)
+    /// Copy `length` bytes from `start` in the source to the target, the
+    /// whole file unless `--skip` or `--count` narrowed it
+    async fn perform_copy(
+        &self,
+        src_metadata: &Metadata,
+        (start, total_size): (u64, u64),
+        mut resume_state: Option<ResumeState>,
+    ) -> Result<TransferStats> {
+        let tracker = ProgressTracker::with_mode(total_size, self.progress);
+
+        // Use temporary file if atomic mode
+        let write_target = if self.atomic {
+            atomic_temp_path(&self.target)
+        } else {
+            self.target.clone()
+        };
+
+        let mut src_file = File::open(&self.source)
+            .map_err(Error::Io)?;
+        src_file.seek(SeekFrom::Start(start))
+            .map_err(Error::Io)?;
+
+        // If resuming, continue after the last recorded offset
+        let resumed_from = resume_state.as_ref().map(|state| state.bytes_completed());
+        // Rewriting the target voids any verification progress saved for it
+        VerifyCheckpoint::discard(&self.target);
+        let mut current_offset: u64 = 0;
+        let mut sink = match resume_state {
+            Some(ref state) => {
+                let bytes_done = state.bytes_completed();
+                let sink = LocalFileSink::resume(&write_target, &self.target, bytes_done)?;
+                src_file.seek(SeekFrom::Start(bytes_done))
                     .map_err(Error::Io)?;
-                
-                match prompt::prompt_overwrite(&self.target, src_metadata, &tgt_metadata)? {
-                    OverwriteChoice::Overwrite => Ok(()),
-                    OverwriteChoice::Skip => Err(Error::Custom("Skipped by user".to_string())),
-                    OverwriteChoice::Rename => Err(Error::Custom("Rename not yet implemented".to_string())),
-                    OverwriteChoice::Abort => Err(Error::UserAborted),
-                }
+                current_offset = bytes_done;
+
+                // Report already-transferred bytes
+                tracker.add_bytes(bytes_done);
+                sink
             }
-            OverwriteMode::Smart => {
-                let tgt_metadata = fs::metadata(&self.target)
-                    .map_err(Error::Io)?;
-                
-                let src_modified = src_metadata.modified()
-                    .map_err(Error::Io)?;
-                let tgt_modified = tgt_metadata.modified()
-                    .map_err(Error::Io)?;
+            None => LocalFileSink::create(&write_target, &self.target)?,
+        }
+        .with_fsync(self.fsync);
+        if self.preallocate {
+            sink.preallocate(total_size)?;
+        }
 
-                if src_modified > tgt_modified {
-                    Ok(())
-                } else {
-                    Err(Error::TargetExists(self.target.to_string_lossy().to_string()))
+        // Create or update resume state; a partial copy can't be resumed
+        // by a later run, which copies whole files
+        if resume_state.is_none() && !self.is_partial() {
+            resume_state = Some(ResumeState::new(
+                self.source.clone(),
+                self.target.clone(),
+                total_size,
+            ));
+        }
+
+        // --verify-source: hash the source as it goes by, starting with
+        // whatever an earlier run already copied
+        let mut source_checksum = None;
+        if self.expected_source_digest().is_some() {
+            let mut checksum = StreamingChecksum::default();
+            let mut copied = File::open(&self.source).map_err(Error::Io)?.take(current_offset);
+            io::copy(&mut copied, &mut checksum).map_err(Error::Io)?;
+            source_checksum = Some(checksum);
+        }
+
+        // A partial copy stops after its range; a whole one reads to the end
+        let limit = if self.is_partial() { total_size } else { u64::MAX };
+        let src_file = src_file.take(limit);
+        self.stream_into(src_file, &mut sink, &tracker, current_offset, &mut resume_state, &mut source_checksum)
+            .await?;
+
+        // A rotted source must not be published, nor resumed from
+        if let Some(checksum) = source_checksum {
+            if let Err(e) = self.check_source_digest(checksum.finish()) {
+                drop(sink);
+                fs::remove_file(&write_target).ok();
+                if let Some(ref state) = resume_state {
+                    state.cleanup().ok();
                 }
+                tracker.finish();
+                return Err(e);
+            }
+        }
+
+        sink.finalize()?;
+
+        self.apply_attributes(src_metadata, &self.preserve)?;
+
+        // Stopped while verifying: every byte is written, so the next run
+        // goes straight to the verification it left off
+        if let Err(e) = self.verify_and_release_source(false, resumed_from) {
+            if let (true, Some(state)) = (e.is_interrupt(), resume_state.as_mut()) {
+                state.advance_to(total_size);
+                state.save()?;
+                tracker.finish();
             }
+            return Err(e);
         }
+
+        // Clean up resume state and any stale corruption marker on success
+        if let Some(ref state) = resume_state {
+            state.cleanup().ok();
+        }
+        let marker = Self::corrupt_marker_path(&self.target);
+        if marker.exists() {
+            fs::remove_file(&marker).ok();
+        }
+
+        tracker.finish();
+        let stats = tracker.get_stats();
+        self.options.report_progress(stats.clone());
+        Ok(stats)
     }
 
-    fn verify_copy(&self) -> Result<()> {
-        let src_checksum = compute_checksum(&self.source)
-            .map_err(Error::Io)?;
-        let tgt_checksum = compute_checksum(&self.target)
+    /// Copy the source into `sink` rather than to the target path, for
+    /// destinations that aren't local files. Only `--verify-source` applies:
+    /// metadata, verification and resuming are up to the sink's backend.
+    pub async fn copy_into<S: CopySink>(&self, mut sink: S) -> Result<TransferStats> {
+        let total_size = fs::metadata(&self.source)
+            .map_err(|_| Error::SourceNotFound(self.source.to_string_lossy().to_string()))?
+            .len();
+        let tracker = ProgressTracker::with_mode(total_size, self.progress);
+        let src_file = File::open(&self.source)
             .map_err(Error::Io)?;
 
-        if src_checksum == tgt_checksum {
-            Ok(())
-        } else {
-            Err(Error::ChecksumMismatch {
-                expected: src_checksum,
-                actual: tgt_checksum,
-            })
-        }
+        let mut source_checksum = self.expected_source_digest().map(|_| StreamingChecksum::default());
+        let src_file = src_file.take(u64::MAX);
+        self.stream_into(src_file, &mut sink, &tracker, 0, &mut None, &mut source_checksum)
+            .await?;
+        let checked = source_checksum.map_or(Ok(()), |checksum| self.check_source_digest(checksum.finish()));
+        tracker.finish();
+        checked?;
+        sink.finalize()?;
+
+        let stats = tracker.get_stats();
+        self.options.report_progress(stats.clone());
+        Ok(stats)
     }
-}
+
+    /// The copy loop: read the source from `offset` (where `src_file` is
+    /// positioned) to its end in chunks and write each into `sink`, saving
+    /// `resume_state` every `--resume-interval` once the sink has flushed.
+    /// A Ctrl-C saves it one last time and ends with `Error::Interrupted`.
+    ///
+    /// Reading happens on a task of its own that hands filled buffers over a
+    /// channel and gets them back once written. With the pipeline there are
+    /// two buffers, so the next read overlaps the current write; without it
+    /// there is one, and reads and writes take turns.
+    async fn stream_into<S: CopySink>(
+        &self,
+        src_file: io::Take<File>,
+        sink: &mut S,
+        tracker: &ProgressTracker,
+        mut offset: u64,
+        resume_state: &mut Option<ResumeState>,
+        source_checksum: &mut Option<StreamingChecksum>,
+    ) -> Result<()> {
+        let buffers = if self.pipeline { 2 } else { 1 };
+        let remaining = src_file.get_ref().metadata().map_err(Error::Io)?.len().saturating_sub(offset);
+        let remaining = remaining.min(src_file.limit());
+        let buffer_size = self.buffer_size(remaining);
+        let (filled_tx, mut filled_rx) = mpsc::channel(buffers);
+        let (empty_tx, empty_rx) = mpsc::channel(buffers);
+        tokio::spawn(read_chunks(
+            src_file,
+            self.source.clone(),
+            self.timeout,
+            (0..buffers).map(|_| vec![0; buffer_size]).collect(),
+            empty_rx,
+            filled_tx,
+        ));
+
+        let mut last_save = Instant::now();
+        loop {
+            // The reader only stops early after sending its error
+            let Some(chunk) = filled_rx.recv().await else {
+                return Err(Error::Custom("Read task ended unexpectedly".to_string()));
+            };
+            let (buffer, bytes_read) = chunk?;
+            if bytes_read == 0 {
+                return Ok(());
+            }
+
+            sink.write_at(offset, &buffer[..bytes_read])?;
+            if let Some(checksum) = source_checksum.as_mut() {
+                checksum.update(&buffer[..bytes_read]);
+            }
+
+            tracker.add_bytes(bytes_read as u64);
+            offset += bytes_read as u64;
+            self.options.report_progress(tracker.get_stats());
+
+            // Save the resume state every --resume-interval bytes or seconds
+            if let Some(state) = resume_state.as_mut() {
+                let unsaved = offset - state.bytes_completed();
+                if self.resume_interval.is_due(unsaved, last_save.elapsed()) && sink.flush().is_ok() {
+                    state.advance_to(offset);
+                    state.save().ok(); // Best effort save, don't fail if it fails
+                    last_save = Instant::now();
+                }
+            }
+
+            // Ctrl-C: record everything written so far and leave the partial target
+            if is_interrupted(&self.interrupt) {
+                if let Some(state) = resume_state.as_mut() {
+                    state.advance_to(offset);
+                    sink.flush().ok();
+                    state.save()?;
+                }
+                tracker.finish();
+                return Err(Error::Interrupted(self.target.to_string_lossy().to_string()));
+            }
+
+            // Nowhere to return it once the reader has hit the end
+            empty_tx.send(buffer).await.ok();
+        }
+    }
+
+    /// `--attrs-only`: give the existing target the source's attributes (the
+    /// preserve set) without touching its contents. A missing target, or one
+    /// whose size differs, means the contents were never copied: an error.
+    fn copy_attrs(&self, src_metadata: &Metadata) -> Result<TransferStats> {
+        let tgt_metadata = match fs::metadata(&self.target) {
+            Ok(metadata) if metadata.is_file() => metadata,
+            _ => {
+                return Err(Error::Custom(format!(
+                    "--attrs-only needs an existing target file: {}\n\
+                     Tip: Copy the contents first, then run with --attrs-only.",
+                    self.target.display()
+                )))
+            }
+        };
+        if tgt_metadata.len() != src_metadata.len() {
+            return Err(Error::Custom(format!(
+                "--attrs-only found {} is {} bytes, but {} is {}\n\
+                 Tip: The contents differ; copy the file again without --attrs-only.",
+                self.target.display(),
+                tgt_metadata.len(),
+                self.source.display(),
+                src_metadata.len()
+            )));
+        }
+
+        self.apply_attributes(src_metadata, &self.preserve)?;
+
+        let mut stats = ProgressTracker::with_mode(src_metadata.len(), ProgressMode::Hidden).get_stats();
+        stats.files_touched = 1;
+        Ok(stats)
+    }
+
+    /// `--touch-only`: when the target's contents match the source's, give it
+    /// the source's timestamps (plus the rest of the preserve set) and leave
+    /// its bytes alone. `None` when there is no such target and a copy is due.
+    fn touch_identical(&self, src_metadata: &Metadata) -> Result<Option<TransferStats>> {
+        let same_size = fs::metadata(&self.target)
+            .is_ok_and(|metadata| metadata.is_file() && metadata.len() == src_metadata.len());
+        if !same_size {
+            return Ok(None);
+        }
+        let digest = compute_checksum(&self.source, None).map_err(Error::Io)?;
+        if compute_checksum(&self.target, None).map_err(Error::Io)? != digest {
+            return Ok(None);
+        }
+
+        let preserve = PreserveSet { timestamps: true, ..self.preserve };
+        self.apply_attributes(src_metadata, &preserve)?;
+        if let Some(manifest) = &self.manifest {
+            manifest.record(&self.target, &digest);
+        }
+        // The target was just checked to match, so the source can go
+        if self.remove_source_files {
+            fs::remove_file(&self.source)
+                .map_err(Error::Io)?;
+        }
+
+        let mut stats = ProgressTracker::with_mode(src_metadata.len(), ProgressMode::Hidden).get_stats();
+        stats.files_touched = 1;
+        Ok(Some(stats))
+    }
+
+    /// Bring an existing target of the same size up to date by rewriting only
+    /// the blocks that differ, then preserve and verify as a full copy would
+    fn update_in_place(&self, src_metadata: &Metadata) -> Result<TransferStats> {
+        self.check_source()?;
+        let tracker = ProgressTracker::with_mode(src_metadata.len(), self.progress);
+        self.write_changed_blocks(&tracker)?;
+        self.sync_written(&self.target)?;
+
+        self.apply_attributes(src_metadata, &self.preserve)?;
+        self.verify_and_release_source(false, None)?;
+
+        tracker.finish();
+        let stats = tracker.get_stats();
+        self.options.report_progress(stats.clone());
+        Ok(stats)
+    }
+
+    /// Read source and target side by side in blocks of up to `--buffer`
+    /// bytes and write back only the target blocks that differ, returning how
+    /// many were written. Unchanged blocks are never written, which copy-on-write and
+    /// deduplicating stores turn into saved space and bandwidth.
+    fn write_changed_blocks(&self, tracker: &ProgressTracker) -> Result<u64> {
+        let total_size = fs::metadata(&self.source).map_err(Error::Io)?.len();
+        let mut src_file = File::open(&self.source)
+            .map_err(Error::Io)?;
+        let mut dst_file = File::options()
+            .read(true)
+            .write(true)
+            .open(&self.target)
+            .map_err(Error::Io)?;
+        // Blocks the target shares or left as holes are reserved too
+        if self.preallocate {
+            preallocate(&dst_file, total_size)?;
+        }
+
+        let block_size = self.buffer_size(total_size);
+        let mut src_block = vec![0; block_size];
+        let mut dst_block = vec![0; block_size];
+        let mut offset = 0;
+        let mut blocks_written = 0;
+
+        while offset < total_size {
+            let len = (total_size - offset).min(block_size as u64) as usize;
+            src_file.read_exact(&mut src_block[..len])
+                .map_err(Error::Io)?;
+            dst_file.read_exact(&mut dst_block[..len])
+                .map_err(Error::Io)?;
+
+            if src_block[..len] != dst_block[..len] {
+                dst_file.seek(SeekFrom::Start(offset))
+                    .map_err(Error::Io)?;
+                dst_file.write_all(&src_block[..len])
+                    .map_err(Error::Io)?;
+                blocks_written += 1;
+            }
+
+            offset += len as u64;
+            tracker.add_bytes(len as u64);
+            self.options.report_progress(tracker.get_stats());
+
+            // Blocks already compared stay correct, so a re-run picks up where this left off
+            if is_interrupted(&self.interrupt) {
+                tracker.finish();
+                return Err(Error::Interrupted(self.target.to_string_lossy().to_string()));
+            }
+        }
+
+        Ok(blocks_written)
+    }
+
+    /// Deal with a `<target>.tmp` left by an interrupted atomic copy: resume
+    /// into it when the saved state is consistent with it, otherwise ask
+    /// before discarding it.
+    fn handle_leftover_temp(&self, resume_state: Option<&ResumeState>) -> Result<()> {
+        let temp_path = atomic_temp_path(&self.target);
+        let temp_len = match fs::metadata(&temp_path) {
+            Ok(metadata) => metadata.len(),
+            Err(_) => return Ok(()),
+        };
+
+        if let Some(state) = resume_state {
+            if temp_len >= state.bytes_completed() {
+                return Ok(());
+            }
+        }
+
+        let question = format!("Discard leftover partial file {}?", temp_path.display());
+        if (self.options.on_confirm)(&question)? {
+            fs::remove_file(&temp_path)
+                .map_err(Error::Io)
+        } else {
+            Err(Error::Custom(format!(
+                "Leftover partial file kept: {}\n\
+                 Tip: Re-run with --resume to continue it, or remove it with `better-cp clean`.",
+                temp_path.display()
+            )))
+        }
+    }
+
+    /// `--fsync` for copies that don't write through a `LocalFileSink`:
+    /// flush the file written at `path` to disk
+    fn sync_written(&self, path: &Path) -> Result<()> {
+        if self.fsync {
+            sync_file(&File::open(path).map_err(Error::Io)?, path)?;
+        }
+        Ok(())
+    }
+
+    /// `--fsync`: flush the directory the target was created or renamed into
+    fn sync_target_dir(&self) -> Result<()> {
+        if self.fsync {
+            sync_parent_dir(&self.target)?;
+        }
+        Ok(())
+    }
+
+    /// Give the target the attributes in `preserve`, then the `--chmod`
+    /// mode, which wins over a preserved one
+    fn apply_attributes(&self, src_metadata: &Metadata, preserve: &PreserveSet) -> Result<()> {
+        apply_metadata(&self.source, src_metadata, &self.target, preserve)?;
+        if let Some(chmod) = &self.chmod {
+            chmod.apply_to(&self.target)?;
+        }
+        Ok(())
+    }
+
+    /// `--update`: whether the target exists and is at least as new as the
+    /// source. A target with a resume state is an unfinished copy, and never
+    /// up to date.
+    fn is_up_to_date(&self, src_metadata: &Metadata) -> bool {
+        let Ok(tgt_metadata) = fs::metadata(&self.target) else {
+            return false;
+        };
+        if ResumeState::state_file_path(&self.target).exists() {
+            return false;
+        }
+        match (src_metadata.modified(), tgt_metadata.modified()) {
+            (Ok(src_mtime), Ok(tgt_mtime)) => tgt_mtime >= src_mtime,
+            _ => false,
+        }
+    }
+
+    /// Decide whether the existing target may be replaced and, if so,
+    /// move it to its backup name (or the trash) first
+    fn handle_overwrite(&self, src_metadata: &Metadata) -> Result<()> {
+        self.check_overwrite(src_metadata)?;
+        if backup_existing(&self.target, self.backup)?.is_none() && self.trash {
+            remove_path(&self.target, true)?;
+        }
+        Ok(())
+    }
+
+    fn check_overwrite(&self, src_metadata: &Metadata) -> Result<()> {
+        let target = || self.target.to_string_lossy().to_string();
+        match file_action(&self.source, src_metadata, &self.target, &self.overwrite_mode)? {
+            PlannedAction::Create | PlannedAction::Overwrite => Ok(()),
+            PlannedAction::Skip if matches!(self.overwrite_mode, OverwriteMode::NoClobber | OverwriteMode::Policy(_)) => {
+                Err(Error::Skipped(target()))
+            }
+            PlannedAction::Skip | PlannedAction::Excluded => Err(Error::TargetExists(target())),
+            PlannedAction::Prompt => {
+                let tgt_metadata = fs::metadata(&self.target)
+                    .map_err(Error::Io)?;
+                
+                match (self.options.on_conflict)(&self.target, src_metadata, &tgt_metadata)? {
+                    OverwriteChoice::Overwrite => Ok(()),
+                    OverwriteChoice::Skip => Err(Error::Skipped(target())),
+                    OverwriteChoice::Rename => Err(Error::Custom("Rename not yet implemented".to_string())),
+                    OverwriteChoice::Abort => Err(Error::UserAborted),
+                }
+            }
+        }
+    }
+
+    /// Verify the target if requested and, with `--remove-source-files`,
+    /// delete the source. The source is only removed after verification passed.
+    /// The verified digest goes into the checksum manifest, if there is one.
+    ///
+    /// A copy resumed at `resumed_from` is always verified in full, even
+    /// with `--no-verify`: the bytes an earlier run wrote were never read back.
+    fn verify_and_release_source(&self, reflinked: bool, resumed_from: Option<u64>) -> Result<()> {
+        // Part of the source has no whole-file checksum to match
+        let digest = if (self.verify || resumed_from.is_some()) && !self.is_partial() {
+            self.verify_copy(reflinked, resumed_from)?
+        } else {
+            None
+        };
+        // After transcoding, the verified digest is of the decoded contents
+        let digest = digest.filter(|_| self.transcode.is_none());
+        if let Some(manifest) = &self.manifest {
+            let digest = match digest {
+                Some(digest) => digest,
+                None => compute_checksum(&self.target, None).map_err(Error::Io)?,
+            };
+            manifest.record(&self.target, &digest);
+        }
+        if let Some(run_stats) = &self.run_stats {
+            let size = fs::metadata(&self.source).map_err(Error::Io)?.len();
+            run_stats.record(&self.source, size);
+        }
+        if self.remove_source_files {
+            fs::remove_file(&self.source)
+                .map_err(Error::Io)?;
+        }
+        Ok(())
+    }
+
+    /// The digest `--verify-source` expects the source to have, if its
+    /// manifest lists it
+    fn expected_source_digest(&self) -> Option<&str> {
+        self.verify_source.as_ref()?.expected(&self.source)
+    }
+
+    /// Read the whole source to check it against `--verify-source`, for the
+    /// copies that don't stream it through the copy loop
+    fn check_source(&self) -> Result<()> {
+        if self.expected_source_digest().is_none() {
+            return Ok(());
+        }
+        self.check_source_digest(compute_checksum(&self.source, None).map_err(Error::Io)?)
+    }
+
+    /// Fail with `Error::SourceCorrupted` unless `actual` is the digest
+    /// `--verify-source` expects
+    fn check_source_digest(&self, actual: String) -> Result<()> {
+        match (&self.verify_source, self.expected_source_digest()) {
+            (Some(sums), Some(expected)) if expected != actual => Err(Error::SourceCorrupted {
+                path: self.source.to_string_lossy().to_string(),
+                manifest: sums.path().to_string_lossy().to_string(),
+                expected: expected.to_string(),
+                actual,
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Compare source and target digests, returning the target's. Transcoded
+    /// copies compare the uncompressed contents.
+    ///
+    /// A reflinked target shares the source's blocks, so unless `--verify=full`
+    /// insists, only the source is checked to still be readable and no digest
+    /// is returned. When no manifest wants the digest, the files are compared
+    /// byte for byte instead, which also finds where a bad copy first
+    /// differs; no digest is returned then either. Files above
+    /// `MMAP_COMPARE_LIMIT` are compared block by block, checkpointing as
+    /// they go, so an interrupted verification resumes on the next run.
+    fn verify_copy(&self, reflinked: bool, resumed_from: Option<u64>) -> Result<Option<String>> {
+        if reflinked && self.verify_mode != VerifyMode::Full {
+            File::open(&self.source).map_err(Error::Io)?;
+            return Ok(None);
+        }
+
+        let src_size = fs::metadata(&self.source).map_err(Error::Io)?.len();
+        if self.transcode.is_none() && self.manifest.is_none() {
+            let difference = if src_size <= MMAP_COMPARE_LIMIT {
+                first_difference(&self.source, &self.target).map_err(Error::Io)?
+            } else {
+                let tracker = self.verify_tracker(src_size);
+                let verdict = BlockVerifier::new(&self.source, &self.target)
+                    .with_interrupt(&self.interrupt)
+                    .with_progress(&|bytes| tracker.advance_to(bytes))
+                    .run();
+                tracker.finish();
+                match verdict? {
+                    BlockVerdict::Match { .. } => None,
+                    BlockVerdict::Differs { offset } => Some(offset),
+                }
+            };
+            let Some(offset) = difference else {
+                return Ok(None);
+            };
+            // Only a failure is worth the digests, for the report and marker
+            let src_checksum = compute_checksum(&self.source, None).map_err(Error::Io)?;
+            let tgt_checksum = compute_checksum(&self.target, None).map_err(Error::Io)?;
+            return Err(self.handle_verify_failure(src_checksum, tgt_checksum, Some(offset), resumed_from));
+        }
+
+        let (src_checksum, tgt_checksum) = match self.transcode {
+            Some(transcode) => transcode.plain_digests(&self.source, &self.target),
+            None => {
+                // Both files are read in full: one bar for the two
+                let tracker = self.verify_tracker(2 * src_size);
+                let digests = compute_checksum(&self.source, Some(&|bytes| tracker.advance_to(bytes))).and_then(|src| {
+                    let tgt = compute_checksum(&self.target, Some(&|bytes| tracker.advance_to(src_size + bytes)))?;
+                    Ok((src, tgt))
+                });
+                tracker.finish();
+                digests
+            }
+        }
+        .map_err(Error::Io)?;
+
+        if src_checksum == tgt_checksum {
+            Ok(Some(tgt_checksum))
+        } else {
+            Err(self.handle_verify_failure(src_checksum, tgt_checksum, None, resumed_from))
+        }
+    }
+
+    /// A "verifying" bar for re-reading `bytes` after the copy, so a long
+    /// verification doesn't look like a hang; drawn only where the copy's
+    /// own bar was
+    fn verify_tracker(&self, bytes: u64) -> ProgressTracker {
+        let mode = match self.progress {
+            ProgressMode::Bar => ProgressMode::Bar,
+            _ => ProgressMode::Hidden,
+        };
+        ProgressTracker::labeled(bytes, mode, "verifying")
+    }
+
+    /// Flag a destination that failed verification.
+    ///
+    /// Writes a `<target>.corrupt` marker next to the file and, when resume is
+    /// enabled, rewinds the saved state so the next run re-copies everything
+    /// instead of appending to bad data.
+    fn handle_verify_failure(
+        &self,
+        expected: String,
+        actual: String,
+        offset: Option<u64>,
+        resumed_from: Option<u64>,
+    ) -> Error {
+        let marker = Self::corrupt_marker_path(&self.target);
+        let mut contents = format!(
+            "source: {}\nexpected: {}\nactual: {}\n",
+            self.source.display(),
+            expected,
+            actual
+        );
+        if let Some(offset) = offset {
+            contents.push_str(&format!("offset: {}\n", offset));
+        }
+        if let Some(resumed_from) = resumed_from {
+            contents.push_str(&format!("resumed_from: {}\n", resumed_from));
+        }
+        fs::write(&marker, contents).ok();
+
+        if self.resume {
+            let total_size = fs::metadata(&self.source).map(|m| m.len()).unwrap_or(0);
+            ResumeState::new(self.source.clone(), self.target.clone(), total_size)
+                .save()
+                .ok();
+        }
+
+        Error::VerificationFailed {
+            target: self.target.to_string_lossy().to_string(),
+            marker: marker.to_string_lossy().to_string(),
+            expected,
+            actual,
+            offset,
+            resumed_from: resumed_from.unwrap_or(0),
+        }
+    }
+
+    /// Path of the sidecar marker written when verification fails
+    pub fn corrupt_marker_path(target: &Path) -> PathBuf {
+        let mut marker = target.to_path_buf();
+        let filename = format!(
+            "{}.corrupt",
+            target
+                .file_name()
+                .map(|n| n.to_string_lossy())
+                .unwrap_or_default()
+        );
+        marker.set_file_name(filename);
+        marker
+    }
+}
+
+/// Resolve the final target path for `source`, following `cp` semantics.
+///
+/// When `destination` is an existing directory, or ends with a path
+/// separator, the source's file name is appended (`cp file.txt dir` ->
+/// `dir/file.txt`). Otherwise the destination is used as the new name.
+pub fn resolve_target(source: &Path, destination: &Path) -> Result<PathBuf> {
+    if destination.is_dir() || has_trailing_slash(destination) {
+        let file_name = source
+            .file_name()
+            .ok_or_else(|| Error::Custom(format!("Invalid source path: {}", source.display())))?;
+        Ok(destination.join(file_name))
+    } else {
+        Ok(destination.to_path_buf())
+    }
+}
+
+/// Whether `path` was written with a trailing separator (`backup/`), which
+/// marks it as a directory even before it exists
+pub fn has_trailing_slash(path: &Path) -> bool {
+    path.as_os_str()
+        .to_string_lossy()
+        .ends_with(std::path::is_separator)
+}
+
+/// Where `source`, as named on the command line, points when it is a
+/// symlink to a directory, for a copy that recreates such a link rather than
+/// the tree behind it (`--no-dereference-args`, as `cp -r` does); a trailing
+/// slash (`link/`) names the directory itself. Only unix recreates links, so
+/// elsewhere this is always `None`.
+pub fn source_dir_link(source: &Path) -> Option<PathBuf> {
+    let is_link = fs::symlink_metadata(source).is_ok_and(|m| m.file_type().is_symlink());
+    if !cfg!(unix) || !is_link || has_trailing_slash(source) || !source.is_dir() {
+        return None;
+    }
+    fs::read_link(source).ok()
+}
+
+/// Create any missing directories above `target`, so copying or moving to
+/// `a/b/c/file.txt` works when `a/b/c` doesn't exist yet
+pub fn create_parent_dirs(target: &Path) -> Result<()> {
+    match target.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => fs::create_dir_all(parent)
+            .map_err(|e| Error::Custom(format!("Failed to create parent directory: {}", e))),
+        _ => Ok(()),
+    }
+}
+
+/// Check `destination` can receive several sources: it must be a directory,
+/// or end in a separator (`backup/`), in which case it is created
+pub fn prepare_destination_dir(destination: &Path) -> Result<()> {
+    if destination.is_dir() {
+        return Ok(());
+    }
+    if !destination.exists() && has_trailing_slash(destination) {
+        return fs::create_dir_all(destination).map_err(|e| {
+            Error::Custom(format!("Failed to create {}: {}", destination.display(), e))
+        });
+    }
+    Err(Error::Usage(format!(
+        "destination must be a directory for multiple sources: {} (end it with '/' to create it)",
+        destination.display()
+    )))
+}
+
+/// Path sentinel meaning stdin (as a source) or stdout (as a destination)
+pub const STDIO_PATH: &str = "-";
+
+/// Check whether a path is the `-` stdin/stdout sentinel
+pub fn is_stdio(path: &Path) -> bool {
+    path.as_os_str() == STDIO_PATH
+}
+
+/// Size of the buffer to copy `size` bytes through: the data itself, but at
+/// least `MIN_BUFFER_SIZE` and at most `cap` (`--buffer`), so a tree of small
+/// files doesn't allocate 64 MB for each one
+fn buffer_size_for(size: u64, cap: usize) -> usize {
+    size.max(MIN_BUFFER_SIZE as u64).min(cap as u64) as usize
+}
+
+/// Copy everything from `reader` to `writer` through a `buffer_size`-byte
+/// buffer, reporting progress as it goes
+pub fn copy_stream<R: Read, W: Write>(
+    reader: &mut R,
+    writer: &mut W,
+    tracker: &ProgressTracker,
+    buffer_size: usize,
+) -> Result<u64> {
+    let mut buffer = vec![0; buffer_size];
+    let mut total = 0u64;
+    loop {
+        let bytes_read = match reader.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(Error::Io(e)),
+        };
+
+        writer.write_all(&buffer[..bytes_read])
+            .map_err(Error::Io)?;
+        tracker.add_bytes(bytes_read as u64);
+        total += bytes_read as u64;
+    }
+    writer.flush().map_err(Error::Io)?;
+    Ok(total)
+}
+
+/// Copy stdin into a file
+///
+/// The input length is unknown up front, so progress is a spinner showing
+/// the bytes so far rather than a bar. An existing target is only replaced
+/// with `--overwrite=always`, since stdin cannot be used to answer a prompt.
+///
+/// With no length to go by, the buffer is the smallest one (within
+/// `max_buffer`): a pipe hands over no more than that per read anyway.
+pub async fn copy_from_stdin(
+    target: &Path,
+    overwrite_mode: OverwriteMode,
+    progress: ProgressMode,
+    max_buffer: usize,
+) -> Result<TransferStats> {
+    if target.exists() {
+        match overwrite_mode {
+            OverwriteMode::Always => {}
+            OverwriteMode::NoClobber => {
+                return Err(Error::Skipped(target.to_string_lossy().to_string()));
+            }
+            _ => return Err(Error::TargetExists(target.to_string_lossy().to_string())),
+        }
+    }
+
+    create_parent_dirs(target)?;
+
+    let tracker = ProgressTracker::unbounded(progress);
+    let mut dst_file = File::create(target).map_err(Error::Io)?;
+    copy_stream(&mut std::io::stdin().lock(), &mut dst_file, &tracker, buffer_size_for(0, max_buffer))?;
+
+    tracker.finish();
+    Ok(tracker.get_stats())
+}
+
+/// Copy a file to stdout, through a buffer sized as a file copy's would be
+/// (at most `max_buffer`)
+pub async fn copy_to_stdout(source: &Path, max_buffer: usize) -> Result<TransferStats> {
+    let src_metadata = fs::metadata(source)
+        .map_err(|_| Error::SourceNotFound(source.to_string_lossy().to_string()))?;
+    if !src_metadata.is_file() {
+        return Err(Error::Custom("Source is not a file".to_string()));
+    }
+
+    // Never draw a bar here: it would interleave with the data on a terminal
+    let tracker = ProgressTracker::new(src_metadata.len(), false);
+    let mut src_file = File::open(source).map_err(Error::Io)?;
+    let buffer_size = buffer_size_for(src_metadata.len(), max_buffer);
+    copy_stream(&mut src_file, &mut std::io::stdout().lock(), &tracker, buffer_size)?;
+
+    tracker.finish();
+    Ok(tracker.get_stats())
+}
 
 /// Copy a directory recursively (async version with proper boxing for recursion)
+///
+/// Returns the combined transfer statistics of every file copied.
 pub async fn copy_directory(
     source: &Path,
     target: &Path,
     overwrite_mode: OverwriteMode,
     verify: bool,
-) -> Result<()> {
-    copy_directory_impl(source, target, overwrite_mode, verify).await
+    show_progress: bool,
+) -> Result<TransferStats> {
+    DirectoryCopier::new(source.to_path_buf(), target.to_path_buf(), overwrite_mode, verify)
+        .with_progress(show_progress)
+        .copy()
+        .await
+}
+
+/// Copy a directory tree file by file
+pub struct DirectoryCopier {
+    source: PathBuf,
+    target: PathBuf,
+    overwrite_mode: OverwriteMode,
+    verify: bool,
+    progress: ProgressMode,
+    preserve: PreserveSet,
+    filter: WalkFilter,
+    remove_source_files: bool,
+    interrupt: InterruptFlag,
+    atomic: bool,
+    resume: bool,
+    backup: BackupMode,
+    trash: bool,
+    update_inplace: bool,
+    specials: bool,
+    timeout: Option<Duration>,
+    manifest: Option<ChecksumManifest>,
+    verify_source: Option<ExpectedChecksums>,
+    transcode: Option<Transcode>,
+    run_stats: Option<RunStats>,
+    file_results: Option<FileResults>,
+    logger: Option<Logger>,
+    reflink: ReflinkMode,
+    verify_mode: VerifyMode,
+    resume_interval: ResumeInterval,
+    fsync: bool,
+    preallocate: bool,
+    update: bool,
+    chmod: Option<ChmodSpec>,
+    sparse: bool,
+    touch_only: bool,
+    ignore_errors: bool,
+    delete: bool,
+    strip_components: usize,
+    buffer_size: usize,
+    pipeline: bool,
+    attrs_only: bool,
+    dedup: bool,
+    case_collision: CaseCollisionMode,
+    options: CopyOptions,
+}
+
+/// Results accumulated while walking a tree
+#[derive(Default)]
+struct DirWalk {
+    stats: Vec<TransferStats>,
+    skipped: usize,
+    /// Files left as they were because the target already had them
+    kept: usize,
+    failed: usize,
+    manifest: Option<DirResumeState>,
+    checksums: Option<ChecksumManifest>,
+    /// Where each file's outcome is recorded
+    results: Option<FileResults>,
+    logger: Option<Logger>,
+    /// With `--dedup`, the sources copied so far and their targets by size,
+    /// each with the source checksum once another file of that size needed
+    /// comparing to it
+    written: HashMap<u64, Vec<(PathBuf, PathBuf, Option<String>)>>,
+    /// With `--preserve=links`, the first target written for each
+    /// hard-linked source file
+    linked: HashMap<(u64, u64), PathBuf>,
+}
+
+impl DirWalk {
+    /// `--dedup`: a target already written with the contents of `source`,
+    /// along with the source's checksum. Only files whose size matches one
+    /// written before are hashed; a unique size can't have a duplicate.
+    fn same_contents(&mut self, source: &Path, size: u64) -> Result<(Option<PathBuf>, Option<String>)> {
+        let Some(written) = self.written.get_mut(&size) else {
+            return Ok((None, None));
+        };
+        let digest = compute_checksum(source, None).map_err(Error::Io)?;
+        for (copied, target, copied_digest) in written.iter_mut() {
+            // A source removed by --remove-source-files left its copy behind
+            if copied_digest.is_none() {
+                *copied_digest = compute_checksum(&*copied, None)
+                    .or_else(|_| compute_checksum(&*target, None))
+                    .ok();
+            }
+            if copied_digest.as_ref() == Some(&digest) {
+                return Ok((Some(target.clone()), Some(digest)));
+            }
+        }
+        Ok((None, Some(digest)))
+    }
+
+    /// `--dedup`: remember `target`, copied from `source` of `size` bytes,
+    /// unless a source with the same checksum is on record already
+    fn wrote(&mut self, source: &Path, target: &Path, size: u64, digest: Option<String>) {
+        let written = self.written.entry(size).or_default();
+        if digest.is_none() || !written.iter().any(|(_, _, known)| *known == digest) {
+            written.push((source.to_path_buf(), target.to_path_buf(), digest));
+        }
+    }
+
+    /// Note how one entry fared, if anyone is collecting or logging
+    fn record(&self, entry: FileResult) {
+        if let Some(logger) = &self.logger {
+            logger.record("copy", &entry);
+        }
+        if let Some(results) = &self.results {
+            results.record(entry);
+        }
+    }
 }
 
-/// Internal async implementation using a helper to allow recursion
-async fn copy_directory_impl(
-    source: &Path,
-    target: &Path,
-    overwrite_mode: OverwriteMode,
-    verify: bool,
-) -> Result<()> {
-    if !source.is_dir() {
-        return Err(Error::Custom("Source is not a directory".to_string()));
+impl DirectoryCopier {
+    pub fn new(
+        source: PathBuf,
+        target: PathBuf,
+        overwrite_mode: OverwriteMode,
+        verify: bool,
+    ) -> Self {
+        Self {
+            source,
+            target,
+            overwrite_mode,
+            verify,
+            progress: ProgressMode::Bar,
+            preserve: PreserveSet::mode_and_timestamps(),
+            filter: WalkFilter::new(),
+            remove_source_files: false,
+            interrupt: InterruptFlag::default(),
+            atomic: false,
+            resume: false,
+            backup: BackupMode::None,
+            trash: false,
+            update_inplace: false,
+            specials: false,
+            timeout: None,
+            manifest: None,
+            verify_source: None,
+            transcode: None,
+            run_stats: None,
+            file_results: None,
+            logger: None,
+            reflink: ReflinkMode::Never,
+            verify_mode: VerifyMode::Fast,
+            resume_interval: ResumeInterval::default(),
+            fsync: false,
+            preallocate: false,
+            update: false,
+            chmod: None,
+            sparse: false,
+            touch_only: false,
+            ignore_errors: false,
+            delete: false,
+            strip_components: 0,
+            buffer_size: CHUNK_SIZE,
+            pipeline: false,
+            attrs_only: false,
+            dedup: false,
+            case_collision: CaseCollisionMode::Warn,
+            options: CopyOptions::interactive(),
+        }
+    }
+
+    /// Attributes kept on every copied file and directory (mode and
+    /// timestamps by default)
+    pub fn with_preserve(mut self, preserve: PreserveSet) -> Self {
+        self.preserve = preserve;
+        self
+    }
+
+    /// Choose how progress is reported (a bar by default); accepts a bool
+    pub fn with_progress(mut self, progress: impl Into<ProgressMode>) -> Self {
+        self.progress = progress.into();
+        self
+    }
+
+    /// Restrict which entries are visited
+    pub fn with_filter(mut self, filter: WalkFilter) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    /// Delete each source file once its copy is verified; directories are kept
+    pub fn with_remove_source_files(mut self, remove_source_files: bool) -> Self {
+        self.remove_source_files = remove_source_files;
+        self
+    }
+
+    /// Stop before the next file (or chunk) once this flag is raised
+    pub fn with_interrupt(mut self, interrupt: InterruptFlag) -> Self {
+        self.interrupt = interrupt;
+        self
+    }
+
+    /// Build the tree in a staging directory and rename it into place only
+    /// once everything copied. The target must be new or an empty directory.
+    pub fn with_atomic(mut self, atomic: bool) -> Self {
+        self.atomic = atomic;
+        self
+    }
+
+    /// Skip files an interrupted earlier run already copied, and continue
+    /// the one it was in the middle of. Ignored for atomic copies, whose
+    /// staging directory is rebuilt from scratch.
+    pub fn with_resume(mut self, resume: bool) -> Self {
+        self.resume = resume;
+        self
+    }
+
+    /// Back up existing files before they are overwritten
+    pub fn with_backup(mut self, backup: BackupMode) -> Self {
+        self.backup = backup;
+        self
+    }
+
+    /// Send existing files to the trash before they are overwritten
+    pub fn with_trash(mut self, trash: bool) -> Self {
+        self.trash = trash;
+        self
+    }
+
+    /// Rewrite only the changed blocks of existing files of the same size
+    pub fn with_update_inplace(mut self, update_inplace: bool) -> Self {
+        self.update_inplace = update_inplace;
+        self
+    }
+
+    /// Recreate FIFOs, sockets and device nodes instead of skipping them
+    pub fn with_specials(mut self, specials: bool) -> Self {
+        self.specials = specials;
+        self
+    }
+
+    /// Give up on a file once reading one of its chunks stalls this long
+    pub fn with_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Record the digest of every copied file in this manifest
+    pub fn with_manifest(mut self, manifest: Option<ChecksumManifest>) -> Self {
+        self.manifest = manifest;
+        self
+    }
+
+    /// Check every source file listed in these checksums as it is read
+    pub fn with_verify_source(mut self, verify_source: Option<ExpectedChecksums>) -> Self {
+        self.verify_source = verify_source;
+        self
+    }
+
+    /// Compress or decompress every file on the way
+    pub fn with_transcode(mut self, transcode: Option<Transcode>) -> Self {
+        self.transcode = transcode;
+        self
+    }
+
+    /// Count every copied file towards the `--stats` report
+    pub fn with_run_stats(mut self, run_stats: Option<RunStats>) -> Self {
+        self.run_stats = run_stats;
+        self
+    }
+
+    /// Record how each file fared, for the `--json` file list
+    pub fn with_file_results(mut self, file_results: Option<FileResults>) -> Self {
+        self.file_results = file_results;
+        self
+    }
+
+    /// Append how each file fared to the `--log` file as it goes
+    pub fn with_logger(mut self, logger: Option<Logger>) -> Self {
+        self.logger = logger;
+        self
+    }
+
+    /// Clone every file's blocks instead of copying bytes where possible
+    pub fn with_reflink(mut self, reflink: ReflinkMode) -> Self {
+        self.reflink = reflink;
+        self
+    }
+
+    /// How thoroughly every file is verified
+    pub fn with_verify_mode(mut self, verify_mode: VerifyMode) -> Self {
+        self.verify_mode = verify_mode;
+        self
+    }
+
+    /// How often the file being copied saves its resume state
+    pub fn with_resume_interval(mut self, resume_interval: ResumeInterval) -> Self {
+        self.resume_interval = resume_interval;
+        self
+    }
+
+    /// Flush every copied file (and its directory) to disk
+    pub fn with_fsync(mut self, fsync: bool) -> Self {
+        self.fsync = fsync;
+        self
+    }
+
+    /// Reserve each target's full size before writing it
+    pub fn with_preallocate(mut self, preallocate: bool) -> Self {
+        self.preallocate = preallocate;
+        self
+    }
+
+    /// Only copy files whose target is missing or older than the source
+    pub fn with_update(mut self, update: bool) -> Self {
+        self.update = update;
+        self
+    }
+
+    /// Force this mode on every copied file
+    pub fn with_chmod(mut self, chmod: Option<ChmodSpec>) -> Self {
+        self.chmod = chmod;
+        self
+    }
+
+    /// Keep the holes of sparse files in the copies
+    pub fn with_sparse(mut self, sparse: bool) -> Self {
+        self.sparse = sparse;
+        self
+    }
+
+    /// Only re-timestamp targets whose contents already match
+    pub fn with_touch_only(mut self, touch_only: bool) -> Self {
+        self.touch_only = touch_only;
+        self
+    }
+
+    /// Warn about a file that fails to copy and carry on with the rest,
+    /// counting it in `files_failed`, instead of stopping the whole copy
+    pub fn with_ignore_errors(mut self, ignore_errors: bool) -> Self {
+        self.ignore_errors = ignore_errors;
+        self
+    }
+
+    /// Once everything is copied, remove whatever the target holds that the
+    /// source doesn't, making the target a mirror (like `rsync --delete`)
+    pub fn with_delete(mut self, delete: bool) -> Self {
+        self.delete = delete;
+        self
+    }
+
+    /// Drop the first `count` components of every path below the source
+    /// (like `tar --strip-components`); entries that shallow are skipped
+    pub fn with_strip_components(mut self, count: usize) -> Self {
+        self.strip_components = count;
+        self
+    }
+
+    /// Largest read buffer each file copy uses (`--buffer`)
+    pub fn with_buffer_size(mut self, buffer_size: usize) -> Self {
+        self.buffer_size = buffer_size;
+        self
+    }
+
+    /// Overlap each file's reads and writes (`--pipeline`)
+    pub fn with_pipeline(mut self, pipeline: bool) -> Self {
+        self.pipeline = pipeline;
+        self
+    }
+
+    /// Only apply attributes to files already in the target (`--attrs-only`)
+    pub fn with_attrs_only(mut self, attrs_only: bool) -> Self {
+        self.attrs_only = attrs_only;
+        self
+    }
+
+    /// Hard-link a new target to the first target copied this run from a
+    /// source with the same checksum, instead of copying it (`--dedup`).
+    /// Such a file counts in `files_linked`.
+    pub fn with_dedup(mut self, dedup: bool) -> Self {
+        self.dedup = dedup;
+        self
+    }
+
+    /// What to do when the target ignores case and two source names differ
+    /// only in case (`--case-collision`)
+    pub fn with_case_collision(mut self, case_collision: CaseCollisionMode) -> Self {
+        self.case_collision = case_collision;
+        self
+    }
+
+    /// Hooks handed to every file copy (terminal prompts by default)
+    pub fn with_options(mut self, options: CopyOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Execute the copy, returning the combined statistics of every file
+    pub async fn copy(&self) -> Result<TransferStats> {
+        let start = std::time::Instant::now();
+        let filter = self.filter.clone().rooted_at(&self.source)?;
+        let mut walk = DirWalk { logger: self.logger.clone(), ..DirWalk::default() };
+
+        if self.atomic {
+            let staging = self.prepare_staging()?;
+            walk.checksums = self.manifest.as_ref().map(|m| m.staged(&staging, &self.target));
+            walk.results = self.file_results.as_ref().map(|r| r.staged(&staging, &self.target));
+            self.copy_dir(&self.source, &staging, &filter, &mut walk).await?;
+            fs::rename(&staging, &self.target)
+                .map_err(Error::Io)?;
+        } else {
+            walk.checksums = self.manifest.clone();
+            walk.results = self.file_results.clone();
+            // Always keep the manifest, so a later --resume can pick up from here
+            walk.manifest = Some(if self.resume {
+                DirResumeState::load(&self.target)?
+            } else {
+                DirResumeState::new(&self.target)
+            });
+            self.copy_dir(&self.source, &self.target, &filter, &mut walk).await?;
+            if let Some(manifest) = walk.manifest.as_mut() {
+                manifest.cleanup()?;
+            }
+        }
+
+        // Anything left out on purpose (kept targets, failures, transformed or
+        // relocated files, sources already removed) would read as missing
+        let whole_tree = walk.kept == 0
+            && walk.failed == 0
+            && self.transcode.is_none()
+            && self.strip_components == 0
+            && !self.remove_source_files;
+        if self.verify && whole_tree {
+            verify_tree(&self.source, &self.target, &filter, self.verify_mode == VerifyMode::Full)?;
+        }
+
+        // An atomic target was new, so it has nothing extraneous
+        let deleted = if self.delete && !self.atomic {
+            delete_extraneous(&self.source, &self.target, &filter, self.transcode)?
+        } else {
+            0
+        };
+
+        let mut total = TransferStats::aggregate(&walk.stats, start.elapsed());
+        total.files_skipped += walk.skipped;
+        total.files_deleted += deleted;
+        total.files_failed += walk.failed;
+        Ok(total)
+    }
+
+    /// Check the target can be replaced wholesale and return a fresh staging
+    /// directory path. A staging tree left by an earlier failed run is discarded.
+    fn prepare_staging(&self) -> Result<PathBuf> {
+        if self.target.exists() {
+            let is_empty_dir = fs::read_dir(&self.target)
+                .map(|mut entries| entries.next().is_none())
+                .unwrap_or(false);
+            if !is_empty_dir {
+                return Err(Error::Custom(format!(
+                    "Atomic directory copy needs a new or empty target: {}\n\
+                     Tip: Choose a new destination, or drop --atomic to merge into it.",
+                    self.target.display()
+                )));
+            }
+            fs::remove_dir(&self.target)
+                .map_err(Error::Io)?;
+        }
+
+        let staging = staging_dir_path(&self.target);
+        if staging.exists() {
+            fs::remove_dir_all(&staging)
+                .map_err(Error::Io)?;
+        }
+        Ok(staging)
+    }
+
+    /// Copy everything the walk plans for `source` into `target`
+    async fn copy_dir(
+        &self,
+        source: &Path,
+        target: &Path,
+        filter: &WalkFilter,
+        walk: &mut DirWalk,
+    ) -> Result<()> {
+        let plan = plan_tree(source, target, filter)?;
+        let (plan, too_shallow) = strip_components(plan, target, self.strip_components);
+        for entry in &too_shallow {
+            warn_too_shallow(&entry.source, self.strip_components);
+        }
+        walk.skipped += too_shallow.len();
+        let plan = handle_case_collisions(plan, target, self.case_collision)?;
+
+        for entry in &plan {
+            if is_interrupted(&self.interrupt) {
+                return Err(Error::Interrupted(entry.target.to_string_lossy().to_string()));
+            }
+
+            match &entry.kind {
+                PlanKind::Dir => {
+                    fs::create_dir_all(&entry.target)
+                        .map_err(Error::Io)?;
+                }
+                PlanKind::Symlink(link_target) => {
+                    let copied = self
+                        .link_overwrite_mode(entry, Some(link_target))
+                        .and_then(|mode| copy_symlink(link_target, &entry.target, &mode));
+                    match copied {
+                        Ok(()) => {}
+                        Err(e) if e.is_skip() => walk.skipped += 1,
+                        Err(e) => self.keep_going(entry, e, walk)?,
+                    }
+                }
+                PlanKind::Special(metadata) if self.specials => {
+                    let copied = self
+                        .link_overwrite_mode(entry, None)
+                        .and_then(|mode| copy_special(metadata, &entry.target, &mode));
+                    match copied {
+                        Ok(()) => {}
+                        Err(e) if e.is_skip() => walk.skipped += 1,
+                        Err(e) => self.keep_going(entry, e, walk)?,
+                    }
+                }
+                PlanKind::Special(metadata) => {
+                    warn_special_skipped(&entry.source, metadata);
+                    walk.skipped += 1;
+                }
+                PlanKind::Cycle => {
+                    warn_symlink_cycle(&entry.source);
+                    walk.skipped += 1;
+                }
+                PlanKind::Filtered => walk.skipped += 1,
+                PlanKind::Excluded => {}
+                PlanKind::File(metadata) => {
+                    // Finished by an earlier run: counted as skipped
+                    let already_copied = walk
+                        .manifest
+                        .as_ref()
+                        .is_some_and(|manifest| manifest.is_complete(&entry.source, metadata));
+                    if already_copied && entry.target.exists() {
+                        if let Some(checksums) = &walk.checksums {
+                            let digest = compute_checksum(&entry.target, None).map_err(Error::Io)?;
+                            checksums.record(&entry.target, &digest);
+                        }
+                        walk.record(FileResult::skipped(
+                            entry.source.clone(),
+                            entry.target.clone(),
+                            "already copied".to_string(),
+                        ));
+                        walk.skipped += 1;
+                        walk.kept += 1;
+                        continue;
+                    }
+
+                    let inode = if self.preserve.links { hard_link_key(metadata) } else { None };
+                    let mut first = inode.and_then(|key| walk.linked.get(&key)).cloned();
+                    let mut digest = None;
+                    if first.is_none() && self.dedup {
+                        (first, digest) = walk.same_contents(&entry.source, metadata.len())?;
+                    }
+                    if let Some(first) = first {
+                        match self.link_duplicate(&first, &entry.source, &entry.target, metadata, walk) {
+                            Ok(Some(file_stats)) => {
+                                if let Some(manifest) = walk.manifest.as_mut() {
+                                    manifest.mark_complete(&entry.source, metadata)?;
+                                }
+                                walk.record(FileResult::copied(entry.source.clone(), entry.target.clone(), 0));
+                                walk.stats.push(file_stats);
+                                continue;
+                            }
+                            Ok(None) => {}
+                            Err(e) => {
+                                self.keep_going(entry, e, walk)?;
+                                continue;
+                            }
+                        }
+                    }
+
+                    let existed = fs::symlink_metadata(&entry.target).is_ok();
+                    let copier = FileCopier::new(
+                        entry.source.clone(),
+                        entry.target.clone(),
+                        self.overwrite_mode.clone(),
+                        self.verify,
+                        self.resume,
+                        false,
+                    )
+                    .with_progress(self.progress)
+                    .with_remove_source_files(self.remove_source_files)
+                    .with_interrupt(self.interrupt.clone())
+                    .with_preserve(self.preserve)
+                    .with_backup(self.backup)
+                    .with_trash(self.trash)
+                    .with_update_inplace(self.update_inplace)
+                    .with_timeout(self.timeout)
+                    .with_manifest(walk.checksums.clone())
+                    .with_verify_source(self.verify_source.clone())
+                    .with_transcode(self.transcode)
+                    .with_run_stats(self.run_stats.clone())
+                    .with_reflink(self.reflink)
+                    .with_verify_mode(self.verify_mode)
+                    .with_resume_interval(self.resume_interval)
+                    .with_fsync(self.fsync)
+                    .with_preallocate(self.preallocate)
+                    .with_update(self.update)
+                    .with_chmod(self.chmod.clone())
+                    .with_sparse(self.sparse)
+                    .with_touch_only(self.touch_only)
+                    .with_buffer_size(self.buffer_size)
+                    .with_pipeline(self.pipeline)
+                    .with_attrs_only(self.attrs_only)
+                    .with_options(self.options.clone());
+                    match copier.copy().await {
+                        Ok(file_stats) => {
+                            if let Some(manifest) = walk.manifest.as_mut() {
+                                manifest.mark_complete(&entry.source, metadata)?;
+                            }
+                            if self.dedup {
+                                walk.wrote(&entry.source, &entry.target, metadata.len(), digest);
+                            }
+                            if let Some(key) = inode {
+                                walk.linked.entry(key).or_insert_with(|| entry.target.clone());
+                            }
+                            let (source, target) = (entry.source.clone(), entry.target.clone());
+                            walk.record(if file_stats.files_touched > 0 {
+                                FileResult::touched(source, target)
+                            } else {
+                                FileResult::copied(source, target, file_stats.transferred_bytes).overwriting(existed)
+                            });
+                            walk.stats.push(file_stats);
+                        }
+                        Err(e) if e.is_skip() => {
+                            walk.record(FileResult::skipped(entry.source.clone(), entry.target.clone(), e.to_string()));
+                            walk.skipped += 1;
+                            walk.kept += 1;
+                        }
+                        Err(e) => self.keep_going(entry, e, walk)?,
+                    }
+                }
+            }
+        }
+
+        // Applied last, deepest first, so writing children doesn't bump the mtime again
+        for entry in plan.iter().rev().filter(|entry| matches!(entry.kind, PlanKind::Dir)) {
+            let src_metadata = fs::metadata(&entry.source).map_err(Error::Io)?;
+            apply_metadata(&entry.source, &src_metadata, &entry.target, &self.preserve)?;
+        }
+        Ok(())
+    }
+
+    /// `--dedup` and `--preserve=links`: hard-link `target` to `first`,
+    /// already copied from a file with the same contents as `source` (or
+    /// from another name of the same file). `None` when the target exists
+    /// (the overwrite mode decides about it) or the link can't be made, such
+    /// as on a filesystem without hard links; the file is copied instead.
+    fn link_duplicate(
+        &self,
+        first: &Path,
+        source: &Path,
+        target: &Path,
+        src_metadata: &Metadata,
+        walk: &DirWalk,
+    ) -> Result<Option<TransferStats>> {
+        if fs::symlink_metadata(target).is_ok() || fs::hard_link(first, target).is_err() {
+            return Ok(None);
+        }
+        if let Some(checksums) = &walk.checksums {
+            let digest = compute_checksum(target, None).map_err(Error::Io)?;
+            checksums.record(target, &digest);
+        }
+        if self.remove_source_files {
+            fs::remove_file(source)
+                .map_err(Error::Io)?;
+        }
+
+        let mut stats = ProgressTracker::with_mode(src_metadata.len(), ProgressMode::Hidden).get_stats();
+        stats.files_linked = 1;
+        Ok(Some(stats))
+    }
+
+    /// With `--ignore-errors`, warn that `source` failed and count it, so
+    /// the walk goes on; otherwise, or when the user stopped the run or the
+    /// run can't go on as asked (a usage error), the error ends the copy
+    /// The overwrite mode to recreate `entry`'s link or special file with.
+    /// Where the mode would prompt, the conflict hook is asked now, as it is
+    /// for files, and its answer becomes `always` or a skip.
+    fn link_overwrite_mode(&self, entry: &PlanEntry, link_target: Option<&PathBuf>) -> Result<OverwriteMode> {
+        let link_target = link_target.map(PathBuf::as_path);
+        if link_action(link_target, &entry.target, &self.overwrite_mode) != PlannedAction::Prompt {
+            return Ok(self.overwrite_mode.clone());
+        }
+        let src_metadata = fs::symlink_metadata(&entry.source).map_err(Error::Io)?;
+        let tgt_metadata = fs::symlink_metadata(&entry.target).map_err(Error::Io)?;
+        match (self.options.on_conflict)(&entry.target, &src_metadata, &tgt_metadata)? {
+            OverwriteChoice::Overwrite => Ok(OverwriteMode::Always),
+            OverwriteChoice::Skip => Err(Error::Skipped(entry.target.to_string_lossy().to_string())),
+            OverwriteChoice::Rename => Err(Error::Custom("Rename not yet implemented".to_string())),
+            OverwriteChoice::Abort => Err(Error::UserAborted),
+        }
+    }
+
+    fn keep_going(&self, entry: &PlanEntry, e: Error, walk: &mut DirWalk) -> Result<()> {
+        walk.record(FileResult::failed(entry.source.clone(), entry.target.clone(), e.to_string()));
+        if !self.ignore_errors || matches!(e, Error::Interrupted(_) | Error::UserAborted | Error::Usage(_)) {
+            return Err(e);
+        }
+        warn(format_args!("Failed to copy {}: {}", entry.source.display(), e));
+        walk.failed += 1;
+        Ok(())
+    }
+}
+
+/// Clear the way for a link or special file at `target` as `action` says:
+/// remove what is there, or fail with a skip (`--no-clobber` and the modes
+/// that compare files) or `Error::TargetExists`
+fn replace_link_target(action: PlannedAction, target: &Path, overwrite_mode: &OverwriteMode) -> Result<()> {
+    let target_str = || target.to_string_lossy().to_string();
+    match action {
+        PlannedAction::Create => Ok(()),
+        PlannedAction::Overwrite => fs::remove_file(target).map_err(Error::Io),
+        PlannedAction::Skip if !matches!(overwrite_mode, OverwriteMode::Never) => Err(Error::Skipped(target_str())),
+        PlannedAction::Skip | PlannedAction::Prompt | PlannedAction::Excluded => {
+            Err(Error::TargetExists(target_str()))
+        }
+    }
+}
+
+/// Recreate a symbolic link at `target` pointing where the original did.
+/// An identical link already in place is left alone.
+pub fn copy_symlink(link_target: &Path, target: &Path, overwrite_mode: &OverwriteMode) -> Result<()> {
+    if fs::read_link(target).ok().as_deref() == Some(link_target) {
+        return Ok(());
+    }
+    replace_link_target(link_action(Some(link_target), target, overwrite_mode), target, overwrite_mode)?;
+
+    #[cfg(unix)]
+    {
+        std::os::unix::fs::symlink(link_target, target).map_err(Error::Io)
+    }
+
+    #[cfg(not(unix))]
+    {
+        Err(Error::Custom(format!(
+            "Recreating symlinks is not supported on this platform: {}",
+            target.display()
+        )))
+    }
+}
+
+/// Recreate a FIFO, socket or device node at `target` with the source's
+/// type, permission bits and device number, without reading from it.
+/// Device nodes can usually only be created by root.
+pub fn copy_special(src_metadata: &Metadata, target: &Path, overwrite_mode: &OverwriteMode) -> Result<()> {
+    replace_link_target(link_action(None, target, overwrite_mode), target, overwrite_mode)?;
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::ffi::OsStrExt;
+        use std::os::unix::fs::MetadataExt;
+
+        let path = std::ffi::CString::new(target.as_os_str().as_bytes())
+            .map_err(|_| Error::Custom(format!("Invalid target path: {}", target.display())))?;
+        let mode = src_metadata.mode() as libc::mode_t;
+        let device = src_metadata.rdev() as libc::dev_t;
+        // SAFETY: `path` is a valid NUL-terminated string that outlives the call
+        if unsafe { libc::mknod(path.as_ptr(), mode, device) } != 0 {
+            return Err(Error::Io(std::io::Error::last_os_error()));
+        }
+        Ok(())
+    }
+
+    #[cfg(not(unix))]
+    {
+        let _ = src_metadata;
+        Err(Error::Custom(format!(
+            "Recreating special files is not supported on this platform: {}",
+            target.display()
+        )))
+    }
+}
+
+/// Tell the user a special file was left out of a directory copy
+pub fn warn_special_skipped(source: &Path, metadata: &Metadata) {
+    warn(format_args!(
+        "Skipping {} {} (use --specials to recreate it)",
+        special_kind(metadata),
+        source.display()
+    ));
+}
+
+/// Tell the user a followed symlink leading back into the tree being copied
+/// was left out, rather than copied over and over
+pub fn warn_symlink_cycle(source: &Path) {
+    warn(format_args!(
+        "Skipping {} (a symlink loop back to a directory already being copied)",
+        source.display()
+    ));
+}
+
+/// Tell the user an entry was left out for having no path left once
+/// `--strip-components` dropped `count` leading components
+pub fn warn_too_shallow(source: &Path, count: usize) {
+    warn(format_args!(
+        "Skipping {} (fewer than {} path components to strip)",
+        source.display(),
+        count + 1
+    ));
+}
+
+/// Apply `--case-collision` to the plan of a tree going to `target`, if
+/// its filesystem ignores case: warn about each source name that differs
+/// from an earlier one only in case, refuse the copy, or rename the later one
+pub fn handle_case_collisions(
+    plan: Vec<PlanEntry>,
+    target: &Path,
+    mode: CaseCollisionMode,
+) -> Result<Vec<PlanEntry>> {
+    if !is_case_insensitive(target) {
+        return Ok(plan);
+    }
+    let (plan, collisions) = resolve_case_collisions(plan, mode);
+    if let (CaseCollisionMode::Error, Some(collision)) = (mode, collisions.first()) {
+        return Err(Error::Usage(format!(
+            "{} and {} would be the same file: {} ignores case\n\
+             Tip: Use --case-collision=rename to copy the second under a new name.",
+            collision.first.display(),
+            collision.second.display(),
+            target.display()
+        )));
+    }
+    for collision in &collisions {
+        let warning = match &collision.renamed {
+            Some(renamed) => format!(
+                "Copying {} as {} ({} has the same name, ignoring case)",
+                collision.second.display(),
+                renamed.display(),
+                collision.first.display()
+            ),
+            None => format!(
+                "{} lands on {}: the target ignores case",
+                collision.second.display(),
+                collision.first.display()
+            ),
+        };
+        warn(warning);
+    }
+    Ok(plan)
+}
+
+/// Human-readable name for the type of a special file
+fn special_kind(metadata: &Metadata) -> &'static str {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::FileTypeExt;
+
+        let file_type = metadata.file_type();
+        if file_type.is_fifo() {
+            return "FIFO";
+        } else if file_type.is_socket() {
+            return "socket";
+        } else if file_type.is_block_device() {
+            return "block device";
+        } else if file_type.is_char_device() {
+            return "character device";
+        }
+    }
+
+    let _ = metadata;
+    "special file"
+}
+
+/// Directory an atomic directory copy is built in (`<target>.better-cp.partial`)
+pub fn staging_dir_path(target: &Path) -> PathBuf {
+    let mut name = target.as_os_str().to_os_string();
+    name.push(".better-cp.partial");
+    PathBuf::from(name)
+}
+
+/// Path an atomic copy writes to before renaming into place (`<target>.tmp`)
+pub fn atomic_temp_path(target: &Path) -> PathBuf {
+    let mut name = target.as_os_str().to_os_string();
+    name.push(".tmp");
+    PathBuf::from(name)
+}
+
+/// Decide whether smart overwrite mode should replace an existing target.
+///
+/// The target is replaced when the source is newer, or when both have the same
+/// mtime but different sizes (typically left behind by an interrupted copy).
+/// With `compare_checksums`, a tie on both mtime and size falls back to
+/// comparing checksums.
+pub(crate) fn smart_should_overwrite(
+    source: &Path,
+    src_metadata: &Metadata,
+    target: &Path,
+    compare_checksums: bool,
+) -> Result<bool> {
+    let tgt_metadata = fs::metadata(target).map_err(Error::Io)?;
+
+    let src_modified = src_metadata.modified().map_err(Error::Io)?;
+    let tgt_modified = tgt_metadata.modified().map_err(Error::Io)?;
+
+    if src_modified != tgt_modified {
+        return Ok(src_modified > tgt_modified);
+    }
+    if src_metadata.len() != tgt_metadata.len() {
+        return Ok(true);
+    }
+    if compare_checksums {
+        let src_checksum = compute_checksum(source, None).map_err(Error::Io)?;
+        let tgt_checksum = compute_checksum(target, None).map_err(Error::Io)?;
+        return Ok(src_checksum != tgt_checksum);
+    }
+    Ok(false)
+}
+
+/// Whether `--on-conflict=policy` lets `source` replace the existing `target`
+pub(crate) fn policy_allows_overwrite(
+    policy: ConflictPolicy,
+    source: &Path,
+    src_metadata: &Metadata,
+    target: &Path,
+) -> Result<bool> {
+    let tgt_metadata = fs::metadata(target).map_err(Error::Io)?;
+    let newer = || -> Result<bool> {
+        Ok(src_metadata.modified().map_err(Error::Io)? > tgt_metadata.modified().map_err(Error::Io)?)
+    };
+    let larger = src_metadata.len() > tgt_metadata.len();
+
+    match policy {
+        ConflictPolicy::Newer => newer(),
+        ConflictPolicy::Larger => Ok(larger),
+        ConflictPolicy::NewerOrLarger => Ok(larger || newer()?),
+        ConflictPolicy::DifferentChecksum => {
+            if src_metadata.len() != tgt_metadata.len() {
+                return Ok(true);
+            }
+            let src_checksum = compute_checksum(source, None).map_err(Error::Io)?;
+            let tgt_checksum = compute_checksum(target, None).map_err(Error::Io)?;
+            Ok(src_checksum != tgt_checksum)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use tempfile::{NamedTempFile, TempDir};
+
+    #[tokio::test]
+    async fn test_file_copy() {
+        let mut src = NamedTempFile::new().unwrap();
+        src.write_all(b"test content").unwrap();
+        src.flush().unwrap();
+
+        let dst = NamedTempFile::new().unwrap();
+        let dst_path = dst.path().to_path_buf();
+        drop(dst);
+
+        let copier = FileCopier::new(
+            src.path().to_path_buf(),
+            dst_path.clone(),
+            OverwriteMode::Always,
+            false,
+            false,
+            false,
+        );
+
+        let stats = copier.copy().await.unwrap();
+        assert_eq!(stats.transferred_bytes, 12);
+        let content = fs::read(&dst_path).unwrap();
+        assert_eq!(content, b"test content");
+    }
+
+    #[tokio::test]
+    async fn test_verify_reports_first_differing_byte() {
+        let temp_dir = TempDir::new().unwrap();
+        let src_path = temp_dir.path().join("source.bin");
+        let dst_path = temp_dir.path().join("dest.bin");
+        let data: Vec<u8> = (0..300_000u32).map(|i| (i % 251) as u8).collect();
+        fs::write(&src_path, &data).unwrap();
+
+        let copier = FileCopier::new(src_path, dst_path.clone(), OverwriteMode::Always, true, false, false)
+            .with_progress(false);
+        copier.copy().await.unwrap();
+        assert_eq!(copier.verify_copy(false, None).unwrap(), None);
+
+        // Flip one byte past the first comparison block
+        let mut corrupted = data;
+        corrupted[200_001] ^= 0x01;
+        fs::write(&dst_path, &corrupted).unwrap();
+        let err = copier.verify_copy(false, None).unwrap_err();
+        assert!(matches!(err, Error::VerificationFailed { offset: Some(200_001), .. }));
+        assert!(err.detailed_message().contains("First difference at byte 200001"));
+    }
+
+    #[tokio::test]
+    async fn test_verify_failure_writes_marker_and_rewinds_resume() {
+        let temp_dir = TempDir::new().unwrap();
+        let src_path = temp_dir.path().join("source.bin");
+        let dst_path = temp_dir.path().join("dest.bin");
+        fs::write(&src_path, b"original content").unwrap();
+
+        let copier = FileCopier::new(
+            src_path.clone(),
+            dst_path.clone(),
+            OverwriteMode::Always,
+            true,
+            true,
+            false,
+        )
+        .with_progress(false);
+        copier.copy().await.unwrap();
+
+        // Swap the target contents behind the copier's back
+        fs::write(&dst_path, b"tampered content").unwrap();
+        let err = copier.verify_copy(false, None).unwrap_err();
+        assert!(matches!(err, Error::VerificationFailed { .. }));
+        assert!(err.detailed_message().contains(".corrupt"));
+
+        let marker = FileCopier::corrupt_marker_path(&dst_path);
+        assert!(marker.exists());
+
+        let state = ResumeState::load(&dst_path).unwrap().unwrap();
+        assert_eq!(state.bytes_completed(), 0);
+
+        // A fresh run re-copies from the start and clears the marker
+        copier.copy().await.unwrap();
+        assert_eq!(fs::read(&dst_path).unwrap(), b"original content");
+        assert!(!marker.exists());
+    }
+
+    #[test]
+    fn test_resolve_target_into_existing_directory() {
+        let temp_dir = TempDir::new().unwrap();
+        let source = PathBuf::from("/data/file.txt");
+
+        let target = resolve_target(&source, temp_dir.path()).unwrap();
+        assert_eq!(target, temp_dir.path().join("file.txt"));
+    }
+
+    #[test]
+    fn test_resolve_target_rename() {
+        let temp_dir = TempDir::new().unwrap();
+        let source = PathBuf::from("/data/file.txt");
+        let destination = temp_dir.path().join("newname");
+
+        let target = resolve_target(&source, &destination).unwrap();
+        assert_eq!(target, destination);
+    }
+
+    #[test]
+    fn test_resolve_target_trailing_slash() {
+        let temp_dir = TempDir::new().unwrap();
+        let source = PathBuf::from("/data/file.txt");
+        let destination = PathBuf::from(format!(
+            "{}{}",
+            temp_dir.path().join("missing").display(),
+            std::path::MAIN_SEPARATOR
+        ));
+
+        let target = resolve_target(&source, &destination).unwrap();
+        assert_eq!(target, temp_dir.path().join("missing").join("file.txt"));
+    }
+
+    #[tokio::test]
+    async fn test_directory_copy() {
+        // Create source directory with files
+        let src_dir = TempDir::new().unwrap();
+        let src_path = src_dir.path();
+        
+        // Create test files
+        let mut file1 = fs::File::create(src_path.join("file1.txt")).unwrap();
+        file1.write_all(b"content1").unwrap();
+        
+        let mut file2 = fs::File::create(src_path.join("file2.txt")).unwrap();
+        file2.write_all(b"content2").unwrap();
+        
+        // Create subdirectory with file
+        fs::create_dir(src_path.join("subdir")).unwrap();
+        let mut file3 = fs::File::create(src_path.join("subdir/file3.txt")).unwrap();
+        file3.write_all(b"content3").unwrap();
+        
+        // Create destination directory
+        let dst_dir = TempDir::new().unwrap();
+        let dst_path = dst_dir.path().join("copy");
+        
+        // Copy directory
+        let result = copy_directory(src_path, &dst_path, OverwriteMode::Always, false, false).await;
+        assert!(result.is_ok(), "Directory copy failed: {:?}", result.err());
+        assert_eq!(result.unwrap().transferred_bytes, 24);
+        
+        // Verify files were copied
+        assert_eq!(fs::read(dst_path.join("file1.txt")).unwrap(), b"content1");
+        assert_eq!(fs::read(dst_path.join("file2.txt")).unwrap(), b"content2");
+        assert_eq!(fs::read(dst_path.join("subdir/file3.txt")).unwrap(), b"content3");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_directory_copy_preserves_mode_and_mtime() {
+        use std::os::unix::fs::PermissionsExt;
+        use std::time::{Duration, SystemTime};
+
+        let temp_dir = TempDir::new().unwrap();
+        let src_dir = temp_dir.path().join("private");
+        fs::create_dir(&src_dir).unwrap();
+        fs::write(src_dir.join("secret.txt"), b"shh").unwrap();
+
+        let old_time = SystemTime::now() - Duration::from_secs(3600);
+        File::open(&src_dir).unwrap().set_modified(old_time).unwrap();
+        fs::set_permissions(&src_dir, fs::Permissions::from_mode(0o700)).unwrap();
+
+        let dst_dir = temp_dir.path().join("copy");
+        copy_directory(&src_dir, &dst_dir, OverwriteMode::Always, false, false)
+            .await
+            .unwrap();
+
+        let dst_metadata = fs::metadata(&dst_dir).unwrap();
+        assert_eq!(dst_metadata.permissions().mode() & 0o777, 0o700);
+        assert_eq!(
+            dst_metadata.modified().unwrap(),
+            fs::metadata(&src_dir).unwrap().modified().unwrap()
+        );
+    }
+
+    /// Write both files and give them the same mtime
+    fn write_same_age(source: &Path, source_data: &[u8], target: &Path, target_data: &[u8]) {
+        let stamp = std::time::SystemTime::now() - std::time::Duration::from_secs(60);
+        fs::write(source, source_data).unwrap();
+        fs::write(target, target_data).unwrap();
+        for path in [source, target] {
+            File::options().write(true).open(path).unwrap().set_modified(stamp).unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn test_smart_overwrites_equal_mtime_different_size() {
+        let temp_dir = TempDir::new().unwrap();
+        let src_path = temp_dir.path().join("source.bin");
+        let dst_path = temp_dir.path().join("dest.bin");
+        write_same_age(&src_path, b"complete contents", &dst_path, b"partial");
+
+        let copier = FileCopier::new(
+            src_path.clone(),
+            dst_path.clone(),
+            OverwriteMode::Smart,
+            false,
+            false,
+            false,
+        )
+        .with_progress(false);
+        copier.copy().await.unwrap();
+
+        assert_eq!(fs::read(&dst_path).unwrap(), b"complete contents");
+    }
+
+    #[tokio::test]
+    async fn test_smart_checksum_breaks_mtime_and_size_ties() {
+        let temp_dir = TempDir::new().unwrap();
+        let src_path = temp_dir.path().join("source.bin");
+        let dst_path = temp_dir.path().join("dest.bin");
+        write_same_age(&src_path, b"new data", &dst_path, b"old data");
+
+        let src_metadata = fs::metadata(&src_path).unwrap();
+        assert!(!smart_should_overwrite(&src_path, &src_metadata, &dst_path, false).unwrap());
+        assert!(smart_should_overwrite(&src_path, &src_metadata, &dst_path, true).unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_on_conflict_larger_skips_bigger_target() {
+        let temp_dir = TempDir::new().unwrap();
+        let src_path = temp_dir.path().join("source.bin");
+        let dst_path = temp_dir.path().join("dest.bin");
+        fs::write(&src_path, b"short").unwrap();
+        fs::write(&dst_path, b"the longer original").unwrap();
+
+        let copier = |src: &Path| {
+            FileCopier::new(
+                src.to_path_buf(),
+                dst_path.clone(),
+                OverwriteMode::Policy(ConflictPolicy::Larger),
+                false,
+                false,
+                false,
+            )
+            .with_progress(false)
+        };
+        assert!(copier(&src_path).copy().await.unwrap_err().is_skip());
+        assert_eq!(fs::read(&dst_path).unwrap(), b"the longer original");
+
+        fs::write(&src_path, b"an even longer replacement").unwrap();
+        copier(&src_path).copy().await.unwrap();
+        assert_eq!(fs::read(&dst_path).unwrap(), b"an even longer replacement");
+    }
+
+    #[tokio::test]
+    async fn test_on_conflict_different_checksum_skips_identical() {
+        let temp_dir = TempDir::new().unwrap();
+        let src_path = temp_dir.path().join("source.bin");
+        let dst_path = temp_dir.path().join("dest.bin");
+        fs::write(&src_path, b"same bytes").unwrap();
+        fs::write(&dst_path, b"same bytes").unwrap();
+
+        let policy = ConflictPolicy::DifferentChecksum;
+        let src_metadata = fs::metadata(&src_path).unwrap();
+        assert!(!policy_allows_overwrite(policy, &src_path, &src_metadata, &dst_path).unwrap());
+
+        let err = FileCopier::new(
+            src_path.clone(),
+            dst_path.clone(),
+            OverwriteMode::Policy(policy),
+            false,
+            false,
+            false,
+        )
+        .with_progress(false)
+        .copy()
+        .await
+        .unwrap_err();
+        assert!(err.is_skip());
+
+        // Same size, different contents: replaced
+        fs::write(&dst_path, b"some bytes").unwrap();
+        assert!(policy_allows_overwrite(policy, &src_path, &src_metadata, &dst_path).unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_preallocate_reserves_the_target_up_front() {
+        use std::sync::atomic::{AtomicU64, Ordering};
+        use std::sync::Arc;
+
+        let temp_dir = TempDir::new().unwrap();
+        let src_path = temp_dir.path().join("source.bin");
+        let content: Vec<u8> = (0..1_000_000u32).map(|i| (i % 251) as u8).collect();
+        fs::write(&src_path, &content).unwrap();
+
+        for preallocate in [false, true] {
+            let dst_path = temp_dir.path().join(format!("dest-{}.bin", preallocate));
+            // The target's length when the first chunk has landed
+            let first_len = Arc::new(AtomicU64::new(u64::MAX));
+            let options = {
+                let first_len = first_len.clone();
+                let dst_path = dst_path.clone();
+                CopyOptions::unattended().on_progress(move |_| {
+                    if first_len.load(Ordering::SeqCst) == u64::MAX {
+                        first_len.store(fs::metadata(&dst_path).unwrap().len(), Ordering::SeqCst);
+                    }
+                })
+            };
+            FileCopier::new(src_path.clone(), dst_path.clone(), OverwriteMode::Always, true, false, false)
+                .with_progress(false)
+                .with_buffer_size(MIN_BUFFER_SIZE)
+                .with_preallocate(preallocate)
+                .with_options(options)
+                .copy()
+                .await
+                .unwrap();
+
+            let expected = if preallocate { content.len() } else { MIN_BUFFER_SIZE } as u64;
+            assert_eq!(first_len.load(Ordering::SeqCst), expected, "preallocate: {}", preallocate);
+            assert_eq!(fs::read(&dst_path).unwrap(), content);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_copy_through_callbacks_only() {
+        use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+        use std::sync::Arc;
+
+        let temp_dir = TempDir::new().unwrap();
+        let src_path = temp_dir.path().join("source.bin");
+        let dst_path = temp_dir.path().join("dest.bin");
+        fs::write(&src_path, b"replacement").unwrap();
+        fs::write(&dst_path, b"existing").unwrap();
+
+        let conflicts = Arc::new(AtomicUsize::new(0));
+        let reported = Arc::new(AtomicU64::new(0));
+        let options = {
+            let conflicts = conflicts.clone();
+            let reported = reported.clone();
+            CopyOptions::new(move |_, _, _| {
+                conflicts.fetch_add(1, Ordering::SeqCst);
+                Ok(OverwriteChoice::Overwrite)
+            })
+            .on_progress(move |stats| reported.store(stats.transferred_bytes, Ordering::SeqCst))
+        };
+
+        let copier = FileCopier::new(
+            src_path.clone(),
+            dst_path.clone(),
+            OverwriteMode::Prompt,
+            true,
+            false,
+            false,
+        )
+        .with_progress(false)
+        .with_options(options);
+        copier.copy().await.unwrap();
+
+        assert_eq!(fs::read(&dst_path).unwrap(), b"replacement");
+        assert_eq!(conflicts.load(Ordering::SeqCst), 1);
+        assert_eq!(reported.load(Ordering::SeqCst), 11);
+    }
+
+    #[tokio::test]
+    async fn test_pipelined_copy_is_byte_identical() {
+        let temp_dir = TempDir::new().unwrap();
+        let src_path = temp_dir.path().join("source.bin");
+        // Many chunks, the last one partial
+        let source: Vec<u8> = (0..25_321u32).map(|i| (i * 7 % 253) as u8).collect();
+        fs::write(&src_path, &source).unwrap();
+
+        for pipeline in [true, false] {
+            let dst_path = temp_dir.path().join(format!("dest-{}.bin", pipeline));
+            let stats = FileCopier::new(
+                src_path.clone(),
+                dst_path.clone(),
+                OverwriteMode::Never,
+                true,
+                false,
+                true,
+            )
+            .with_progress(false)
+            .with_buffer_size(1_000)
+            .with_pipeline(pipeline)
+            .copy()
+            .await
+            .unwrap();
+
+            assert_eq!(stats.transferred_bytes, source.len() as u64);
+            assert_eq!(fs::read(&dst_path).unwrap(), source);
+        }
+    }
+
+    #[test]
+    fn test_buffer_sized_to_the_file() {
+        let copier = FileCopier::new(
+            PathBuf::from("source"),
+            PathBuf::from("target"),
+            OverwriteMode::Never,
+            true,
+            false,
+            false,
+        );
+
+        // A 2 KB file gets the floor, not a 64 MB buffer
+        assert_eq!(copier.buffer_size(2 * 1024), MIN_BUFFER_SIZE);
+        assert_eq!(copier.buffer_size(3 * 1024 * 1024), 3 * 1024 * 1024);
+        assert_eq!(copier.buffer_size(50 << 30), CHUNK_SIZE);
+
+        let copier = copier.with_buffer_size(1 << 30);
+        assert_eq!(copier.buffer_size(50 << 30), 1 << 30);
+
+        // Streams of unknown length (stdin) get the floor, within --buffer
+        assert_eq!(buffer_size_for(0, CHUNK_SIZE), MIN_BUFFER_SIZE);
+        assert_eq!(buffer_size_for(0, 4096), 4096);
+    }
+
+    #[test]
+    fn test_copy_stream_through_a_small_buffer() {
+        let data: Vec<u8> = (0..10_000u32).map(|i| (i % 251) as u8).collect();
+        let mut written = Vec::new();
+        let tracker = ProgressTracker::new(data.len() as u64, false);
+
+        let total = copy_stream(&mut data.as_slice(), &mut written, &tracker, 1024).unwrap();
+
+        assert_eq!(total, data.len() as u64);
+        assert_eq!(written, data);
+    }
+
+    #[tokio::test]
+    async fn test_update_inplace_writes_only_changed_blocks() {
+        let temp_dir = TempDir::new().unwrap();
+        let src_path = temp_dir.path().join("source.bin");
+        let dst_path = temp_dir.path().join("dest.bin");
+
+        let source: Vec<u8> = (0..10_000u32).map(|i| (i % 251) as u8).collect();
+        let mut target = source.clone();
+        target[4_321] ^= 0xff;
+        fs::write(&src_path, &source).unwrap();
+        fs::write(&dst_path, &target).unwrap();
+
+        let copier = FileCopier::new(
+            src_path,
+            dst_path.clone(),
+            OverwriteMode::Always,
+            true,
+            false,
+            false,
+        )
+        .with_progress(false)
+        .with_buffer_size(1_000)
+        .with_update_inplace(true);
+
+        // Ten blocks compared, only the one holding the flipped byte rewritten
+        let tracker = ProgressTracker::with_mode(source.len() as u64, ProgressMode::Hidden);
+        assert_eq!(copier.write_changed_blocks(&tracker).unwrap(), 1);
+        assert_eq!(fs::read(&dst_path).unwrap(), source);
+
+        // Already identical: nothing left to write, and the full path still verifies
+        let tracker = ProgressTracker::with_mode(source.len() as u64, ProgressMode::Hidden);
+        assert_eq!(copier.write_changed_blocks(&tracker).unwrap(), 0);
+        let stats = copier.copy().await.unwrap();
+        assert_eq!(stats.transferred_bytes, source.len() as u64);
+    }
+
+    #[tokio::test]
+    async fn test_compress_then_decompress_round_trips() {
+        let temp_dir = TempDir::new().unwrap();
+        let src_path = temp_dir.path().join("app.log");
+        let original: Vec<u8> = b"GET /index.html 200\n".repeat(5_000);
+        fs::write(&src_path, &original).unwrap();
+
+        let compressed = temp_dir.path().join("backup/app.log");
+        let copier = FileCopier::new(src_path, compressed, OverwriteMode::Always, true, false, false)
+            .with_progress(false)
+            .with_transcode(Some(Transcode::Compress(Compression::Zstd)));
+        let stats = copier.copy().await.unwrap();
+        assert_eq!(stats.transferred_bytes, original.len() as u64);
+
+        let compressed = temp_dir.path().join("backup/app.log.zst");
+        assert!(fs::metadata(&compressed).unwrap().len() < original.len() as u64);
+
+        let restored = temp_dir.path().join("restored/app.log.zst");
+        FileCopier::new(compressed, restored, OverwriteMode::Always, true, false, false)
+            .with_progress(false)
+            .with_transcode(Some(Transcode::Decompress(Compression::Zstd)))
+            .copy()
+            .await
+            .unwrap();
+        assert_eq!(fs::read(temp_dir.path().join("restored/app.log")).unwrap(), original);
+    }
+
+    #[tokio::test]
+    async fn test_directory_resume_skips_completed_files() {
+        let temp_dir = TempDir::new().unwrap();
+        let source = temp_dir.path().join("source");
+        let target = temp_dir.path().join("target");
+        fs::create_dir_all(&source).unwrap();
+        for name in ["a.txt", "b.txt", "c.txt", "d.txt"] {
+            fs::write(source.join(name), name).unwrap();
+        }
+
+        // An interrupted run that got through half the files
+        fs::create_dir_all(&target).unwrap();
+        let mut manifest = DirResumeState::new(&target);
+        for name in ["a.txt", "b.txt"] {
+            let path = source.join(name);
+            fs::write(target.join(name), b"copied earlier").unwrap();
+            manifest.mark_complete(&path, &fs::metadata(&path).unwrap()).unwrap();
+        }
+        drop(manifest);
+
+        // `Never` makes any second copy onto an existing file an error
+        let stats = DirectoryCopier::new(source.clone(), target.clone(), OverwriteMode::Never, true)
+            .with_progress(false)
+            .with_resume(true)
+            .copy()
+            .await
+            .unwrap();
+
+        assert_eq!(stats.files_skipped, 2);
+        assert_eq!(fs::read(target.join("a.txt")).unwrap(), b"copied earlier");
+        assert_eq!(fs::read(target.join("b.txt")).unwrap(), b"copied earlier");
+        assert_eq!(fs::read(target.join("c.txt")).unwrap(), b"c.txt");
+        assert_eq!(fs::read(target.join("d.txt")).unwrap(), b"d.txt");
+        assert!(!ResumeState::state_file_path(&target).exists());
+    }
+
+    #[tokio::test]
+    async fn test_remove_source_files_after_verified_copy() {
+        let temp_dir = TempDir::new().unwrap();
+        let src_path = temp_dir.path().join("source.bin");
+        let dst_path = temp_dir.path().join("dest.bin");
+        fs::write(&src_path, b"move me").unwrap();
+
+        let copier = FileCopier::new(
+            src_path.clone(),
+            dst_path.clone(),
+            OverwriteMode::Always,
+            true,
+            false,
+            false,
+        )
+        .with_progress(false)
+        .with_remove_source_files(true);
+        copier.copy().await.unwrap();
+
+        assert!(!src_path.exists());
+        assert_eq!(fs::read(&dst_path).unwrap(), b"move me");
+    }
+
+    #[test]
+    fn test_reflinked_copy_skips_reading_the_target() {
+        let temp_dir = TempDir::new().unwrap();
+        let src_path = temp_dir.path().join("source.bin");
+        let dst_path = temp_dir.path().join("dest.bin");
+        fs::write(&src_path, b"shared blocks").unwrap();
+        // Were the target read, this would fail verification
+        fs::write(&dst_path, b"never read").unwrap();
+
+        let copier = FileCopier::new(
+            src_path.clone(),
+            dst_path.clone(),
+            OverwriteMode::Always,
+            true,
+            false,
+            false,
+        )
+        .with_progress(false);
+        assert_eq!(copier.verify_copy(true, None).unwrap(), None);
+        assert!(copier.verify_copy(false, None).is_err());
+
+        // --verify=full still compares both sides
+        let copier = copier.with_verify_mode(VerifyMode::Full);
+        assert!(matches!(copier.verify_copy(true, None), Err(Error::VerificationFailed { .. })));
+    }
+
+    #[tokio::test]
+    async fn test_remove_source_files_keeps_source_on_mismatch() {
+        let temp_dir = TempDir::new().unwrap();
+        let src_path = temp_dir.path().join("source.bin");
+        let dst_path = temp_dir.path().join("dest.bin");
+        fs::write(&src_path, b"move me").unwrap();
+
+        let copier = FileCopier::new(
+            src_path.clone(),
+            dst_path.clone(),
+            OverwriteMode::Always,
+            true,
+            false,
+            false,
+        )
+        .with_progress(false)
+        .with_remove_source_files(true);
+
+        // Simulate a copy that landed corrupted
+        fs::write(&dst_path, b"garbled").unwrap();
+        let err = copier.verify_and_release_source(false, None).unwrap_err();
+        assert!(matches!(err, Error::VerificationFailed { .. }));
+        assert!(src_path.exists());
     }
 
-    // Create target directory
-    fs::create_dir_all(target)
-        .map_err(Error::Io)?;
+    #[tokio::test]
+    async fn test_remove_source_files_requires_verification() {
+        let temp_dir = TempDir::new().unwrap();
+        let src_path = temp_dir.path().join("source.bin");
+        let dst_path = temp_dir.path().join("dest.bin");
+        fs::write(&src_path, b"move me").unwrap();
 
-    // Walk source directory
-    for entry in fs::read_dir(source)
-        .map_err(Error::Io)?
-    {
-        let entry = entry.map_err(Error::Io)?;
-        let path = entry.path();
-        let file_name = entry.file_name();
-        let target_path = target.join(&file_name);
-
-        if path.is_dir() {
-            // Use Box::pin to allow recursion without requiring infinite-sized future
-            Box::pin(copy_directory_impl(&path, &target_path, overwrite_mode.clone(), verify)).await?;
-        } else {
-            let copier = FileCopier::new(
-                path,
-                target_path,
-                overwrite_mode.clone(),
-                verify,
-                false,
-                false,
-            );
-            copier.copy().await?;
+        let copier = FileCopier::new(
+            src_path.clone(),
+            dst_path.clone(),
+            OverwriteMode::Always,
+            false,
+            false,
+            false,
+        )
+        .with_progress(false)
+        .with_remove_source_files(true);
+
+        assert!(matches!(copier.copy().await, Err(Error::Usage(_))));
+        assert!(src_path.exists());
+        assert!(!dst_path.exists());
+    }
+
+    #[tokio::test]
+    async fn test_update_leaves_newer_target_alone() {
+        let temp_dir = TempDir::new().unwrap();
+        let src_path = temp_dir.path().join("source.txt");
+        let dst_path = temp_dir.path().join("dest.txt");
+        fs::write(&src_path, b"new contents").unwrap();
+        fs::write(&dst_path, b"old").unwrap();
+        let older = std::time::SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000);
+        let newer = older + Duration::from_secs(60);
+        File::options().write(true).open(&src_path).unwrap().set_modified(older).unwrap();
+        File::options().write(true).open(&dst_path).unwrap().set_modified(newer).unwrap();
+        #[cfg(unix)]
+        let inode = std::os::unix::fs::MetadataExt::ino(&fs::metadata(&dst_path).unwrap());
+
+        let copier = || {
+            FileCopier::new(src_path.clone(), dst_path.clone(), OverwriteMode::Always, true, false, true)
+                .with_progress(false)
+                .with_update(true)
+        };
+        let err = copier().copy().await.unwrap_err();
+        assert!(err.is_skip(), "{:?}", err);
+        assert_eq!(fs::read(&dst_path).unwrap(), b"old");
+        assert_eq!(fs::metadata(&dst_path).unwrap().modified().unwrap(), newer);
+        #[cfg(unix)]
+        assert_eq!(std::os::unix::fs::MetadataExt::ino(&fs::metadata(&dst_path).unwrap()), inode);
+
+        // A source newer than the target is copied
+        File::options().write(true).open(&src_path).unwrap().set_modified(newer + Duration::from_secs(60)).unwrap();
+        copier().copy().await.unwrap();
+        assert_eq!(fs::read(&dst_path).unwrap(), b"new contents");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_chmod_overrides_the_preserved_mode() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp_dir = TempDir::new().unwrap();
+        let src_path = temp_dir.path().join("source.sh");
+        fs::write(&src_path, b"#!/bin/sh\n").unwrap();
+        fs::set_permissions(&src_path, fs::Permissions::from_mode(0o755)).unwrap();
+
+        for (spec, expected) in [("0o600", 0o600), ("go-rwx", 0o700)] {
+            let dst_path = temp_dir.path().join(format!("dest-{}.sh", spec));
+            FileCopier::new(src_path.clone(), dst_path.clone(), OverwriteMode::Always, true, false, true)
+                .with_progress(false)
+                .with_preserve(PreserveSet::mode_and_timestamps())
+                .with_chmod(Some(ChmodSpec::parse(spec).unwrap()))
+                .copy()
+                .await
+                .unwrap();
+            let mode = fs::metadata(&dst_path).unwrap().permissions().mode() & 0o7777;
+            assert_eq!(mode, expected, "--chmod {}", spec);
         }
     }
 
-    Ok(())
-}
+    #[tokio::test]
+    async fn test_touch_only_retimestamps_identical_target() {
+        let temp_dir = TempDir::new().unwrap();
+        let src_path = temp_dir.path().join("source.txt");
+        let dst_path = temp_dir.path().join("dest.txt");
+        fs::write(&src_path, b"same bytes").unwrap();
+        fs::write(&dst_path, b"same bytes").unwrap();
+        let drifted = std::time::SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000);
+        File::options().write(true).open(&dst_path).unwrap().set_modified(drifted).unwrap();
+        #[cfg(unix)]
+        let inode = std::os::unix::fs::MetadataExt::ino(&fs::metadata(&dst_path).unwrap());
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use std::io::Write;
-    use tempfile::{NamedTempFile, TempDir};
+        // Atomic: a real copy would rename a new file into place
+        let stats = FileCopier::new(src_path.clone(), dst_path.clone(), OverwriteMode::Always, true, false, true)
+            .with_progress(false)
+            .with_touch_only(true)
+            .copy()
+            .await
+            .unwrap();
+
+        assert_eq!(stats.files_touched, 1);
+        assert_eq!(stats.transferred_bytes, 0);
+        assert_eq!(fs::read(&dst_path).unwrap(), b"same bytes");
+        assert_eq!(
+            fs::metadata(&dst_path).unwrap().modified().unwrap(),
+            fs::metadata(&src_path).unwrap().modified().unwrap()
+        );
+        #[cfg(unix)]
+        assert_eq!(std::os::unix::fs::MetadataExt::ino(&fs::metadata(&dst_path).unwrap()), inode);
+
+        // A target that differs is copied as usual
+        fs::write(&dst_path, b"other bytes").unwrap();
+        let stats = FileCopier::new(src_path, dst_path.clone(), OverwriteMode::Always, true, false, true)
+            .with_progress(false)
+            .with_touch_only(true)
+            .copy()
+            .await
+            .unwrap();
+        assert_eq!(stats.files_touched, 0);
+        assert_eq!(fs::read(&dst_path).unwrap(), b"same bytes");
+    }
 
     #[tokio::test]
-    async fn test_file_copy() {
-        let mut src = NamedTempFile::new().unwrap();
-        src.write_all(b"test content").unwrap();
-        src.flush().unwrap();
+    async fn test_attrs_only_leaves_contents_alone() {
+        let temp_dir = TempDir::new().unwrap();
+        let src_path = temp_dir.path().join("source.txt");
+        let dst_path = temp_dir.path().join("dest.txt");
+        fs::write(&src_path, b"source bytes").unwrap();
+        fs::write(&dst_path, b"target bytes").unwrap();
+        let stamp = std::time::SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000);
+        File::options().write(true).open(&src_path).unwrap().set_modified(stamp).unwrap();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&src_path, fs::Permissions::from_mode(0o640)).unwrap();
+            fs::set_permissions(&dst_path, fs::Permissions::from_mode(0o600)).unwrap();
+        }
 
-        let dst = NamedTempFile::new().unwrap();
-        let dst_path = dst.path().to_path_buf();
-        drop(dst);
+        let copier = |target: &Path| {
+            FileCopier::new(src_path.clone(), target.to_path_buf(), OverwriteMode::Never, true, false, false)
+                .with_progress(false)
+                .with_preserve(PreserveSet { mode: true, timestamps: true, ..PreserveSet::default() })
+                .with_attrs_only(true)
+        };
+        let stats = copier(&dst_path).copy().await.unwrap();
+
+        assert_eq!(stats.files_touched, 1);
+        assert_eq!(fs::read(&dst_path).unwrap(), b"target bytes");
+        let target = fs::metadata(&dst_path).unwrap();
+        assert_eq!(target.modified().unwrap(), stamp);
+        #[cfg(unix)]
+        assert_eq!(std::os::unix::fs::PermissionsExt::mode(&target.permissions()) & 0o777, 0o640);
+
+        // Nothing to fix up: no target, or one whose size says it isn't a copy
+        assert!(copier(&temp_dir.path().join("missing.txt")).copy().await.is_err());
+        fs::write(&dst_path, b"short").unwrap();
+        assert!(copier(&dst_path).copy().await.is_err());
+        assert_eq!(fs::read(&dst_path).unwrap(), b"short");
+    }
+
+    #[tokio::test]
+    async fn test_skip_and_count_extract_a_slice() {
+        let temp_dir = TempDir::new().unwrap();
+        let src_path = temp_dir.path().join("source.bin");
+        let dst_path = temp_dir.path().join("slice.bin");
+        let source: Vec<u8> = (0..10_000u32).map(|i| (i % 251) as u8).collect();
+        fs::write(&src_path, &source).unwrap();
+
+        let copier = |skip, count| {
+            FileCopier::new(src_path.clone(), dst_path.clone(), OverwriteMode::Always, true, false, true)
+                .with_progress(false)
+                .with_buffer_size(1_000)
+                .with_range(skip, count)
+        };
+        let stats = copier(2_500, Some(4_321)).copy().await.unwrap();
+
+        assert_eq!(stats.transferred_bytes, 4_321);
+        assert_eq!(fs::read(&dst_path).unwrap(), &source[2_500..6_821]);
+        assert!(!ResumeState::state_file_path(&dst_path).exists());
+
+        // Without --count, the rest of the file
+        copier(9_000, None).copy().await.unwrap();
+        assert_eq!(fs::read(&dst_path).unwrap(), &source[9_000..]);
+
+        let err = copier(9_000, Some(1_001)).copy().await.unwrap_err();
+        assert!(matches!(err, Error::Usage(_)));
+    }
+
+    #[tokio::test]
+    async fn test_ignore_errors_copies_the_rest() {
+        let temp_dir = TempDir::new().unwrap();
+        let source = temp_dir.path().join("src");
+        let target = temp_dir.path().join("dst");
+        fs::create_dir_all(source.join("sub")).unwrap();
+        fs::write(source.join("a.txt"), b"a").unwrap();
+        fs::write(source.join("sub/bad.txt"), b"bad").unwrap();
+        fs::write(source.join("sub/z.txt"), b"z").unwrap();
+
+        // An unreadable source, or for root (who reads anything) a target
+        // path a directory already holds
+        #[cfg(unix)]
+        let unreadable = unsafe { libc::geteuid() } != 0;
+        #[cfg(unix)]
+        if unreadable {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(source.join("sub/bad.txt"), fs::Permissions::from_mode(0o000)).unwrap();
+        }
+        #[cfg(not(unix))]
+        let unreadable = false;
+        if !unreadable {
+            fs::create_dir_all(target.join("sub/bad.txt/occupied")).unwrap();
+        }
+
+        let copier = || {
+            DirectoryCopier::new(source.clone(), target.clone(), OverwriteMode::Always, true)
+                .with_progress(false)
+        };
+        assert!(copier().copy().await.is_err());
+
+        let stats = copier().with_ignore_errors(true).copy().await.unwrap();
+        assert_eq!(stats.files_failed, 1);
+        assert_eq!(fs::read(target.join("a.txt")).unwrap(), b"a");
+        assert_eq!(fs::read(target.join("sub/z.txt")).unwrap(), b"z");
+    }
+
+    #[tokio::test]
+    async fn test_strip_components_places_files_shallower() {
+        let temp_dir = TempDir::new().unwrap();
+        let source = temp_dir.path().join("src");
+        let target = temp_dir.path().join("dst");
+        fs::create_dir_all(source.join("a/b/c")).unwrap();
+        fs::write(source.join("a/b/c/deep.txt"), b"deep").unwrap();
+        fs::write(source.join("a/b/mid.txt"), b"mid").unwrap();
+        fs::write(source.join("top.txt"), b"top").unwrap();
+
+        let stats = DirectoryCopier::new(source, target.clone(), OverwriteMode::Always, true)
+            .with_progress(false)
+            .with_strip_components(1)
+            .copy()
+            .await
+            .unwrap();
+
+        assert_eq!(fs::read(target.join("b/c/deep.txt")).unwrap(), b"deep");
+        assert_eq!(fs::read(target.join("b/mid.txt")).unwrap(), b"mid");
+        assert!(!target.join("a").exists());
+        // Nothing is left of top.txt once one component is dropped
+        assert!(!target.join("top.txt").exists());
+        assert_eq!(stats.files_skipped, 1);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_preserve_links_keeps_hard_links() {
+        use std::os::unix::fs::MetadataExt;
+
+        let temp_dir = TempDir::new().unwrap();
+        let source = temp_dir.path().join("src");
+        let target = temp_dir.path().join("dst");
+        fs::create_dir_all(source.join("bin")).unwrap();
+        fs::write(source.join("bin/tool"), b"one file, two names").unwrap();
+        fs::hard_link(source.join("bin/tool"), source.join("tool-alias")).unwrap();
+        fs::write(source.join("copy-of-tool"), b"one file, two names").unwrap();
+
+        let preserve = PreserveSet { links: true, ..PreserveSet::default() };
+        let stats = DirectoryCopier::new(source, target.clone(), OverwriteMode::Always, true)
+            .with_progress(false)
+            .with_preserve(preserve)
+            .copy()
+            .await
+            .unwrap();
+
+        let inode = |path: &str| fs::metadata(target.join(path)).unwrap().ino();
+        assert_eq!(inode("bin/tool"), inode("tool-alias"));
+        // Same contents is not the same file
+        assert_ne!(inode("bin/tool"), inode("copy-of-tool"));
+        assert_eq!(stats.files_linked, 1);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_dedup_links_identical_files() {
+        use std::os::unix::fs::MetadataExt;
+
+        let temp_dir = TempDir::new().unwrap();
+        let source = temp_dir.path().join("src");
+        let target = temp_dir.path().join("dst");
+        fs::create_dir_all(source.join("assets")).unwrap();
+        fs::write(source.join("logo.png"), b"the same bytes").unwrap();
+        fs::write(source.join("assets/logo.png"), b"the same bytes").unwrap();
+        fs::write(source.join("other.png"), b"something else").unwrap();
+
+        let stats = DirectoryCopier::new(source, target.clone(), OverwriteMode::Always, true)
+            .with_progress(false)
+            .with_dedup(true)
+            .copy()
+            .await
+            .unwrap();
+
+        let inode = |path: &str| fs::metadata(target.join(path)).unwrap().ino();
+        assert_eq!(inode("logo.png"), inode("assets/logo.png"));
+        assert_ne!(inode("logo.png"), inode("other.png"));
+        assert_eq!(fs::read(target.join("assets/logo.png")).unwrap(), b"the same bytes");
+        assert_eq!(stats.files_linked, 1);
+    }
+
+    #[test]
+    fn test_dedup_hashes_only_colliding_sizes() {
+        let temp_dir = TempDir::new().unwrap();
+        let (first, second, source) =
+            (temp_dir.path().join("first"), temp_dir.path().join("second"), temp_dir.path().join("source"));
+        fs::write(&first, b"four").unwrap();
+        fs::write(&second, b"five!").unwrap();
+        fs::write(&source, b"five!").unwrap();
+        let target = |path: &Path| path.with_extension("copy");
+
+        let mut walk = DirWalk::default();
+        walk.wrote(&first, &target(&first), 4, None);
+        walk.wrote(&second, &target(&second), 5, None);
+        // No other file of its size: nothing is read
+        assert_eq!(walk.same_contents(&temp_dir.path().join("missing"), 6).unwrap(), (None, None));
+
+        let (duplicate, digest) = walk.same_contents(&source, 5).unwrap();
+        assert_eq!(duplicate, Some(target(&second)));
+        assert!(digest.is_some());
+        // The file of the other size was never hashed
+        assert_eq!(walk.written[&4], vec![(first.clone(), target(&first), None)]);
+    }
+
+    #[tokio::test]
+    async fn test_fsync_flushes_temp_file_and_directory() {
+        let temp_dir = TempDir::new().unwrap();
+        let src_path = temp_dir.path().join("source.bin");
+        let dst_path = temp_dir.path().join("dest.bin");
+        fs::write(&src_path, b"durable content").unwrap();
+
+        FileCopier::new(src_path.clone(), dst_path.clone(), OverwriteMode::Always, true, false, true)
+            .with_progress(false)
+            .with_fsync(true)
+            .copy()
+            .await
+            .unwrap();
+
+        assert_eq!(fs::read(&dst_path).unwrap(), b"durable content");
+        // The temp file was flushed before the rename, the directory after
+        assert!(crate::durability::was_synced(&atomic_temp_path(&dst_path)));
+        #[cfg(unix)]
+        assert!(crate::durability::was_synced(temp_dir.path()));
+
+        // Copies that bypass the usual writer are flushed too
+        FileCopier::new(src_path.clone(), dst_path.clone(), OverwriteMode::Always, true, false, true)
+            .with_progress(false)
+            .with_fsync(true)
+            .with_transcode(Some(Transcode::Compress(Compression::Zstd)))
+            .copy()
+            .await
+            .unwrap();
+        assert!(crate::durability::was_synced(&temp_dir.path().join("dest.bin.zst.tmp")));
+
+        fs::write(&src_path, b"durable CONTENT").unwrap();
+        FileCopier::new(src_path, dst_path.clone(), OverwriteMode::Always, true, false, false)
+            .with_progress(false)
+            .with_fsync(true)
+            .with_update_inplace(true)
+            .copy()
+            .await
+            .unwrap();
+        assert_eq!(fs::read(&dst_path).unwrap(), b"durable CONTENT");
+        assert!(crate::durability::was_synced(&dst_path));
+    }
+
+    /// Keeps what it is given in memory, recording each write's range
+    #[derive(Clone, Default)]
+    struct MemorySink {
+        writes: std::sync::Arc<std::sync::Mutex<Vec<(u64, usize)>>>,
+        data: std::sync::Arc<std::sync::Mutex<Vec<u8>>>,
+        finalized: std::sync::Arc<std::sync::atomic::AtomicBool>,
+    }
+
+    impl CopySink for MemorySink {
+        fn write_at(&mut self, offset: u64, data: &[u8]) -> Result<()> {
+            self.writes.lock().unwrap().push((offset, data.len()));
+            let mut contents = self.data.lock().unwrap();
+            contents.truncate(offset as usize);
+            contents.extend_from_slice(data);
+            Ok(())
+        }
+
+        fn flush(&mut self) -> Result<()> {
+            Ok(())
+        }
+
+        fn finalize(self) -> Result<()> {
+            self.finalized.store(true, std::sync::atomic::Ordering::SeqCst);
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_copy_into_writes_through_sink() {
+        let temp_dir = TempDir::new().unwrap();
+        let src_path = temp_dir.path().join("source.bin");
+        let contents: Vec<u8> = (0..40).collect();
+        fs::write(&src_path, &contents).unwrap();
+
+        let sink = MemorySink::default();
+        let stats = FileCopier::new(src_path, temp_dir.path().join("unused"), OverwriteMode::Always, true, false, false)
+            .with_progress(false)
+            .with_buffer_size(16)
+            .copy_into(sink.clone())
+            .await
+            .unwrap();
+
+        assert_eq!(*sink.writes.lock().unwrap(), vec![(0, 16), (16, 16), (32, 8)]);
+        assert_eq!(*sink.data.lock().unwrap(), contents);
+        assert!(sink.finalized.load(std::sync::atomic::Ordering::SeqCst));
+        assert_eq!(stats.transferred_bytes, 40);
+        assert!(!temp_dir.path().join("unused").exists());
+    }
+
+    #[tokio::test]
+    async fn test_verify_source_refuses_rotted_source() {
+        let temp_dir = TempDir::new().unwrap();
+        let src_path = temp_dir.path().join("photo.raw");
+        let dst_path = temp_dir.path().join("copy.raw");
+        let sums_path = temp_dir.path().join("SHA256SUMS");
+        let original = b"original pixels".to_vec();
+        let digest = checksum_reader(&original[..]).unwrap();
+        fs::write(&sums_path, format!("{}  ./photo.raw\n", digest)).unwrap();
+
+        // One flipped byte since the manifest was made
+        let mut rotted = original.clone();
+        rotted[3] ^= 0x01;
+        fs::write(&src_path, &rotted).unwrap();
+
+        let sums = ExpectedChecksums::load(&sums_path).unwrap();
+        let copier = |sums| {
+            FileCopier::new(src_path.clone(), dst_path.clone(), OverwriteMode::Always, true, false, false)
+                .with_progress(false)
+                .with_verify_source(Some(sums))
+        };
+        let err = copier(sums.clone()).copy().await.unwrap_err();
+        assert!(matches!(err, Error::SourceCorrupted { ref expected, .. } if *expected == digest));
+        assert_eq!(err.exit_code(), 5);
+        assert!(!dst_path.exists());
+
+        fs::write(&src_path, &original).unwrap();
+        copier(sums).copy().await.unwrap();
+        assert_eq!(fs::read(&dst_path).unwrap(), original);
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "macos"))]
+    #[tokio::test]
+    async fn test_sparse_copy_keeps_holes() {
+        use crate::reflink::map_extents;
+
+        let temp_dir = TempDir::new().unwrap();
+        let src_path = temp_dir.path().join("source.img");
+        let dst_path = temp_dir.path().join("dest.img");
+
+        // 8 MiB with data at the start and in the middle, holes elsewhere
+        let mut file = File::create(&src_path).unwrap();
+        file.set_len(8 << 20).unwrap();
+        file.write_all(&vec![1u8; 64 << 10]).unwrap();
+        file.seek(SeekFrom::Start(4 << 20)).unwrap();
+        file.write_all(&vec![2u8; 128 << 10]).unwrap();
+        file.sync_all().unwrap();
+        drop(file);
+        if !is_sparse_file(&src_path).unwrap() {
+            return; // This filesystem can't hold holes
+        }
+
+        FileCopier::new(src_path.clone(), dst_path.clone(), OverwriteMode::Always, true, false, true)
+            .with_progress(false)
+            .with_sparse(true)
+            .copy()
+            .await
+            .unwrap();
+
+        assert_eq!(fs::read(&dst_path).unwrap(), fs::read(&src_path).unwrap());
+        assert!(is_sparse_file(&dst_path).unwrap());
+        assert_eq!(map_extents(&dst_path).unwrap(), map_extents(&src_path).unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_interrupt_saves_resume_state() {
+        let temp_dir = TempDir::new().unwrap();
+        let src_path = temp_dir.path().join("source.bin");
+        let dst_path = temp_dir.path().join("dest.bin");
+        fs::write(&src_path, vec![7u8; 40]).unwrap();
+
+        // Raised up front, the flag is seen right after the first chunk lands
+        let interrupt = InterruptFlag::default();
+        interrupt.store(true, std::sync::atomic::Ordering::SeqCst);
 
         let copier = FileCopier::new(
-            src.path().to_path_buf(),
+            src_path.clone(),
+            dst_path.clone(),
+            OverwriteMode::Always,
+            true,
+            true,
+            false,
+        )
+        .with_progress(false)
+        .with_interrupt(interrupt)
+        .with_buffer_size(16);
+
+        let err = copier.copy().await.unwrap_err();
+        assert!(err.is_interrupt());
+        assert!(err.detailed_message().contains("--resume"));
+
+        let state = ResumeState::load(&dst_path).unwrap().unwrap();
+        assert_eq!(state.bytes_completed(), 16);
+        assert_eq!(fs::metadata(&dst_path).unwrap().len(), 16);
+    }
+
+    #[tokio::test]
+    async fn test_resumed_copy_accounts_for_every_byte() {
+        let temp_dir = TempDir::new().unwrap();
+        let src_path = temp_dir.path().join("source.bin");
+        let dst_path = temp_dir.path().join("dest.bin");
+        let contents: Vec<u8> = (0..50u8).collect();
+        fs::write(&src_path, &contents).unwrap();
+
+        // Stop after the third chunk, between two interval saves
+        let interrupt = InterruptFlag::default();
+        let raise = interrupt.clone();
+        let options = CopyOptions::new(|_, _, _| Ok(OverwriteChoice::Overwrite)).on_progress(move |stats| {
+            if stats.transferred_bytes >= 48 {
+                raise.store(true, std::sync::atomic::Ordering::SeqCst);
+            }
+        });
+        let err = FileCopier::new(src_path.clone(), dst_path.clone(), OverwriteMode::Always, true, true, false)
+            .with_progress(false)
+            .with_interrupt(interrupt)
+            .with_buffer_size(16)
+            .with_resume_interval(ResumeInterval { bytes: 32, time: Duration::from_secs(60) })
+            .with_options(options)
+            .copy()
+            .await
+            .unwrap_err();
+        assert!(err.is_interrupt());
+
+        let state = ResumeState::load(&dst_path).unwrap().unwrap();
+        assert_eq!(state.bytes_completed(), 48);
+        assert_eq!(fs::metadata(&dst_path).unwrap().len(), 48);
+
+        FileCopier::new(src_path, dst_path.clone(), OverwriteMode::Always, true, true, false)
+            .with_progress(false)
+            .with_buffer_size(16)
+            .with_options(CopyOptions::new(|_, _, _| Ok(OverwriteChoice::Overwrite)).on_resume(|_, _, _, done| {
+                assert_eq!(done, 48);
+                Ok(true)
+            }))
+            .copy()
+            .await
+            .unwrap();
+        assert_eq!(fs::read(&dst_path).unwrap(), contents);
+    }
+
+    #[tokio::test]
+    async fn test_resumed_copy_verifies_the_earlier_prefix() {
+        let temp_dir = TempDir::new().unwrap();
+        let src_path = temp_dir.path().join("source.bin");
+        let dst_path = temp_dir.path().join("dest.bin");
+        let contents: Vec<u8> = (0..300_000u32).map(|i| (i % 251) as u8).collect();
+        fs::write(&src_path, &contents).unwrap();
+
+        // An earlier run wrote 100 KB, and a byte of it rotted since
+        let mut partial = contents[..100_000].to_vec();
+        partial[50_000] ^= 0xff;
+        fs::write(&dst_path, &partial).unwrap();
+        let mut state = ResumeState::new(src_path.clone(), dst_path.clone(), contents.len() as u64);
+        state.advance_to(100_000);
+        state.save().unwrap();
+
+        // Verified in full despite --no-verify
+        let err = FileCopier::new(src_path, dst_path.clone(), OverwriteMode::Always, false, true, false)
+            .with_progress(false)
+            .with_options(CopyOptions::new(|_, _, _| Ok(OverwriteChoice::Overwrite)).on_resume(|_, _, _, _| Ok(true)))
+            .copy()
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(err, Error::VerificationFailed { offset: Some(50_000), resumed_from: 100_000, .. }),
+            "{:?}",
+            err
+        );
+        assert!(err.detailed_message().contains("written before the interruption is corrupt"));
+        // The next run starts over rather than trusting the prefix
+        assert_eq!(ResumeState::load(&dst_path).unwrap().unwrap().bytes_completed(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_resume_state_saved_every_interval() {
+        let temp_dir = TempDir::new().unwrap();
+        let src_path = temp_dir.path().join("source.bin");
+        let dst_path = temp_dir.path().join("dest.bin");
+        fs::write(&src_path, vec![3u8; 80]).unwrap();
+
+        // Read the saved state after every chunk lands
+        let saved = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let seen = std::sync::Arc::clone(&saved);
+        let state_target = dst_path.clone();
+        let options = CopyOptions::new(|_, _, _| Ok(OverwriteChoice::Overwrite)).on_progress(move |_| {
+            if let Ok(Some(state)) = ResumeState::load(&state_target) {
+                seen.lock().unwrap().push(state.bytes_completed());
+            }
+        });
+
+        FileCopier::new(src_path, dst_path.clone(), OverwriteMode::Always, true, true, false)
+            .with_progress(false)
+            .with_buffer_size(16)
+            .with_resume_interval(ResumeInterval { bytes: 16, time: Duration::from_secs(60) })
+            .with_options(options)
+            .copy()
+            .await
+            .unwrap();
+
+        // The state lags one chunk behind the progress report
+        assert_eq!(*saved.lock().unwrap(), vec![16, 32, 48, 64]);
+        assert!(ResumeState::load(&dst_path).unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_resume_refuses_state_of_another_source() {
+        let temp_dir = TempDir::new().unwrap();
+        let saved_source = temp_dir.path().join("first.bin");
+        let other_source = temp_dir.path().join("second.bin");
+        let dst_path = temp_dir.path().join("dest.bin");
+        fs::write(&saved_source, vec![1u8; 40]).unwrap();
+        fs::write(&other_source, vec![2u8; 40]).unwrap();
+
+        // Half of first.bin landed before the interruption
+        fs::write(&dst_path, vec![1u8; 20]).unwrap();
+        let mut state = ResumeState::new(saved_source.clone(), dst_path.clone(), 40);
+        state.advance_to(20);
+        state.save().unwrap();
+
+        let copier = FileCopier::new(
+            other_source.clone(),
             dst_path.clone(),
             OverwriteMode::Always,
+            true,
+            true,
             false,
+        )
+        .with_progress(false)
+        .with_options(CopyOptions::new(|_, _, _| Ok(OverwriteChoice::Overwrite)).on_resume(|_, _, _, _| Ok(true)));
+
+        assert!(matches!(copier.copy().await, Err(Error::InvalidResumeState)));
+        assert_eq!(fs::read(&dst_path).unwrap(), vec![1u8; 20]);
+
+        // The same path at another size is refused too
+        assert!(state.validate_source(&saved_source, 41).is_err());
+        assert!(state.validate_source(&saved_source, 40).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_leftover_atomic_temp_is_not_silently_reused() {
+        let temp_dir = TempDir::new().unwrap();
+        let src_path = temp_dir.path().join("source.bin");
+        let dst_path = temp_dir.path().join("dest.bin");
+        fs::write(&src_path, b"fresh data").unwrap();
+
+        let leftover = atomic_temp_path(&dst_path);
+        assert_eq!(leftover, temp_dir.path().join("dest.bin.tmp"));
+        fs::write(&leftover, b"stale").unwrap();
+
+        let copier = FileCopier::new(
+            src_path.clone(),
+            dst_path.clone(),
+            OverwriteMode::Always,
+            true,
             false,
+            true,
+        )
+        .with_progress(false);
+
+        // The test prompt declines to discard, so the copy stops and names the file
+        let err = copier.copy().await.unwrap_err();
+        assert!(err.detailed_message().contains("dest.bin.tmp"));
+        assert_eq!(fs::read(&leftover).unwrap(), b"stale");
+        assert!(!dst_path.exists());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_preserve_xattrs_round_trip() {
+        let temp_dir = TempDir::new().unwrap();
+        let src_path = temp_dir.path().join("source.txt");
+        let dst_path = temp_dir.path().join("dest.txt");
+        fs::write(&src_path, b"tagged").unwrap();
+
+        // Not every filesystem accepts user xattrs; nothing to check there
+        if xattr::set(&src_path, "user.test", b"hello").is_err() {
+            return;
+        }
+
+        let copier = FileCopier::new(
+            src_path.clone(),
+            dst_path.clone(),
+            OverwriteMode::Always,
+            true,
             false,
+            true,
+        )
+        .with_progress(false)
+        .with_preserve(PreserveSet { xattr: true, ..PreserveSet::default() });
+        copier.copy().await.unwrap();
+
+        assert_eq!(
+            xattr::get(&dst_path, "user.test").unwrap(),
+            Some(b"hello".to_vec())
         );
+    }
 
-        assert!(copier.copy().await.is_ok());
-        let content = fs::read(&dst_path).unwrap();
-        assert_eq!(content, b"test content");
+    #[tokio::test]
+    async fn test_atomic_directory_copy_renames_into_place() {
+        let temp_dir = TempDir::new().unwrap();
+        let src_dir = temp_dir.path().join("src");
+        fs::create_dir_all(src_dir.join("sub")).unwrap();
+        fs::write(src_dir.join("sub/file.txt"), b"content").unwrap();
+
+        let dst_dir = temp_dir.path().join("dst");
+        DirectoryCopier::new(src_dir, dst_dir.clone(), OverwriteMode::Always, true)
+            .with_progress(false)
+            .with_atomic(true)
+            .copy()
+            .await
+            .unwrap();
+
+        assert_eq!(fs::read(dst_dir.join("sub/file.txt")).unwrap(), b"content");
+        assert!(!staging_dir_path(&dst_dir).exists());
     }
 
     #[tokio::test]
-    async fn test_directory_copy() {
-        // Create source directory with files
-        let src_dir = TempDir::new().unwrap();
-        let src_path = src_dir.path();
-        
-        // Create test files
-        let mut file1 = fs::File::create(src_path.join("file1.txt")).unwrap();
-        file1.write_all(b"content1").unwrap();
-        
-        let mut file2 = fs::File::create(src_path.join("file2.txt")).unwrap();
-        file2.write_all(b"content2").unwrap();
-        
-        // Create subdirectory with file
-        fs::create_dir(src_path.join("subdir")).unwrap();
-        let mut file3 = fs::File::create(src_path.join("subdir/file3.txt")).unwrap();
-        file3.write_all(b"content3").unwrap();
-        
-        // Create destination directory
-        let dst_dir = TempDir::new().unwrap();
-        let dst_path = dst_dir.path().join("copy");
-        
-        // Copy directory
-        let result = copy_directory(src_path, &dst_path, OverwriteMode::Always, false).await;
-        assert!(result.is_ok(), "Directory copy failed: {:?}", result.err());
-        
-        // Verify files were copied
-        assert_eq!(fs::read(dst_path.join("file1.txt")).unwrap(), b"content1");
-        assert_eq!(fs::read(dst_path.join("file2.txt")).unwrap(), b"content2");
-        assert_eq!(fs::read(dst_path.join("subdir/file3.txt")).unwrap(), b"content3");
+    async fn test_atomic_directory_copy_failure_leaves_only_staging() {
+        let temp_dir = TempDir::new().unwrap();
+        let src_dir = temp_dir.path().join("src");
+        fs::create_dir(&src_dir).unwrap();
+        fs::write(src_dir.join("file.txt"), b"content").unwrap();
+        fs::write(src_dir.join("other.txt"), b"more").unwrap();
+
+        // Fail partway through: stop as soon as the first chunk is written
+        let interrupt = InterruptFlag::default();
+        let raise = interrupt.clone();
+        let options = CopyOptions::interactive()
+            .on_progress(move |_| raise.store(true, std::sync::atomic::Ordering::SeqCst));
+
+        let dst_dir = temp_dir.path().join("dst");
+        let result = DirectoryCopier::new(src_dir, dst_dir.clone(), OverwriteMode::Always, true)
+            .with_progress(false)
+            .with_atomic(true)
+            .with_interrupt(interrupt)
+            .with_options(options)
+            .copy()
+            .await;
+
+        assert!(result.is_err());
+        assert!(!dst_dir.exists());
+        assert!(staging_dir_path(&dst_dir).is_dir());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_specials_recreate_fifo() {
+        use std::os::unix::fs::FileTypeExt;
+
+        let temp_dir = TempDir::new().unwrap();
+        let src_dir = temp_dir.path().join("src");
+        fs::create_dir(&src_dir).unwrap();
+        fs::write(src_dir.join("file.txt"), b"data").unwrap();
+        let fifo = std::ffi::CString::new(src_dir.join("pipe").to_str().unwrap()).unwrap();
+        assert_eq!(unsafe { libc::mkfifo(fifo.as_ptr(), 0o644) }, 0);
+
+        // Skipped with a warning by default, rather than blocking on open
+        let skipped_dir = temp_dir.path().join("skipped");
+        let stats = DirectoryCopier::new(src_dir.clone(), skipped_dir.clone(), OverwriteMode::Always, true)
+            .with_progress(false)
+            .copy()
+            .await
+            .unwrap();
+        assert_eq!(stats.files_skipped, 1);
+        assert!(!skipped_dir.join("pipe").exists());
+
+        let dst_dir = temp_dir.path().join("dst");
+        DirectoryCopier::new(src_dir, dst_dir.clone(), OverwriteMode::Always, true)
+            .with_progress(false)
+            .with_specials(true)
+            .copy()
+            .await
+            .unwrap();
+        let file_type = fs::symlink_metadata(dst_dir.join("pipe")).unwrap().file_type();
+        assert!(file_type.is_fifo());
+        assert_eq!(fs::read(dst_dir.join("file.txt")).unwrap(), b"data");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_dangling_symlink_is_recreated_when_preserving() {
+        let temp_dir = TempDir::new().unwrap();
+        let src_dir = temp_dir.path().join("src");
+        fs::create_dir(&src_dir).unwrap();
+        std::os::unix::fs::symlink("gone.txt", src_dir.join("broken")).unwrap();
+
+        let dst_dir = temp_dir.path().join("dst");
+        copy_directory(&src_dir, &dst_dir, OverwriteMode::Always, true, false)
+            .await
+            .unwrap();
+
+        assert_eq!(fs::read_link(dst_dir.join("broken")).unwrap(), Path::new("gone.txt"));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_on_conflict_skips_changed_symlink() {
+        use crate::cli::ConflictPolicy;
+
+        let temp_dir = TempDir::new().unwrap();
+        let src_dir = temp_dir.path().join("src");
+        let dst_dir = temp_dir.path().join("dst");
+        fs::create_dir_all(&src_dir).unwrap();
+        fs::create_dir_all(&dst_dir).unwrap();
+        std::os::unix::fs::symlink("new.txt", src_dir.join("link")).unwrap();
+        std::os::unix::fs::symlink("old.txt", dst_dir.join("link")).unwrap();
+        fs::write(src_dir.join("file.txt"), b"data").unwrap();
+
+        let stats = copy_directory(&src_dir, &dst_dir, OverwriteMode::Policy(ConflictPolicy::Newer), false, false)
+            .await
+            .unwrap();
+
+        assert_eq!(stats.files_skipped, 1);
+        assert_eq!(fs::read_link(dst_dir.join("link")).unwrap(), Path::new("old.txt"));
+        assert_eq!(fs::read(dst_dir.join("file.txt")).unwrap(), b"data");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_prompt_asks_before_replacing_changed_symlink() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+
+        let temp_dir = TempDir::new().unwrap();
+        let src_dir = temp_dir.path().join("src");
+        let dst_dir = temp_dir.path().join("dst");
+        fs::create_dir_all(&src_dir).unwrap();
+        std::os::unix::fs::symlink("new.txt", src_dir.join("link")).unwrap();
+        std::os::unix::fs::symlink("same.txt", src_dir.join("same")).unwrap();
+
+        // First copy, then change where the link points and copy again
+        copy_directory(&src_dir, &dst_dir, OverwriteMode::Prompt, false, false).await.unwrap();
+        fs::remove_file(dst_dir.join("link")).unwrap();
+        std::os::unix::fs::symlink("old.txt", dst_dir.join("link")).unwrap();
+
+        let asked = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&asked);
+        let options = CopyOptions::new(move |target, _, tgt_metadata| {
+            assert!(target.ends_with("link"));
+            assert!(tgt_metadata.file_type().is_symlink());
+            counter.fetch_add(1, Ordering::SeqCst);
+            Ok(OverwriteChoice::Overwrite)
+        });
+        DirectoryCopier::new(src_dir.clone(), dst_dir.clone(), OverwriteMode::Prompt, false)
+            .with_progress(false)
+            .with_options(options)
+            .copy()
+            .await
+            .unwrap();
+
+        // Only the changed link was asked about
+        assert_eq!(asked.load(Ordering::SeqCst), 1);
+        assert_eq!(fs::read_link(dst_dir.join("link")).unwrap(), Path::new("new.txt"));
+
+        fs::remove_file(dst_dir.join("link")).unwrap();
+        std::os::unix::fs::symlink("old.txt", dst_dir.join("link")).unwrap();
+        let stats = DirectoryCopier::new(src_dir, dst_dir.clone(), OverwriteMode::Prompt, false)
+            .with_progress(false)
+            .with_options(CopyOptions::new(|_, _, _| Ok(OverwriteChoice::Skip)))
+            .copy()
+            .await
+            .unwrap();
+        assert_eq!(stats.files_skipped, 1);
+        assert_eq!(fs::read_link(dst_dir.join("link")).unwrap(), Path::new("old.txt"));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_dangling_symlink_errors_when_following() {
+        let temp_dir = TempDir::new().unwrap();
+        let src_dir = temp_dir.path().join("src");
+        fs::create_dir(&src_dir).unwrap();
+        std::os::unix::fs::symlink("gone.txt", src_dir.join("broken")).unwrap();
+
+        let err = DirectoryCopier::new(src_dir, temp_dir.path().join("dst"), OverwriteMode::Always, true)
+            .with_progress(false)
+            .with_filter(WalkFilter::new().with_follow_symlinks(true))
+            .copy()
+            .await
+            .unwrap_err();
+
+        assert!(matches!(err, Error::DanglingSymlink { ref target, .. } if target == "gone.txt"));
+        assert!(err.detailed_message().contains("broken"));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_following_symlink_loop_terminates() {
+        let temp_dir = TempDir::new().unwrap();
+        let src_dir = temp_dir.path().join("src");
+        let dst_dir = temp_dir.path().join("dst");
+        fs::create_dir_all(src_dir.join("sub")).unwrap();
+        fs::write(src_dir.join("sub/file.txt"), b"data").unwrap();
+        // sub/back -> src: following it leads back into the tree
+        std::os::unix::fs::symlink("..", src_dir.join("sub/back")).unwrap();
+
+        let stats = DirectoryCopier::new(src_dir, dst_dir.clone(), OverwriteMode::Always, true)
+            .with_progress(false)
+            .with_filter(WalkFilter::new().with_follow_symlinks(true))
+            .copy()
+            .await
+            .unwrap();
+
+        assert_eq!(stats.files_skipped, 1);
+        assert_eq!(fs::read(dst_dir.join("sub/file.txt")).unwrap(), b"data");
+        assert!(!dst_dir.join("sub/back").exists());
     }
 }
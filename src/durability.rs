@@ -0,0 +1,46 @@
+use std::fs::File;
+use std::path::Path;
+use crate::error::{Error, Result};
+
+#[cfg(test)]
+use std::path::PathBuf;
+#[cfg(test)]
+use std::sync::Mutex;
+
+/// Paths synced so far, so tests can tell `--fsync` took effect
+#[cfg(test)]
+static SYNCED: Mutex<Vec<PathBuf>> = Mutex::new(Vec::new());
+
+/// Flush a written file's data and metadata to disk (`--fsync`)
+pub fn sync_file(file: &File, path: &Path) -> Result<()> {
+    file.sync_all().map_err(Error::Io)?;
+    #[cfg(test)]
+    SYNCED.lock().unwrap().push(path.to_path_buf());
+    #[cfg(not(test))]
+    let _ = path;
+    Ok(())
+}
+
+/// Flush the directory holding `path`, so a rename into it (or a file newly
+/// created in it) survives a power loss. Only unix can open a directory for
+/// this; elsewhere the entry is as durable as the filesystem makes it.
+pub fn sync_parent_dir(path: &Path) -> Result<()> {
+    let parent = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    #[cfg(unix)]
+    {
+        let dir = File::open(parent).map_err(Error::Io)?;
+        sync_file(&dir, parent)?;
+    }
+    #[cfg(not(unix))]
+    let _ = parent;
+    Ok(())
+}
+
+/// Whether `path` was synced by this test process
+#[cfg(test)]
+pub(crate) fn was_synced(path: &Path) -> bool {
+    SYNCED.lock().unwrap().iter().any(|synced| synced == path)
+}
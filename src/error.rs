@@ -1,5 +1,6 @@
 use thiserror::Error;
 use std::io;
+use crate::interrupt::INTERRUPTED_EXIT_CODE;
 
 #[derive(Error, Debug)]
 pub enum Error {
@@ -12,29 +13,119 @@ pub enum Error {
     #[error("Target already exists: {0}")]
     TargetExists(String),
 
+    #[error("Skipped existing target: {0}")]
+    Skipped(String),
+
     #[error("Permission denied: {0}")]
     PermissionDenied(String),
 
+    #[error("Dangling symlink {link} -> {target}")]
+    DanglingSymlink { link: String, target: String },
+
     #[error("Checksum mismatch: expected {expected}, got {actual}")]
     ChecksumMismatch { expected: String, actual: String },
 
+    #[error("Verification failed for {target}: expected {expected}, got {actual}")]
+    VerificationFailed {
+        target: String,
+        marker: String,
+        expected: String,
+        actual: String,
+        /// First differing byte, when the files were compared directly
+        offset: Option<u64>,
+        /// Where a resumed copy picked up, 0 for a fresh one; bytes before
+        /// it were written by an earlier run
+        resumed_from: u64,
+    },
+
+    #[error("Source {path} does not match {manifest}: expected {expected}, got {actual}")]
+    SourceCorrupted {
+        path: String,
+        manifest: String,
+        expected: String,
+        actual: String,
+    },
+
+    #[error("{target} holds {actual_files} of {expected_files} file(s) copied to it")]
+    TreeMismatch {
+        target: String,
+        expected_files: usize,
+        expected_bytes: u64,
+        actual_files: usize,
+        actual_bytes: u64,
+        /// Targets missing, or with the wrong size or contents
+        missing: Vec<String>,
+    },
+
+    #[error("{0} file(s) could not be copied")]
+    FilesFailed(usize),
+
     #[error("Resume state invalid or corrupted")]
     InvalidResumeState,
 
     #[error("Operation aborted by user")]
     UserAborted,
 
+    #[error("Interrupted while copying to {0}")]
+    Interrupted(String),
+
+    #[error("No progress for {seconds}s while reading {path}")]
+    Timeout { path: String, seconds: u64 },
+
+    #[error("{from} and {to} are on different filesystems")]
+    CrossDevice { from: String, to: String },
+
+    #[error("Reflink not supported on {}", .fs_hint.as_deref().unwrap_or("this filesystem"))]
+    ReflinkUnsupported { fs_hint: Option<String> },
+
+    #[error("Destination is not writable: {path}")]
+    ReadOnlyDestination { path: String, read_only_fs: bool },
+
     #[error("Insufficient disk space")]
     DiskFull,
 
     #[error("Configuration error: {0}")]
     ConfigError(String),
 
+    #[error("{0}")]
+    Usage(String),
+
     #[error("{0}")]
     Custom(String),
 }
 
 impl Error {
+    /// Whether this error only means a target was intentionally left alone
+    pub fn is_skip(&self) -> bool {
+        matches!(self, Error::Skipped(_))
+    }
+
+    /// Whether this error means the user stopped the transfer with Ctrl-C
+    pub fn is_interrupt(&self) -> bool {
+        matches!(self, Error::Interrupted(_))
+    }
+
+    /// Process exit code for this error, so scripts can tell failures apart:
+    /// 1 generic, 2 usage, 3 source not found, 4 target exists or skipped,
+    /// 5 checksum mismatch, 6 disk full, 7 some files failed under
+    /// `--ignore-errors`, 130 aborted or interrupted
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Error::Usage(_) => 2,
+            Error::SourceNotFound(_) => 3,
+            Error::TargetExists(_) | Error::Skipped(_) => 4,
+            Error::ChecksumMismatch { .. }
+            | Error::VerificationFailed { .. }
+            | Error::SourceCorrupted { .. }
+            | Error::TreeMismatch { .. } => 5,
+            Error::DiskFull => 6,
+            Error::Io(e) if e.kind() == io::ErrorKind::StorageFull => 6,
+            Error::FilesFailed(_) => 7,
+            Error::UserAborted | Error::Interrupted(_) => INTERRUPTED_EXIT_CODE,
+            _ => 1,
+        }
+    }
+
     /// Get a detailed message with recovery suggestions
     pub fn detailed_message(&self) -> String {
         match self {
@@ -71,6 +162,9 @@ impl Error {
                     path
                 )
             }
+            Error::Skipped(path) => {
+                format!("Skipped existing target: {}", path)
+            }
             Error::PermissionDenied(path) => {
                 format!(
                     "Permission denied: {}\n\
@@ -79,6 +173,13 @@ impl Error {
                     path
                 )
             }
+            Error::DanglingSymlink { link, target } => {
+                format!(
+                    "Dangling symlink: {} points to {}, which does not exist\n\
+                     Tip: Fix or remove the link, or drop --follow-symlinks to copy the link itself.",
+                    link, target
+                )
+            }
             Error::ChecksumMismatch { expected, actual } => {
                 format!(
                     "Checksum mismatch!\n\
@@ -89,15 +190,119 @@ impl Error {
                     expected, actual
                 )
             }
+            Error::VerificationFailed { target, marker, expected, actual, offset, resumed_from } => {
+                let resumed = match (*resumed_from, offset) {
+                    (0, _) => String::new(),
+                    (from, Some(offset)) if *offset < from => format!(
+                        "The copy was resumed at byte {}: the data written before the interruption is corrupt\n",
+                        from
+                    ),
+                    (from, _) => format!("The copy was resumed at byte {}\n", from),
+                };
+                let offset = offset
+                    .map(|offset| format!("First difference at byte {}\n", offset))
+                    .unwrap_or_default();
+                format!(
+                    "Checksum mismatch after copying to {}\n\
+                     Expected: {}\n\
+                     Actual:   {}\n\
+                     {}{}The destination was left in place and flagged with {}.\n\
+                     Tip: Run the copy again (with --resume if you used it) to re-copy from the start;\n\
+                     the bad data will not be trusted.",
+                    target, expected, actual, offset, resumed, marker
+                )
+            }
+            Error::SourceCorrupted { path, manifest, expected, actual } => {
+                format!(
+                    "Source does not match its checksum in {}: {}\n\
+                     Expected: {}\n\
+                     Actual:   {}\n\
+                     The source has changed or rotted since the manifest was made; nothing was kept from this copy.\n\
+                     Tip: Restore the file from another copy, or regenerate the manifest if the change is intended.",
+                    manifest, path, expected, actual
+                )
+            }
+            Error::TreeMismatch { target, expected_files, expected_bytes, actual_files, actual_bytes, missing } => {
+                const SHOWN: usize = 10;
+                let mut listed: String = missing.iter().take(SHOWN).map(|path| format!("\n  {}", path)).collect();
+                if missing.len() > SHOWN {
+                    listed.push_str(&format!("\n  ... and {} more", missing.len() - SHOWN));
+                }
+                format!(
+                    "The copy in {} is incomplete\n\
+                     Expected: {} file(s), {} bytes\n\
+                     Found:    {} file(s), {} bytes\n\
+                     Missing or different:{}\n\
+                     Tip: Run the copy again to fill in what's missing, then `better-cp verify` to compare the trees.",
+                    target, expected_files, expected_bytes, actual_files, actual_bytes, listed
+                )
+            }
+            Error::FilesFailed(count) => {
+                format!(
+                    "{} file(s) could not be copied; everything else was.\n\
+                     The warnings above name each file and why it failed.\n\
+                     Tip: Fix what they report (e.g. permissions) and run the copy again to fill the gaps.",
+                    count
+                )
+            }
             Error::InvalidResumeState => {
-                "Resume state is invalid or corrupted.\n\
-                 Tip: The operation will restart from the beginning.\n\
-                 Use --no-resume to force a fresh copy without using saved state."
+                "Resume state is invalid, corrupted, or was saved for a different source.\n\
+                 Tip: Use --no-resume to force a fresh copy without using saved state,\n\
+                 or better-cp clean to remove the leftover state."
                     .to_string()
             }
             Error::UserAborted => {
                 "Operation cancelled by user.".to_string()
             }
+            Error::Interrupted(target) => {
+                format!(
+                    "Interrupted while copying to {}\n\
+                     The partial destination and its resume state were kept.\n\
+                     Tip: Run the same command with --resume to continue where it stopped.",
+                    target
+                )
+            }
+            Error::Timeout { path, seconds } => {
+                format!(
+                    "No progress for {}s while reading {}\n\
+                     Tip: Check that the mount is still responding (a hung NFS server blocks reads),\n\
+                     or raise --timeout for slow storage.",
+                    seconds, path
+                )
+            }
+            Error::CrossDevice { from, to } => {
+                format!(
+                    "{} and {} are on different filesystems, so moving would copy and delete\n\
+                     rather than rename. Nothing was moved.\n\
+                     Tip: Drop --same-device-only to allow the copy+delete move.",
+                    from, to
+                )
+            }
+            Error::ReflinkUnsupported { fs_hint } => {
+                format!(
+                    "Reflink (copy-on-write clone) not supported on {}\n\
+                     Reflinks need source and target on the same filesystem, and one that shares\n\
+                     blocks between files: Btrfs, XFS (made with reflink=1), APFS or ZFS 2.2+.\n\
+                     Tip: Use --reflink=auto to fall back to a regular copy when cloning isn't possible.",
+                    fs_hint.as_deref().unwrap_or("this filesystem")
+                )
+            }
+            Error::ReadOnlyDestination { path, read_only_fs: true } => {
+                format!(
+                    "Destination is on a read-only filesystem: {}\n\
+                     Nothing was copied.\n\
+                     Tip: Remount it read-write (e.g. `mount -o remount,rw`) or choose another destination.",
+                    path
+                )
+            }
+            Error::ReadOnlyDestination { path, read_only_fs: false } => {
+                format!(
+                    "Destination directory is not writable: {}\n\
+                     Nothing was copied.\n\
+                     Tip: Check its permissions and owner, or choose another destination.",
+                    path
+                )
+            }
             Error::DiskFull => {
                 "Insufficient disk space.\n\
                  Tip: Free up space on the destination disk and try again.\n\
@@ -112,9 +317,31 @@ impl Error {
                     msg
                 )
             }
+            Error::Usage(msg) => {
+                format!("{}\nTip: Run with --help to see the accepted options.", msg)
+            }
             Error::Custom(msg) => msg.clone(),
         }
     }
 }
 
 pub type Result<T> = std::result::Result<T, Error>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exit_codes() {
+        assert_eq!(Error::Custom("boom".to_string()).exit_code(), 1);
+        assert_eq!(Error::Usage("bad flag".to_string()).exit_code(), 2);
+        assert_eq!(Error::SourceNotFound("a".to_string()).exit_code(), 3);
+        assert_eq!(Error::Skipped("b".to_string()).exit_code(), 4);
+        assert_eq!(
+            Error::ChecksumMismatch { expected: "x".to_string(), actual: "y".to_string() }.exit_code(),
+            5
+        );
+        assert_eq!(Error::Io(io::Error::from(io::ErrorKind::StorageFull)).exit_code(), 6);
+        assert_eq!(Error::Interrupted("c".to_string()).exit_code(), 130);
+    }
+}
@@ -0,0 +1,341 @@
+use std::cmp::Reverse;
+use std::fs::{self, DirEntry, Metadata};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use glob::Pattern;
+use crate::cli::SortOrder;
+use crate::error::{Error, Result};
+
+/// What a walk found at one path
+#[derive(Debug)]
+pub enum WalkEntry {
+    Dir(Metadata),
+    File(Metadata),
+    /// A symbolic link to recreate as-is, holding the path it points to
+    Symlink(PathBuf),
+    /// A FIFO, socket or device node, which must not be read like a file
+    Special(Metadata),
+}
+
+/// Rules deciding which entries a recursive directory walk visits
+#[derive(Debug, Clone, Default)]
+pub struct WalkFilter {
+    one_file_system: bool,
+    root_dev: Option<u64>,
+    includes: Vec<Pattern>,
+    excludes: Vec<Pattern>,
+    max_depth: Option<usize>,
+    min_size: Option<u64>,
+    max_size: Option<u64>,
+    newer_than: Option<SystemTime>,
+    follow_symlinks: bool,
+    sort: SortOrder,
+}
+
+impl WalkFilter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Stay on the filesystem of the walk root (like `rsync -x`)
+    pub fn with_one_file_system(mut self, one_file_system: bool) -> Self {
+        self.one_file_system = one_file_system;
+        self
+    }
+
+    /// Stop descending below this depth; the source's own entries are depth 0
+    pub fn with_max_depth(mut self, max_depth: Option<usize>) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    /// Only copy files whose size lies within these bounds (inclusive)
+    pub fn with_size_range(mut self, min_size: Option<u64>, max_size: Option<u64>) -> Self {
+        self.min_size = min_size;
+        self.max_size = max_size;
+        self
+    }
+
+    /// Only copy files modified after this time
+    pub fn with_newer_than(mut self, newer_than: Option<SystemTime>) -> Self {
+        self.newer_than = newer_than;
+        self
+    }
+
+    /// Copy what symlinks point to instead of recreating the links
+    pub fn with_follow_symlinks(mut self, follow_symlinks: bool) -> Self {
+        self.follow_symlinks = follow_symlinks;
+        self
+    }
+
+    /// Visit each directory's entries in this order (`--sort`)
+    pub fn with_sort(mut self, sort: SortOrder) -> Self {
+        self.sort = sort;
+        self
+    }
+
+    /// Always keep entries matching any of these glob patterns, even if excluded
+    pub fn with_includes(mut self, patterns: &[String]) -> Result<Self> {
+        self.includes.extend(compile_patterns("include", patterns)?);
+        Ok(self)
+    }
+
+    /// Skip entries matching any of these glob patterns
+    pub fn with_excludes(mut self, patterns: &[String]) -> Result<Self> {
+        self.excludes.extend(compile_patterns("exclude", patterns)?);
+        Ok(self)
+    }
+
+    /// Record the device of the directory the walk starts from
+    pub fn rooted_at(mut self, root: &Path) -> Result<Self> {
+        if self.one_file_system {
+            let metadata = fs::metadata(root).map_err(Error::Io)?;
+            self.root_dev = device_id(&metadata);
+        }
+        Ok(self)
+    }
+
+    /// Whether an entry, given by its path relative to the walk root, is kept.
+    ///
+    /// A pattern matches either the entry's file name or its relative path,
+    /// so `*.log` drops log files at any depth and `build/*` only the top-level
+    /// `build` directory's contents.
+    ///
+    /// Include patterns are checked first, then excludes, and the first match
+    /// decides: an entry matching an include is kept even if an exclude would
+    /// drop it. Entries matching neither are kept. As with rsync, an excluded
+    /// directory is pruned whole, so under `--exclude '*'` subdirectories are
+    /// only walked if an include pattern matches their names too.
+    pub fn is_included(&self, relative_path: &Path) -> bool {
+        if self.includes.iter().any(|pattern| matches_pattern(pattern, relative_path)) {
+            return true;
+        }
+        !self.excludes.iter().any(|pattern| matches_pattern(pattern, relative_path))
+    }
+
+    /// Put one directory's entries in the `--sort` order. Sizes and times are
+    /// the entries' own, not those of what a symlink points to.
+    pub fn sort_entries(&self, entries: &mut [DirEntry]) {
+        match self.sort {
+            SortOrder::None => {}
+            SortOrder::Name => entries.sort_by_key(DirEntry::file_name),
+            SortOrder::Size => entries.sort_by_cached_key(|entry| {
+                (Reverse(entry.metadata().map(|m| m.len()).unwrap_or(0)), entry.file_name())
+            }),
+            SortOrder::Mtime => entries.sort_by_cached_key(|entry| {
+                (Reverse(entry.metadata().and_then(|m| m.modified()).ok()), entry.file_name())
+            }),
+        }
+    }
+
+    /// Look at one entry, following it if it is a symlink and we were asked to.
+    ///
+    /// Links are only recreated on Unix; elsewhere they are always followed.
+    /// Following a link whose target is gone is a `DanglingSymlink` error.
+    pub fn classify(&self, path: &Path) -> Result<WalkEntry> {
+        let link_metadata = fs::symlink_metadata(path).map_err(Error::Io)?;
+        let metadata = if link_metadata.file_type().is_symlink() {
+            let link_target = fs::read_link(path).map_err(Error::Io)?;
+            if cfg!(unix) && !self.follow_symlinks {
+                return Ok(WalkEntry::Symlink(link_target));
+            }
+            fs::metadata(path).map_err(|e| match e.kind() {
+                std::io::ErrorKind::NotFound => Error::DanglingSymlink {
+                    link: path.to_string_lossy().to_string(),
+                    target: link_target.to_string_lossy().to_string(),
+                },
+                _ => Error::Io(e),
+            })?
+        } else {
+            link_metadata
+        };
+
+        Ok(if metadata.is_dir() {
+            WalkEntry::Dir(metadata)
+        } else if metadata.is_file() {
+            WalkEntry::File(metadata)
+        } else {
+            WalkEntry::Special(metadata)
+        })
+    }
+
+    /// Whether a file passes the size and age predicates. Files that fail are
+    /// skipped (and counted) by the walk; directories are never checked.
+    pub fn accepts_file(&self, metadata: &Metadata) -> bool {
+        let len = metadata.len();
+        if self.min_size.is_some_and(|min| len < min) || self.max_size.is_some_and(|max| len > max) {
+            return false;
+        }
+        match (self.newer_than, metadata.modified()) {
+            (Some(reference), Ok(modified)) => modified > reference,
+            _ => true,
+        }
+    }
+
+    /// Whether the walk should descend into a subdirectory
+    pub fn should_descend(&self, metadata: &Metadata) -> bool {
+        !(self.one_file_system && on_other_device(self.root_dev, device_id(metadata)))
+    }
+
+    /// Whether a subdirectory found at `depth` may be entered. Files at the
+    /// boundary are still copied; only deeper levels are cut off.
+    pub fn within_depth(&self, depth: usize) -> bool {
+        self.max_depth.is_none_or(|max| depth < max)
+    }
+}
+
+/// Device id of a file, where the platform exposes one
+pub fn device_id(metadata: &Metadata) -> Option<u64> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        Some(metadata.dev())
+    }
+
+    #[cfg(not(unix))]
+    {
+        let _ = metadata;
+        None
+    }
+}
+
+/// Device and inode of a file, which identify it however it was reached,
+/// where the platform exposes them
+pub fn file_id(metadata: &Metadata) -> Option<(u64, u64)> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        Some((metadata.dev(), metadata.ino()))
+    }
+
+    #[cfg(not(unix))]
+    {
+        let _ = metadata;
+        None
+    }
+}
+
+/// Parse a `--newer-than` reference: the mtime of an existing path, or a
+/// date (`2024-05-01`) or RFC 3339 timestamp (`2024-05-01T12:00:00Z`)
+pub fn parse_reference_time(value: &str) -> Result<SystemTime> {
+    if let Ok(metadata) = fs::metadata(value) {
+        return metadata.modified().map_err(Error::Io);
+    }
+    if let Ok(timestamp) = chrono::DateTime::parse_from_rfc3339(value) {
+        return Ok(timestamp.into());
+    }
+    if let Some(midnight) = chrono::NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .ok()
+        .and_then(|date| date.and_hms_opt(0, 0, 0))
+    {
+        return Ok(midnight.and_utc().into());
+    }
+    Err(Error::Usage(format!(
+        "'{}' is neither an existing path nor a date (YYYY-MM-DD or RFC 3339)",
+        value
+    )))
+}
+
+fn compile_patterns(kind: &str, patterns: &[String]) -> Result<Vec<Pattern>> {
+    patterns
+        .iter()
+        .map(|pattern| {
+            Pattern::new(pattern)
+                .map_err(|e| Error::Usage(format!("Invalid {} pattern '{}': {}", kind, pattern, e)))
+        })
+        .collect()
+}
+
+fn matches_pattern(pattern: &Pattern, relative_path: &Path) -> bool {
+    pattern.matches_path(relative_path)
+        || relative_path
+            .file_name()
+            .map(|name| pattern.matches(&name.to_string_lossy()))
+            .unwrap_or(false)
+}
+
+/// An entry is on another device only when both ids are known and differ
+fn on_other_device(root_dev: Option<u64>, dev: Option<u64>) -> bool {
+    matches!((root_dev, dev), (Some(root), Some(dev)) if root != dev)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_other_device_is_pruned() {
+        assert!(on_other_device(Some(1), Some(2)));
+        assert!(!on_other_device(Some(1), Some(1)));
+        assert!(!on_other_device(None, Some(2)));
+    }
+
+    #[test]
+    fn test_excludes_match_name_or_relative_path() {
+        let filter = WalkFilter::new()
+            .with_excludes(&["*.log".to_string(), "build/*".to_string()])
+            .unwrap();
+
+        assert!(!filter.is_included(Path::new("app.log")));
+        assert!(!filter.is_included(Path::new("logs/deep/app.log")));
+        assert!(!filter.is_included(Path::new("build/output.o")));
+        assert!(filter.is_included(Path::new("src/build.rs")));
+        assert!(filter.is_included(Path::new("readme.txt")));
+    }
+
+    #[test]
+    fn test_include_wins_over_exclude() {
+        let filter = WalkFilter::new()
+            .with_includes(&["*.txt".to_string()])
+            .unwrap()
+            .with_excludes(&["*".to_string()])
+            .unwrap();
+
+        assert!(filter.is_included(Path::new("notes.txt")));
+        assert!(!filter.is_included(Path::new("app.log")));
+        assert!(!filter.is_included(Path::new("subdir")));
+    }
+
+    #[test]
+    fn test_max_depth_limits_descent() {
+        let filter = WalkFilter::new().with_max_depth(Some(2));
+        assert!(filter.within_depth(0));
+        assert!(filter.within_depth(1));
+        assert!(!filter.within_depth(2));
+
+        assert!(!WalkFilter::new().with_max_depth(Some(0)).within_depth(0));
+        assert!(WalkFilter::new().within_depth(100));
+    }
+
+    #[test]
+    fn test_reference_time_from_date_or_path() {
+        let date = parse_reference_time("2024-05-01").unwrap();
+        let timestamp = parse_reference_time("2024-05-01T00:00:00Z").unwrap();
+        assert_eq!(date, timestamp);
+
+        let dir = tempfile::TempDir::new().unwrap();
+        let from_path = parse_reference_time(dir.path().to_str().unwrap()).unwrap();
+        assert_eq!(from_path, fs::metadata(dir.path()).unwrap().modified().unwrap());
+
+        assert!(parse_reference_time("last tuesday").is_err());
+    }
+
+    #[test]
+    fn test_invalid_exclude_pattern_is_rejected() {
+        assert!(WalkFilter::new().with_excludes(&["[".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_same_device_is_descended() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::create_dir(temp_dir.path().join("sub")).unwrap();
+
+        let filter = WalkFilter::new()
+            .with_one_file_system(true)
+            .rooted_at(temp_dir.path())
+            .unwrap();
+        let metadata = fs::metadata(temp_dir.path().join("sub")).unwrap();
+        assert!(filter.should_descend(&metadata));
+    }
+}
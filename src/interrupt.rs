@@ -0,0 +1,30 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use crate::error::{Error, Result};
+
+/// Exit code for a transfer stopped by Ctrl-C (128 + SIGINT, as shells report it)
+pub const INTERRUPTED_EXIT_CODE: i32 = 130;
+
+/// Shared flag raised when the user asks the transfer to stop
+pub type InterruptFlag = Arc<AtomicBool>;
+
+/// Install a Ctrl-C handler and return the flag it raises.
+///
+/// The first Ctrl-C only sets the flag so copy loops can stop between chunks
+/// and save their resume state; a second one exits immediately.
+pub fn install_handler() -> Result<InterruptFlag> {
+    let flag: InterruptFlag = Arc::new(AtomicBool::new(false));
+    let handler_flag = flag.clone();
+    ctrlc::set_handler(move || {
+        if handler_flag.swap(true, Ordering::SeqCst) {
+            std::process::exit(INTERRUPTED_EXIT_CODE);
+        }
+    })
+    .map_err(|e| Error::Custom(format!("Failed to install Ctrl-C handler: {}", e)))?;
+    Ok(flag)
+}
+
+/// Whether a stop has been requested
+pub fn is_interrupted(flag: &InterruptFlag) -> bool {
+    flag.load(Ordering::SeqCst)
+}
@@ -1,5 +1,7 @@
 use serde::{Serialize, Deserialize};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use crate::plan::PlannedAction;
 
 /// Structured JSON output for copy operations
 #[derive(Serialize, Deserialize, Debug)]
@@ -14,8 +16,129 @@ pub struct OperationResult {
     pub destination: PathBuf,
     /// Detailed summary
     pub summary: OperationSummary,
+    /// Outcome of each file (a directory source lists the files in it), in
+    /// the order they were processed
+    #[serde(default)]
+    pub files: Vec<FileResult>,
     /// Any error message if operation failed
     pub error: Option<String>,
+    /// Set by `--dry-run`: nothing was written, and the summary counts what
+    /// the copy would do
+    #[serde(default)]
+    pub planned: bool,
+    /// With `planned`, every file the copy would visit and what it would do
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub plan: Vec<PlannedFile>,
+}
+
+/// One entry of a `--dry-run --json` plan
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PlannedFile {
+    pub source: PathBuf,
+    pub destination: PathBuf,
+    pub action: PlannedAction,
+    /// Size of the source file
+    pub bytes: u64,
+}
+
+/// Outcome of copying one source
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum FileStatus {
+    Copied,
+    /// The target already matched; only its timestamps were updated (`--touch-only`)
+    Touched,
+    Skipped,
+    Failed,
+}
+
+/// Per-source entry of the `files` list
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct FileResult {
+    /// Source file or directory
+    pub source: PathBuf,
+    /// Where it was (or would have been) copied to
+    pub destination: PathBuf,
+    /// What happened to it
+    pub status: FileStatus,
+    /// Bytes written for this source
+    pub bytes: u64,
+    /// Why it failed or was skipped, if known
+    pub error: Option<String>,
+    /// Whether a copy replaced a target that already existed
+    #[serde(default)]
+    pub overwritten: bool,
+}
+
+impl FileResult {
+    /// A source that was copied in full
+    pub fn copied(source: PathBuf, destination: PathBuf, bytes: u64) -> Self {
+        Self { source, destination, status: FileStatus::Copied, bytes, error: None, overwritten: false }
+    }
+
+    /// A source whose identical target only had its timestamps brought over
+    pub fn touched(source: PathBuf, destination: PathBuf) -> Self {
+        Self { source, destination, status: FileStatus::Touched, bytes: 0, error: None, overwritten: false }
+    }
+
+    /// A source left alone because its target already existed
+    pub fn skipped(source: PathBuf, destination: PathBuf, reason: String) -> Self {
+        Self { source, destination, status: FileStatus::Skipped, bytes: 0, error: Some(reason), overwritten: false }
+    }
+
+    /// A source whose copy failed
+    pub fn failed(source: PathBuf, destination: PathBuf, error: String) -> Self {
+        Self { source, destination, status: FileStatus::Failed, bytes: 0, error: Some(error), overwritten: false }
+    }
+
+    /// Mark a copy as having replaced its target when one `existed` beforehand
+    pub fn overwriting(mut self, existed: bool) -> Self {
+        self.overwritten = existed && self.status == FileStatus::Copied;
+        self
+    }
+}
+
+/// Per-file results gathered as a run goes, for the `--json` file list.
+/// A directory copy records each file it copies, skips or fails on.
+///
+/// Clones share the same list, so one collector can be handed to every copier.
+#[derive(Debug, Clone, Default)]
+pub struct FileResults {
+    /// Files written under a staging directory that will be renamed to a target
+    staged: Option<(PathBuf, PathBuf)>,
+    files: Arc<Mutex<Vec<FileResult>>>,
+}
+
+impl FileResults {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A handle to the same list for a tree being built in `staging`,
+    /// recording each file under the `target` it will be renamed to
+    pub fn staged(&self, staging: &Path, target: &Path) -> Self {
+        Self {
+            staged: Some((staging.to_path_buf(), target.to_path_buf())),
+            ..self.clone()
+        }
+    }
+
+    /// Record the outcome of one file
+    pub fn record(&self, mut entry: FileResult) {
+        if let Some((staging, target)) = &self.staged {
+            if let Ok(rest) = entry.destination.strip_prefix(staging) {
+                entry.destination = target.join(rest);
+            }
+        }
+        if let Ok(mut files) = self.files.lock() {
+            files.push(entry);
+        }
+    }
+
+    /// Everything recorded so far, in order
+    pub fn to_vec(&self) -> Vec<FileResult> {
+        self.files.lock().map(|files| files.clone()).unwrap_or_default()
+    }
 }
 
 /// Summary statistics for operation
@@ -52,7 +175,33 @@ impl OperationResult {
             source,
             destination,
             summary,
+            files: Vec::new(),
             error: None,
+            planned: false,
+            plan: Vec::new(),
+        }
+    }
+
+    /// The plan of a dry run. Files that would be created, overwritten or
+    /// prompted for count as copied (as if every prompt were answered yes),
+    /// skipped ones as skipped; excluded files count as neither.
+    pub fn planned(source: Vec<PathBuf>, destination: PathBuf, plan: Vec<PlannedFile>) -> Self {
+        let copied: Vec<_> = plan
+            .iter()
+            .filter(|file| {
+                matches!(file.action, PlannedAction::Create | PlannedAction::Overwrite | PlannedAction::Prompt)
+            })
+            .collect();
+        let summary = OperationSummary {
+            bytes_transferred: copied.iter().map(|file| file.bytes).sum(),
+            files_copied: copied.len(),
+            files_skipped: plan.iter().filter(|file| file.action == PlannedAction::Skip).count(),
+            ..OperationSummary::default()
+        };
+        Self {
+            planned: true,
+            plan,
+            ..Self::success(source, destination, summary)
         }
     }
 
@@ -68,10 +217,24 @@ impl OperationResult {
             source,
             destination,
             summary: OperationSummary::default(),
+            files: Vec::new(),
             error: Some(error_msg),
+            planned: false,
+            plan: Vec::new(),
         }
     }
 
+    /// Attach the per-file results, recounting the summary's copied and
+    /// skipped files and bytes from them so the two always agree
+    pub fn with_files(mut self, files: Vec<FileResult>) -> Self {
+        let count = |status| files.iter().filter(|f| f.status == status).count();
+        self.summary.files_copied = count(FileStatus::Copied);
+        self.summary.files_skipped = count(FileStatus::Skipped);
+        self.summary.bytes_transferred = files.iter().map(|f| f.bytes).sum();
+        self.files = files;
+        self
+    }
+
     /// Serialize to JSON string
     pub fn to_json(&self) -> String {
         serde_json::to_string_pretty(self).unwrap_or_else(|_| {
@@ -126,4 +289,23 @@ mod tests {
         assert!(json.contains("\"success\": false"));
         assert!(json.contains("File not found"));
     }
+
+    #[test]
+    fn test_summary_follows_file_list() {
+        let result = OperationResult::success(
+            vec![PathBuf::from("a.txt"), PathBuf::from("b.txt")],
+            PathBuf::from("dest"),
+            OperationSummary::default(),
+        )
+        .with_files(vec![
+            FileResult::copied(PathBuf::from("a.txt"), PathBuf::from("dest/a.txt"), 10),
+            FileResult::skipped(PathBuf::from("b.txt"), PathBuf::from("dest/b.txt"), "exists".to_string()),
+        ]);
+
+        let parsed: OperationResult = serde_json::from_str(&result.to_json()).unwrap();
+        assert_eq!(parsed.summary.files_copied, 1);
+        assert_eq!(parsed.summary.files_skipped, 1);
+        assert_eq!(parsed.summary.bytes_transferred, 10);
+        assert_eq!(parsed.files[1].status, FileStatus::Skipped);
+    }
 }
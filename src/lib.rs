@@ -11,5 +11,23 @@ pub mod output;
 pub mod r#move;
 pub mod parallel;
 pub mod reflink;
+pub mod filter;
+pub mod interrupt;
+pub mod preserve;
+pub mod transaction;
 
 pub use error::{Error, Result};
+pub mod options;
+pub mod color;
+pub mod backup;
+pub mod plan;
+pub mod watchdog;
+pub mod manifest;
+pub mod removal;
+pub mod preflight;
+pub mod report;
+pub mod mirror;
+pub mod durability;
+pub mod rename;
+pub mod sink;
+pub mod chmod;
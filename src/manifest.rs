@@ -0,0 +1,158 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use crate::error::{Error, Result};
+
+/// Digests of every file a run copied, written out as a `SHA256SUMS`-style
+/// manifest (`<hexdigest>  <relative/path>`) that `sha256sum -c` accepts
+/// when run from the destination root.
+///
+/// Clones share the same list, so one manifest can be handed to every copier.
+#[derive(Debug, Clone)]
+pub struct ChecksumManifest {
+    root: PathBuf,
+    /// Files written under a staging directory that will be renamed to a target
+    staged: Option<(PathBuf, PathBuf)>,
+    entries: Arc<Mutex<Vec<(String, String)>>>,
+}
+
+impl ChecksumManifest {
+    /// Paths are recorded relative to `root`, the destination directory
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self {
+            root: root.into(),
+            staged: None,
+            entries: Arc::default(),
+        }
+    }
+
+    /// A handle to the same manifest for a tree being built in `staging`,
+    /// recording each file under the `target` it will be renamed to
+    pub fn staged(&self, staging: &Path, target: &Path) -> Self {
+        Self {
+            staged: Some((staging.to_path_buf(), target.to_path_buf())),
+            ..self.clone()
+        }
+    }
+
+    /// Record the SHA-256 digest of a copied file
+    pub fn record(&self, path: &Path, digest: &str) {
+        let path = match &self.staged {
+            Some((staging, target)) => match path.strip_prefix(staging) {
+                Ok(rest) => target.join(rest),
+                Err(_) => path.to_path_buf(),
+            },
+            None => path.to_path_buf(),
+        };
+        let relative = path.strip_prefix(&self.root).unwrap_or(&path);
+        let line_path = relative
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+
+        if let Ok(mut entries) = self.entries.lock() {
+            entries.push((line_path, digest.to_string()));
+        }
+    }
+
+    /// Write the manifest, one line per file sorted by path
+    pub fn write(&self, path: &Path) -> Result<()> {
+        let mut entries = self.entries.lock().map(|e| e.clone()).unwrap_or_default();
+        entries.sort();
+
+        let contents: String = entries
+            .iter()
+            .map(|(file, digest)| format!("{}  {}\n", digest, file))
+            .collect();
+        fs::write(path, contents).map_err(|e| {
+            Error::Custom(format!("Failed to write manifest {}: {}", path.display(), e))
+        })
+    }
+}
+
+/// Digests the sources are expected to have (`--verify-source`), read from a
+/// `SHA256SUMS`-style file. As with `sha256sum -c`, its paths are relative to
+/// the directory the manifest is in.
+///
+/// Clones share the same digests.
+#[derive(Debug, Clone)]
+pub struct ExpectedChecksums {
+    path: PathBuf,
+    digests: Arc<HashMap<PathBuf, String>>,
+}
+
+impl ExpectedChecksums {
+    pub fn load(path: &Path) -> Result<Self> {
+        let contents = fs::read_to_string(path).map_err(|e| {
+            Error::Custom(format!("Failed to read manifest {}: {}", path.display(), e))
+        })?;
+        let root = match path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent,
+            _ => Path::new("."),
+        };
+        let root = fs::canonicalize(root).map_err(Error::Io)?;
+
+        let mut digests = HashMap::new();
+        for (number, line) in contents.lines().enumerate() {
+            if line.trim().is_empty() || line.starts_with('#') {
+                continue;
+            }
+            // `<digest>  <path>`, or `<digest> *<path>` for binary mode
+            let entry = line.split_once("  ").or_else(|| line.split_once(" *"));
+            let Some((digest, file)) = entry.filter(|(digest, _)| {
+                digest.len() == 64 && digest.chars().all(|c| c.is_ascii_hexdigit())
+            }) else {
+                return Err(Error::Usage(format!(
+                    "{}:{} is not a SHA256SUMS line: {}",
+                    path.display(),
+                    number + 1,
+                    line
+                )));
+            };
+            digests.insert(normalize(&root.join(file)), digest.to_ascii_lowercase());
+        }
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            digests: Arc::new(digests),
+        })
+    }
+
+    /// The digest listed for `source`, if the manifest has it
+    pub fn expected(&self, source: &Path) -> Option<&str> {
+        let source = fs::canonicalize(source).ok()?;
+        self.digests.get(&source).map(String::as_str)
+    }
+
+    /// The manifest file the digests came from
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+/// `path` without `.` components, so `./a/b` and `a/b` are the same entry
+fn normalize(path: &Path) -> PathBuf {
+    path.components().filter(|c| *c != Component::CurDir).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_staged_files_are_recorded_under_target() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let root = dir.path().join("dest");
+        let manifest = ChecksumManifest::new(&root);
+        manifest.record(&root.join("b.txt"), "bb");
+        manifest
+            .staged(&dir.path().join("dest.partial"), &root)
+            .record(&dir.path().join("dest.partial/sub/a.txt"), "aa");
+
+        let out = dir.path().join("SHA256SUMS");
+        manifest.write(&out).unwrap();
+        assert_eq!(fs::read_to_string(&out).unwrap(), "bb  b.txt\naa  sub/a.txt\n");
+    }
+}
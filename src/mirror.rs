@@ -0,0 +1,171 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+use crate::copy::Transcode;
+use crate::error::{Error, Result};
+use crate::filter::WalkFilter;
+use crate::removal::remove_path;
+
+/// Entries under `target` with nothing at the same relative path under
+/// `source`, which a `--delete` mirror removes (like `rsync --delete`).
+///
+/// A directory with no counterpart is listed once rather than entry by
+/// entry. Symlinks are never followed, so nothing outside `target` is
+/// looked at, and entries `filter` excludes are kept. Under `transcode`
+/// the files a source file was written as (`a.txt.zst` for `a.txt`) count
+/// as its counterparts.
+pub fn extraneous_entries(
+    source: &Path,
+    target: &Path,
+    filter: &WalkFilter,
+    transcode: Option<Transcode>,
+) -> Result<Vec<PathBuf>> {
+    let mut extraneous = Vec::new();
+    if !target.is_dir() {
+        return Ok(extraneous);
+    }
+    let transcoded = match transcode {
+        Some(transcode) => transcoded_files(source, transcode)?,
+        None => HashSet::new(),
+    };
+
+    let mut walker = WalkDir::new(target).min_depth(1).follow_links(false).into_iter();
+    while let Some(entry) = walker.next() {
+        let entry = entry.map_err(|e| Error::Custom(e.to_string()))?;
+        let relative = entry
+            .path()
+            .strip_prefix(target)
+            .map_err(|e| Error::Custom(e.to_string()))?;
+
+        if !filter.is_included(relative) {
+            if entry.file_type().is_dir() {
+                walker.skip_current_dir();
+            }
+            continue;
+        }
+        if fs::symlink_metadata(source.join(relative)).is_err() && !transcoded.contains(relative) {
+            if entry.file_type().is_dir() {
+                walker.skip_current_dir();
+            }
+            extraneous.push(entry.into_path());
+        }
+    }
+    Ok(extraneous)
+}
+
+/// Where each file under `source` lands relative to the target once
+/// `transcode` renamed it
+fn transcoded_files(source: &Path, transcode: Transcode) -> Result<HashSet<PathBuf>> {
+    let mut files = HashSet::new();
+    for entry in WalkDir::new(source).min_depth(1).follow_links(false) {
+        let entry = entry.map_err(|e| Error::Custom(e.to_string()))?;
+        if entry.file_type().is_file() {
+            let relative = entry
+                .path()
+                .strip_prefix(source)
+                .map_err(|e| Error::Custom(e.to_string()))?;
+            files.insert(transcode.target_path(relative));
+        }
+    }
+    Ok(files)
+}
+
+/// Remove every extraneous entry of `target`, returning how many went
+pub fn delete_extraneous(
+    source: &Path,
+    target: &Path,
+    filter: &WalkFilter,
+    transcode: Option<Transcode>,
+) -> Result<usize> {
+    let extraneous = extraneous_entries(source, target, filter, transcode)?;
+    for path in &extraneous {
+        // The walk never leaves the target, but never delete outside it regardless
+        if !path.starts_with(target) {
+            return Err(Error::Custom(format!(
+                "Refusing to delete {} outside {}",
+                path.display(),
+                target.display()
+            )));
+        }
+        remove_path(path, false)?;
+    }
+    Ok(extraneous.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::OverwriteMode;
+    use crate::copy::DirectoryCopier;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_delete_removes_only_extraneous_files() {
+        let temp_dir = TempDir::new().unwrap();
+        let source = temp_dir.path().join("source");
+        let target = temp_dir.path().join("target");
+        fs::create_dir_all(source.join("sub")).unwrap();
+        fs::write(source.join("kept.txt"), b"kept").unwrap();
+        fs::write(source.join("sub/nested.txt"), b"nested").unwrap();
+        fs::create_dir_all(target.join("sub")).unwrap();
+        fs::write(target.join("sub/extra.txt"), b"extra").unwrap();
+
+        let outside = temp_dir.path().join("outside");
+        fs::create_dir_all(&outside).unwrap();
+        fs::write(outside.join("precious.txt"), b"precious").unwrap();
+        #[cfg(unix)]
+        std::os::unix::fs::symlink(&outside, target.join("link")).unwrap();
+
+        DirectoryCopier::new(source.clone(), target.clone(), OverwriteMode::Always, true)
+            .with_progress(false)
+            .with_delete(true)
+            .copy()
+            .await
+            .unwrap();
+
+        assert!(!target.join("sub/extra.txt").exists());
+        assert_eq!(fs::read(target.join("kept.txt")).unwrap(), b"kept");
+        assert_eq!(fs::read(target.join("sub/nested.txt")).unwrap(), b"nested");
+        // The link itself went, not what it points to
+        assert!(fs::symlink_metadata(target.join("link")).is_err());
+        assert_eq!(fs::read(outside.join("precious.txt")).unwrap(), b"precious");
+        assert!(extraneous_entries(&source, &target, &WalkFilter::new(), None).unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_delete_keeps_transcoded_files() {
+        use crate::cli::Compression;
+
+        let temp_dir = TempDir::new().unwrap();
+        let source = temp_dir.path().join("source");
+        let packed = temp_dir.path().join("packed");
+        let unpacked = temp_dir.path().join("unpacked");
+        fs::create_dir_all(source.join("sub")).unwrap();
+        fs::write(source.join("a.txt"), b"alpha").unwrap();
+        fs::write(source.join("sub/b.txt"), b"beta").unwrap();
+        fs::create_dir_all(&packed).unwrap();
+        fs::write(packed.join("stale.txt.zst"), b"stale").unwrap();
+
+        DirectoryCopier::new(source.clone(), packed.clone(), OverwriteMode::Always, true)
+            .with_progress(false)
+            .with_transcode(Some(Transcode::Compress(Compression::Zstd)))
+            .with_delete(true)
+            .copy()
+            .await
+            .unwrap();
+        assert!(packed.join("a.txt.zst").exists());
+        assert!(packed.join("sub/b.txt.zst").exists());
+        assert!(!packed.join("stale.txt.zst").exists());
+
+        DirectoryCopier::new(packed, unpacked.clone(), OverwriteMode::Always, true)
+            .with_progress(false)
+            .with_transcode(Some(Transcode::Decompress(Compression::Zstd)))
+            .with_delete(true)
+            .copy()
+            .await
+            .unwrap();
+        assert_eq!(fs::read(unpacked.join("a.txt")).unwrap(), b"alpha");
+        assert_eq!(fs::read(unpacked.join("sub/b.txt")).unwrap(), b"beta");
+    }
+}
@@ -1,9 +1,58 @@
-use std::fs::{self, File};
+use std::fs;
+use std::io::ErrorKind;
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::sync::Semaphore;
 use crate::error::{Error, Result};
-use crate::progress::ProgressTracker;
-use crate::cli::OverwriteMode;
-use crate::prompt::{self, OverwriteChoice};
+use crate::progress::ProgressMode;
+use crate::cli::{BackupMode, OverwriteMode};
+use crate::backup::backup_existing;
+use crate::copy::{create_parent_dirs, DirectoryCopier, FileCopier};
+use crate::plan::{file_action, PlannedAction};
+use crate::interrupt::{is_interrupted, InterruptFlag};
+use crate::prompt::OverwriteChoice;
+use crate::options::CopyOptions;
+use crate::preserve::PreserveSet;
+use crate::resume::ResumeState;
+use crate::reflink::same_filesystem;
+use crate::json_output::{FileResult, FileResults};
+use crate::output::Logger;
+use crate::removal::remove_path;
+
+/// How moves treat existing targets and the files they remove
+#[derive(Debug, Clone)]
+pub struct MoveSettings {
+    pub overwrite_mode: OverwriteMode,
+    pub backup: BackupMode,
+    /// Send removed sources and replaced targets to the trash instead of deleting them
+    pub trash: bool,
+    pub verbose: bool,
+    pub show_progress: bool,
+    /// Fail with `Error::CrossDevice` instead of copying and deleting
+    pub same_device_only: bool,
+    /// Stops copy+delete moves between chunks, keeping their resume state
+    pub interrupt: InterruptFlag,
+    /// Hooks for conflicts and resume decisions
+    pub options: CopyOptions,
+    /// How each item fared, for the `--json` file list
+    pub file_results: Option<FileResults>,
+}
+
+impl MoveSettings {
+    pub fn new(overwrite_mode: OverwriteMode) -> Self {
+        Self {
+            overwrite_mode,
+            backup: BackupMode::None,
+            trash: false,
+            verbose: false,
+            show_progress: false,
+            same_device_only: false,
+            interrupt: InterruptFlag::default(),
+            options: CopyOptions::interactive(),
+            file_results: None,
+        }
+    }
+}
 
 /// Move a file or directory with progress tracking
 pub struct FileMover {
@@ -11,6 +60,12 @@ pub struct FileMover {
     target: PathBuf,
     overwrite_mode: OverwriteMode,
     verbose: bool,
+    progress: ProgressMode,
+    backup: BackupMode,
+    trash: bool,
+    same_device_only: bool,
+    interrupt: InterruptFlag,
+    options: CopyOptions,
 }
 
 impl FileMover {
@@ -25,9 +80,54 @@ impl FileMover {
             target,
             overwrite_mode,
             verbose,
+            progress: ProgressMode::Bar,
+            backup: BackupMode::None,
+            trash: false,
+            same_device_only: false,
+            interrupt: InterruptFlag::default(),
+            options: CopyOptions::interactive(),
         }
     }
 
+    /// Choose how progress is reported (a bar by default); accepts a bool
+    pub fn with_progress(mut self, progress: impl Into<ProgressMode>) -> Self {
+        self.progress = progress.into();
+        self
+    }
+
+    /// Rename an existing target to a backup before replacing it
+    pub fn with_backup(mut self, backup: BackupMode) -> Self {
+        self.backup = backup;
+        self
+    }
+
+    /// Send the source (after a copy+delete move) and any replaced target to
+    /// the trash instead of deleting them
+    pub fn with_trash(mut self, trash: bool) -> Self {
+        self.trash = trash;
+        self
+    }
+
+    /// Only ever rename: a move to another filesystem fails with
+    /// `Error::CrossDevice` before anything is touched
+    pub fn with_same_device_only(mut self, same_device_only: bool) -> Self {
+        self.same_device_only = same_device_only;
+        self
+    }
+
+    /// Stop a copy+delete move between chunks once this flag is raised,
+    /// keeping the partial target and its resume state for the next run
+    pub fn with_interrupt(mut self, interrupt: InterruptFlag) -> Self {
+        self.interrupt = interrupt;
+        self
+    }
+
+    /// Hooks for conflicts, resume decisions and progress (terminal prompts by default)
+    pub fn with_options(mut self, options: CopyOptions) -> Self {
+        self.options = options;
+        self
+    }
+
     /// Execute the move operation
     pub async fn move_file(&self) -> Result<()> {
         // Validate source exists
@@ -39,15 +139,21 @@ impl FileMover {
 
         let src_metadata = fs::metadata(&self.source).map_err(Error::Io)?;
 
-        // Check if target exists and handle overwrite logic
-        if self.target.exists() {
-            self.handle_overwrite(&src_metadata)?;
+        if self.same_device_only {
+            check_same_device(&self.source, &self.target, detect_same_device(&self.source, &self.target)?)?;
         }
+        create_parent_dirs(&self.target)?;
+
+        // A partial target left by an interrupted move of this same source is
+        // picked up again below rather than treated as a file to protect
+        let resuming = ResumeState::load(&self.target)
+            .ok()
+            .flatten()
+            .is_some_and(|state| state.source == self.source);
 
-        // Create parent directories if needed
-        if let Some(parent) = self.target.parent() {
-            fs::create_dir_all(parent)
-                .map_err(|e| Error::Custom(format!("Failed to create parent directory: {}", e)))?;
+        // Check if target exists and handle overwrite logic
+        if self.target.exists() && !resuming {
+            self.handle_overwrite(&src_metadata)?;
         }
 
         // Try simple rename first (same filesystem)
@@ -58,8 +164,8 @@ impl FileMover {
                 }
                 return Ok(());
             }
-            Err(e) if e.kind() == std::io::ErrorKind::InvalidInput
-                || e.kind() == std::io::ErrorKind::PermissionDenied => {
+            Err(e) if is_cross_device(&e) => {
+                check_same_device(&self.source, &self.target, !self.same_device_only)?;
                 // Cross-filesystem move: copy then delete
                 if self.verbose {
                     println!(
@@ -76,119 +182,94 @@ impl FileMover {
         Ok(())
     }
 
-    /// Move via copy and delete (for cross-filesystem moves)
+    /// Move via copy and delete (for cross-filesystem moves).
+    ///
+    /// The copy is a resumable, verified `FileCopier` copy: an interrupted
+    /// move continues where it stopped on the next run, and the source is
+    /// only removed once the whole target matches it. The overwrite decision
+    /// has already been made by the caller.
     async fn move_via_copy(&self) -> Result<()> {
-        let src_metadata = fs::metadata(&self.source).map_err(Error::Io)?;
-        let total_size = src_metadata.len();
-
-        let tracker = ProgressTracker::new(total_size, true);
-
-        // Copy file in chunks
-        let mut src_file =
-            File::open(&self.source).map_err(Error::Io)?;
-        let mut dst_file =
-            File::create(&self.target).map_err(Error::Io)?;
-
-        const CHUNK_SIZE: usize = 64 * 1024 * 1024; // 64 MB
-        let mut buffer = vec![0; CHUNK_SIZE];
-
-        use std::io::{Read, Write};
-
-        loop {
-            let bytes_read = src_file.read(&mut buffer).map_err(Error::Io)?;
-
-            if bytes_read == 0 {
-                break;
-            }
-
-            dst_file.write_all(&buffer[..bytes_read]).map_err(Error::Io)?;
-            tracker.add_bytes(bytes_read as u64);
-        }
+        FileCopier::new(
+            self.source.clone(),
+            self.target.clone(),
+            OverwriteMode::Always,
+            true,
+            true,
+            false,
+        )
+        .with_progress(self.progress)
+        .with_interrupt(self.interrupt.clone())
+        .with_preserve(PreserveSet::mode_and_timestamps())
+        .with_options(self.options.clone())
+        .copy()
+        .await?;
+
+        remove_path(&self.source, self.trash)
+    }
 
-        drop(src_file);
-        drop(dst_file);
-        tracker.finish();
-
-        // Delete source after successful copy
-        if src_metadata.is_dir() {
-            fs::remove_dir_all(&self.source).map_err(Error::Io)?;
-        } else {
-            fs::remove_file(&self.source).map_err(Error::Io)?;
+    /// Decide whether the existing target may be replaced and, if so,
+    /// move it to its backup name (or the trash) first
+    fn handle_overwrite(&self, src_metadata: &fs::Metadata) -> Result<()> {
+        self.check_overwrite(src_metadata)?;
+        if backup_existing(&self.target, self.backup)?.is_none() && self.trash {
+            remove_path(&self.target, true)?;
         }
-
         Ok(())
     }
 
-    fn handle_overwrite(&self, src_metadata: &fs::Metadata) -> Result<()> {
-        match self.overwrite_mode {
-            OverwriteMode::Never => {
-                Err(Error::TargetExists(
-                    self.target.to_string_lossy().to_string(),
-                ))
+    fn check_overwrite(&self, src_metadata: &fs::Metadata) -> Result<()> {
+        let target = || self.target.to_string_lossy().to_string();
+        match file_action(&self.source, src_metadata, &self.target, &self.overwrite_mode)? {
+            PlannedAction::Create | PlannedAction::Overwrite => Ok(()),
+            PlannedAction::Skip if matches!(self.overwrite_mode, OverwriteMode::NoClobber | OverwriteMode::Policy(_)) => {
+                Err(Error::Skipped(target()))
             }
-            OverwriteMode::Always => Ok(()),
-            OverwriteMode::Prompt => {
+            PlannedAction::Skip | PlannedAction::Excluded => Err(Error::TargetExists(target())),
+            PlannedAction::Prompt => {
                 let tgt_metadata = fs::metadata(&self.target).map_err(Error::Io)?;
 
-                match prompt::prompt_overwrite(&self.target, src_metadata, &tgt_metadata)? {
+                match (self.options.on_conflict)(&self.target, src_metadata, &tgt_metadata)? {
                     OverwriteChoice::Overwrite => Ok(()),
-                    OverwriteChoice::Skip => {
-                        Err(Error::Custom("Skipped by user".to_string()))
-                    }
+                    OverwriteChoice::Skip => Err(Error::Skipped(target())),
                     OverwriteChoice::Rename => {
                         Err(Error::Custom("Rename not yet implemented".to_string()))
                     }
                     OverwriteChoice::Abort => Err(Error::UserAborted),
                 }
             }
-            OverwriteMode::Smart => {
-                let tgt_metadata = fs::metadata(&self.target).map_err(Error::Io)?;
-
-                let src_modified = src_metadata.modified().map_err(Error::Io)?;
-                let tgt_modified = tgt_metadata.modified().map_err(Error::Io)?;
-
-                if src_modified > tgt_modified {
-                    Ok(())
-                } else {
-                    Err(Error::TargetExists(
-                        self.target.to_string_lossy().to_string(),
-                    ))
-                }
-            }
         }
     }
 }
 
 /// Move a directory recursively
-pub async fn move_directory(
-    source: &Path,
-    target: &Path,
-    overwrite_mode: OverwriteMode,
-    verbose: bool,
-) -> Result<()> {
+pub async fn move_directory(source: &Path, target: &Path, settings: &MoveSettings) -> Result<()> {
     if !source.is_dir() {
         return Err(Error::Custom("Source is not a directory".to_string()));
     }
+    if settings.same_device_only {
+        check_same_device(source, target, detect_same_device(source, target)?)?;
+    }
+    create_parent_dirs(target)?;
 
     // Try simple rename first
     match fs::rename(source, target) {
         Ok(_) => {
-            if verbose {
+            if settings.verbose {
                 println!("Moved directory: {} → {}", source.display(), target.display());
             }
             return Ok(());
         }
-        Err(e) if e.kind() == std::io::ErrorKind::InvalidInput
-            || e.kind() == std::io::ErrorKind::PermissionDenied => {
+        Err(e) if is_cross_device(&e) => {
+            check_same_device(source, target, !settings.same_device_only)?;
             // Cross-filesystem move: copy directory then delete
-            if verbose {
+            if settings.verbose {
                 println!(
                     "Cross-filesystem move (copy+delete): {} → {}",
                     source.display(),
                     target.display()
                 );
             }
-            move_directory_via_copy(source, target, overwrite_mode, verbose).await?;
+            move_directory_via_copy(source, target, settings).await?;
         }
         Err(e) => return Err(Error::Io(e)),
     }
@@ -196,28 +277,191 @@ pub async fn move_directory(
     Ok(())
 }
 
-/// Move directory via copy and delete (cross-filesystem)
-async fn move_directory_via_copy(
-    source: &Path,
-    target: &Path,
-    overwrite_mode: OverwriteMode,
-    verbose: bool,
-) -> Result<()> {
-    use crate::copy::copy_directory;
+/// Whether a rename failed because source and target are on different
+/// filesystems, so the move has to copy and delete instead
+fn is_cross_device(e: &std::io::Error) -> bool {
+    matches!(
+        e.kind(),
+        ErrorKind::CrossesDevices | ErrorKind::InvalidInput | ErrorKind::PermissionDenied
+    )
+}
 
+/// Whether `source` and `target` share a filesystem, as far as can be told
+/// before trying. Only Unix can tell; elsewhere the rename itself decides.
+fn detect_same_device(source: &Path, target: &Path) -> Result<bool> {
+    if cfg!(unix) {
+        same_filesystem(source, target)
+    } else {
+        Ok(true)
+    }
+}
+
+/// Refuse a move the caller wanted as a rename when it would cross
+/// filesystems (`--same-device-only`)
+fn check_same_device(source: &Path, target: &Path, same_device: bool) -> Result<()> {
+    if same_device {
+        Ok(())
+    } else {
+        Err(Error::CrossDevice {
+            from: source.to_string_lossy().to_string(),
+            to: target.to_string_lossy().to_string(),
+        })
+    }
+}
+
+/// Move directory via copy and delete (cross-filesystem). When the copy left
+/// anything out, such as files kept by `--no-clobber` or `--on-conflict`, the
+/// source stays whole and the move ends as a skip, so nothing is lost.
+async fn move_directory_via_copy(source: &Path, target: &Path, settings: &MoveSettings) -> Result<()> {
     // Copy entire directory
-    copy_directory(source, target, overwrite_mode, false).await?;
+    let stats = DirectoryCopier::new(source.to_path_buf(), target.to_path_buf(), settings.overwrite_mode.clone(), false)
+        .with_progress(settings.show_progress)
+        .with_backup(settings.backup)
+        .with_trash(settings.trash)
+        .with_interrupt(settings.interrupt.clone())
+        .with_options(settings.options.clone())
+        .copy()
+        .await?;
+    if stats.files_skipped > 0 {
+        return Err(Error::Skipped(target.to_string_lossy().to_string()));
+    }
 
     // Delete source directory
-    fs::remove_dir_all(source).map_err(Error::Io)?;
+    remove_path(source, settings.trash)?;
 
-    if verbose {
+    if settings.verbose {
         println!("Moved directory: {} → {}", source.display(), target.display());
     }
 
     Ok(())
 }
 
+/// Move a single file or directory to its target
+pub async fn move_item(source: &Path, target: &Path, settings: &MoveSettings) -> Result<()> {
+    if source.is_dir() {
+        move_directory(source, target, settings).await
+    } else {
+        FileMover::new(
+            source.to_path_buf(),
+            target.to_path_buf(),
+            settings.overwrite_mode.clone(),
+            settings.verbose,
+        )
+        .with_progress(settings.show_progress)
+        .with_backup(settings.backup)
+        .with_trash(settings.trash)
+        .with_same_device_only(settings.same_device_only)
+        .with_interrupt(settings.interrupt.clone())
+        .with_options(settings.options.clone())
+        .move_file()
+        .await
+    }
+}
+
+/// Move a single file or directory, appending the outcome to `logger` and
+/// the settings' file results
+pub async fn move_item_logged(
+    source: &Path,
+    target: &Path,
+    settings: &MoveSettings,
+    logger: Option<&Logger>,
+) -> Result<()> {
+    // Measured up front: once moved, the source is gone
+    let bytes = fs::metadata(source)
+        .ok()
+        .filter(|metadata| metadata.is_file())
+        .map_or(0, |metadata| metadata.len());
+
+    let result = move_item(source, target, settings).await;
+
+    let (source, target) = (source.to_path_buf(), target.to_path_buf());
+    let entry = match &result {
+        Ok(()) => FileResult::copied(source, target, bytes),
+        Err(e) if e.is_skip() => FileResult::skipped(source, target, e.to_string()),
+        Err(e) => FileResult::failed(source, target, e.to_string()),
+    };
+    if let Some(logger) = logger {
+        logger.record("move", &entry);
+    }
+    if let Some(file_results) = &settings.file_results {
+        file_results.record(entry);
+    }
+    result
+}
+
+/// Move several `(source, target)` pairs, returning the targets skipped
+/// because they already existed.
+///
+/// Same-filesystem moves are plain renames and run sequentially; the
+/// copy+delete moves across filesystems run up to `parallel` at a time.
+pub async fn move_many(
+    moves: Vec<(PathBuf, PathBuf)>,
+    settings: &MoveSettings,
+    parallel: usize,
+    logger: Option<&Logger>,
+) -> Result<Vec<PathBuf>> {
+    let (renames, cross_device): (Vec<_>, Vec<_>) = moves
+        .into_iter()
+        .partition(|(source, target)| {
+            parallel == 0 || same_filesystem(source, target).unwrap_or(true)
+        });
+
+    let mut skipped = Vec::new();
+    for (source, target) in renames {
+        if is_interrupted(&settings.interrupt) {
+            return Err(Error::Interrupted(target.to_string_lossy().to_string()));
+        }
+        match move_item_logged(&source, &target, settings, logger).await {
+            Ok(()) => {}
+            Err(e) if e.is_skip() => skipped.push(target),
+            Err(e) => return Err(e),
+        }
+    }
+
+    skipped.extend(move_concurrently(cross_device, settings, parallel, logger).await?);
+    Ok(skipped)
+}
+
+/// Run moves on up to `parallel` tasks at once, returning the targets skipped
+async fn move_concurrently(
+    moves: Vec<(PathBuf, PathBuf)>,
+    settings: &MoveSettings,
+    parallel: usize,
+    logger: Option<&Logger>,
+) -> Result<Vec<PathBuf>> {
+    let permits = Arc::new(Semaphore::new(parallel.max(1)));
+    let mut handles = Vec::new();
+
+    for (source, target) in moves {
+        let task_target = target.clone();
+        let permits = Arc::clone(&permits);
+        let settings = settings.clone();
+        let logger = logger.cloned();
+        let handle = tokio::spawn(async move {
+            let _permit = permits
+                .acquire_owned()
+                .await
+                .map_err(|e| Error::Custom(e.to_string()))?;
+            if is_interrupted(&settings.interrupt) {
+                return Err(Error::Interrupted(task_target.to_string_lossy().to_string()));
+            }
+            move_item_logged(&source, &task_target, &settings, logger.as_ref()).await
+        });
+        handles.push((target, handle));
+    }
+
+    let mut skipped = Vec::new();
+    for (target, handle) in handles {
+        match handle.await.map_err(|e| Error::Custom(e.to_string()))? {
+            Ok(()) => {}
+            Err(e) if e.is_skip() => skipped.push(target),
+            Err(e) => return Err(e),
+        }
+    }
+
+    Ok(skipped)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -248,6 +492,56 @@ mod tests {
         assert_eq!(content, b"test content");
     }
 
+    #[tokio::test]
+    async fn test_interrupted_cross_device_move_resumes() {
+        let temp_dir = TempDir::new().unwrap();
+        let src_path = temp_dir.path().join("source.bin");
+        let dst_path = temp_dir.path().join("dest.bin");
+        let contents: Vec<u8> = (0..100u8).collect();
+        fs::write(&src_path, &contents).unwrap();
+
+        // What an interrupted run leaves: the first half and its resume state
+        fs::write(&dst_path, &contents[..50]).unwrap();
+        let mut state = ResumeState::new(src_path.clone(), dst_path.clone(), 100);
+        state.advance_to(50);
+        state.save().unwrap();
+
+        let resumed_from = Arc::new(std::sync::Mutex::new(None));
+        let seen = Arc::clone(&resumed_from);
+        let options = CopyOptions::new(|_, _, _| Ok(OverwriteChoice::Skip)).on_resume(move |_, _, _, done| {
+            *seen.lock().unwrap() = Some(done);
+            Ok(true)
+        });
+        let mover = FileMover::new(src_path.clone(), dst_path.clone(), OverwriteMode::Never, false)
+            .with_progress(false)
+            .with_options(options);
+        mover.move_via_copy().await.unwrap();
+
+        assert_eq!(*resumed_from.lock().unwrap(), Some(50));
+        assert!(!src_path.exists());
+        assert_eq!(fs::read(&dst_path).unwrap(), contents);
+        assert!(ResumeState::load(&dst_path).unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_same_device_only_refuses_cross_device_moves() {
+        let temp_dir = TempDir::new().unwrap();
+        let source = temp_dir.path().join("source.txt");
+        let target = temp_dir.path().join("target.txt");
+        fs::write(&source, b"stay").unwrap();
+
+        let err = check_same_device(&source, &target, false).unwrap_err();
+        assert!(matches!(err, Error::CrossDevice { .. }));
+        assert!(err.detailed_message().contains("--same-device-only"));
+        assert!(check_same_device(&source, &target, true).is_ok());
+
+        // On one filesystem the guard lets the rename through
+        let mover = FileMover::new(source.clone(), target.clone(), OverwriteMode::Never, false)
+            .with_same_device_only(true);
+        mover.move_file().await.unwrap();
+        assert_eq!(fs::read(&target).unwrap(), b"stay");
+    }
+
     #[tokio::test]
     async fn test_directory_move() {
         let temp_dir = TempDir::new().unwrap();
@@ -260,7 +554,7 @@ mod tests {
         src_file.write_all(b"content").unwrap();
         drop(src_file);
 
-        let result = move_directory(&src_dir, &dst_dir, OverwriteMode::Always, false).await;
+        let result = move_directory(&src_dir, &dst_dir, &MoveSettings::new(OverwriteMode::Always)).await;
         assert!(result.is_ok());
 
         // Source should be gone
@@ -270,4 +564,150 @@ mod tests {
         assert!(dst_dir.exists());
         assert_eq!(fs::read(dst_dir.join("file.txt")).unwrap(), b"content");
     }
+
+    #[tokio::test]
+    async fn test_directory_move_via_copy_backs_up_replaced_files() {
+        let temp_dir = TempDir::new().unwrap();
+        let src_dir = temp_dir.path().join("source_dir");
+        let dst_dir = temp_dir.path().join("dest_dir");
+        fs::create_dir(&src_dir).unwrap();
+        fs::create_dir(&dst_dir).unwrap();
+        fs::write(src_dir.join("file.txt"), b"new").unwrap();
+        fs::write(dst_dir.join("file.txt"), b"old").unwrap();
+
+        let mut settings = MoveSettings::new(OverwriteMode::Always);
+        settings.backup = BackupMode::Simple;
+        move_directory_via_copy(&src_dir, &dst_dir, &settings).await.unwrap();
+
+        assert!(!src_dir.exists());
+        assert_eq!(fs::read(dst_dir.join("file.txt")).unwrap(), b"new");
+        assert_eq!(fs::read(dst_dir.join("file.txt~")).unwrap(), b"old");
+    }
+
+    #[tokio::test]
+    async fn test_directory_move_via_copy_keeps_source_when_files_are_skipped() {
+        let temp_dir = TempDir::new().unwrap();
+        let src_dir = temp_dir.path().join("source_dir");
+        let dst_dir = temp_dir.path().join("dest_dir");
+        fs::create_dir(&src_dir).unwrap();
+        fs::create_dir(&dst_dir).unwrap();
+        fs::write(src_dir.join("kept.txt"), b"precious").unwrap();
+        fs::write(src_dir.join("new.txt"), b"new").unwrap();
+        fs::write(dst_dir.join("kept.txt"), b"existing").unwrap();
+
+        let settings = MoveSettings::new(OverwriteMode::NoClobber);
+        let err = move_directory_via_copy(&src_dir, &dst_dir, &settings).await.unwrap_err();
+
+        assert!(err.is_skip(), "{:?}", err);
+        assert_eq!(fs::read(src_dir.join("kept.txt")).unwrap(), b"precious");
+        assert_eq!(fs::read(dst_dir.join("kept.txt")).unwrap(), b"existing");
+        assert_eq!(fs::read(dst_dir.join("new.txt")).unwrap(), b"new");
+    }
+
+    #[tokio::test]
+    async fn test_directory_move_via_copy_asks_through_the_options() {
+        let temp_dir = TempDir::new().unwrap();
+        let src_dir = temp_dir.path().join("source_dir");
+        let dst_dir = temp_dir.path().join("dest_dir");
+        fs::create_dir(&src_dir).unwrap();
+        fs::create_dir(&dst_dir).unwrap();
+        fs::write(src_dir.join("file.txt"), b"new").unwrap();
+        fs::write(dst_dir.join("file.txt"), b"old").unwrap();
+
+        let mut settings = MoveSettings::new(OverwriteMode::Prompt);
+        settings.options = CopyOptions::non_interactive();
+        let err = move_directory_via_copy(&src_dir, &dst_dir, &settings).await.unwrap_err();
+
+        assert!(matches!(err, Error::Usage(_)), "{:?}", err);
+        assert!(src_dir.join("file.txt").exists());
+        assert_eq!(fs::read(dst_dir.join("file.txt")).unwrap(), b"old");
+    }
+
+    #[tokio::test]
+    async fn test_directory_move_via_copy_keeps_source_files_the_policy_keeps_out() {
+        use crate::cli::ConflictPolicy;
+        use std::time::{Duration, SystemTime};
+
+        let temp_dir = TempDir::new().unwrap();
+        let src_dir = temp_dir.path().join("source_dir");
+        let dst_dir = temp_dir.path().join("dest_dir");
+        fs::create_dir(&src_dir).unwrap();
+        fs::create_dir(&dst_dir).unwrap();
+        fs::write(src_dir.join("old.txt"), b"older source").unwrap();
+        File::options()
+            .write(true)
+            .open(src_dir.join("old.txt"))
+            .unwrap()
+            .set_modified(SystemTime::now() - Duration::from_secs(3600))
+            .unwrap();
+        fs::write(dst_dir.join("old.txt"), b"newer target").unwrap();
+
+        let settings = MoveSettings::new(OverwriteMode::Policy(ConflictPolicy::Newer));
+        let err = move_directory_via_copy(&src_dir, &dst_dir, &settings).await.unwrap_err();
+
+        assert!(err.is_skip(), "{:?}", err);
+        assert_eq!(fs::read(src_dir.join("old.txt")).unwrap(), b"older source");
+        assert_eq!(fs::read(dst_dir.join("old.txt")).unwrap(), b"newer target");
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_concurrent_move_of_many_files() {
+        let temp_dir = TempDir::new().unwrap();
+        let dest_dir = temp_dir.path().join("dest");
+        fs::create_dir(&dest_dir).unwrap();
+
+        let mut moves = Vec::new();
+        for i in 0..8 {
+            let source = temp_dir.path().join(format!("file_{}.txt", i));
+            fs::write(&source, format!("content {}", i)).unwrap();
+            moves.push((source.clone(), dest_dir.join(format!("file_{}.txt", i))));
+        }
+
+        let skipped = move_concurrently(moves.clone(), &MoveSettings::new(OverwriteMode::Always), 3, None)
+            .await
+            .unwrap();
+        assert!(skipped.is_empty());
+
+        for (i, (source, target)) in moves.iter().enumerate() {
+            assert!(!source.exists());
+            assert_eq!(fs::read_to_string(target).unwrap(), format!("content {}", i));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_raised_interrupt_stops_move_many() {
+        let temp_dir = TempDir::new().unwrap();
+        let source = temp_dir.path().join("file.txt");
+        let target = temp_dir.path().join("moved.txt");
+        fs::write(&source, "content").unwrap();
+
+        let settings = MoveSettings::new(OverwriteMode::Always);
+        settings.interrupt.store(true, std::sync::atomic::Ordering::SeqCst);
+        for parallel in [0, 2] {
+            let result = move_many(vec![(source.clone(), target.clone())], &settings, parallel, None).await;
+            assert!(result.unwrap_err().is_interrupt());
+            assert!(source.exists());
+            assert!(!target.exists());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_smart_move_overwrites_equal_mtime_different_size() {
+        let temp_dir = TempDir::new().unwrap();
+        let src_path = temp_dir.path().join("source.bin");
+        let dst_path = temp_dir.path().join("dest.bin");
+        fs::write(&src_path, b"complete contents").unwrap();
+        fs::write(&dst_path, b"partial").unwrap();
+
+        let stamp = std::time::SystemTime::now() - std::time::Duration::from_secs(60);
+        for path in [&src_path, &dst_path] {
+            File::options().write(true).open(path).unwrap().set_modified(stamp).unwrap();
+        }
+
+        let mover = FileMover::new(src_path.clone(), dst_path.clone(), OverwriteMode::Smart, false);
+        mover.move_file().await.unwrap();
+
+        assert!(!src_path.exists());
+        assert_eq!(fs::read(&dst_path).unwrap(), b"complete contents");
+    }
 }
@@ -0,0 +1,158 @@
+use std::fs::Metadata;
+use std::path::Path;
+use std::sync::Arc;
+use crate::error::{Error, Result};
+use crate::progress::TransferStats;
+use crate::prompt::{self, OverwriteChoice};
+
+/// Decides what to do about an existing target: (target, source metadata, target metadata)
+pub type ConflictHandler =
+    Arc<dyn Fn(&Path, &Metadata, &Metadata) -> Result<OverwriteChoice> + Send + Sync>;
+
+/// Decides whether to continue a partial copy: (source, target, total bytes, bytes done)
+pub type ResumeHandler = Arc<dyn Fn(&Path, &Path, u64, u64) -> Result<bool> + Send + Sync>;
+
+/// Answers a yes/no question, such as discarding a leftover partial file
+pub type ConfirmHandler = Arc<dyn Fn(&str) -> Result<bool> + Send + Sync>;
+
+/// Receives the running statistics of the file being copied after every chunk
+pub type ProgressHandler = Arc<dyn Fn(TransferStats) + Send + Sync>;
+
+/// The hooks a copy calls whenever it needs a decision or has progress to
+/// report, so it can run without touching stdin or stdout.
+///
+/// The CLI uses [`CopyOptions::interactive`]; library callers build their
+/// own with [`CopyOptions::new`] and pair it with `with_progress(false)`.
+#[derive(Clone)]
+pub struct CopyOptions {
+    pub on_conflict: ConflictHandler,
+    pub on_resume: ResumeHandler,
+    pub on_confirm: ConfirmHandler,
+    pub on_progress: Option<ProgressHandler>,
+}
+
+impl CopyOptions {
+    /// Resolve conflicts with `on_conflict`; partial copies start over and
+    /// other questions are answered "no"
+    pub fn new(
+        on_conflict: impl Fn(&Path, &Metadata, &Metadata) -> Result<OverwriteChoice> + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            on_conflict: Arc::new(on_conflict),
+            on_resume: Arc::new(|_, _, _, _| Ok(false)),
+            on_confirm: Arc::new(|_| Ok(false)),
+            on_progress: None,
+        }
+    }
+
+    /// Ask on the terminal, as the command-line tools do
+    pub fn interactive() -> Self {
+        Self {
+            on_conflict: Arc::new(prompt::prompt_overwrite),
+            on_resume: Arc::new(prompt::prompt_resume),
+            on_confirm: Arc::new(prompt::confirm_action),
+            on_progress: None,
+        }
+    }
+
+    /// Like [`CopyOptions::interactive`], for runs with no terminal to answer
+    /// on (stdin isn't one, or `--json`): a conflict that would prompt fails
+    /// with a usage error instead of waiting on stdin, partial copies
+    /// continue and other questions are answered "no"
+    pub fn non_interactive() -> Self {
+        Self::new(|_, _, _| {
+            Err(Error::Usage(
+                "prompt mode requires a terminal; use --overwrite=always/never".to_string(),
+            ))
+        })
+        .on_resume(|_, _, _, _| Ok(true))
+    }
+
+    /// Never ask, for runs nobody is watching (`--quiet`): conflicts are
+    /// skipped, partial copies continue and other questions are answered "no"
+    pub fn unattended() -> Self {
+        Self::new(|_, _, _| Ok(OverwriteChoice::Skip)).on_resume(|_, _, _, _| Ok(true))
+    }
+
+    /// Decide whether partial copies are continued
+    pub fn on_resume(
+        mut self,
+        on_resume: impl Fn(&Path, &Path, u64, u64) -> Result<bool> + Send + Sync + 'static,
+    ) -> Self {
+        self.on_resume = Arc::new(on_resume);
+        self
+    }
+
+    /// Answer yes/no questions
+    pub fn on_confirm(mut self, on_confirm: impl Fn(&str) -> Result<bool> + Send + Sync + 'static) -> Self {
+        self.on_confirm = Arc::new(on_confirm);
+        self
+    }
+
+    /// Report progress through `on_progress`
+    pub fn on_progress(mut self, on_progress: impl Fn(TransferStats) + Send + Sync + 'static) -> Self {
+        self.on_progress = Some(Arc::new(on_progress));
+        self
+    }
+
+    /// Pass the latest statistics to the progress hook, if any
+    pub fn report_progress(&self, stats: TransferStats) {
+        if let Some(on_progress) = &self.on_progress {
+            on_progress(stats);
+        }
+    }
+}
+
+impl std::fmt::Debug for CopyOptions {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CopyOptions")
+            .field("on_progress", &self.on_progress.is_some())
+            .finish_non_exhaustive()
+    }
+}
+
+impl Default for CopyOptions {
+    fn default() -> Self {
+        Self::interactive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_non_interactive_never_reads_stdin() {
+        let options = CopyOptions::non_interactive();
+        let here = Path::new(".");
+        let metadata = std::fs::metadata(here).unwrap();
+
+        assert!(matches!((options.on_conflict)(here, &metadata, &metadata), Err(Error::Usage(_))));
+        assert!((options.on_resume)(here, here, 10, 5).unwrap());
+        assert!(!(options.on_confirm)("Discard the partial file?").unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_unattended_conflicts_are_skips() {
+        use crate::cli::OverwriteMode;
+        use crate::copy::FileCopier;
+        use tempfile::TempDir;
+
+        let temp_dir = TempDir::new().unwrap();
+        let source = temp_dir.path().join("source.txt");
+        let target = temp_dir.path().join("target.txt");
+        std::fs::write(&source, b"new").unwrap();
+        std::fs::write(&target, b"old").unwrap();
+
+        let err = FileCopier::new(source, target.clone(), OverwriteMode::Prompt, false, false, false)
+            .with_progress(false)
+            .with_options(CopyOptions::unattended())
+            .copy()
+            .await
+            .unwrap_err();
+
+        assert!(err.is_skip(), "{:?}", err);
+        assert_eq!(err.exit_code(), 4);
+        assert_eq!(std::fs::read(&target).unwrap(), b"old");
+    }
+}
@@ -1,4 +1,37 @@
-use console::style;
+use crate::color::style;
+use serde::Serialize;
+use std::fs::{File, OpenOptions};
+use std::collections::BTreeMap;
+use std::ffi::OsString;
+use std::io::Write;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use crate::cli::LogFormat;
+use crate::error::{Error, Result};
+use crate::json_output::{FileResult, FileStatus};
+
+/// Set once `--json` claims the output: nothing meant for people is printed
+static HUMAN_OUTPUT_SUPPRESSED: AtomicBool = AtomicBool::new(false);
+
+/// Drop warnings and other messages for people for the rest of the run, so
+/// `--json` output is the only thing printed
+pub fn suppress_human_output() {
+    HUMAN_OUTPUT_SUPPRESSED.store(true, Ordering::Relaxed);
+}
+
+/// Whether messages for people are printed (not under `--json`)
+pub fn human_output() -> bool {
+    !HUMAN_OUTPUT_SUPPRESSED.load(Ordering::Relaxed)
+}
+
+/// Print a warning on stderr, unless `--json` silenced them
+pub fn warn(message: impl fmt::Display) {
+    if human_output() {
+        eprintln!("  {} {}", style("⚠️").yellow(), message);
+    }
+}
 
 /// Output level for logging operations
 #[derive(Debug, Clone, Copy)]
@@ -63,9 +96,9 @@ impl OutputManager {
         }
     }
 
-    /// Log a warning message (always shown)
+    /// Log a warning message (shown unless `--json`)
     pub fn warning(&self, msg: &str) {
-        eprintln!("  {} {}", style("⚠️").yellow(), msg);
+        warn(msg);
     }
 
     /// Log summary (shown unless quiet)
@@ -86,6 +119,155 @@ impl OutputManager {
     }
 }
 
+/// Appends one line per file operation to the `--log` file.
+///
+/// Each entry is flushed as soon as it is written, so the log survives a
+/// crash halfway through a run. Clones share the same file.
+#[derive(Debug, Clone)]
+pub struct Logger {
+    file: Arc<Mutex<File>>,
+    format: LogFormat,
+}
+
+/// One `--log-format jsonl` line
+#[derive(Serialize)]
+struct LogEntry<'a> {
+    timestamp: String,
+    operation: &'a str,
+    source: &'a Path,
+    destination: &'a Path,
+    bytes: u64,
+    result: &'a str,
+    error: Option<&'a str>,
+}
+
+impl Logger {
+    /// Open `path` for appending, creating it if needed
+    pub fn open(path: &Path, format: LogFormat) -> Result<Self> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(|e| Error::Custom(format!("Failed to open log file {}: {}", path.display(), e)))?;
+        Ok(Self {
+            file: Arc::new(Mutex::new(file)),
+            format,
+        })
+    }
+
+    /// Open the log named by `--log`, if any
+    pub fn from_args(path: Option<&PathBuf>, format: LogFormat) -> Result<Option<Self>> {
+        path.map(|path| Self::open(path, format)).transpose()
+    }
+
+    /// Append the outcome of one operation (`copy`, `move`, ...)
+    pub fn record(&self, operation: &str, entry: &FileResult) {
+        let result = match entry.status {
+            FileStatus::Copied => "ok",
+            FileStatus::Touched => "touched",
+            FileStatus::Skipped => "skipped",
+            FileStatus::Failed => "failed",
+        };
+        let line = match self.format {
+            LogFormat::Text => {
+                let outcome = match &entry.error {
+                    Some(error) => format!("{}: {}", result.to_uppercase(), error),
+                    None => result.to_uppercase(),
+                };
+                format!(
+                    "{} {} -> {} ({} bytes) {}",
+                    operation.to_uppercase(),
+                    entry.source.display(),
+                    entry.destination.display(),
+                    entry.bytes,
+                    outcome
+                )
+            }
+            LogFormat::Jsonl => serde_json::to_string(&LogEntry {
+                timestamp: chrono::Local::now().to_rfc3339(),
+                operation,
+                source: &entry.source,
+                destination: &entry.destination,
+                bytes: entry.bytes,
+                result,
+                error: entry.error.as_deref(),
+            })
+            .unwrap_or_default(),
+        };
+
+        // Logging is best effort; a full disk must not fail the copy itself
+        if let Ok(mut file) = self.file.lock() {
+            writeln!(file, "{}", line).ok();
+            file.flush().ok();
+        }
+    }
+}
+
+/// One directory level of a rendered result tree
+#[derive(Default)]
+struct TreeNode<'a> {
+    children: BTreeMap<OsString, TreeNode<'a>>,
+    result: Option<&'a FileResult>,
+}
+
+/// Render the outcome of a run as a tree of destinations, grouped by
+/// directory, each marked `+` created, `~` overwritten, `-` skipped or
+/// `!` failed. Printed by `--verbose --tree` after the summary.
+pub fn render_tree(files: &[FileResult]) -> String {
+    let Some(first) = files.first() else {
+        return String::new();
+    };
+
+    // Everything hangs off the deepest directory all destinations share
+    let mut root = first.destination.parent().unwrap_or(Path::new("")).to_path_buf();
+    for file in files {
+        while !file.destination.starts_with(&root) && root.pop() {}
+    }
+
+    let mut tree = TreeNode::default();
+    for file in files {
+        let relative = file.destination.strip_prefix(&root).unwrap_or(&file.destination);
+        let node = relative
+            .iter()
+            .fold(&mut tree, |node, part| node.children.entry(part.to_os_string()).or_default());
+        node.result = Some(file);
+    }
+
+    let mut out = if root.as_os_str().is_empty() {
+        ".\n".to_string()
+    } else {
+        format!("{}\n", root.display())
+    };
+    render_children(&tree, "", &mut out);
+    out
+}
+
+fn render_children(node: &TreeNode, prefix: &str, out: &mut String) {
+    let count = node.children.len();
+    for (i, (name, child)) in node.children.iter().enumerate() {
+        let last = i + 1 == count;
+        let branch = if last { "└─" } else { "├─" };
+        let name = name.to_string_lossy();
+        let line = match child.result {
+            Some(result) => format!("{} {}", tree_marker(result), name),
+            None => format!("{}/", name),
+        };
+        out.push_str(&format!("{}{} {}\n", prefix, branch, line));
+        let nested = format!("{}{}", prefix, if last { "   " } else { "│  " });
+        render_children(child, &nested, out);
+    }
+}
+
+fn tree_marker(result: &FileResult) -> char {
+    match result.status {
+        FileStatus::Copied if result.overwritten => '~',
+        FileStatus::Copied => '+',
+        FileStatus::Touched => '=',
+        FileStatus::Skipped => '-',
+        FileStatus::Failed => '!',
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -109,4 +291,56 @@ mod tests {
         let both = OutputManager::new(true, true);
         assert!(both.is_quiet());
     }
+
+    #[test]
+    fn test_logger_formats() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let text_log = temp_dir.path().join("copy.log");
+        let jsonl_log = temp_dir.path().join("copy.jsonl");
+        let entry = FileResult::copied(PathBuf::from("a.txt"), PathBuf::from("b.txt"), 42);
+
+        Logger::open(&text_log, LogFormat::Text).unwrap().record("copy", &entry);
+        assert_eq!(
+            std::fs::read_to_string(&text_log).unwrap(),
+            "COPY a.txt -> b.txt (42 bytes) OK\n"
+        );
+
+        let logger = Logger::open(&jsonl_log, LogFormat::Jsonl).unwrap();
+        logger.record("copy", &entry);
+        logger.record("copy", &FileResult::failed(PathBuf::from("c"), PathBuf::from("d"), "boom".to_string()));
+        let content = std::fs::read_to_string(&jsonl_log).unwrap();
+        let lines: Vec<serde_json::Value> =
+            content.lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["result"], "ok");
+        assert_eq!(lines[0]["bytes"], 42);
+        assert!(lines[0]["timestamp"].is_string());
+        assert_eq!(lines[1]["result"], "failed");
+        assert_eq!(lines[1]["error"], "boom");
+    }
+
+    #[test]
+    fn test_render_tree_nests_by_directory() {
+        let dest = PathBuf::from("/backup");
+        let files = vec![
+            FileResult::copied(PathBuf::from("a.txt"), dest.join("a.txt"), 1),
+            FileResult::copied(PathBuf::from("b.txt"), dest.join("docs/b.txt"), 2).overwriting(true),
+            FileResult::skipped(PathBuf::from("c.txt"), dest.join("docs/c.txt"), "exists".to_string()),
+            FileResult::failed(PathBuf::from("d.txt"), dest.join("docs/deep/d.txt"), "boom".to_string()),
+            FileResult::copied(PathBuf::from("z"), dest.join("z"), 3).overwriting(false),
+        ];
+
+        assert_eq!(
+            render_tree(&files),
+            "/backup\n\
+             ├─ + a.txt\n\
+             ├─ docs/\n\
+             │  ├─ ~ b.txt\n\
+             │  ├─ - c.txt\n\
+             │  └─ deep/\n\
+             │     └─ ! d.txt\n\
+             └─ + z\n"
+        );
+        assert_eq!(render_tree(&[]), "");
+    }
 }
@@ -1,19 +1,115 @@
+use std::collections::VecDeque;
 use std::fs::{self, File};
 use std::io::{Read, Write, Seek, SeekFrom};
 use std::path::{Path, PathBuf};
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 use crate::error::{Error, Result};
-use crate::progress::ProgressTracker;
-use crate::verify::compute_checksum;
+use crate::progress::{ProgressMode, ProgressTracker, TransferStats};
+use crate::verify::{compute_checksum, verify_tree};
+use crate::filter::WalkFilter;
+use crate::plan::{plan_tree, PlanKind};
+use crate::cli::{CaseCollisionMode, OverwriteMode};
+use crate::copy::{copy_symlink, create_parent_dirs, handle_case_collisions, warn_special_skipped, warn_symlink_cycle};
+use crate::preserve::{apply_metadata, PreserveSet};
+use crate::watchdog::read_within;
+use crate::report::RunStats;
+use crate::sink::preallocate;
+use crate::interrupt::{is_interrupted, InterruptFlag};
+use crate::json_output::{FileResult, FileResults};
+use crate::output::Logger;
 
 const CHUNK_SIZE: usize = 64 * 1024 * 1024; // 64 MB chunks
 
+/// Most threads `--parallel 0` picks; past this the disks, not the cores,
+/// are the bottleneck
+const MAX_AUTO_THREADS: usize = 8;
+
+/// Smallest file `--parallel 0` splits across threads
+const AUTO_MIN_FILE_SIZE: u64 = 2 * CHUNK_SIZE as u64;
+
+/// Turn `--parallel N` into a thread count for copying `source`.
+///
+/// An explicit N is used as is. For 0 (auto), directories and files of at
+/// least two chunks get one thread per core, up to `MAX_AUTO_THREADS`;
+/// anything smaller stays sequential (1).
+pub fn resolve_parallelism(requested: usize, source: &Path) -> usize {
+    if requested > 0 {
+        return requested;
+    }
+    let worth_splitting = fs::metadata(source)
+        .map(|metadata| metadata.is_dir() || metadata.len() >= AUTO_MIN_FILE_SIZE)
+        .unwrap_or(false);
+    if worth_splitting {
+        auto_threads()
+    } else {
+        1
+    }
+}
+
+/// How a copy is spread out: files in flight, and threads per file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Parallelism {
+    /// Files of a directory copied at the same time
+    pub tree: usize,
+    /// Threads sharing the chunks of one file
+    pub file: usize,
+}
+
+impl Parallelism {
+    /// Resolve `--tree-parallel` and `--file-parallel` for `source`, with
+    /// `--parallel` standing in for whichever one left at 0 fits the source:
+    /// files in flight for a directory, chunks for a single file. Files in a
+    /// directory are only split when `--file-parallel` asks for it.
+    pub fn resolve(parallel: usize, tree: usize, file: usize, source: &Path) -> Self {
+        let is_dir = source.is_dir();
+        Self {
+            tree: match tree {
+                _ if !is_dir => 1,
+                0 => resolve_parallelism(parallel, source),
+                n => n,
+            },
+            file: match file {
+                0 if is_dir => 1,
+                0 => resolve_parallelism(parallel, source),
+                n => n,
+            },
+        }
+    }
+
+    /// Whether more than one thread would be put to work
+    pub fn is_parallel(&self) -> bool {
+        self.tree > 1 || self.file > 1
+    }
+}
+
+/// One thread per available core, capped
+fn auto_threads() -> usize {
+    std::thread::available_parallelism()
+        .map_or(1, |cores| cores.get())
+        .min(MAX_AUTO_THREADS)
+}
+
 /// Parallel file copier - splits large files across multiple threads
 pub struct ParallelFileCopier {
     source: PathBuf,
     target: PathBuf,
     parallel_threads: usize,
     verify: bool,
+    progress: ProgressMode,
+    preserve: PreserveSet,
+    chunk_size: usize,
+    timeout: Option<Duration>,
+    run_stats: Option<RunStats>,
+    interrupt: InterruptFlag,
+}
+
+/// How a file is cut into chunks and shared out between threads
+#[derive(Clone, Copy)]
+struct ChunkLayout {
+    total_size: u64,
+    chunk_size: usize,
+    num_threads: usize,
 }
 
 impl ParallelFileCopier {
@@ -28,11 +124,52 @@ impl ParallelFileCopier {
             target,
             parallel_threads,
             verify,
+            progress: ProgressMode::Bar,
+            preserve: PreserveSet::default(),
+            chunk_size: CHUNK_SIZE,
+            timeout: None,
+            run_stats: None,
+            interrupt: InterruptFlag::default(),
         }
     }
 
-    /// Execute parallel copy
-    pub async fn copy(&self) -> Result<()> {
+    /// Choose how progress is reported (a bar by default); accepts a bool
+    pub fn with_progress(mut self, progress: impl Into<ProgressMode>) -> Self {
+        self.progress = progress.into();
+        self
+    }
+
+    /// Attributes to carry over once the content is written (none by default)
+    pub fn with_preserve(mut self, preserve: PreserveSet) -> Self {
+        self.preserve = preserve;
+        self
+    }
+
+    /// Fail with `Error::Timeout` when reading one chunk takes longer than this
+    pub fn with_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Count the copied file towards the `--stats` report
+    pub fn with_run_stats(mut self, run_stats: Option<RunStats>) -> Self {
+        self.run_stats = run_stats;
+        self
+    }
+
+    /// Stop between chunks once this flag is raised
+    pub fn with_interrupt(mut self, interrupt: InterruptFlag) -> Self {
+        self.interrupt = interrupt;
+        self
+    }
+
+    fn with_chunk_size(mut self, chunk_size: usize) -> Self {
+        self.chunk_size = chunk_size;
+        self
+    }
+
+    /// Execute parallel copy, returning the transfer statistics
+    pub async fn copy(&self) -> Result<TransferStats> {
         let src_metadata = fs::metadata(&self.source)
             .map_err(|_| Error::SourceNotFound(self.source.to_string_lossy().to_string()))?;
 
@@ -41,33 +178,54 @@ impl ParallelFileCopier {
         }
 
         let total_size = src_metadata.len();
+        create_parent_dirs(&self.target)?;
 
-        // For small files, fall back to single-threaded copy
-        if total_size < CHUNK_SIZE as u64 {
-            return self.sequential_copy(total_size).await;
-        }
+        let tracker = Arc::new(ProgressTracker::with_mode(total_size, self.progress));
+        self.transfer(total_size, &tracker).await?;
+        tracker.finish();
+        let stats = tracker.get_stats();
 
-        // Use parallel copy for large files
-        self.parallel_copy(total_size).await?;
+        apply_metadata(&self.source, &src_metadata, &self.target, &self.preserve)?;
 
         // Verify if requested
         if self.verify {
             self.verify_copy()?;
         }
+        if let Some(run_stats) = &self.run_stats {
+            run_stats.record(&self.source, total_size);
+        }
 
-        Ok(())
+        Ok(stats)
     }
 
-    /// Sequential copy for small files
-    async fn sequential_copy(&self, total_size: u64) -> Result<()> {
-        let tracker = ProgressTracker::new(total_size, true);
+    /// Write the contents to the target, counting bytes into `tracker`
+    async fn transfer(&self, total_size: u64, tracker: &Arc<ProgressTracker>) -> Result<()> {
+        // For small files, fall back to single-threaded copy
+        if total_size < self.chunk_size as u64 {
+            self.sequential_copy(tracker).await
+        } else {
+            self.parallel_copy(total_size, tracker).await
+        }
+    }
 
+    /// Sequential copy for small files
+    async fn sequential_copy(&self, tracker: &ProgressTracker) -> Result<()> {
         let mut src_file = File::open(&self.source).map_err(Error::Io)?;
         let mut dst_file = File::create(&self.target).map_err(Error::Io)?;
 
-        let mut buffer = vec![0; CHUNK_SIZE];
+        let mut buffer = vec![0; self.chunk_size];
         loop {
-            let bytes_read = src_file.read(&mut buffer).map_err(Error::Io)?;
+            if is_interrupted(&self.interrupt) {
+                return Err(Error::Interrupted(self.target.to_string_lossy().to_string()));
+            }
+            let (file, chunk, bytes_read) = read_within(self.timeout, &self.source, move || {
+                let bytes_read = src_file.read(&mut buffer)?;
+                Ok((src_file, buffer, bytes_read))
+            })
+            .await?;
+            src_file = file;
+            buffer = chunk;
+
             if bytes_read == 0 {
                 break;
             }
@@ -76,44 +234,38 @@ impl ParallelFileCopier {
             tracker.add_bytes(bytes_read as u64);
         }
 
-        tracker.finish();
         Ok(())
     }
 
     /// Parallel copy for large files
-    async fn parallel_copy(&self, total_size: u64) -> Result<()> {
-        let tracker = Arc::new(ProgressTracker::new(total_size, true));
-
+    async fn parallel_copy(&self, total_size: u64, tracker: &Arc<ProgressTracker>) -> Result<()> {
         // Pre-allocate destination file
         let dst_file = File::create(&self.target).map_err(Error::Io)?;
-        dst_file
-            .set_len(total_size)
-            .map_err(Error::Io)?;
+        preallocate(&dst_file, total_size)?;
 
         // Calculate chunk boundaries
-        let num_chunks = (total_size as usize + CHUNK_SIZE - 1) / CHUNK_SIZE;
-        let actual_threads = std::cmp::min(self.parallel_threads, num_chunks);
+        let num_chunks = total_size.div_ceil(self.chunk_size as u64) as usize;
+        let layout = ChunkLayout {
+            total_size,
+            chunk_size: self.chunk_size,
+            num_threads: std::cmp::min(self.parallel_threads, num_chunks),
+        };
+        let timeout = self.timeout;
+        let interrupt = &self.interrupt;
 
         let src_path = Arc::new(self.source.clone());
         let dst_path = Arc::new(self.target.clone());
 
         let mut handles = Vec::new();
 
-        for thread_id in 0..actual_threads {
+        for thread_id in 0..layout.num_threads {
             let src = Arc::clone(&src_path);
             let dst = Arc::clone(&dst_path);
-            let tracker = Arc::clone(&tracker);
+            let tracker = Arc::clone(tracker);
+            let interrupt = interrupt.clone();
 
             let handle = tokio::spawn(async move {
-                Self::copy_chunk(
-                    &src,
-                    &dst,
-                    thread_id,
-                    num_chunks,
-                    actual_threads,
-                    tracker,
-                )
-                .await
+                Self::copy_chunks(&src, &dst, thread_id, layout, timeout, tracker, &interrupt).await
             });
 
             handles.push(handle);
@@ -122,55 +274,63 @@ impl ParallelFileCopier {
         // Wait for all threads to complete
         for handle in handles {
             handle.await.map_err(|e| Error::Custom(e.to_string()))?
-                .map_err(|e| Error::Custom(format!("Thread error: {}", e)))?;
+                .map_err(thread_error)?;
         }
 
-        tracker.finish();
         Ok(())
     }
 
-    /// Copy a chunk of file (used by parallel threads)
-    async fn copy_chunk(
+    /// Copy every `num_threads`-th chunk of the file, starting at `thread_id`
+    /// (used by parallel threads). Each chunk is read in full before it is
+    /// written; the last one is usually shorter than `chunk_size`.
+    async fn copy_chunks(
         src_path: &Path,
         dst_path: &Path,
         thread_id: usize,
-        total_chunks: usize,
-        num_threads: usize,
+        layout: ChunkLayout,
+        timeout: Option<Duration>,
         tracker: Arc<ProgressTracker>,
+        interrupt: &InterruptFlag,
     ) -> Result<()> {
-        let chunk_size = CHUNK_SIZE;
+        let ChunkLayout { total_size, chunk_size, num_threads } = layout;
+        let total_chunks = total_size.div_ceil(chunk_size as u64) as usize;
+
+        let mut src = File::open(src_path).map_err(Error::Io)?;
+        let mut dst = File::options().write(true).open(dst_path).map_err(Error::Io)?;
+        let mut buffer = Vec::new();
 
-        // Calculate which chunks this thread should handle
         for chunk_idx in (thread_id..total_chunks).step_by(num_threads) {
-            let offset = (chunk_idx * chunk_size) as u64;
+            if is_interrupted(interrupt) {
+                return Err(Error::Interrupted(dst_path.to_string_lossy().to_string()));
+            }
+            let offset = chunk_idx as u64 * chunk_size as u64;
+            let chunk_len = (total_size - offset).min(chunk_size as u64) as usize;
+
+            // read_exact keeps reading past short reads; a source that
+            // shrank mid-copy fails instead of leaving a hole
+            buffer.resize(chunk_len, 0);
+            (src, buffer) = read_within(timeout, src_path, move || {
+                src.seek(SeekFrom::Start(offset))?;
+                src.read_exact(&mut buffer)?;
+                Ok((src, buffer))
+            })
+            .await?;
 
             tokio::task::block_in_place(|| {
-                let mut src = File::open(src_path).map_err(Error::Io)?;
-                src.seek(SeekFrom::Start(offset)).map_err(Error::Io)?;
-
-                let mut dst = File::open(dst_path).map_err(Error::Io)?;
-                dst.seek(SeekFrom::Start(offset)).map_err(Error::Io)?;
-
-                let mut buffer = vec![0; chunk_size];
-                let bytes_read = src.read(&mut buffer).map_err(Error::Io)?;
-
-                if bytes_read > 0 {
-                    dst.write_all(&buffer[..bytes_read])
-                        .map_err(Error::Io)?;
-                    tracker.add_bytes(bytes_read as u64);
-                }
-
-                Ok::<(), Error>(())
-            })?;
+                dst.seek(SeekFrom::Start(offset))?;
+                dst.write_all(&buffer)
+            })
+            .map_err(Error::Io)?;
+            tracker.add_bytes(chunk_len as u64);
         }
 
         Ok(())
     }
 
     fn verify_copy(&self) -> Result<()> {
-        let src_checksum = compute_checksum(&self.source)
+        let src_checksum = compute_checksum(&self.source, None)
             .map_err(Error::Io)?;
-        let tgt_checksum = compute_checksum(&self.target)
+        let tgt_checksum = compute_checksum(&self.target, None)
             .map_err(Error::Io)?;
 
         if src_checksum == tgt_checksum {
@@ -184,107 +344,213 @@ impl ParallelFileCopier {
     }
 }
 
-/// Parallel directory copy - copies multiple files in parallel
+/// What every worker of a parallel directory copy does with its files
+#[derive(Debug, Clone)]
+pub struct TreeCopy {
+    pub parallelism: Parallelism,
+    pub verify: bool,
+    pub preserve: PreserveSet,
+    /// What to do with links already at the target (files are replaced)
+    pub overwrite_mode: OverwriteMode,
+    /// Per-file totals for the `--stats` report
+    pub run_stats: Option<RunStats>,
+    /// How each file fared, for the `--json` file list
+    pub file_results: Option<FileResults>,
+    /// Where each copied file is logged (`--log`)
+    pub logger: Option<Logger>,
+    /// What to do with names that differ only in case on a target that ignores case
+    pub case_collision: CaseCollisionMode,
+    /// Raised to stop the copy between files and chunks
+    pub interrupt: InterruptFlag,
+    chunk_size: usize,
+}
+
+impl TreeCopy {
+    /// Verify every file and keep no attributes, until told otherwise
+    pub fn new(parallelism: Parallelism) -> Self {
+        Self {
+            parallelism,
+            verify: true,
+            preserve: PreserveSet::default(),
+            overwrite_mode: OverwriteMode::Always,
+            run_stats: None,
+            file_results: None,
+            logger: None,
+            case_collision: CaseCollisionMode::Warn,
+            interrupt: InterruptFlag::default(),
+            chunk_size: CHUNK_SIZE,
+        }
+    }
+}
+
+/// Parallel directory copy - `parallelism.tree` files in flight at once,
+/// each split across `parallelism.file` threads when it is large enough
 pub async fn parallel_copy_directory(
     source: &Path,
     target: &Path,
-    parallel_threads: usize,
-) -> Result<()> {
-    if !source.is_dir() {
-        return Err(Error::Custom("Source is not a directory".to_string()));
-    }
-
-    // Create target directory
-    fs::create_dir_all(target).map_err(Error::Io)?;
-
-    // Collect all files to copy
+    progress: impl Into<ProgressMode>,
+    filter: &WalkFilter,
+    settings: &TreeCopy,
+) -> Result<TransferStats> {
+    let preserve = settings.preserve;
+
+    // Create the directories and links up front, and collect the files to copy
+    let filter = filter.clone().rooted_at(source)?;
     let mut files_to_copy = Vec::new();
-    collect_files_recursive(source, target, &mut files_to_copy)?;
+    let mut dirs = Vec::new();
+    let mut skipped = 0;
+    let plan = handle_case_collisions(plan_tree(source, target, &filter)?, target, settings.case_collision)?;
+    for entry in plan {
+        match entry.kind {
+            PlanKind::Dir => {
+                fs::create_dir_all(&entry.target).map_err(Error::Io)?;
+                dirs.push((entry.source, entry.target));
+            }
+            PlanKind::Symlink(link_target) => {
+                match copy_symlink(&link_target, &entry.target, &settings.overwrite_mode) {
+                    Ok(()) => {}
+                    Err(e) if e.is_skip() => skipped += 1,
+                    Err(e) => return Err(e),
+                }
+            }
+            PlanKind::File(metadata) => files_to_copy.push((entry.source, entry.target, metadata.len())),
+            // --specials copies go through DirectoryCopier
+            PlanKind::Special(metadata) => {
+                warn_special_skipped(&entry.source, &metadata);
+                skipped += 1;
+            }
+            PlanKind::Cycle => {
+                warn_symlink_cycle(&entry.source);
+                skipped += 1;
+            }
+            PlanKind::Filtered => skipped += 1,
+            PlanKind::Excluded => {}
+        }
+    }
 
     if files_to_copy.is_empty() {
-        return Ok(());
+        preserve_dir_metadata(&dirs, &preserve)?;
+        let mut stats = ProgressTracker::new(0, false).get_stats();
+        stats.files_skipped = skipped;
+        return Ok(stats);
     }
 
-    let tracker = Arc::new(ProgressTracker::new(
+    let tracker = Arc::new(ProgressTracker::with_mode(
         files_to_copy.iter().map(|(_, _, size)| size).sum(),
-        true,
+        progress.into(),
     ));
+    copy_queued_files(files_to_copy, &tracker, settings).await?;
 
-    // Split work among threads
-    let chunk_size = (files_to_copy.len() + parallel_threads - 1) / parallel_threads;
-    let mut handles = Vec::new();
+    preserve_dir_metadata(&dirs, &preserve)?;
+    if settings.verify {
+        verify_tree(source, target, &filter, false)?;
+    }
 
-    for thread_idx in 0..parallel_threads {
-        let start = thread_idx * chunk_size;
-        let end = std::cmp::min(start + chunk_size, files_to_copy.len());
+    tracker.finish();
+    let mut stats = tracker.get_stats();
+    stats.files_skipped = skipped;
+    Ok(stats)
+}
 
-        if start >= files_to_copy.len() {
-            break;
-        }
+/// Copy `files` (source, target, size) with `parallelism.tree` workers
+/// pulling from one shared queue, so a worker done with small files moves on
+/// to whatever is left instead of idling while another works through a
+/// slice of big ones. Returns how many files each worker copied.
+async fn copy_queued_files(
+    mut files: Vec<(PathBuf, PathBuf, u64)>,
+    tracker: &Arc<ProgressTracker>,
+    settings: &TreeCopy,
+) -> Result<Vec<usize>> {
+    let TreeCopy { parallelism, preserve, .. } = *settings;
+
+    // Workers take the largest remaining file next, so one big file
+    // doesn't end up last with every other worker idle
+    files.sort_by_key(|(_, _, size)| std::cmp::Reverse(*size));
+    let queue = Arc::new(Mutex::new(VecDeque::from(files)));
+    let mut handles = Vec::new();
 
-        let files = files_to_copy[start..end].to_vec();
-        let tracker = Arc::clone(&tracker);
+    for _ in 0..parallelism.tree {
+        let queue = Arc::clone(&queue);
+        let tracker = Arc::clone(tracker);
+        let settings = settings.clone();
 
         let handle = tokio::spawn(async move {
-            for (src, dst, _size) in files {
-                let mut src_file = tokio::task::block_in_place(|| File::open(&src))
-                    .map_err(Error::Io)?;
-                let mut dst_file =
-                    tokio::task::block_in_place(|| File::create(&dst)).map_err(Error::Io)?;
-
-                let mut buffer = vec![0; CHUNK_SIZE];
-                loop {
-                    let bytes_read =
-                        tokio::task::block_in_place(|| src_file.read(&mut buffer))
-                            .map_err(Error::Io)?;
-
-                    if bytes_read == 0 {
-                        break;
-                    }
-
-                    tokio::task::block_in_place(|| dst_file.write_all(&buffer[..bytes_read]))
-                        .map_err(Error::Io)?;
-                    tracker.add_bytes(bytes_read as u64);
+            let mut copied = 0;
+            loop {
+                let next = queue.lock().map_err(|e| Error::Custom(e.to_string()))?.pop_front();
+                let Some((src, dst, size)) = next else {
+                    break;
+                };
+                if is_interrupted(&settings.interrupt) {
+                    return Err(Error::Interrupted(dst.to_string_lossy().to_string()));
+                }
+                let existed = fs::symlink_metadata(&dst).is_ok();
+                ParallelFileCopier::new(src.clone(), dst.clone(), parallelism.file, settings.verify)
+                    .with_chunk_size(settings.chunk_size)
+                    .with_interrupt(settings.interrupt.clone())
+                    .transfer(size, &tracker)
+                    .await?;
+                tokio::task::block_in_place(|| finish_file(&src, &dst, settings.verify, &preserve))?;
+                if let Some(run_stats) = &settings.run_stats {
+                    run_stats.record(&src, size);
+                }
+                let entry = FileResult::copied(src, dst, size).overwriting(existed);
+                if let Some(logger) = &settings.logger {
+                    logger.record("copy", &entry);
+                }
+                if let Some(file_results) = &settings.file_results {
+                    file_results.record(entry);
                 }
+                copied += 1;
             }
 
-            Ok::<(), Error>(())
+            Ok::<usize, Error>(copied)
         });
 
         handles.push(handle);
     }
 
-    // Wait for all threads
+    // Wait for all workers
+    let mut copied = Vec::with_capacity(handles.len());
     for handle in handles {
-        handle.await.map_err(|e| Error::Custom(e.to_string()))?
-            .map_err(|e| Error::Custom(format!("Thread error: {}", e)))?;
+        copied.push(
+            handle.await.map_err(|e| Error::Custom(e.to_string()))?
+                .map_err(thread_error)?,
+        );
     }
+    Ok(copied)
+}
 
-    tracker.finish();
-    Ok(())
+/// Report a worker's failure, keeping an interrupt an interrupt so the
+/// run still exits with its own code
+fn thread_error(e: Error) -> Error {
+    if e.is_interrupt() {
+        e
+    } else {
+        Error::Custom(format!("Thread error: {}", e))
+    }
 }
 
-/// Collect all files to copy (recursive)
-fn collect_files_recursive(
-    source: &Path,
-    target: &Path,
-    files: &mut Vec<(PathBuf, PathBuf, u64)>,
-) -> Result<()> {
-    for entry in fs::read_dir(source).map_err(Error::Io)? {
-        let entry = entry.map_err(Error::Io)?;
-        let path = entry.path();
-        let file_name = entry.file_name();
-        let target_path = target.join(&file_name);
-
-        if path.is_dir() {
-            fs::create_dir_all(&target_path).map_err(Error::Io)?;
-            collect_files_recursive(&path, &target_path, files)?;
-        } else {
-            let metadata = entry.metadata().map_err(Error::Io)?;
-            files.push((path, target_path, metadata.len()));
+/// Verify a copied file if asked, then give it the source's attributes
+fn finish_file(source: &Path, target: &Path, verify: bool, preserve: &PreserveSet) -> Result<()> {
+    if verify {
+        let expected = compute_checksum(source, None).map_err(Error::Io)?;
+        let actual = compute_checksum(target, None).map_err(Error::Io)?;
+        if expected != actual {
+            return Err(Error::ChecksumMismatch { expected, actual });
         }
     }
+    let src_metadata = fs::metadata(source).map_err(Error::Io)?;
+    apply_metadata(source, &src_metadata, target, preserve)
+}
 
+/// Apply directory attributes once their contents are written, deepest
+/// first so setting a child's mtime doesn't bump its parent's again
+fn preserve_dir_metadata(dirs: &[(PathBuf, PathBuf)], preserve: &PreserveSet) -> Result<()> {
+    for (source, target) in dirs.iter().rev() {
+        let src_metadata = fs::metadata(source).map_err(Error::Io)?;
+        apply_metadata(source, &src_metadata, target, preserve)?;
+    }
     Ok(())
 }
 
@@ -295,6 +561,164 @@ mod tests {
     use std::io::Write;
     use tempfile::TempDir;
 
+    #[test]
+    fn test_auto_parallelism() {
+        let temp_dir = TempDir::new().unwrap();
+        let small = temp_dir.path().join("small.bin");
+        let large = temp_dir.path().join("large.bin");
+        fs::write(&small, b"tiny").unwrap();
+        // Sparse, so the test doesn't write 128 MB
+        File::create(&large).unwrap().set_len(AUTO_MIN_FILE_SIZE).unwrap();
+
+        let cores = std::thread::available_parallelism().map_or(1, |n| n.get());
+        assert_eq!(resolve_parallelism(0, &large), cores.min(MAX_AUTO_THREADS));
+        assert_eq!(resolve_parallelism(0, temp_dir.path()), cores.min(MAX_AUTO_THREADS));
+        if cores > 1 {
+            assert!(resolve_parallelism(0, &large) > 1);
+        }
+        assert_eq!(resolve_parallelism(0, &small), 1);
+        assert_eq!(resolve_parallelism(3, &small), 3);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_queue_shares_skewed_files_between_workers() {
+        let temp_dir = TempDir::new().unwrap();
+        let src_dir = temp_dir.path().join("src");
+        let dst_dir = temp_dir.path().join("dst");
+        fs::create_dir_all(&src_dir).unwrap();
+        fs::create_dir_all(&dst_dir).unwrap();
+
+        // One file as big as all the others together many times over
+        let mut files = vec![("big.bin".to_string(), vec![7u8; 8 << 20])];
+        files.extend((0..20).map(|i| (format!("small{}.bin", i), vec![i as u8; 1024])));
+        let mut queued = Vec::new();
+        for (name, data) in &files {
+            fs::write(src_dir.join(name), data).unwrap();
+            queued.push((src_dir.join(name), dst_dir.join(name), data.len() as u64));
+        }
+
+        let mut settings = TreeCopy::new(Parallelism { tree: 2, file: 1 });
+        settings.chunk_size = 64 << 10;
+        let total = queued.iter().map(|(_, _, size)| size).sum();
+        let tracker = Arc::new(ProgressTracker::new(total, false));
+        let copied = copy_queued_files(queued, &tracker, &settings).await.unwrap();
+
+        for (name, data) in &files {
+            assert_eq!(&fs::read(dst_dir.join(name)).unwrap(), data);
+        }
+        assert_eq!(copied.iter().sum::<usize>(), files.len());
+        // Whoever took the big file, the other worker got through the small ones
+        assert_eq!(copied.len(), 2);
+        assert!(copied.iter().all(|&count| count > 0), "{:?}", copied);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_raised_interrupt_stops_parallel_copies() {
+        let temp_dir = TempDir::new().unwrap();
+        let src_path = temp_dir.path().join("source.bin");
+        fs::write(&src_path, vec![3u8; 4096]).unwrap();
+        let interrupt = InterruptFlag::default();
+        interrupt.store(true, std::sync::atomic::Ordering::SeqCst);
+
+        let result = ParallelFileCopier::new(src_path.clone(), temp_dir.path().join("dest.bin"), 2, false)
+            .with_progress(false)
+            .with_chunk_size(1024)
+            .with_interrupt(interrupt.clone())
+            .copy()
+            .await;
+        assert!(result.unwrap_err().is_interrupt());
+
+        let mut settings = TreeCopy::new(Parallelism { tree: 2, file: 1 });
+        settings.interrupt = interrupt;
+        let queued = vec![(src_path, temp_dir.path().join("queued.bin"), 4096)];
+        let tracker = Arc::new(ProgressTracker::new(4096, false));
+        let result = copy_queued_files(queued, &tracker, &settings).await;
+        assert!(result.unwrap_err().is_interrupt());
+        assert!(!temp_dir.path().join("queued.bin").exists());
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_parallel_copy_uneven_last_chunk() {
+        let temp_dir = TempDir::new().unwrap();
+        let src_path = temp_dir.path().join("source.bin");
+        let dst_path = temp_dir.path().join("dest.bin");
+
+        // Three full 1000-byte chunks and a 517-byte tail
+        let data: Vec<u8> = (0..3517u32).map(|i| (i % 251) as u8).collect();
+        fs::write(&src_path, &data).unwrap();
+
+        let copier = ParallelFileCopier::new(src_path, dst_path.clone(), 3, true)
+            .with_progress(false)
+            .with_chunk_size(1000);
+        let stats = copier.copy().await.unwrap();
+
+        assert_eq!(stats.transferred_bytes, 3517);
+        assert_eq!(fs::read(&dst_path).unwrap(), data);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_parallel_directory_copy_keeps_links_under_no_clobber() {
+        let temp_dir = TempDir::new().unwrap();
+        let source = temp_dir.path().join("source");
+        let target = temp_dir.path().join("target");
+        fs::create_dir_all(&source).unwrap();
+        fs::create_dir_all(&target).unwrap();
+        fs::write(source.join("data.txt"), b"data").unwrap();
+        std::os::unix::fs::symlink("data.txt", source.join("current")).unwrap();
+        std::os::unix::fs::symlink("elsewhere", target.join("current")).unwrap();
+
+        let mut settings = TreeCopy::new(Parallelism { tree: 2, file: 1 });
+        settings.overwrite_mode = OverwriteMode::NoClobber;
+        let stats = parallel_copy_directory(&source, &target, false, &WalkFilter::new(), &settings)
+            .await
+            .unwrap();
+
+        assert_eq!(fs::read_link(target.join("current")).unwrap(), Path::new("elsewhere"));
+        assert_eq!(stats.files_skipped, 1);
+        assert_eq!(fs::read(target.join("data.txt")).unwrap(), b"data");
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_tree_and_file_parallel_directory_copy() {
+        let temp_dir = TempDir::new().unwrap();
+        let source = temp_dir.path().join("source");
+        let target = temp_dir.path().join("target");
+        fs::create_dir_all(source.join("sub")).unwrap();
+
+        // Files spanning several 1000-byte chunks, plus one smaller than a chunk
+        let files = [("a.bin", 4_500), ("b.bin", 3_001), ("sub/c.bin", 2_000), ("small.txt", 10)];
+        for (index, (name, len)) in files.iter().enumerate() {
+            let data: Vec<u8> = (0..*len).map(|i| ((i + index) % 251) as u8).collect();
+            fs::write(source.join(name), data).unwrap();
+        }
+
+        let settings = TreeCopy {
+            chunk_size: 1000,
+            ..TreeCopy::new(Parallelism { tree: 2, file: 3 })
+        };
+        let stats = parallel_copy_directory(&source, &target, false, &WalkFilter::new(), &settings)
+            .await
+            .unwrap();
+
+        assert_eq!(stats.transferred_bytes, 4_500 + 3_001 + 2_000 + 10);
+        for (name, _) in files {
+            assert_eq!(fs::read(target.join(name)).unwrap(), fs::read(source.join(name)).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_parallelism_follows_source_kind() {
+        let temp_dir = TempDir::new().unwrap();
+        let file = temp_dir.path().join("file.bin");
+        fs::write(&file, b"data").unwrap();
+
+        // --parallel means files in flight for a directory, chunks for a file
+        assert_eq!(Parallelism::resolve(4, 0, 0, temp_dir.path()), Parallelism { tree: 4, file: 1 });
+        assert_eq!(Parallelism::resolve(4, 0, 0, &file), Parallelism { tree: 1, file: 4 });
+        assert_eq!(Parallelism::resolve(4, 2, 3, temp_dir.path()), Parallelism { tree: 2, file: 3 });
+    }
+
     #[tokio::test]
     async fn test_parallel_copy_small_file() {
         let temp_dir = TempDir::new().unwrap();
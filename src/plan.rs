@@ -0,0 +1,496 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::fs::{self, Metadata};
+use std::path::{Path, PathBuf};
+use crate::cli::{CaseCollisionMode, OverwriteMode};
+use crate::copy::{policy_allows_overwrite, smart_should_overwrite};
+use crate::error::{Error, Result};
+use crate::filter::{file_id, WalkEntry, WalkFilter};
+
+/// One entry a directory copy visits, with where it goes
+#[derive(Debug)]
+pub struct PlanEntry {
+    pub source: PathBuf,
+    pub target: PathBuf,
+    pub kind: PlanKind,
+}
+
+#[derive(Debug)]
+pub enum PlanKind {
+    /// A directory to create; its attributes are applied after its contents
+    Dir,
+    File(Metadata),
+    /// A symbolic link to recreate, holding the path it points to
+    Symlink(PathBuf),
+    /// A FIFO, socket or device node, recreated only with `--specials`
+    Special(Metadata),
+    /// A file dropped by the size or age filters (counted as skipped)
+    Filtered,
+    /// An entry dropped by an exclude pattern (not counted)
+    Excluded,
+    /// A directory the walk is already inside, reached again through a
+    /// followed symlink; not descended into (counted as skipped)
+    Cycle,
+}
+
+/// What copying one file would do to its target
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PlannedAction {
+    Create,
+    Overwrite,
+    /// The user is asked when the copy gets there
+    Prompt,
+    Skip,
+    Excluded,
+}
+
+impl fmt::Display for PlannedAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            PlannedAction::Create => "create",
+            PlannedAction::Overwrite => "overwrite",
+            PlannedAction::Prompt => "prompt",
+            PlannedAction::Skip => "skip",
+            PlannedAction::Excluded => "excluded",
+        })
+    }
+}
+
+/// Walk `source` the way a directory copy does and list every entry in
+/// copy order: the root first, and each directory before its contents,
+/// which come in the filter's `--sort` order.
+///
+/// Nothing is written, so a dry run and the real copy share one walk.
+///
+/// With `--follow-symlinks`, a link back to a directory the walk is inside
+/// would recurse forever; such a directory, recognized by its device and
+/// inode, is listed as a `Cycle` instead of being entered again.
+pub fn plan_tree(source: &Path, target: &Path, filter: &WalkFilter) -> Result<Vec<PlanEntry>> {
+    if !source.is_dir() {
+        return Err(Error::Custom("Source is not a directory".to_string()));
+    }
+
+    let mut plan = vec![PlanEntry {
+        source: source.to_path_buf(),
+        target: target.to_path_buf(),
+        kind: PlanKind::Dir,
+    }];
+    let mut ancestors = HashSet::new();
+    ancestors.extend(file_id(&fs::metadata(source).map_err(Error::Io)?));
+    plan_dir(source, source, target, filter, 0, &mut ancestors, &mut plan)?;
+    Ok(plan)
+}
+
+/// Plan the contents of `source`. `ancestors` holds the directories from
+/// the root down to `source`, so a loop back to any of them is caught.
+fn plan_dir(
+    root: &Path,
+    source: &Path,
+    target: &Path,
+    filter: &WalkFilter,
+    depth: usize,
+    ancestors: &mut HashSet<(u64, u64)>,
+    plan: &mut Vec<PlanEntry>,
+) -> Result<()> {
+    let mut entries = fs::read_dir(source)
+        .and_then(|entries| entries.collect::<std::io::Result<Vec<_>>>())
+        .map_err(Error::Io)?;
+    filter.sort_entries(&mut entries);
+
+    for entry in entries {
+        let path = entry.path();
+        let target_path = target.join(entry.file_name());
+
+        let relative = path.strip_prefix(root).unwrap_or(&path);
+        if !filter.is_included(relative) {
+            plan.push(PlanEntry { source: path, target: target_path, kind: PlanKind::Excluded });
+            continue;
+        }
+
+        let kind = match filter.classify(&path)? {
+            WalkEntry::Dir(metadata) => {
+                if !filter.within_depth(depth) || !filter.should_descend(&metadata) {
+                    continue;
+                }
+                let id = file_id(&metadata);
+                if id.is_some_and(|id| !ancestors.insert(id)) {
+                    plan.push(PlanEntry { source: path, target: target_path, kind: PlanKind::Cycle });
+                    continue;
+                }
+                plan.push(PlanEntry {
+                    source: path.clone(),
+                    target: target_path.clone(),
+                    kind: PlanKind::Dir,
+                });
+                plan_dir(root, &path, &target_path, filter, depth + 1, ancestors, plan)?;
+                if let Some(id) = id {
+                    ancestors.remove(&id);
+                }
+                continue;
+            }
+            WalkEntry::Symlink(link_target) => PlanKind::Symlink(link_target),
+            WalkEntry::Special(metadata) => PlanKind::Special(metadata),
+            WalkEntry::File(metadata) if filter.accepts_file(&metadata) => PlanKind::File(metadata),
+            WalkEntry::File(_) => PlanKind::Filtered,
+        };
+        plan.push(PlanEntry { source: path, target: target_path, kind });
+    }
+    Ok(())
+}
+
+/// Drop the first `count` components of every entry's path below `target`,
+/// like `tar --strip-components`: with 1, `a/b/c.txt` lands at `b/c.txt`.
+///
+/// Directories no deeper than `count` vanish with nothing to create. Any
+/// other entry that shallow has nowhere to go and is returned separately.
+pub fn strip_components(plan: Vec<PlanEntry>, target: &Path, count: usize) -> (Vec<PlanEntry>, Vec<PlanEntry>) {
+    if count == 0 {
+        return (plan, Vec::new());
+    }
+
+    let mut kept = Vec::new();
+    let mut dropped = Vec::new();
+    for mut entry in plan {
+        let relative = match entry.target.strip_prefix(target) {
+            Ok(relative) if !relative.as_os_str().is_empty() => relative,
+            // The root itself
+            _ => {
+                kept.push(entry);
+                continue;
+            }
+        };
+        let remaining: PathBuf = relative.components().skip(count).collect();
+        if !remaining.as_os_str().is_empty() {
+            entry.target = target.join(remaining);
+            kept.push(entry);
+        } else if matches!(entry.kind, PlanKind::Filtered | PlanKind::Excluded | PlanKind::Cycle) {
+            // Never written, so where they would have gone doesn't matter
+            kept.push(entry);
+        } else if !matches!(entry.kind, PlanKind::Dir) {
+            dropped.push(entry);
+        }
+    }
+    (kept, dropped)
+}
+
+/// Two entries of a plan whose targets differ only in case, which a
+/// case-insensitive filesystem would write to the same file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CaseCollision {
+    /// The entry planned first, which keeps its name
+    pub first: PathBuf,
+    /// Where the later entry was planned to go
+    pub second: PathBuf,
+    /// Where `--case-collision=rename` sends the later entry instead
+    pub renamed: Option<PathBuf>,
+}
+
+/// Find the entries of `plan` that would land on an earlier entry's target
+/// if the filesystem ignored case. With `CaseCollisionMode::Rename` each such
+/// entry, and everything planned under it, moves to the first free
+/// `name (2).ext`, `name (3).ext`, ...; otherwise the plan is unchanged.
+pub fn resolve_case_collisions(
+    plan: Vec<PlanEntry>,
+    mode: CaseCollisionMode,
+) -> (Vec<PlanEntry>, Vec<CaseCollision>) {
+    let case_key = |path: &Path| path.to_string_lossy().to_lowercase();
+
+    // A new name must not be one the plan uses later either
+    let planned: HashSet<String> = plan.iter().map(|entry| case_key(&entry.target)).collect();
+    let mut seen: HashMap<String, PathBuf> = HashMap::new();
+    // Renamed directories, whose contents follow them
+    let mut moved: Vec<(PathBuf, PathBuf)> = Vec::new();
+    let mut collisions = Vec::new();
+    let mut resolved = Vec::with_capacity(plan.len());
+    for mut entry in plan {
+        for (from, to) in &moved {
+            if let Ok(relative) = entry.target.strip_prefix(from) {
+                entry.target = to.join(relative);
+            }
+        }
+        // Never written, so they can't collide
+        if matches!(entry.kind, PlanKind::Filtered | PlanKind::Excluded | PlanKind::Cycle) {
+            resolved.push(entry);
+            continue;
+        }
+
+        if let Some(first) = seen.get(&case_key(&entry.target)) {
+            let renamed = (mode == CaseCollisionMode::Rename).then(|| {
+                let free = (2..)
+                    .map(|n| numbered_name(&entry.target, n, matches!(entry.kind, PlanKind::Dir)))
+                    .find(|candidate| {
+                        let key = case_key(candidate);
+                        !seen.contains_key(&key) && !planned.contains(&key)
+                    })
+                    .expect("some number is free");
+                if matches!(entry.kind, PlanKind::Dir) {
+                    moved.push((entry.target.clone(), free.clone()));
+                }
+                free
+            });
+            collisions.push(CaseCollision {
+                first: first.clone(),
+                second: entry.target.clone(),
+                renamed: renamed.clone(),
+            });
+            if let Some(renamed) = renamed {
+                entry.target = renamed;
+            }
+        }
+        seen.entry(case_key(&entry.target)).or_insert_with(|| entry.target.clone());
+        resolved.push(entry);
+    }
+    (resolved, collisions)
+}
+
+/// `path` with ` (n)` added to its name, before the extension of a file
+fn numbered_name(path: &Path, n: usize, is_dir: bool) -> PathBuf {
+    let name = match (path.file_stem(), path.extension()) {
+        (Some(stem), Some(ext)) if !is_dir => {
+            format!("{} ({}).{}", stem.to_string_lossy(), n, ext.to_string_lossy())
+        }
+        _ => format!("{} ({})", path.file_name().unwrap_or_default().to_string_lossy(), n),
+    };
+    path.with_file_name(name)
+}
+
+/// What copying `source` onto `target` does under `overwrite_mode`
+pub fn file_action(
+    source: &Path,
+    src_metadata: &Metadata,
+    target: &Path,
+    overwrite_mode: &OverwriteMode,
+) -> Result<PlannedAction> {
+    if !target.exists() {
+        return Ok(PlannedAction::Create);
+    }
+    Ok(match overwrite_mode {
+        OverwriteMode::Always => PlannedAction::Overwrite,
+        OverwriteMode::Never | OverwriteMode::NoClobber => PlannedAction::Skip,
+        OverwriteMode::Prompt => PlannedAction::Prompt,
+        OverwriteMode::Smart | OverwriteMode::SmartChecksum => {
+            let compare_checksums = matches!(overwrite_mode, OverwriteMode::SmartChecksum);
+            if smart_should_overwrite(source, src_metadata, target, compare_checksums)? {
+                PlannedAction::Overwrite
+            } else {
+                PlannedAction::Skip
+            }
+        }
+        OverwriteMode::Policy(policy) => {
+            if policy_allows_overwrite(*policy, source, src_metadata, target)? {
+                PlannedAction::Overwrite
+            } else {
+                PlannedAction::Skip
+            }
+        }
+    })
+}
+
+/// What recreating a symlink (pointing at `link_target`) or special file at
+/// `target` does under `overwrite_mode`. An identical link is left alone.
+/// Links and special files have no contents to compare, so the modes that
+/// weigh one file against another keep whatever is there.
+pub fn link_action(link_target: Option<&Path>, target: &Path, overwrite_mode: &OverwriteMode) -> PlannedAction {
+    if fs::symlink_metadata(target).is_err() {
+        return PlannedAction::Create;
+    }
+    if link_target.is_some() && fs::read_link(target).ok().as_deref() == link_target {
+        return PlannedAction::Skip;
+    }
+    match overwrite_mode {
+        OverwriteMode::Always => PlannedAction::Overwrite,
+        OverwriteMode::Prompt => PlannedAction::Prompt,
+        OverwriteMode::Never
+        | OverwriteMode::NoClobber
+        | OverwriteMode::Smart
+        | OverwriteMode::SmartChecksum
+        | OverwriteMode::Policy(_) => PlannedAction::Skip,
+    }
+}
+
+impl PlanEntry {
+    /// Bytes copying this entry writes (only files have any)
+    pub fn bytes(&self) -> u64 {
+        match &self.kind {
+            PlanKind::File(metadata) => metadata.len(),
+            _ => 0,
+        }
+    }
+
+    /// The action a copy takes for this entry, or `None` for directories
+    pub fn action(&self, overwrite_mode: &OverwriteMode) -> Result<Option<PlannedAction>> {
+        Ok(Some(match &self.kind {
+            PlanKind::Dir => return Ok(None),
+            PlanKind::File(metadata) => file_action(&self.source, metadata, &self.target, overwrite_mode)?,
+            PlanKind::Symlink(link_target) => link_action(Some(link_target), &self.target, overwrite_mode),
+            PlanKind::Special(_) => link_action(None, &self.target, overwrite_mode),
+            PlanKind::Filtered | PlanKind::Cycle => PlannedAction::Skip,
+            PlanKind::Excluded => PlannedAction::Excluded,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::SortOrder;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_case_collisions_keyed_by_lowercased_target() {
+        let entry = |target: &str, kind: PlanKind| PlanEntry {
+            source: PathBuf::from("src").join(target),
+            target: PathBuf::from("dst").join(target),
+            kind,
+        };
+        let plan = || {
+            vec![
+                entry("", PlanKind::Dir),
+                entry("File.txt", PlanKind::Filtered),
+                entry("Docs", PlanKind::Dir),
+                entry("Docs/a.txt", PlanKind::Symlink(PathBuf::from("x"))),
+                entry("docs", PlanKind::Dir),
+                entry("docs/A.TXT", PlanKind::Symlink(PathBuf::from("y"))),
+                entry("docs/b.txt", PlanKind::Symlink(PathBuf::from("z"))),
+                entry("README", PlanKind::Symlink(PathBuf::from("r"))),
+                entry("readme", PlanKind::Symlink(PathBuf::from("r"))),
+                entry("readme (2)", PlanKind::Symlink(PathBuf::from("r"))),
+            ]
+        };
+        let targets = |plan: &[PlanEntry]| plan.iter().map(|e| e.target.clone()).collect::<Vec<_>>();
+
+        // A filtered entry is never written, so File.txt collides with nothing
+        let (kept, collisions) = resolve_case_collisions(plan(), CaseCollisionMode::Warn);
+        assert_eq!(targets(&kept), targets(&plan()));
+        let pairs: Vec<_> = collisions.iter().map(|c| (c.first.clone(), c.second.clone())).collect();
+        assert_eq!(
+            pairs,
+            [
+                (PathBuf::from("dst/Docs"), PathBuf::from("dst/docs")),
+                (PathBuf::from("dst/Docs/a.txt"), PathBuf::from("dst/docs/A.TXT")),
+                (PathBuf::from("dst/README"), PathBuf::from("dst/readme")),
+            ]
+        );
+
+        // Renaming a directory takes its contents along, out of the way of
+        // Docs/a.txt; names the plan already uses are passed over
+        let (renamed, collisions) = resolve_case_collisions(plan(), CaseCollisionMode::Rename);
+        assert_eq!(collisions.len(), 2);
+        assert_eq!(
+            targets(&renamed)[4..],
+            [
+                PathBuf::from("dst/docs (2)"),
+                PathBuf::from("dst/docs (2)/A.TXT"),
+                PathBuf::from("dst/docs (2)/b.txt"),
+                PathBuf::from("dst/README"),
+                PathBuf::from("dst/readme (3)"),
+                PathBuf::from("dst/readme (2)"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_sort_orders_each_directory() {
+        let temp_dir = TempDir::new().unwrap();
+        let source = temp_dir.path().join("src");
+        fs::create_dir_all(source.join("m")).unwrap();
+        for (name, size) in [("c.txt", 1), ("a.txt", 3), ("m/z.txt", 2), ("m/b.txt", 1), ("b.txt", 2)] {
+            fs::write(source.join(name), vec![0; size]).unwrap();
+        }
+        let order = |sort: SortOrder| {
+            let filter = WalkFilter::new().with_sort(sort);
+            plan_tree(&source, &temp_dir.path().join("dst"), &filter)
+                .unwrap()
+                .iter()
+                .skip(1)
+                .map(|e| e.source.strip_prefix(&source).unwrap().to_string_lossy().into_owned())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(order(SortOrder::Name), ["a.txt", "b.txt", "c.txt", "m", "m/b.txt", "m/z.txt"]);
+        // Where `m` itself lands depends on the filesystem's directory size,
+        // so each directory's files are checked on their own
+        let by_size = order(SortOrder::Size);
+        let top: Vec<_> = by_size.iter().filter(|p| p.ends_with(".txt") && !p.contains('/')).collect();
+        let nested: Vec<_> = by_size.iter().filter(|p| p.starts_with("m/")).collect();
+        assert_eq!(top, ["a.txt", "b.txt", "c.txt"]);
+        assert_eq!(nested, ["m/z.txt", "m/b.txt"]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_link_actions_match_the_copy() {
+        let temp_dir = TempDir::new().unwrap();
+        let source = temp_dir.path().join("src");
+        let target = temp_dir.path().join("dst");
+        fs::create_dir_all(&source).unwrap();
+        fs::create_dir_all(&target).unwrap();
+        std::os::unix::fs::symlink("a.txt", source.join("same")).unwrap();
+        std::os::unix::fs::symlink("a.txt", target.join("same")).unwrap();
+        std::os::unix::fs::symlink("new.txt", source.join("changed")).unwrap();
+        std::os::unix::fs::symlink("old.txt", target.join("changed")).unwrap();
+        std::os::unix::fs::symlink("a.txt", source.join("fresh")).unwrap();
+
+        let plan = plan_tree(&source, &target, &WalkFilter::new()).unwrap();
+        let action = |name: &str, mode: OverwriteMode| {
+            let entry = plan.iter().find(|e| e.source == source.join(name)).unwrap();
+            entry.action(&mode).unwrap().unwrap()
+        };
+
+        assert_eq!(action("fresh", OverwriteMode::NoClobber), PlannedAction::Create);
+        assert_eq!(action("same", OverwriteMode::Always), PlannedAction::Skip);
+        assert_eq!(action("changed", OverwriteMode::Always), PlannedAction::Overwrite);
+        assert_eq!(action("changed", OverwriteMode::NoClobber), PlannedAction::Skip);
+        assert_eq!(action("changed", OverwriteMode::Never), PlannedAction::Skip);
+        assert_eq!(action("changed", OverwriteMode::Prompt), PlannedAction::Prompt);
+    }
+
+    #[test]
+    fn test_plan_lists_dirs_before_contents() {
+        let temp_dir = TempDir::new().unwrap();
+        let source = temp_dir.path().join("src");
+        fs::create_dir_all(source.join("a/b")).unwrap();
+        fs::write(source.join("a/b/deep.txt"), b"deep").unwrap();
+        fs::write(source.join("skip.log"), b"log").unwrap();
+
+        let filter = WalkFilter::new().with_excludes(&["*.log".to_string()]).unwrap();
+        let target = temp_dir.path().join("dst");
+        let plan = plan_tree(&source, &target, &filter).unwrap();
+
+        let position = |path: &str| plan.iter().position(|e| e.source == source.join(path)).unwrap();
+        assert_eq!(plan[0].source, source);
+        assert!(position("a") < position("a/b"));
+        assert!(position("a/b") < position("a/b/deep.txt"));
+
+        let deep = &plan[position("a/b/deep.txt")];
+        assert_eq!(deep.target, target.join("a/b/deep.txt"));
+        assert_eq!(deep.action(&OverwriteMode::Always).unwrap(), Some(PlannedAction::Create));
+        assert_eq!(
+            plan[position("skip.log")].action(&OverwriteMode::Always).unwrap(),
+            Some(PlannedAction::Excluded)
+        );
+    }
+
+    #[test]
+    fn test_strip_components_shortens_targets() {
+        let temp_dir = TempDir::new().unwrap();
+        let source = temp_dir.path().join("src");
+        fs::create_dir_all(source.join("a/b")).unwrap();
+        fs::write(source.join("a/b/deep.txt"), b"deep").unwrap();
+        fs::write(source.join("a/mid.txt"), b"mid").unwrap();
+        fs::write(source.join("top.txt"), b"top").unwrap();
+
+        let target = temp_dir.path().join("dst");
+        let plan = plan_tree(&source, &target, &WalkFilter::new()).unwrap();
+        let (kept, dropped) = strip_components(plan, &target, 1);
+
+        let targets: Vec<_> = kept.iter().map(|e| e.target.clone()).collect();
+        assert!(targets.contains(&target.join("b/deep.txt")));
+        assert!(targets.contains(&target.join("mid.txt")));
+        assert!(!targets.contains(&target.join("a")));
+        assert_eq!(dropped.len(), 1);
+        assert_eq!(dropped[0].source, source.join("top.txt"));
+    }
+}
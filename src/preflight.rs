@@ -0,0 +1,287 @@
+use std::fs::{self, OpenOptions};
+use std::io;
+use std::ops::AddAssign;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+use walkdir::WalkDir;
+use crate::cli::{ConflictPolicy, OverwriteMode};
+use crate::color::style;
+use crate::error::{Error, Result};
+use crate::filter::WalkFilter;
+use crate::plan::{file_action, plan_tree, PlanKind, PlannedAction};
+use crate::prompt::confirm_action;
+
+/// Files and bytes a run would overwrite or move away
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Impact {
+    pub files: usize,
+    pub bytes: u64,
+}
+
+/// How large a run may get before it asks for confirmation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ConfirmThreshold {
+    pub files: usize,
+    pub bytes: u64,
+}
+
+impl Default for ConfirmThreshold {
+    fn default() -> Self {
+        Self {
+            files: 100,
+            bytes: 1024 * 1024 * 1024,
+        }
+    }
+}
+
+impl Impact {
+    /// Existing files that copying `source` to `target` would overwrite,
+    /// taken from the same plan the copy and `--dry-run` use. No file is
+    /// read: see [`would_overwrite`].
+    pub fn of_copy(
+        source: &Path,
+        target: &Path,
+        filter: &WalkFilter,
+        overwrite_mode: &OverwriteMode,
+    ) -> Result<Self> {
+        let mut impact = Self::default();
+        if source.is_dir() {
+            let filter = filter.clone().rooted_at(source)?;
+            for entry in plan_tree(source, target, &filter)? {
+                if let PlanKind::File(metadata) = &entry.kind {
+                    if would_overwrite(&entry.source, metadata, &entry.target, overwrite_mode)? {
+                        impact.add(metadata.len());
+                    }
+                }
+            }
+        } else if let Ok(metadata) = fs::metadata(source) {
+            if metadata.is_file() && would_overwrite(source, &metadata, target, overwrite_mode)? {
+                impact.add(metadata.len());
+            }
+        }
+        Ok(impact)
+    }
+
+    /// Every file moving `source` takes away from where it is now
+    pub fn of_move(source: &Path) -> Result<Self> {
+        let mut impact = Self::default();
+        for entry in WalkDir::new(source) {
+            let entry = entry.map_err(|e| Error::Custom(e.to_string()))?;
+            if !entry.file_type().is_dir() {
+                let metadata = entry.metadata().map_err(|e| Error::Custom(e.to_string()))?;
+                impact.add(metadata.len());
+            }
+        }
+        Ok(impact)
+    }
+
+    fn add(&mut self, bytes: u64) {
+        self.files += 1;
+        self.bytes += bytes;
+    }
+
+    /// Whether this is more than `threshold` lets through without asking
+    pub fn exceeds(&self, threshold: &ConfirmThreshold) -> bool {
+        self.files > threshold.files || self.bytes > threshold.bytes
+    }
+}
+
+/// Whether copying `source` would overwrite `target`, judged by existence,
+/// size and mtime alone. Where the copy would compare checksums, hashing
+/// every pair just to ask first would read both trees in full, so any
+/// existing target counts and the estimate errs towards asking.
+fn would_overwrite(
+    source: &Path,
+    src_metadata: &fs::Metadata,
+    target: &Path,
+    overwrite_mode: &OverwriteMode,
+) -> Result<bool> {
+    if matches!(
+        overwrite_mode,
+        OverwriteMode::SmartChecksum | OverwriteMode::Policy(ConflictPolicy::DifferentChecksum)
+    ) {
+        return Ok(target.exists());
+    }
+    Ok(file_action(source, src_metadata, target, overwrite_mode)? == PlannedAction::Overwrite)
+}
+
+impl AddAssign for Impact {
+    fn add_assign(&mut self, other: Self) {
+        self.files += other.files;
+        self.bytes += other.bytes;
+    }
+}
+
+/// Show the totals of a run that would `verb` ("overwrite", "move") more
+/// than `threshold` allows and ask before going ahead. Declining fails with
+/// `Error::UserAborted` before anything is written.
+pub fn confirm_impact(impact: Impact, threshold: &ConfirmThreshold, verb: &str) -> Result<()> {
+    if !impact.exceeds(threshold) {
+        return Ok(());
+    }
+
+    println!("\n{}", style("⚠️  Large operation").yellow().bold());
+    println!(
+        "  This will {} {} file(s), {}",
+        verb,
+        impact.files,
+        humansize::format_size(impact.bytes, humansize::BINARY)
+    );
+    if confirm_action("Continue?")? {
+        Ok(())
+    } else {
+        Err(Error::UserAborted)
+    }
+}
+
+/// Check a file can be created where `target` goes, before anything is
+/// written: create and remove a probe file in the nearest directory above
+/// `target` that exists. A read-only mount or a directory without write
+/// permission fails with `Error::ReadOnlyDestination` rather than a bare
+/// permission error halfway through a copy.
+///
+/// Other failures are left for the copy itself to report.
+pub fn check_destination_writable(target: &Path) -> Result<()> {
+    let Some(dir) = nearest_existing_dir(target) else {
+        return Ok(());
+    };
+    let probe = dir.join(probe_name("probe"));
+    match OpenOptions::new().write(true).create_new(true).open(&probe) {
+        Ok(_) => {
+            fs::remove_file(&probe).ok();
+            Ok(())
+        }
+        Err(e) => match unwritable_error(dir, &e) {
+            Some(err) => Err(err),
+            None => Ok(()),
+        },
+    }
+}
+
+/// Whether the filesystem `target` goes on treats names that differ only in
+/// case as the same (macOS and Windows by default): create a probe file in
+/// the nearest directory above `target` that exists and look it up in lower
+/// case. Taken as case-sensitive when no probe can be created there.
+pub fn is_case_insensitive(target: &Path) -> bool {
+    let Some(dir) = nearest_existing_dir(target) else {
+        return false;
+    };
+    let name = probe_name("CASE");
+    let probe = dir.join(&name);
+    if OpenOptions::new().write(true).create_new(true).open(&probe).is_err() {
+        return false;
+    }
+    let insensitive = fs::symlink_metadata(dir.join(name.to_lowercase())).is_ok();
+    fs::remove_file(&probe).ok();
+    insensitive
+}
+
+/// The nearest directory above `target` that exists
+fn nearest_existing_dir(target: &Path) -> Option<&Path> {
+    let dir = target.ancestors().skip(1).find(|dir| dir.as_os_str().is_empty() || dir.is_dir())?;
+    Some(if dir.as_os_str().is_empty() { Path::new(".") } else { dir })
+}
+
+/// A file name no other run or file will be using
+fn probe_name(kind: &str) -> String {
+    let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.subsec_nanos()).unwrap_or(0);
+    format!(".better-cp-{}-{}-{}", kind, std::process::id(), nanos)
+}
+
+/// `Error::ReadOnlyDestination` for a failed probe in `dir`, if that's what it means
+fn unwritable_error(dir: &Path, error: &io::Error) -> Option<Error> {
+    let read_only_fs = match error.kind() {
+        io::ErrorKind::ReadOnlyFilesystem => true,
+        io::ErrorKind::PermissionDenied => false,
+        _ => return None,
+    };
+    Some(Error::ReadOnlyDestination { path: dir.display().to_string(), read_only_fs })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::copy::DirectoryCopier;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_declined_confirmation_writes_nothing() {
+        let temp_dir = TempDir::new().unwrap();
+        let source = temp_dir.path().join("source");
+        let target = temp_dir.path().join("target");
+        for (root, contents) in [(&source, "new"), (&target, "old")] {
+            fs::create_dir_all(root).unwrap();
+            for i in 0..3 {
+                fs::write(root.join(format!("{}.txt", i)), contents).unwrap();
+            }
+        }
+
+        let impact =
+            Impact::of_copy(&source, &target, &WalkFilter::new(), &OverwriteMode::Always).unwrap();
+        assert_eq!(impact, Impact { files: 3, bytes: 9 });
+
+        // Under test the prompt answers "n"
+        let threshold = ConfirmThreshold { files: 2, bytes: u64::MAX };
+        let result = match confirm_impact(impact, &threshold, "overwrite") {
+            Ok(()) => DirectoryCopier::new(source, target.clone(), OverwriteMode::Always, false)
+                .with_progress(false)
+                .copy()
+                .await
+                .map(|_| ()),
+            Err(e) => Err(e),
+        };
+
+        assert!(matches!(result, Err(Error::UserAborted)));
+        for i in 0..3 {
+            assert_eq!(fs::read_to_string(target.join(format!("{}.txt", i))).unwrap(), "old");
+        }
+        // Under the threshold nothing is asked
+        assert!(confirm_impact(impact, &ConfirmThreshold::default(), "overwrite").is_ok());
+    }
+
+    #[test]
+    fn test_checksum_modes_are_counted_without_hashing() {
+        let temp_dir = TempDir::new().unwrap();
+        let source = temp_dir.path().join("source.txt");
+        let target = temp_dir.path().join("target.txt");
+        let missing = temp_dir.path().join("missing.txt");
+        fs::write(&source, "same").unwrap();
+        fs::write(&target, "same").unwrap();
+
+        // Identical files, which a checksum would skip, count all the same
+        for mode in [OverwriteMode::SmartChecksum, OverwriteMode::Policy(ConflictPolicy::DifferentChecksum)] {
+            let filter = WalkFilter::new();
+            assert_eq!(Impact::of_copy(&source, &target, &filter, &mode).unwrap(), Impact { files: 1, bytes: 4 });
+            assert_eq!(Impact::of_copy(&source, &missing, &filter, &mode).unwrap(), Impact::default());
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_unwritable_destination_is_reported_up_front() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp_dir = TempDir::new().unwrap();
+        let locked = temp_dir.path().join("locked");
+        fs::create_dir(&locked).unwrap();
+        fs::set_permissions(&locked, fs::Permissions::from_mode(0o555)).unwrap();
+
+        let result = check_destination_writable(&locked.join("sub/file.txt"));
+        fs::set_permissions(&locked, fs::Permissions::from_mode(0o755)).unwrap();
+        // Root writes through 0o555 regardless
+        if unsafe { libc::geteuid() } != 0 {
+            assert!(matches!(
+                result,
+                Err(Error::ReadOnlyDestination { ref path, read_only_fs: false }) if path == &locked.display().to_string()
+            ));
+        }
+
+        let err = unwritable_error(&locked, &io::Error::from(io::ErrorKind::ReadOnlyFilesystem)).unwrap();
+        assert!(err.detailed_message().contains("read-only filesystem"));
+        assert!(unwritable_error(&locked, &io::Error::from(io::ErrorKind::NotFound)).is_none());
+
+        // A writable destination passes and leaves no probe behind
+        check_destination_writable(&temp_dir.path().join("new/file.txt")).unwrap();
+        assert_eq!(fs::read_dir(temp_dir.path()).unwrap().count(), 1);
+    }
+}
@@ -0,0 +1,431 @@
+use std::fs::{self, File, Metadata};
+use std::path::Path;
+use std::str::FromStr;
+use crate::error::{Error, Result};
+
+/// Which attributes a copy carries over besides file contents, parsed from
+/// `--preserve mode,timestamps,ownership,xattr,streams,links,all`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PreserveSet {
+    /// Permission bits
+    pub mode: bool,
+    /// Modification time
+    pub timestamps: bool,
+    /// Owner and group
+    pub ownership: bool,
+    /// Extended attributes
+    pub xattr: bool,
+    /// NTFS alternate data streams, such as `Zone.Identifier` (Windows only)
+    pub streams: bool,
+    /// Hard links between copied files: names of one source file stay
+    /// links to one target file (directory copies only)
+    pub links: bool,
+    /// Set by `all`: attributes that cannot be kept only produce warnings
+    pub best_effort: bool,
+}
+
+impl PreserveSet {
+    /// Everything this platform can preserve, like `cp -a`
+    pub fn all() -> Self {
+        Self {
+            mode: true,
+            timestamps: true,
+            ownership: cfg!(unix),
+            xattr: cfg!(unix),
+            streams: cfg!(windows),
+            links: true,
+            best_effort: true,
+        }
+    }
+
+    /// Permission bits and modification time: what a directory copy keeps by default
+    pub fn mode_and_timestamps() -> Self {
+        Self {
+            mode: true,
+            timestamps: true,
+            ..Self::default()
+        }
+    }
+
+    /// Whether failing to keep an attribute should abort the copy
+    pub fn is_strict(&self) -> bool {
+        !self.best_effort
+    }
+}
+
+impl FromStr for PreserveSet {
+    type Err = Error;
+
+    fn from_str(list: &str) -> Result<Self> {
+        let mut set = Self::default();
+        for token in list.split(',').map(str::trim).filter(|t| !t.is_empty()) {
+            match token {
+                "mode" => set.mode = true,
+                "timestamps" => set.timestamps = true,
+                "ownership" => set.ownership = true,
+                "xattr" => set.xattr = true,
+                "streams" => set.streams = true,
+                "links" => set.links = true,
+                "all" => {
+                    let all = Self::all();
+                    set = Self {
+                        mode: true,
+                        timestamps: true,
+                        ownership: set.ownership || all.ownership,
+                        xattr: set.xattr || all.xattr,
+                        streams: set.streams || all.streams,
+                        links: true,
+                        best_effort: true,
+                    };
+                }
+                other => {
+                    return Err(Error::Usage(format!(
+                        "Unknown --preserve attribute '{}' (expected mode, timestamps, ownership, xattr, streams, links or all)",
+                        other
+                    )))
+                }
+            }
+        }
+        Ok(set)
+    }
+}
+
+/// Apply the attributes in `preserve` from `source` onto the copied `target`
+/// (a file or directory). The mode goes last so a read-only source doesn't
+/// lock us out of the other steps.
+pub fn apply_metadata(
+    source: &Path,
+    src_metadata: &Metadata,
+    target: &Path,
+    preserve: &PreserveSet,
+) -> Result<()> {
+    if preserve.xattr {
+        copy_xattrs(source, target, preserve.is_strict())?;
+    }
+
+    // Only the main stream counts towards progress and verification
+    if preserve.streams && src_metadata.is_file() {
+        copy_streams(source, target, preserve.is_strict())?;
+    }
+
+    // Before the mode: chown clears setuid/setgid bits
+    if preserve.ownership {
+        copy_ownership(src_metadata, target, preserve.is_strict())?;
+    }
+
+    if preserve.timestamps {
+        let modified = src_metadata.modified().map_err(Error::Io)?;
+        set_modified(target, modified).map_err(Error::Io)?;
+    }
+
+    if preserve.mode {
+        fs::set_permissions(target, src_metadata.permissions())
+            .map_err(Error::Io)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(unix)]
+fn set_modified(target: &Path, modified: std::time::SystemTime) -> std::io::Result<()> {
+    File::open(target)?.set_modified(modified)
+}
+
+/// Directories can't be opened for writing here, so only files get their mtime
+#[cfg(not(unix))]
+fn set_modified(target: &Path, modified: std::time::SystemTime) -> std::io::Result<()> {
+    if target.is_dir() {
+        return Ok(());
+    }
+    File::options().write(true).open(target)?.set_modified(modified)
+}
+
+/// Copy extended attributes (SELinux contexts, `user.*` tags, ...) from
+/// `source` onto `target`.
+///
+/// When `strict` is false, attributes that cannot be set (typically
+/// privileged namespaces like `security.*` without root) are reported as
+/// warnings and the copy carries on.
+#[cfg(unix)]
+pub fn copy_xattrs(source: &Path, target: &Path, strict: bool) -> Result<()> {
+    let names = match xattr::list(source) {
+        Ok(names) => names,
+        Err(e) => return xattr_failure(source, "read extended attributes of", e, strict),
+    };
+
+    for name in names {
+        let value = match xattr::get(source, &name) {
+            Ok(Some(value)) => value,
+            Ok(None) => continue,
+            Err(e) => {
+                xattr_failure(source, "read extended attributes of", e, strict)?;
+                continue;
+            }
+        };
+        if let Err(e) = xattr::set(target, &name, &value) {
+            let action = format!("set {} on", name.to_string_lossy());
+            xattr_failure(target, &action, e, strict)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Extended attributes are not supported on this platform
+#[cfg(not(unix))]
+pub fn copy_xattrs(_source: &Path, _target: &Path, strict: bool) -> Result<()> {
+    if strict {
+        return Err(Error::Custom(
+            "Extended attributes are not supported on this platform".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Copy NTFS alternate data streams (`file.txt:Zone.Identifier`, ...) from
+/// `source` onto `target`. The unnamed main stream is the copy itself, so
+/// only the named streams are written here.
+#[cfg(windows)]
+pub fn copy_streams(source: &Path, target: &Path, strict: bool) -> Result<()> {
+    let streams = match list_streams(source) {
+        Ok(streams) => streams,
+        Err(e) => {
+            let msg = format!("Failed to list data streams of {}: {}", source.display(), e);
+            return warn_or_fail(Error::Custom(msg), strict);
+        }
+    };
+
+    for name in streams {
+        let copied = File::open(stream_path(source, &name)).and_then(|mut from| {
+            let mut to = File::create(stream_path(target, &name))?;
+            std::io::copy(&mut from, &mut to)
+        });
+        if let Err(e) = copied {
+            let msg = format!(
+                "Failed to copy stream {} of {}: {}",
+                name.to_string_lossy(),
+                source.display(),
+                e
+            );
+            warn_or_fail(Error::Custom(msg), strict)?;
+        }
+    }
+    Ok(())
+}
+
+/// Alternate data streams only exist on Windows
+#[cfg(not(windows))]
+pub fn copy_streams(_source: &Path, _target: &Path, strict: bool) -> Result<()> {
+    if strict {
+        return Err(Error::Custom(
+            "Alternate data streams are only supported on Windows".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Names of the named streams of `path`, as `:name:$DATA`
+#[cfg(windows)]
+fn list_streams(path: &Path) -> std::io::Result<Vec<std::ffi::OsString>> {
+    use std::ffi::OsString;
+    use std::os::windows::ffi::{OsStrExt, OsStringExt};
+    use windows_sys::Win32::Foundation::{GetLastError, ERROR_HANDLE_EOF, INVALID_HANDLE_VALUE};
+    use windows_sys::Win32::Storage::FileSystem::{
+        FindClose, FindFirstStreamW, FindNextStreamW, FindStreamInfoStandard, WIN32_FIND_STREAM_DATA,
+    };
+
+    let wide: Vec<u16> = path.as_os_str().encode_wide().chain(Some(0)).collect();
+    // SAFETY: WIN32_FIND_STREAM_DATA is plain data, and `wide` is NUL-terminated
+    let mut data: WIN32_FIND_STREAM_DATA = unsafe { std::mem::zeroed() };
+    let handle = unsafe {
+        FindFirstStreamW(wide.as_ptr(), FindStreamInfoStandard, &mut data as *mut _ as *mut _, 0)
+    };
+    if handle == INVALID_HANDLE_VALUE {
+        let code = unsafe { GetLastError() };
+        return if code == ERROR_HANDLE_EOF {
+            Ok(Vec::new())
+        } else {
+            Err(std::io::Error::from_raw_os_error(code as i32))
+        };
+    }
+
+    let mut streams = Vec::new();
+    loop {
+        let len = data.cStreamName.iter().position(|&c| c == 0).unwrap_or(data.cStreamName.len());
+        let name = OsString::from_wide(&data.cStreamName[..len]);
+        if name != "::$DATA" {
+            streams.push(name);
+        }
+        if unsafe { FindNextStreamW(handle, &mut data as *mut _ as *mut _) } == 0 {
+            break;
+        }
+    }
+    unsafe { FindClose(handle) };
+    Ok(streams)
+}
+
+/// `file.txt` plus `:name:$DATA` names that stream of the file
+#[cfg(windows)]
+fn stream_path(path: &Path, stream: &std::ffi::OsStr) -> std::path::PathBuf {
+    let mut full = path.as_os_str().to_os_string();
+    full.push(stream);
+    full.into()
+}
+
+/// Give `target` the owner and group of the source.
+///
+/// Only root may hand files to other users; for anyone else a differing
+/// owner is reported as a warning unless `strict`.
+#[cfg(unix)]
+pub fn copy_ownership(src_metadata: &Metadata, target: &Path, strict: bool) -> Result<()> {
+    use std::os::unix::fs::MetadataExt;
+
+    let tgt_metadata = fs::symlink_metadata(target).map_err(Error::Io)?;
+    if !needs_chown(src_metadata, &tgt_metadata) {
+        return Ok(());
+    }
+
+    match std::os::unix::fs::chown(target, Some(src_metadata.uid()), Some(src_metadata.gid())) {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == std::io::ErrorKind::PermissionDenied => warn_or_fail(
+            Error::PermissionDenied(format!(
+                "{} (setting owner {}:{} requires root)",
+                target.display(),
+                src_metadata.uid(),
+                src_metadata.gid()
+            )),
+            strict,
+        ),
+        Err(e) => warn_or_fail(Error::Io(e), strict),
+    }
+}
+
+/// File ownership is not modelled on this platform
+#[cfg(not(unix))]
+pub fn copy_ownership(_src_metadata: &Metadata, _target: &Path, strict: bool) -> Result<()> {
+    if strict {
+        return Err(Error::Custom(
+            "Preserving ownership is not supported on this platform".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Identifies the file behind a name that shares it with other hard
+/// links, so the copy can link those names again (`--preserve=links`)
+#[cfg(unix)]
+pub fn hard_link_key(metadata: &Metadata) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+
+    (metadata.nlink() > 1).then(|| (metadata.dev(), metadata.ino()))
+}
+
+/// Hard links are not tracked on this platform
+#[cfg(not(unix))]
+pub fn hard_link_key(_metadata: &Metadata) -> Option<(u64, u64)> {
+    None
+}
+
+/// Whether the target's owner or group differs from the source's
+#[cfg(unix)]
+fn needs_chown(src_metadata: &Metadata, tgt_metadata: &Metadata) -> bool {
+    use std::os::unix::fs::MetadataExt;
+
+    src_metadata.uid() != tgt_metadata.uid() || src_metadata.gid() != tgt_metadata.gid()
+}
+
+#[cfg(unix)]
+fn xattr_failure(path: &Path, action: &str, err: std::io::Error, strict: bool) -> Result<()> {
+    let msg = format!("Failed to {} {}: {}", action, path.display(), err);
+    warn_or_fail(Error::Custom(msg), strict)
+}
+
+/// Abort with `err` when strict, otherwise print it as a warning and carry on
+#[cfg(any(unix, windows))]
+fn warn_or_fail(err: Error, strict: bool) -> Result<()> {
+    if strict {
+        Err(err)
+    } else {
+        crate::output::warn(err);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_all() {
+        let set: PreserveSet = "all".parse().unwrap();
+        assert_eq!(set, PreserveSet::all());
+        assert!(set.mode && set.timestamps && set.links);
+        assert!(!set.is_strict());
+    }
+
+    #[test]
+    fn test_parse_mode_and_timestamps() {
+        let set: PreserveSet = "mode,timestamps".parse().unwrap();
+        assert_eq!(set, PreserveSet::mode_and_timestamps());
+        assert!(set.is_strict());
+    }
+
+    #[test]
+    fn test_parse_rejects_unknown_token() {
+        let err = "mode,acl".parse::<PreserveSet>().unwrap_err();
+        assert!(err.to_string().contains("acl"));
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn test_copy_streams() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let source = temp_dir.path().join("source.txt");
+        let target = temp_dir.path().join("target.txt");
+        fs::write(&source, b"main").unwrap();
+        fs::write(temp_dir.path().join("source.txt:test"), b"alternate").unwrap();
+        fs::write(&target, b"main").unwrap();
+
+        let preserve: PreserveSet = "streams".parse().unwrap();
+        apply_metadata(&source, &fs::metadata(&source).unwrap(), &target, &preserve).unwrap();
+
+        assert_eq!(fs::read(temp_dir.path().join("target.txt:test")).unwrap(), b"alternate");
+        // The main stream alone makes up the file's size
+        assert_eq!(fs::metadata(&target).unwrap().len(), 4);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_ownership_failure_warns_unless_strict() {
+        let err = || Error::PermissionDenied("target".to_string());
+        assert!(warn_or_fail(err(), false).is_ok());
+        assert!(matches!(warn_or_fail(err(), true), Err(Error::PermissionDenied(_))));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_copy_ownership() {
+        use std::os::unix::fs::MetadataExt;
+
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let source = temp_dir.path().join("source.txt");
+        let target = temp_dir.path().join("target.txt");
+        fs::write(&source, b"owned").unwrap();
+        fs::write(&target, b"owned").unwrap();
+
+        let privileged = unsafe { libc::geteuid() } == 0;
+        if privileged {
+            // Root can hand the source to nobody and have the copy follow
+            std::os::unix::fs::chown(&source, Some(65534), Some(65534)).unwrap();
+        }
+
+        let src_metadata = fs::metadata(&source).unwrap();
+        // Without root both files already share an owner, so this is a no-op
+        assert_eq!(needs_chown(&src_metadata, &fs::metadata(&target).unwrap()), privileged);
+        copy_ownership(&src_metadata, &target, true).unwrap();
+
+        let tgt_metadata = fs::metadata(&target).unwrap();
+        assert_eq!(tgt_metadata.uid(), src_metadata.uid());
+        assert_eq!(tgt_metadata.gid(), src_metadata.gid());
+    }
+}
@@ -1,7 +1,334 @@
+use std::collections::VecDeque;
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::{Arc, Mutex};
 use std::time::{Instant, Duration};
-use indicatif::{ProgressBar, ProgressStyle};
+use indicatif::{ProgressBar, ProgressDrawTarget, ProgressStyle};
 use humansize::format_size;
+use console::Term;
+use serde::Serialize;
+use crate::cli::ProgressTarget;
+use crate::json_output::FileResult;
+
+/// How often JSON progress events are emitted
+const JSON_EVENT_INTERVAL: Duration = Duration::from_millis(500);
+
+/// How often the progress bar is redrawn unless `--progress-interval` says otherwise
+const DEFAULT_DRAW_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Refresh interval from `--progress-interval` in milliseconds; 0 until set
+static REFRESH_INTERVAL_MS: AtomicU64 = AtomicU64::new(0);
+
+/// Whether `--progress-to=stdout` moved bars off stderr
+static BARS_ON_STDOUT: AtomicBool = AtomicBool::new(false);
+
+/// Bar layout used unless `--progress-template` or `ui.progress_style` says otherwise
+const DEFAULT_BAR_TEMPLATE: &str =
+    "{spinner:.green} [{wide_bar:.cyan/blue}] {bytes}/{total_bytes} ({percent}%) | {per_sec} | ⏱ {msg}";
+
+/// Bar template from `--progress-template` or `ui.progress_style`; None until set
+static BAR_TEMPLATE: Mutex<Option<String>> = Mutex::new(None);
+
+/// Number of recent (time, bytes) samples the throughput window spans
+const SPEED_WINDOW: usize = 8;
+
+/// Weight of the newest window speed in the moving average
+const SPEED_SMOOTHING: f64 = 0.3;
+
+/// How much transfer time each `--bandwidth-report` sample covers
+const BANDWIDTH_SAMPLE_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Throughput samples of finished transfers, in bytes/s; None unless
+/// `--bandwidth-report` asked for them
+static BANDWIDTH_SAMPLES: Mutex<Option<Vec<f64>>> = Mutex::new(None);
+
+/// Decide whether progress bars should be drawn.
+///
+/// Bars are suppressed when `--no-progress` is given or when the stream they
+/// are drawn on (see `--progress-to`) is not a terminal (piped to a file, CI
+/// logs, ...).
+pub fn progress_enabled(no_progress: bool) -> bool {
+    let term = match progress_target() {
+        ProgressTarget::Stdout => Term::stdout(),
+        ProgressTarget::Stderr => Term::stderr(),
+    };
+    !no_progress && term.is_term()
+}
+
+/// Set, once at startup, the stream progress bars are drawn on
+/// (`--progress-to`, stderr by default)
+pub fn set_progress_target(target: ProgressTarget) {
+    BARS_ON_STDOUT.store(target == ProgressTarget::Stdout, Ordering::Relaxed);
+}
+
+/// The stream progress bars are drawn on
+pub fn progress_target() -> ProgressTarget {
+    if BARS_ON_STDOUT.load(Ordering::Relaxed) {
+        ProgressTarget::Stdout
+    } else {
+        ProgressTarget::Stderr
+    }
+}
+
+/// Start collecting throughput samples from every transfer for the
+/// `--bandwidth-report` printed at the end
+pub fn record_bandwidth_samples() {
+    *BANDWIDTH_SAMPLES.lock().unwrap() = Some(Vec::new());
+}
+
+/// Throughput samples (bytes/s) of the transfers finished so far
+pub fn bandwidth_samples() -> Vec<f64> {
+    BANDWIDTH_SAMPLES.lock().unwrap().clone().unwrap_or_default()
+}
+
+/// Set, once at startup, how often progress is redrawn. This also paces
+/// `--json` progress events, which otherwise come every 500ms.
+pub fn set_refresh_interval(interval: Duration) {
+    REFRESH_INTERVAL_MS.store(interval.as_millis().max(1) as u64, Ordering::Relaxed);
+}
+
+/// Bar templates `ui.progress_style` can choose: `bars` (the default),
+/// `minimal` and `detailed`
+pub fn preset_template(name: &str) -> Option<&'static str> {
+    match name {
+        "bars" => Some(DEFAULT_BAR_TEMPLATE),
+        "minimal" => Some("{bytes}/{total_bytes} ({percent}%)"),
+        "detailed" => Some(
+            "{spinner:.green} [{wide_bar:.cyan/blue}] {bytes}/{total_bytes} ({percent}%) \
+             | {per_sec} | elapsed {elapsed_precise} | ⏱ {msg}",
+        ),
+        _ => None,
+    }
+}
+
+/// Set, once at startup, the indicatif template progress bars are drawn
+/// with (see `--progress-template`). A template indicatif can't parse is
+/// reported and the default layout kept; returns whether it was taken.
+pub fn set_progress_template(template: &str) -> bool {
+    match ProgressStyle::default_bar().template(template) {
+        Ok(_) => {
+            *BAR_TEMPLATE.lock().unwrap() = Some(template.to_string());
+            true
+        }
+        Err(e) => {
+            crate::output::warn(format_args!("Ignoring --progress-template ({}); using the default bar", e));
+            false
+        }
+    }
+}
+
+fn bar_style() -> ProgressStyle {
+    let configured = BAR_TEMPLATE.lock().unwrap().clone();
+    configured
+        .and_then(|template| ProgressStyle::default_bar().template(&template).ok())
+        .unwrap_or_else(|| ProgressStyle::default_bar().template(DEFAULT_BAR_TEMPLATE).expect("Template valid"))
+        .progress_chars("████░░░░")
+}
+
+fn configured_interval() -> Option<Duration> {
+    match REFRESH_INTERVAL_MS.load(Ordering::Relaxed) {
+        0 => None,
+        ms => Some(Duration::from_millis(ms)),
+    }
+}
+
+/// How a tracker reports progress
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProgressMode {
+    /// No progress output
+    Hidden,
+    /// Interactive progress bar
+    Bar,
+    /// Newline-delimited JSON events on stdout
+    Json,
+}
+
+impl From<bool> for ProgressMode {
+    fn from(show_progress: bool) -> Self {
+        if show_progress {
+            ProgressMode::Bar
+        } else {
+            ProgressMode::Hidden
+        }
+    }
+}
+
+/// Structured progress event for `--json` consumers
+#[derive(Serialize, Debug, Clone)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum ProgressEvent {
+    /// Periodic update while a transfer runs
+    Progress {
+        bytes: u64,
+        total: u64,
+        speed_bps: u64,
+    },
+    /// Final event once the whole run has finished
+    Done {
+        bytes: u64,
+        total: u64,
+        speed_bps: u64,
+        elapsed_secs: f64,
+        files_skipped: usize,
+        /// Files that couldn't be copied under `--ignore-errors`
+        files_failed: usize,
+        /// Identical targets only re-timestamped under `--touch-only`
+        files_touched: usize,
+        /// Duplicates hard-linked to an earlier copy under `--dedup`
+        files_linked: usize,
+        /// Outcome of each file; a directory source lists the files in it
+        files: Vec<FileResult>,
+        /// Why the run failed, with the same advice a terminal would show
+        #[serde(skip_serializing_if = "Option::is_none")]
+        error: Option<String>,
+    },
+}
+
+impl ProgressEvent {
+    /// Build the final event from aggregated run statistics
+    pub fn done(stats: &TransferStats) -> Self {
+        Self::done_with_files(stats, Vec::new())
+    }
+
+    /// Final event listing what happened to each file
+    pub fn done_with_files(stats: &TransferStats, files: Vec<FileResult>) -> Self {
+        ProgressEvent::Done {
+            bytes: stats.transferred_bytes,
+            total: stats.total_bytes,
+            speed_bps: stats.speed_bps,
+            elapsed_secs: stats.elapsed.as_secs_f64(),
+            files_skipped: stats.files_skipped,
+            files_failed: stats.files_failed,
+            files_touched: stats.files_touched,
+            files_linked: stats.files_linked,
+            files,
+            error: None,
+        }
+    }
+
+    /// Mark the final event as the end of a failed run
+    pub fn with_error(mut self, message: String) -> Self {
+        if let ProgressEvent::Done { ref mut error, .. } = self {
+            *error = Some(message);
+        }
+        self
+    }
+
+    /// Serialize as a single JSON line (without the trailing newline)
+    pub fn to_json_line(&self) -> String {
+        serde_json::to_string(self).unwrap_or_default()
+    }
+}
+
+/// Writes JSON progress events no more often than its interval
+struct JsonEmitter {
+    sink: Box<dyn Write + Send>,
+    interval: Duration,
+    last_emit: Option<Instant>,
+}
+
+impl JsonEmitter {
+    fn emit(&mut self, event: &ProgressEvent, force: bool) {
+        let due = self
+            .last_emit
+            .map(|last| last.elapsed() >= self.interval)
+            .unwrap_or(true);
+        if force || due {
+            // Progress output is best effort; a closed pipe must not fail the copy
+            writeln!(self.sink, "{}", event.to_json_line()).ok();
+            self.sink.flush().ok();
+            self.last_emit = Some(Instant::now());
+        }
+    }
+}
+
+/// Throughput estimate that rides out bursts: the speed over a window of
+/// recent samples, fed through an exponentially-weighted moving average
+#[derive(Debug, Default)]
+struct SpeedEstimator {
+    samples: VecDeque<(Instant, u64)>,
+    smoothed_bps: Option<f64>,
+}
+
+impl SpeedEstimator {
+    /// Record that `total_bytes` had been transferred by `at`
+    fn record(&mut self, at: Instant, total_bytes: u64) {
+        if self.samples.len() == SPEED_WINDOW {
+            self.samples.pop_front();
+        }
+        self.samples.push_back((at, total_bytes));
+
+        let (Some(&(first_at, first_bytes)), Some(&(last_at, last_bytes))) =
+            (self.samples.front(), self.samples.back())
+        else {
+            return;
+        };
+        let span = last_at.duration_since(first_at).as_secs_f64();
+        if span <= 0.0 {
+            return;
+        }
+
+        let window_bps = last_bytes.saturating_sub(first_bytes) as f64 / span;
+        self.smoothed_bps = Some(match self.smoothed_bps {
+            Some(previous) => SPEED_SMOOTHING * window_bps + (1.0 - SPEED_SMOOTHING) * previous,
+            None => window_bps,
+        });
+    }
+
+    /// Smoothed speed, once at least two samples have been seen
+    fn speed_bps(&self) -> Option<f64> {
+        self.smoothed_bps
+    }
+}
+
+/// Throughput over consecutive, fixed stretches of a transfer, kept for
+/// `--bandwidth-report` rather than smoothed away
+#[derive(Debug)]
+struct BandwidthSampler {
+    interval: Duration,
+    /// Start of the stretch being measured, and the bytes moved in it
+    started: Instant,
+    bytes: u64,
+    samples: Vec<f64>,
+}
+
+impl BandwidthSampler {
+    fn new(at: Instant, interval: Duration) -> Self {
+        Self {
+            interval,
+            started: at,
+            bytes: 0,
+            samples: Vec::new(),
+        }
+    }
+
+    /// Record `bytes` moved by `at`, closing the stretch once it spans the interval
+    fn record(&mut self, at: Instant, bytes: u64) {
+        self.bytes += bytes;
+        let span = at.duration_since(self.started);
+        if span >= self.interval {
+            self.samples.push(self.bytes as f64 / span.as_secs_f64());
+            self.started = at;
+            self.bytes = 0;
+        }
+    }
+
+    /// The samples taken, counting the last stretch only if it ran at least
+    /// half the interval; shorter ones are too noisy to say much
+    fn finish(mut self, at: Instant) -> Vec<f64> {
+        let span = at.duration_since(self.started);
+        if self.bytes > 0 && span >= self.interval / 2 {
+            self.samples.push(self.bytes as f64 / span.as_secs_f64());
+        }
+        self.samples
+    }
+}
+
+/// Format a duration as `HH:MM:SS` for the progress bar
+fn format_eta(eta: Duration) -> String {
+    let secs = eta.as_secs();
+    format!("{:02}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+}
 
 /// Track progress of a transfer operation
 #[derive(Clone)]
@@ -10,33 +337,199 @@ pub struct ProgressTracker {
 }
 
 struct ProgressTrackerInner {
-    total_bytes: u64,
+    /// `None` when the source's length can't be known up front (a pipe)
+    total_bytes: Option<u64>,
     transferred_bytes: u64,
     start_time: Instant,
     progress_bar: Option<ProgressBar>,
+    json_events: Option<JsonEmitter>,
+    speed: SpeedEstimator,
+    /// Minimum time between two redraws of the bar
+    draw_interval: Duration,
+    last_draw: Option<Instant>,
+    /// Why the copy finished without moving every byte, shown after "completed"
+    completed_reason: Option<String>,
+    /// The phase the bar stands for when it isn't the transfer, e.g. "verifying"
+    label: Option<String>,
+    /// Throughput samples, taken while `--bandwidth-report` collects them
+    bandwidth: Option<BandwidthSampler>,
+    /// Number of times the bar position was updated
+    #[cfg(test)]
+    draws: usize,
+}
+
+impl ProgressTrackerInner {
+    /// Bytes per second since the start
+    fn average_speed(&self) -> f64 {
+        let elapsed = self.start_time.elapsed().as_secs_f64();
+        if elapsed > 0.0 {
+            self.transferred_bytes as f64 / elapsed
+        } else {
+            0.0
+        }
+    }
+
+    /// Recent throughput, falling back to the average until there is a window
+    fn smoothed_speed(&self) -> f64 {
+        self.speed.speed_bps().unwrap_or_else(|| self.average_speed())
+    }
+
+    /// The expected total, or what has been seen so far when it is unknown
+    fn total(&self) -> u64 {
+        self.total_bytes.unwrap_or(self.transferred_bytes)
+    }
+
+    /// Time left at the smoothed speed
+    fn eta(&self) -> Duration {
+        let speed = self.smoothed_speed();
+        let remaining = self.total().saturating_sub(self.transferred_bytes);
+        if speed > 0.0 {
+            Duration::from_secs_f64(remaining as f64 / speed)
+        } else {
+            Duration::ZERO
+        }
+    }
+
+    /// Move the bar to the current position, at most once per `draw_interval`
+    /// unless `force`d, so a copy issuing many small writes doesn't redraw
+    /// the terminal on every one of them
+    fn draw(&mut self, force: bool) {
+        let Some(pb) = self.progress_bar.as_ref() else {
+            return;
+        };
+        let due = self
+            .last_draw
+            .map(|last| last.elapsed() >= self.draw_interval)
+            .unwrap_or(true);
+        if !force && !due {
+            return;
+        }
+        pb.set_position(self.transferred_bytes);
+        if self.total_bytes.is_some() {
+            match &self.label {
+                Some(label) => pb.set_message(format!("{}, {}", label, format_eta(self.eta()))),
+                None => pb.set_message(format_eta(self.eta())),
+            }
+        }
+        self.last_draw = Some(Instant::now());
+        #[cfg(test)]
+        {
+            self.draws += 1;
+        }
+    }
+
+    fn progress_event(&self) -> ProgressEvent {
+        let elapsed = self.start_time.elapsed().as_secs_f64();
+        let speed_bps = if elapsed > 0.0 {
+            (self.transferred_bytes as f64 / elapsed) as u64
+        } else {
+            0
+        };
+        ProgressEvent::Progress {
+            bytes: self.transferred_bytes,
+            total: self.total(),
+            speed_bps,
+        }
+    }
 }
 
 impl ProgressTracker {
     pub fn new(total_bytes: u64, show_progress: bool) -> Self {
-        let progress_bar = if show_progress {
-            let pb = ProgressBar::new(total_bytes);
+        Self::with_mode(total_bytes, show_progress.into())
+    }
+
+    /// Create a tracker reporting in the given mode
+    pub fn with_mode(total_bytes: u64, mode: ProgressMode) -> Self {
+        Self::with_total(Some(total_bytes), mode)
+    }
+
+    /// Create a tracker for a source whose length isn't known up front, such
+    /// as stdin: a spinner with the bytes so far and the speed replaces the
+    /// percentage bar, and the total grows with the bytes seen
+    pub fn unbounded(mode: ProgressMode) -> Self {
+        Self::with_total(None, mode)
+    }
+
+    fn with_total(total_bytes: Option<u64>, mode: ProgressMode) -> Self {
+        match mode {
+            ProgressMode::Json => {
+                let interval = configured_interval().unwrap_or(JSON_EVENT_INTERVAL);
+                let emitter = JsonEmitter {
+                    sink: Box::new(io::stdout()),
+                    interval,
+                    last_emit: None,
+                };
+                Self::build(total_bytes, None, Some(emitter))
+            }
+            ProgressMode::Bar => {
+                let target = match progress_target() {
+                    ProgressTarget::Stdout => ProgressDrawTarget::stdout(),
+                    ProgressTarget::Stderr => ProgressDrawTarget::stderr(),
+                };
+                Self::build(total_bytes, Some(Self::progress_bar(total_bytes, target)), None)
+            }
+            ProgressMode::Hidden => Self::build(total_bytes, None, None),
+        }
+    }
+
+    /// Create a tracker for a phase after the transfer, such as verifying
+    /// the copy: its bar reads `label`, and its bytes stay out of the
+    /// `--bandwidth-report`, which measures the transfer alone
+    pub fn labeled(total_bytes: u64, mode: ProgressMode, label: &str) -> Self {
+        let tracker = Self::with_mode(total_bytes, mode);
+        {
+            let mut inner = tracker.inner.lock().unwrap();
+            inner.label = Some(label.to_string());
+            inner.bandwidth = None;
+        }
+        tracker
+    }
+
+    /// Create a tracker that writes JSON progress events to `sink`
+    pub fn with_json_sink(total_bytes: u64, sink: Box<dyn Write + Send>, interval: Duration) -> Self {
+        let emitter = JsonEmitter {
+            sink,
+            interval,
+            last_emit: None,
+        };
+        Self::build(Some(total_bytes), None, Some(emitter))
+    }
+
+    /// A bar (a spinner without a total) drawn on `target`
+    fn progress_bar(total_bytes: Option<u64>, target: ProgressDrawTarget) -> ProgressBar {
+        let pb = ProgressBar::with_draw_target(total_bytes, target);
+        if total_bytes.is_none() {
             pb.set_style(
-                ProgressStyle::default_bar()
-                    .template("{spinner:.green} [{wide_bar:.cyan/blue}] {bytes}/{total_bytes} ({percent}%) | {per_sec} | ⏱ {eta_precise}")
+                ProgressStyle::default_spinner()
+                    .template("{spinner:.green} {bytes} | {bytes_per_sec} | ⏱ {elapsed_precise}")
                     .expect("Template valid")
-                    .progress_chars("████░░░░")
             );
-            Some(pb)
-        } else {
-            None
-        };
+            return pb;
+        }
+        pb.set_style(bar_style());
+        pb
+    }
 
+    fn build(total_bytes: Option<u64>, progress_bar: Option<ProgressBar>, json_events: Option<JsonEmitter>) -> Self {
         Self {
             inner: Arc::new(Mutex::new(ProgressTrackerInner {
                 total_bytes,
                 transferred_bytes: 0,
                 start_time: Instant::now(),
                 progress_bar,
+                json_events,
+                speed: SpeedEstimator::default(),
+                draw_interval: configured_interval().unwrap_or(DEFAULT_DRAW_INTERVAL),
+                last_draw: None,
+                completed_reason: None,
+                label: None,
+                bandwidth: BANDWIDTH_SAMPLES
+                    .lock()
+                    .unwrap()
+                    .is_some()
+                    .then(|| BandwidthSampler::new(Instant::now(), BANDWIDTH_SAMPLE_INTERVAL)),
+                #[cfg(test)]
+                draws: 0,
             })),
         }
     }
@@ -44,40 +537,88 @@ impl ProgressTracker {
     pub fn add_bytes(&self, bytes: u64) {
         let mut inner = self.inner.lock().unwrap();
         inner.transferred_bytes += bytes;
-        if let Some(ref pb) = inner.progress_bar {
-            pb.set_position(inner.transferred_bytes);
+        let transferred = inner.transferred_bytes;
+        let now = Instant::now();
+        inner.speed.record(now, transferred);
+        if let Some(ref mut sampler) = inner.bandwidth {
+            sampler.record(now, bytes);
+        }
+        inner.draw(false);
+        let event = inner.progress_event();
+        if let Some(ref mut emitter) = inner.json_events {
+            emitter.emit(&event, false);
         }
     }
 
+    /// Count bytes the target holds without their having been read or
+    /// written, such as cloned blocks or skipped holes. The bar jumps ahead
+    /// at once, but the bytes don't feed the speed and ETA estimate.
+    pub fn add_logical_bytes(&self, bytes: u64) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.transferred_bytes += bytes;
+        inner.draw(true);
+        let event = inner.progress_event();
+        if let Some(ref mut emitter) = inner.json_events {
+            emitter.emit(&event, false);
+        }
+    }
+
+    /// Move to `bytes` done in all, for work that reports its running total
+    /// rather than each step, such as `compute_checksum`
+    pub fn advance_to(&self, bytes: u64) {
+        let done = self.inner.lock().unwrap().transferred_bytes;
+        self.add_bytes(bytes.saturating_sub(done));
+    }
+
+    /// Say why the copy was quicker than its size suggests, e.g. `reflink`;
+    /// the finished bar then reads "completed (reflink)"
+    pub fn set_completed_reason(&self, reason: impl Into<String>) {
+        self.inner.lock().unwrap().completed_reason = Some(reason.into());
+    }
+
     pub fn finish(&self) {
-        let inner = self.inner.lock().unwrap();
+        let mut inner = self.inner.lock().unwrap();
+        inner.draw(true);
         if let Some(ref pb) = inner.progress_bar {
-            pb.finish_with_message("completed");
+            match (&inner.label, &inner.completed_reason) {
+                (Some(label), _) => pb.finish_with_message(format!("{}: completed", label)),
+                (None, Some(reason)) => pb.finish_with_message(format!("completed ({})", reason)),
+                (None, None) => pb.finish_with_message("completed"),
+            }
         }
+        let event = inner.progress_event();
+        if let Some(ref mut emitter) = inner.json_events {
+            emitter.emit(&event, true);
+        }
+        if let Some(sampler) = inner.bandwidth.take() {
+            let samples = sampler.finish(Instant::now());
+            if let Some(all) = BANDWIDTH_SAMPLES.lock().unwrap().as_mut() {
+                all.extend(samples);
+            }
+        }
+    }
+
+    /// Whether this tracker draws a progress bar
+    pub fn has_progress_bar(&self) -> bool {
+        self.inner.lock().unwrap().progress_bar.is_some()
     }
 
     pub fn get_stats(&self) -> TransferStats {
         let inner = self.inner.lock().unwrap();
         let elapsed = inner.start_time.elapsed();
-        let speed = if elapsed.as_secs() > 0 {
-            inner.transferred_bytes as f64 / elapsed.as_secs_f64()
-        } else {
-            0.0
-        };
-
-        let remaining = inner.total_bytes.saturating_sub(inner.transferred_bytes);
-        let eta = if speed > 0.0 {
-            Duration::from_secs_f64(remaining as f64 / speed)
-        } else {
-            Duration::ZERO
-        };
 
         TransferStats {
             transferred_bytes: inner.transferred_bytes,
-            total_bytes: inner.total_bytes,
+            total_bytes: inner.total(),
             elapsed,
-            speed_bps: speed as u64,
-            eta,
+            speed_bps: inner.average_speed() as u64,
+            smoothed_speed_bps: inner.smoothed_speed() as u64,
+            eta: inner.eta(),
+            files_skipped: 0,
+            files_deleted: 0,
+            files_failed: 0,
+            files_touched: 0,
+            files_linked: 0,
         }
     }
 }
@@ -87,11 +628,62 @@ pub struct TransferStats {
     pub transferred_bytes: u64,
     pub total_bytes: u64,
     pub elapsed: Duration,
+    /// Average speed since the start
     pub speed_bps: u64,
+    /// Recent speed, smoothed over bursts; what `eta` is based on
+    pub smoothed_speed_bps: u64,
     pub eta: Duration,
+    /// Existing targets left untouched (no-clobber)
+    pub files_skipped: usize,
+    /// Extraneous target entries removed by `--delete`
+    pub files_deleted: usize,
+    /// Files that failed to copy, warned about and passed over (`--ignore-errors`)
+    pub files_failed: usize,
+    /// Identical targets that only had their timestamps updated (`--touch-only`)
+    pub files_touched: usize,
+    /// Files hard-linked to an identical file copied earlier in the run
+    /// (`--dedup`), or source symlinks recreated as links
+    /// (`--no-dereference-args`)
+    pub files_linked: usize,
 }
 
 impl TransferStats {
+    /// Combine the stats of several operations into one, measuring speed
+    /// against the wall-clock time of the whole run.
+    pub fn aggregate(stats: &[TransferStats], elapsed: Duration) -> Self {
+        let transferred_bytes = stats.iter().map(|s| s.transferred_bytes).sum();
+        let total_bytes = stats.iter().map(|s| s.total_bytes).sum();
+        let files_skipped = stats.iter().map(|s| s.files_skipped).sum();
+        let files_deleted = stats.iter().map(|s| s.files_deleted).sum();
+        let files_failed = stats.iter().map(|s| s.files_failed).sum();
+        let files_touched = stats.iter().map(|s| s.files_touched).sum();
+        let files_linked = stats.iter().map(|s| s.files_linked).sum();
+        let speed = if elapsed.as_secs_f64() > 0.0 {
+            transferred_bytes as f64 / elapsed.as_secs_f64()
+        } else {
+            0.0
+        };
+
+        TransferStats {
+            transferred_bytes,
+            total_bytes,
+            elapsed,
+            speed_bps: speed as u64,
+            smoothed_speed_bps: speed as u64,
+            eta: Duration::ZERO,
+            files_skipped,
+            files_deleted,
+            files_failed,
+            files_touched,
+            files_linked,
+        }
+    }
+
+    /// Average speed in MB/s
+    pub fn speed_mbps(&self) -> f64 {
+        self.speed_bps as f64 / (1024.0 * 1024.0)
+    }
+
     pub fn percent_complete(&self) -> u8 {
         if self.total_bytes == 0 {
             0
@@ -112,3 +704,227 @@ impl TransferStats {
         format_size(self.total_bytes, humansize::BINARY)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_no_progress_bar_when_disabled() {
+        let tracker = ProgressTracker::new(1024, false);
+        assert!(!tracker.has_progress_bar());
+
+        tracker.add_bytes(512);
+        assert_eq!(tracker.get_stats().transferred_bytes, 512);
+    }
+
+    #[test]
+    fn test_aggregate_stats() {
+        let first = ProgressTracker::new(1000, false);
+        first.add_bytes(1000);
+        let second = ProgressTracker::new(500, false);
+        second.add_bytes(500);
+
+        let total = TransferStats::aggregate(
+            &[first.get_stats(), second.get_stats()],
+            Duration::from_millis(250),
+        );
+        assert_eq!(total.transferred_bytes, 1500);
+        assert_eq!(total.total_bytes, 1500);
+        assert!(total.speed_bps > 0);
+        assert_eq!(total.speed_bps, 6000);
+    }
+
+    #[test]
+    fn test_unbounded_tracker_counts_bytes() {
+        let tracker = ProgressTracker::unbounded(ProgressMode::Hidden);
+        tracker.add_bytes(4096);
+        tracker.add_bytes(1000);
+        tracker.finish();
+
+        let stats = tracker.get_stats();
+        assert_eq!(stats.transferred_bytes, 5096);
+        // The total is whatever has been seen, so there is nothing left to wait for
+        assert_eq!(stats.total_bytes, 5096);
+        assert_eq!(stats.eta, Duration::ZERO);
+    }
+
+    #[test]
+    fn test_invalid_progress_template_falls_back() {
+        assert!(!set_progress_template("{bytes:?}"));
+        assert!(BAR_TEMPLATE.lock().unwrap().is_none());
+
+        // The bar still draws with the default layout
+        let tracker = ProgressTracker::with_mode(1000, ProgressMode::Bar);
+        tracker.add_bytes(500);
+        tracker.finish();
+        assert_eq!(tracker.get_stats().transferred_bytes, 500);
+
+        for preset in ["bars", "minimal", "detailed"] {
+            let template = preset_template(preset).unwrap();
+            assert!(ProgressStyle::default_bar().template(template).is_ok(), "{}", preset);
+        }
+        assert!(preset_template("json").is_none());
+    }
+
+    #[test]
+    fn test_rapid_updates_redraw_once_per_interval() {
+        let tracker = ProgressTracker::build(Some(10_000), Some(ProgressBar::hidden()), None);
+        tracker.inner.lock().unwrap().draw_interval = Duration::from_secs(60);
+
+        for _ in 0..100 {
+            tracker.add_bytes(100);
+        }
+        assert_eq!(tracker.inner.lock().unwrap().draws, 1);
+
+        // The final position is always drawn
+        tracker.finish();
+        let inner = tracker.inner.lock().unwrap();
+        assert_eq!(inner.draws, 2);
+        assert_eq!(inner.progress_bar.as_ref().unwrap().position(), 10_000);
+    }
+
+    /// Shared in-memory sink so tests can read emitted events
+    #[derive(Clone, Default)]
+    struct SharedSink(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedSink {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_json_events_stream() {
+        let sink = SharedSink::default();
+        let tracker = ProgressTracker::with_json_sink(
+            300,
+            Box::new(sink.clone()),
+            Duration::from_secs(60),
+        );
+        tracker.add_bytes(100);
+        tracker.add_bytes(100); // within the interval: not emitted
+        tracker.add_bytes(100);
+        tracker.finish();
+
+        let done = ProgressEvent::done(&tracker.get_stats());
+        writeln!(sink.clone(), "{}", done.to_json_line()).unwrap();
+
+        let output = String::from_utf8(sink.0.lock().unwrap().clone()).unwrap();
+        let events: Vec<serde_json::Value> = output
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+
+        assert_eq!(events.len(), 3);
+        assert_eq!(events[0]["event"], "progress");
+        assert_eq!(events[0]["bytes"], 100);
+        assert_eq!(events[1]["bytes"], 300);
+        let last = events.last().unwrap();
+        assert_eq!(last["event"], "done");
+        assert_eq!(last["bytes"], 300);
+        assert_eq!(last["total"], 300);
+    }
+
+    #[test]
+    fn test_bar_output_stays_off_the_event_stream() {
+        assert_eq!(progress_target(), ProgressTarget::Stderr);
+
+        // A bar drawing alongside JSON events: only events reach the sink
+        let sink = SharedSink::default();
+        let emitter = JsonEmitter {
+            sink: Box::new(sink.clone()),
+            interval: Duration::ZERO,
+            last_emit: None,
+        };
+        let bar = ProgressTracker::progress_bar(Some(300), ProgressDrawTarget::hidden());
+        let tracker = ProgressTracker::build(Some(300), Some(bar), Some(emitter));
+        for _ in 0..3 {
+            tracker.add_bytes(100);
+        }
+        tracker.finish();
+
+        let output = String::from_utf8(sink.0.lock().unwrap().clone()).unwrap();
+        assert!(!output.is_empty());
+        for line in output.lines() {
+            let event: serde_json::Value = serde_json::from_str(line).expect("only JSON on the stream");
+            assert_eq!(event["event"], "progress");
+        }
+    }
+
+    #[test]
+    fn test_logical_bytes_complete_an_instant_copy() {
+        // What a reflinked copy reports: no bytes read, all of them in place
+        let bar = ProgressTracker::progress_bar(Some(4096), ProgressDrawTarget::hidden());
+        let tracker = ProgressTracker::build(Some(4096), Some(bar.clone()), None);
+        tracker.add_logical_bytes(4096);
+        tracker.set_completed_reason("reflink");
+        tracker.finish();
+
+        let stats = tracker.get_stats();
+        assert_eq!(stats.percent_complete(), 100);
+        assert_eq!(bar.position(), 4096);
+        assert_eq!(bar.message(), "completed (reflink)");
+        assert!(tracker.inner.lock().unwrap().speed.speed_bps().is_none());
+    }
+
+    #[test]
+    fn test_smoothed_speed_rides_out_a_burst() {
+        let start = Instant::now();
+        let at = |millis: u64| start + Duration::from_millis(millis);
+        let mut speed = SpeedEstimator::default();
+
+        // Steady 1000 B/s: 100 bytes every 100ms
+        for i in 0..=10 {
+            speed.record(at(i * 100), i * 100);
+        }
+        let steady = speed.speed_bps().unwrap();
+        assert!((steady - 1000.0).abs() < 1.0, "steady speed {}", steady);
+
+        // One 100ms burst of 10 KB (100 KB/s instantaneous)
+        speed.record(at(1100), 1000 + 10_000);
+        let after_burst = speed.speed_bps().unwrap();
+        // Window: 10,600 bytes over 700ms ≈ 15,143 B/s; EWMA ≈ 0.3 × that + 0.7 × 1000
+        assert!((5000.0..5500.0).contains(&after_burst), "after burst {}", after_burst);
+
+        // Back to steady: the estimate decays toward 1000 B/s again
+        for i in 12..=50 {
+            speed.record(at(i * 100), 11_000 + (i - 11) * 100);
+        }
+        let settled = speed.speed_bps().unwrap();
+        assert!((1000.0..1100.0).contains(&settled), "settled {}", settled);
+    }
+
+    #[test]
+    fn test_bandwidth_samples_cover_fixed_stretches() {
+        let start = Instant::now();
+        let at = |millis: u64| start + Duration::from_millis(millis);
+        let mut sampler = BandwidthSampler::new(start, Duration::from_millis(250));
+
+        // 1 KB every 50ms for half a second: two stretches at 20,000 B/s
+        for i in 1..=10 {
+            sampler.record(at(i * 50), 1000);
+        }
+        // Then a stall: 1 KB over the next second
+        sampler.record(at(1500), 1000);
+        // And a tail too short to count
+        sampler.record(at(1550), 1000);
+
+        assert_eq!(sampler.finish(at(1550)), vec![20_000.0, 20_000.0, 1_000.0]);
+    }
+
+    #[test]
+    fn test_format_eta() {
+        assert_eq!(format_eta(Duration::from_secs(3 * 3600 + 4 * 60 + 15)), "03:04:15");
+    }
+
+    #[test]
+    fn test_progress_enabled_respects_flag() {
+        assert!(!progress_enabled(true));
+    }
+}
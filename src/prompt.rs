@@ -1,10 +1,27 @@
-use console::style;
+use crate::color::style;
 use std::fs::Metadata;
 use std::path::Path;
 #[allow(unused_imports)]
 use std::io::{self, Write};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime};
+use crate::cli::TimeStyle;
 use crate::error::Result;
-use chrono::{DateTime, Local};
+use chrono::{DateTime, Local, SecondsFormat, Utc};
+
+/// How modification times are shown, from `--time-style`
+static TIME_STYLE: Mutex<TimeStyle> = Mutex::new(TimeStyle::Local);
+
+/// Show modification times in prompts and previews in `time_style`
+/// (`--time-style`)
+pub fn set_time_style(time_style: TimeStyle) {
+    *TIME_STYLE.lock().unwrap() = time_style;
+}
+
+/// The `--time-style` modification times are shown in
+pub fn time_style() -> TimeStyle {
+    *TIME_STYLE.lock().unwrap()
+}
 
 /// User prompt for overwrite confirmation
 #[derive(Debug, Clone, Copy)]
@@ -44,8 +61,9 @@ pub fn prompt_overwrite(
         print!("{} {}: ", style("Your choice").cyan(), options);
         io::stdout().flush().ok();
         
+        // End of input or a read error: nobody is left to answer
         let mut input = String::new();
-        if io::stdin().read_line(&mut input).is_err() {
+        if !matches!(io::stdin().read_line(&mut input), Ok(n) if n > 0) {
             return Ok(OverwriteChoice::Abort);
         }
         
@@ -92,7 +110,7 @@ pub fn prompt_resume(
         io::stdout().flush().ok();
         
         let mut input = String::new();
-        if io::stdin().read_line(&mut input).is_err() {
+        if !matches!(io::stdin().read_line(&mut input), Ok(n) if n > 0) {
             return Ok(false);
         }
         
@@ -122,7 +140,7 @@ pub fn confirm_action(message: &str) -> Result<bool> {
         io::stdout().flush().ok();
         
         let mut input = String::new();
-        if io::stdin().read_line(&mut input).is_err() {
+        if !matches!(io::stdin().read_line(&mut input), Ok(n) if n > 0) {
             return Ok(false);
         }
         
@@ -140,42 +158,66 @@ pub fn confirm_action(message: &str) -> Result<bool> {
 fn format_file_details(label: &str, metadata: &Metadata) -> String {
     let size = humansize::format_size(metadata.len(), humansize::BINARY);
     
-    let modified = metadata
-        .modified()
-        .ok()
-        .and_then(|t| {
-            let duration = t
-                .duration_since(std::time::UNIX_EPOCH)
-                .ok()?;
-            let datetime = DateTime::<Local>::from(
-                std::time::SystemTime::UNIX_EPOCH + duration
-            );
-            Some(datetime.format("%Y-%m-%d %H:%M:%S").to_string())
-        })
-        .unwrap_or_else(|| "unknown".to_string());
-    
     format!(
         "  {}: size {}, modified {}",
         style(label).bold(),
         size,
-        modified
+        modified_time(metadata)
     )
 }
 
+/// When `metadata`'s file was last modified, in the `--time-style`
+pub fn modified_time(metadata: &Metadata) -> String {
+    let time_style = time_style();
+    metadata
+        .modified()
+        .map(|time| format_time(time, time_style, SystemTime::now()))
+        .unwrap_or_else(|_| "unknown".to_string())
+}
+
+/// Show `time` in `time_style`; a relative time is measured back from `now`
+pub fn format_time(time: SystemTime, time_style: TimeStyle, now: SystemTime) -> String {
+    match time_style {
+        TimeStyle::Local => DateTime::<Local>::from(time).format("%Y-%m-%d %H:%M:%S").to_string(),
+        TimeStyle::Iso => DateTime::<Local>::from(time).to_rfc3339_opts(SecondsFormat::Secs, false),
+        TimeStyle::Unix => DateTime::<Utc>::from(time).timestamp().to_string(),
+        TimeStyle::Relative => match now.duration_since(time) {
+            Ok(age) => format!("{} ago", format_age(age)),
+            Err(e) => format!("in {}", format_age(e.duration())),
+        },
+    }
+}
+
+/// `age` in the largest whole unit, up to days
+pub fn format_age(age: Duration) -> String {
+    let secs = age.as_secs();
+    let (count, unit) = match secs {
+        0..=59 => (secs, "second"),
+        60..=3599 => (secs / 60, "minute"),
+        3600..=86_399 => (secs / 3600, "hour"),
+        _ => (secs / 86_400, "day"),
+    };
+    format!("{} {}{}", count, unit, if count == 1 { "" } else { "s" })
+}
+
 /// Show dry-run preview without executing
 pub fn preview_operation(
     source: &Path,
     target: &Path,
-    total_size: u64,
-    target_exists: bool,
+    src_metadata: &Metadata,
+    tgt_metadata: Option<&Metadata>,
 ) {
     println!("\n{}", style("📋 Dry Run Preview").cyan().bold());
-    println!("  Source: {}", source.display());
+    println!("  Source: {} (modified {})", source.display(), modified_time(src_metadata));
     println!("  Target: {}", target.display());
-    println!("  Size: {}", humansize::format_size(total_size, humansize::BINARY));
+    println!("  Size: {}", humansize::format_size(src_metadata.len(), humansize::BINARY));
     
-    if target_exists {
-        println!("  Action: {} (file already exists)", style("overwrite").red());
+    if let Some(tgt_metadata) = tgt_metadata {
+        println!(
+            "  Action: {} (file already exists, modified {})",
+            style("overwrite").red(),
+            modified_time(tgt_metadata)
+        );
     } else {
         println!("  Action: {} (new file)", style("copy").green());
     }
@@ -201,4 +243,28 @@ mod tests {
         assert!(result.contains("Test"));
         assert!(result.contains("modified"));
     }
+
+    #[test]
+    fn test_time_styles() {
+        use std::time::Duration;
+
+        let time = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let now = time + Duration::from_secs(3 * 86_400 + 7200);
+
+        assert_eq!(format_time(time, TimeStyle::Unix, now), "1700000000");
+        assert_eq!(format_time(time, TimeStyle::Relative, now), "3 days ago");
+        assert_eq!(format_time(now, TimeStyle::Relative, time), "in 3 days");
+        assert_eq!(
+            format_time(time, TimeStyle::Relative, time + Duration::from_secs(60)),
+            "1 minute ago"
+        );
+
+        let iso = format_time(time, TimeStyle::Iso, now);
+        let parsed = DateTime::parse_from_rfc3339(&iso).unwrap();
+        assert_eq!(parsed.timestamp(), 1_700_000_000);
+
+        let local = format_time(time, TimeStyle::Local, now);
+        let parsed = chrono::NaiveDateTime::parse_from_str(&local, "%Y-%m-%d %H:%M:%S").unwrap();
+        assert_eq!(parsed, DateTime::<Local>::from(time).naive_local());
+    }
 }
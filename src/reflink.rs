@@ -32,9 +32,7 @@ fn reflink_file(source: &Path, target: &Path) -> Result<()> {
         if result == 0 {
             Ok(())
         } else {
-            Err(Error::Custom(
-                "Reflink not supported on this filesystem".to_string(),
-            ))
+            Err(reflink_error(target, std::io::Error::last_os_error()))
         }
     }
 }
@@ -55,9 +53,7 @@ fn reflink_file(source: &Path, target: &Path) -> Result<()> {
         if result == 0 {
             Ok(())
         } else {
-            Err(Error::Custom(
-                "Reflink not supported on this filesystem".to_string(),
-            ))
+            Err(reflink_error(target, std::io::Error::last_os_error()))
         }
     }
 }
@@ -69,14 +65,83 @@ fn reflink_file(_source: &Path, _target: &Path) -> Result<()> {
     ))
 }
 
-/// Detect if source and target are on the same filesystem (for optimization)
+/// Turn a failed clone into `Error::ReflinkUnsupported` naming the target's
+/// filesystem when the filesystem (or the pair of them) can't clone, and a
+/// plain I/O error otherwise
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+fn reflink_error(target: &Path, err: std::io::Error) -> Error {
+    let unsupported = [libc::EOPNOTSUPP, libc::ENOTSUP, libc::EXDEV, libc::EINVAL, libc::ENOTTY];
+    match err.raw_os_error() {
+        Some(code) if unsupported.contains(&code) => Error::ReflinkUnsupported {
+            fs_hint: filesystem_type(target),
+        },
+        _ => Error::Io(err),
+    }
+}
+
+/// Name of the filesystem `path` lives on, such as `ext4` or `btrfs`, when
+/// it can be told
+#[cfg(target_os = "linux")]
+pub fn filesystem_type(path: &Path) -> Option<String> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    let c_path = CString::new(path.as_os_str().as_bytes()).ok()?;
+    let mut stat: libc::statfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statfs(c_path.as_ptr(), &mut stat) } != 0 {
+        return None;
+    }
+
+    // Superblock magic numbers from linux/magic.h
+    let name = match stat.f_type as u32 {
+        0xEF53 => "ext4",
+        0x9123_683E => "btrfs",
+        0x5846_5342 => "xfs",
+        0x2FC1_2FC1 => "zfs",
+        0xCA45_1A4E => "bcachefs",
+        0xF2F5_2010 => "f2fs",
+        0x4D44 => "FAT",
+        0x2011_BAB0 => "exFAT",
+        0x5346_544E => "NTFS",
+        0x0102_1994 => "tmpfs",
+        0x794C_7630 => "overlayfs",
+        0x6969 => "NFS",
+        0xFF53_4D42 | 0xFE53_4D42 => "SMB",
+        0x6573_5546 => "FUSE",
+        _ => return None,
+    };
+    Some(name.to_string())
+}
+
+#[cfg(target_os = "macos")]
+pub fn filesystem_type(path: &Path) -> Option<String> {
+    use std::ffi::{CStr, CString};
+    use std::os::unix::ffi::OsStrExt;
+
+    let c_path = CString::new(path.as_os_str().as_bytes()).ok()?;
+    let mut stat: libc::statfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statfs(c_path.as_ptr(), &mut stat) } != 0 {
+        return None;
+    }
+    let name = unsafe { CStr::from_ptr(stat.f_fstypename.as_ptr()) };
+    Some(name.to_string_lossy().into_owned())
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+pub fn filesystem_type(_path: &Path) -> Option<String> {
+    None
+}
+
+/// Detect if source and target are on the same filesystem (for optimization).
+/// The target's parent may not exist yet (it is created later), in which case
+/// the nearest ancestor that does decides.
 pub fn same_filesystem(source: &Path, target: &Path) -> Result<bool> {
     let src_metadata = fs::metadata(source).map_err(Error::Io)?;
-    let tgt_metadata = fs::metadata(
+    let tgt_metadata = nearest_existing_dir(
         target
             .parent()
             .ok_or_else(|| Error::Custom("Invalid target path".to_string()))?,
-    ).map_err(Error::Io)?;
+    )?;
 
     #[cfg(unix)]
     {
@@ -91,6 +156,20 @@ pub fn same_filesystem(source: &Path, target: &Path) -> Result<bool> {
     }
 }
 
+/// Metadata of `dir`, or of its closest ancestor if it doesn't exist. An
+/// empty (relative) path stands for the current directory.
+fn nearest_existing_dir(dir: &Path) -> Result<fs::Metadata> {
+    for ancestor in dir.ancestors() {
+        let ancestor = if ancestor.as_os_str().is_empty() { Path::new(".") } else { ancestor };
+        match fs::metadata(ancestor) {
+            Ok(metadata) => return Ok(metadata),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(Error::Io(e)),
+        }
+    }
+    Err(Error::Io(std::io::ErrorKind::NotFound.into()))
+}
+
 /// Detect sparse files (files with holes)
 pub fn is_sparse_file(path: &Path) -> Result<bool> {
     let metadata = fs::metadata(path).map_err(Error::Io)?;
@@ -152,6 +231,69 @@ pub fn find_zero_regions(path: &Path, chunk_size: usize) -> Result<Vec<(u64, u64
     Ok(zero_regions)
 }
 
+/// Data extents `(offset, length)` of a file as the filesystem records them,
+/// found with `SEEK_DATA`/`SEEK_HOLE` instead of reading the file. Whatever
+/// lies between extents is a hole. Filesystems without hole tracking report
+/// the whole file as one extent.
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+pub fn map_extents(path: &Path) -> Result<Vec<(u64, u64)>> {
+    use std::fs::File;
+    use std::os::unix::io::AsRawFd;
+
+    let file = File::open(path).map_err(Error::Io)?;
+    let file_size = file.metadata().map_err(Error::Io)?.len();
+    let fd = file.as_raw_fd();
+
+    let mut extents = Vec::new();
+    let mut offset = 0u64;
+    while offset < file_size {
+        let data = unsafe { libc::lseek(fd, offset as libc::off_t, libc::SEEK_DATA) };
+        if data < 0 {
+            let err = std::io::Error::last_os_error();
+            // ENXIO: nothing but hole from here to the end
+            if err.raw_os_error() == Some(libc::ENXIO) {
+                break;
+            }
+            return Err(Error::Io(err));
+        }
+        let hole = unsafe { libc::lseek(fd, data, libc::SEEK_HOLE) };
+        if hole < 0 {
+            return Err(Error::Io(std::io::Error::last_os_error()));
+        }
+        extents.push((data as u64, (hole - data) as u64));
+        offset = hole as u64;
+    }
+
+    Ok(extents)
+}
+
+/// Data extents of a file: the kernel's hole map where it can be asked for
+/// one, otherwise the gaps between the zero regions a scan finds
+pub fn data_extents(path: &Path, chunk_size: usize) -> Result<Vec<(u64, u64)>> {
+    #[cfg(any(target_os = "linux", target_os = "macos"))]
+    {
+        let _ = chunk_size;
+        map_extents(path)
+    }
+
+    #[cfg(not(any(target_os = "linux", target_os = "macos")))]
+    {
+        let file_size = fs::metadata(path).map_err(Error::Io)?.len();
+        let mut extents = Vec::new();
+        let mut offset = 0u64;
+        for (start, len) in find_zero_regions(path, chunk_size)? {
+            if start > offset {
+                extents.push((offset, start - offset));
+            }
+            offset = start + len;
+        }
+        if offset < file_size {
+            extents.push((offset, file_size - offset));
+        }
+        Ok(extents)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -174,6 +316,18 @@ mod tests {
         assert!(same_filesystem(&src_path, &tgt_path).unwrap());
     }
 
+    #[test]
+    fn test_same_filesystem_before_target_parent_exists() {
+        let temp_dir = TempDir::new().unwrap();
+        let src_path = temp_dir.path().join("source.txt");
+        fs::write(&src_path, b"content").unwrap();
+
+        // Neither parent nor grandparent exists yet; the temp dir decides
+        let tgt_path = temp_dir.path().join("not/yet/target.txt");
+        assert!(same_filesystem(&src_path, &tgt_path).unwrap());
+        assert!(!temp_dir.path().join("not").exists());
+    }
+
     #[test]
     fn test_sparse_file_detection() {
         let temp_dir = TempDir::new().unwrap();
@@ -206,4 +360,46 @@ mod tests {
         assert!(regions.iter().any(|(start, len)| *start == 2048 && *len == 2048),
                 "Expected zero region at offset 2048 with length 2048, got: {:?}", regions);
     }
+
+    #[cfg(any(target_os = "linux", target_os = "macos"))]
+    #[test]
+    fn test_map_extents_finds_holes() {
+        use std::io::{Seek, SeekFrom};
+
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("sparse.bin");
+
+        // 4 MiB with data only in the first and third MiB
+        let mut file = File::create(&path).unwrap();
+        file.set_len(4 << 20).unwrap();
+        file.write_all(&vec![7u8; 1 << 20]).unwrap();
+        file.seek(SeekFrom::Start(2 << 20)).unwrap();
+        file.write_all(&vec![9u8; 1 << 20]).unwrap();
+        file.sync_all().unwrap();
+        drop(file);
+
+        let extents = map_extents(&path).unwrap();
+        if !is_sparse_file(&path).unwrap() {
+            // No hole support here: everything is data
+            assert_eq!(extents, vec![(0, 4 << 20)]);
+            return;
+        }
+        assert_eq!(extents, vec![(0, 1 << 20), (2 << 20, 1 << 20)]);
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "macos"))]
+    #[test]
+    fn test_unsupported_clone_explains_the_filesystem() {
+        let temp_dir = TempDir::new().unwrap();
+        let target = temp_dir.path().join("target.txt");
+
+        // Whatever the temp dir's filesystem, these are the errors of one that can't clone
+        for code in [libc::EOPNOTSUPP, libc::EXDEV, libc::EINVAL] {
+            let err = reflink_error(&target, std::io::Error::from_raw_os_error(code));
+            assert!(matches!(err, Error::ReflinkUnsupported { .. }), "{:?}", err);
+            assert!(err.detailed_message().contains("--reflink=auto"));
+        }
+        let err = reflink_error(&target, std::io::Error::from_raw_os_error(libc::EIO));
+        assert!(matches!(err, Error::Io(_)));
+    }
 }
@@ -0,0 +1,97 @@
+use std::fs;
+use std::path::Path;
+use crate::error::{Error, Result};
+
+/// Whether `--trash` can send files to a trash on this platform
+pub const TRASH_SUPPORTED: bool = cfg!(any(
+    windows,
+    target_os = "macos",
+    target_os = "linux",
+    target_os = "freebsd"
+));
+
+/// Delete `path` (a file, link or whole directory), or with `trash` send it
+/// to the platform trash so it can still be restored
+pub fn remove_path(path: &Path, trash: bool) -> Result<()> {
+    if trash {
+        return move_to_trash(path);
+    }
+    let metadata = fs::symlink_metadata(path).map_err(Error::Io)?;
+    if metadata.is_dir() {
+        fs::remove_dir_all(path).map_err(Error::Io)
+    } else {
+        fs::remove_file(path).map_err(Error::Io)
+    }
+}
+
+/// Fail up front when `--trash` was asked for but has nowhere to go
+pub fn check_trash_supported() -> Result<()> {
+    if TRASH_SUPPORTED {
+        Ok(())
+    } else {
+        Err(Error::Usage(
+            "--trash is not supported on this platform; drop it to delete files outright".to_string(),
+        ))
+    }
+}
+
+#[cfg(any(windows, target_os = "macos", target_os = "linux", target_os = "freebsd"))]
+fn move_to_trash(path: &Path) -> Result<()> {
+    ::trash::delete(path).map_err(|e| {
+        Error::Custom(format!("Failed to move {} to the trash: {}", path.display(), e))
+    })
+}
+
+#[cfg(not(any(windows, target_os = "macos", target_os = "linux", target_os = "freebsd")))]
+fn move_to_trash(_path: &Path) -> Result<()> {
+    check_trash_supported()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_trashed_file_can_be_restored() {
+        let temp_dir = TempDir::new().unwrap();
+        let file = temp_dir.path().join("trash-me.txt");
+        fs::write(&file, b"restorable").unwrap();
+        let original = fs::canonicalize(&file).unwrap();
+
+        remove_path(&file, true).unwrap();
+        assert!(!file.exists());
+
+        let items: Vec<_> = ::trash::os_limited::list()
+            .unwrap()
+            .into_iter()
+            .filter(|item| item.original_path() == original)
+            .collect();
+        assert_eq!(items.len(), 1);
+        // Don't leave the test file behind in the user's trash
+        ::trash::os_limited::purge_all(items).unwrap();
+    }
+
+    #[cfg(not(any(windows, target_os = "macos", target_os = "linux", target_os = "freebsd")))]
+    #[test]
+    fn test_trash_unsupported_is_a_usage_error() {
+        let temp_dir = TempDir::new().unwrap();
+        let file = temp_dir.path().join("kept.txt");
+        fs::write(&file, b"kept").unwrap();
+
+        assert!(matches!(remove_path(&file, true), Err(Error::Usage(_))));
+        assert!(file.exists());
+    }
+
+    #[test]
+    fn test_remove_path_deletes_directories() {
+        let temp_dir = TempDir::new().unwrap();
+        let dir = temp_dir.path().join("tree");
+        fs::create_dir_all(dir.join("nested")).unwrap();
+        fs::write(dir.join("nested/file.txt"), b"gone").unwrap();
+
+        remove_path(&dir, false).unwrap();
+        assert!(!dir.exists());
+    }
+}
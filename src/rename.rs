@@ -0,0 +1,200 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use crate::copy::{has_trailing_slash, resolve_target};
+use crate::error::{Error, Result};
+
+/// A `--rename` template for the names sources get in the destination
+/// directory, e.g. `backup_{name}.{ext}`.
+///
+/// Placeholders are `{name}` (the file name without its extension), `{ext}`
+/// (the extension, without the dot) and `{index}` (the source's position on
+/// the command line, from 1). `{name:lower}`, `{ext:upper}` and so on change
+/// the case. `{{` and `}}` stand for literal braces.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RenameTemplate {
+    parts: Vec<Part>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Part {
+    Literal(String),
+    Name(Case),
+    Ext(Case),
+    Index,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Case {
+    Keep,
+    Lower,
+    Upper,
+}
+
+impl RenameTemplate {
+    pub fn parse(template: &str) -> Result<Self> {
+        let invalid = |why: &str| Error::Usage(format!("Invalid --rename template '{}': {}", template, why));
+
+        let mut parts = Vec::new();
+        let mut literal = String::new();
+        let mut chars = template.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    literal.push('{');
+                }
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    literal.push('}');
+                }
+                '{' => {
+                    let mut placeholder = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(c) => placeholder.push(c),
+                            None => return Err(invalid("unmatched '{'")),
+                        }
+                    }
+                    let (field, case) = match placeholder.split_once(':') {
+                        Some((field, "lower")) => (field, Case::Lower),
+                        Some((field, "upper")) => (field, Case::Upper),
+                        Some((_, modifier)) => return Err(invalid(&format!("unknown modifier :{}", modifier))),
+                        None => (placeholder.as_str(), Case::Keep),
+                    };
+                    let part = match field {
+                        "name" => Part::Name(case),
+                        "ext" => Part::Ext(case),
+                        "index" if case == Case::Keep => Part::Index,
+                        _ => return Err(invalid(&format!("unknown placeholder {{{}}}", placeholder))),
+                    };
+                    if !literal.is_empty() {
+                        parts.push(Part::Literal(std::mem::take(&mut literal)));
+                    }
+                    parts.push(part);
+                }
+                '}' => return Err(invalid("unmatched '}'")),
+                c if std::path::is_separator(c) => return Err(invalid("names can't contain a path separator")),
+                c => literal.push(c),
+            }
+        }
+        if !literal.is_empty() {
+            parts.push(Part::Literal(literal));
+        }
+        if parts.is_empty() {
+            return Err(invalid("it is empty"));
+        }
+        Ok(Self { parts })
+    }
+
+    /// The name `source`, the `index`th source (from 1), gets. A `.` right
+    /// before `{ext}` is dropped when the source has no extension.
+    pub fn apply(&self, source: &Path, index: usize) -> Result<String> {
+        let name = source.file_stem().map(|n| n.to_string_lossy()).unwrap_or_default();
+        let ext = source.extension().map(|e| e.to_string_lossy()).unwrap_or_default();
+
+        let mut out = String::new();
+        for part in &self.parts {
+            match part {
+                Part::Literal(text) => out.push_str(text),
+                Part::Name(case) => out.push_str(&case.apply(&name)),
+                Part::Ext(_) if ext.is_empty() => {
+                    if out.ends_with('.') {
+                        out.pop();
+                    }
+                }
+                Part::Ext(case) => out.push_str(&case.apply(&ext)),
+                Part::Index => out.push_str(&index.to_string()),
+            }
+        }
+        if out.is_empty() || out == "." || out == ".." {
+            return Err(Error::Usage(format!(
+                "--rename gives {} no usable name",
+                source.display()
+            )));
+        }
+        Ok(out)
+    }
+}
+
+impl Case {
+    fn apply(self, text: &str) -> String {
+        match self {
+            Case::Keep => text.to_string(),
+            Case::Lower => text.to_lowercase(),
+            Case::Upper => text.to_uppercase(),
+        }
+    }
+}
+
+/// Where each of `sources` goes in `destination`, renamed by `template` if
+/// given. Two sources landing on the same path is an error, found before
+/// anything is copied.
+pub fn resolve_targets(
+    sources: &[PathBuf],
+    destination: &Path,
+    template: Option<&RenameTemplate>,
+) -> Result<Vec<PathBuf>> {
+    let Some(template) = template else {
+        return sources.iter().map(|source| resolve_target(source, destination)).collect();
+    };
+    if !destination.is_dir() && !has_trailing_slash(destination) && sources.len() == 1 {
+        return Err(Error::Usage(
+            "--rename names files inside a destination directory; end the destination with '/'".to_string(),
+        ));
+    }
+
+    let mut seen: HashMap<PathBuf, &Path> = HashMap::new();
+    let mut targets = Vec::with_capacity(sources.len());
+    for (i, source) in sources.iter().enumerate() {
+        let target = destination.join(template.apply(source, i + 1)?);
+        if let Some(other) = seen.insert(target.clone(), source) {
+            return Err(Error::Usage(format!(
+                "--rename sends both {} and {} to {}\nTip: Add {{index}} to the template to keep names apart.",
+                other.display(),
+                source.display(),
+                target.display()
+            )));
+        }
+        targets.push(target);
+    }
+    Ok(targets)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_template_placeholders() {
+        let template = RenameTemplate::parse("backup_{name:lower}.{ext}").unwrap();
+        assert_eq!(template.apply(Path::new("dir/Report.pdf"), 1).unwrap(), "backup_report.pdf");
+        // No extension, no trailing dot
+        assert_eq!(template.apply(Path::new("Makefile"), 1).unwrap(), "backup_makefile");
+        assert_eq!(
+            RenameTemplate::parse("{{{index}}}_{name}").unwrap().apply(Path::new("a.txt"), 7).unwrap(),
+            "{7}_a"
+        );
+        assert!(RenameTemplate::parse("{size}").is_err());
+        assert!(RenameTemplate::parse("sub/{name}").is_err());
+        assert!(RenameTemplate::parse("{name").is_err());
+    }
+
+    #[test]
+    fn test_index_keeps_targets_apart() {
+        let temp_dir = TempDir::new().unwrap();
+        let sources = vec![PathBuf::from("a/data.csv"), PathBuf::from("b/data.csv")];
+
+        let template = RenameTemplate::parse("{index}_{name}.{ext}").unwrap();
+        let targets = resolve_targets(&sources, temp_dir.path(), Some(&template)).unwrap();
+        assert_eq!(
+            targets,
+            vec![temp_dir.path().join("1_data.csv"), temp_dir.path().join("2_data.csv")]
+        );
+
+        let template = RenameTemplate::parse("copy_{name}.{ext}").unwrap();
+        let err = resolve_targets(&sources, temp_dir.path(), Some(&template)).unwrap_err();
+        assert!(matches!(err, Error::Usage(ref message) if message.contains("copy_data.csv")));
+    }
+}
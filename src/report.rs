@@ -0,0 +1,286 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use humansize::{format_size, BINARY};
+
+/// Size of every file a run copied, gathered as the copy goes for the
+/// `--stats` report.
+///
+/// Clones share the same list, so one accumulator can be handed to every copier.
+#[derive(Debug, Clone, Default)]
+pub struct RunStats {
+    files: Arc<Mutex<Vec<(PathBuf, u64)>>>,
+}
+
+impl RunStats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record a copied file by its source path and size
+    pub fn record(&self, path: &Path, bytes: u64) {
+        if let Ok(mut files) = self.files.lock() {
+            files.push((path.to_path_buf(), bytes));
+        }
+    }
+
+    /// Summarize everything recorded, for a run that took `elapsed`
+    pub fn report(&self, elapsed: Duration) -> RunReport {
+        let files = self.files.lock().map(|f| f.clone()).unwrap_or_default();
+
+        let mut by_extension: BTreeMap<String, ExtensionTotals> = BTreeMap::new();
+        for (path, bytes) in &files {
+            let extension = path
+                .extension()
+                .map(|ext| ext.to_string_lossy().to_lowercase())
+                .unwrap_or_default();
+            let totals = by_extension.entry(extension).or_default();
+            totals.files += 1;
+            totals.bytes += bytes;
+        }
+        let mut by_extension: Vec<_> = by_extension.into_iter().collect();
+        by_extension.sort_by(|a, b| b.1.bytes.cmp(&a.1.bytes).then_with(|| a.0.cmp(&b.0)));
+
+        let mut sizes: Vec<u64> = files.iter().map(|(_, bytes)| *bytes).collect();
+        sizes.sort_unstable();
+        let total_bytes: u64 = sizes.iter().sum();
+        let median_size = match sizes.len() {
+            0 => 0,
+            n if n % 2 == 1 => sizes[n / 2],
+            n => (sizes[n / 2 - 1] + sizes[n / 2]) / 2,
+        };
+
+        let secs = elapsed.as_secs_f64();
+        RunReport {
+            file_count: files.len(),
+            total_bytes,
+            by_extension,
+            largest: files.into_iter().max_by_key(|(_, bytes)| *bytes),
+            average_size: total_bytes.checked_div(sizes.len() as u64).unwrap_or(0),
+            median_size,
+            elapsed,
+            throughput_bps: if secs > 0.0 { (total_bytes as f64 / secs) as u64 } else { 0 },
+        }
+    }
+}
+
+/// Files and bytes copied with one extension
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ExtensionTotals {
+    pub files: usize,
+    pub bytes: u64,
+}
+
+/// The `--stats` report of a finished run
+#[derive(Debug, Clone)]
+pub struct RunReport {
+    pub file_count: usize,
+    pub total_bytes: u64,
+    /// Totals per lowercased extension (`""` for none), largest first
+    pub by_extension: Vec<(String, ExtensionTotals)>,
+    pub largest: Option<(PathBuf, u64)>,
+    pub average_size: u64,
+    pub median_size: u64,
+    pub elapsed: Duration,
+    pub throughput_bps: u64,
+}
+
+impl fmt::Display for RunReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "📊 Run statistics")?;
+        writeln!(f, "  Files:       {}", self.file_count)?;
+        writeln!(f, "  Total size:  {}", format_size(self.total_bytes, BINARY))?;
+        if let Some((path, bytes)) = &self.largest {
+            writeln!(f, "  Largest:     {} ({})", path.display(), format_size(*bytes, BINARY))?;
+        }
+        writeln!(f, "  Average:     {}", format_size(self.average_size, BINARY))?;
+        writeln!(f, "  Median:      {}", format_size(self.median_size, BINARY))?;
+        writeln!(f, "  Wall time:   {:.2}s", self.elapsed.as_secs_f64())?;
+        writeln!(f, "  Throughput:  {}/s", format_size(self.throughput_bps, BINARY))?;
+        if !self.by_extension.is_empty() {
+            writeln!(f, "  By extension:")?;
+            for (extension, totals) in &self.by_extension {
+                let label = if extension.is_empty() { "(none)".to_string() } else { format!(".{}", extension) };
+                writeln!(
+                    f,
+                    "    {:<10} {:>6} file(s)  {}",
+                    label,
+                    totals.files,
+                    format_size(totals.bytes, BINARY)
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Number of MB/s buckets in the `--bandwidth-report` histogram
+const HISTOGRAM_BUCKETS: usize = 8;
+
+/// Length of the histogram's longest bar
+const HISTOGRAM_WIDTH: usize = 30;
+
+/// The `--bandwidth-report` of a finished run: how throughput was spread
+/// over the samples the transfers took
+#[derive(Debug, Clone)]
+pub struct BandwidthReport {
+    /// Bytes per second, slowest first
+    samples: Vec<f64>,
+}
+
+impl BandwidthReport {
+    pub fn from_samples(mut samples: Vec<f64>) -> Self {
+        samples.sort_by(f64::total_cmp);
+        Self { samples }
+    }
+
+    pub fn sample_count(&self) -> usize {
+        self.samples.len()
+    }
+
+    /// The `percent`th percentile in bytes/s (nearest rank); 0 without samples
+    pub fn percentile(&self, percent: f64) -> f64 {
+        if self.samples.is_empty() {
+            return 0.0;
+        }
+        let rank = (percent / 100.0 * self.samples.len() as f64).ceil() as usize;
+        self.samples[rank.clamp(1, self.samples.len()) - 1]
+    }
+
+    pub fn min(&self) -> f64 {
+        self.samples.first().copied().unwrap_or(0.0)
+    }
+
+    pub fn max(&self) -> f64 {
+        self.samples.last().copied().unwrap_or(0.0)
+    }
+
+    /// Equal-width buckets from the slowest sample to the fastest, as
+    /// (low, high, count) in bytes/s; a single one when all samples agree
+    pub fn histogram(&self) -> Vec<(f64, f64, usize)> {
+        if self.samples.is_empty() {
+            return Vec::new();
+        }
+        let (min, max) = (self.min(), self.max());
+        let width = (max - min) / HISTOGRAM_BUCKETS as f64;
+        if width <= 0.0 {
+            return vec![(min, max, self.samples.len())];
+        }
+        let mut counts = [0; HISTOGRAM_BUCKETS];
+        for sample in &self.samples {
+            let bucket = ((sample - min) / width) as usize;
+            counts[bucket.min(HISTOGRAM_BUCKETS - 1)] += 1;
+        }
+        counts
+            .iter()
+            .enumerate()
+            .map(|(i, &count)| (min + width * i as f64, min + width * (i + 1) as f64, count))
+            .collect()
+    }
+}
+
+/// Bytes per second in MB/s, as the copy summary shows speeds
+fn mbps(bps: f64) -> f64 {
+    bps / (1024.0 * 1024.0)
+}
+
+impl fmt::Display for BandwidthReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "📶 Bandwidth ({} samples)", self.samples.len())?;
+        if self.samples.is_empty() {
+            writeln!(f, "  The copy finished too quickly to sample")?;
+            return Ok(());
+        }
+        let histogram = self.histogram();
+        let tallest = histogram.iter().map(|&(_, _, count)| count).max().unwrap_or(0);
+        for (low, high, count) in histogram {
+            // Any sample at all gets a visible bar
+            let bar = (count * HISTOGRAM_WIDTH).div_ceil(tallest);
+            writeln!(
+                f,
+                "  {:>8.1} - {:<8.1} MB/s │{:<width$} {}",
+                mbps(low),
+                mbps(high),
+                "█".repeat(bar),
+                count,
+                width = HISTOGRAM_WIDTH
+            )?;
+        }
+        writeln!(
+            f,
+            "  p50 {:.1} MB/s  p95 {:.1} MB/s  min {:.1} MB/s  max {:.1} MB/s",
+            mbps(self.percentile(50.0)),
+            mbps(self.percentile(95.0)),
+            mbps(self.min()),
+            mbps(self.max())
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::OverwriteMode;
+    use crate::copy::DirectoryCopier;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_report_totals_and_largest_file() {
+        let temp_dir = TempDir::new().unwrap();
+        let source = temp_dir.path().join("source");
+        fs::create_dir_all(source.join("sub")).unwrap();
+        for (name, len) in [("a.txt", 100), ("b.TXT", 300), ("sub/c.bin", 1_000), ("README", 50)] {
+            fs::write(source.join(name), vec![b'x'; len]).unwrap();
+        }
+
+        let run_stats = RunStats::new();
+        DirectoryCopier::new(source.clone(), temp_dir.path().join("target"), OverwriteMode::Always, true)
+            .with_progress(false)
+            .with_run_stats(Some(run_stats.clone()))
+            .copy()
+            .await
+            .unwrap();
+
+        let report = run_stats.report(Duration::from_secs(2));
+        assert_eq!(report.file_count, 4);
+        assert_eq!(report.total_bytes, 1_450);
+        assert_eq!(report.largest, Some((source.join("sub/c.bin"), 1_000)));
+        assert_eq!(report.average_size, 362);
+        assert_eq!(report.median_size, 200);
+        assert_eq!(report.throughput_bps, 725);
+        assert_eq!(
+            report.by_extension,
+            vec![
+                ("bin".to_string(), ExtensionTotals { files: 1, bytes: 1_000 }),
+                ("txt".to_string(), ExtensionTotals { files: 2, bytes: 400 }),
+                (String::new(), ExtensionTotals { files: 1, bytes: 50 }),
+            ]
+        );
+        assert!(report.to_string().contains("Files:       4"));
+    }
+
+    #[test]
+    fn test_bandwidth_percentiles_and_histogram() {
+        const MB: f64 = 1024.0 * 1024.0;
+        // 100 samples at 1..=100 MB/s, fastest first
+        let report = BandwidthReport::from_samples((1..=100).rev().map(|n| n as f64 * MB).collect());
+
+        assert_eq!(report.percentile(50.0), 50.0 * MB);
+        assert_eq!(report.percentile(95.0), 95.0 * MB);
+        assert_eq!(report.min(), 1.0 * MB);
+        assert_eq!(report.max(), 100.0 * MB);
+
+        let histogram = report.histogram();
+        assert_eq!(histogram.len(), HISTOGRAM_BUCKETS);
+        assert_eq!(histogram.iter().map(|&(_, _, count)| count).sum::<usize>(), 100);
+        let text = report.to_string();
+        assert!(text.contains("p50 50.0 MB/s  p95 95.0 MB/s  min 1.0 MB/s  max 100.0 MB/s"), "{}", text);
+
+        // One steady speed is a single bucket; no samples, no histogram
+        assert_eq!(BandwidthReport::from_samples(vec![MB; 3]).histogram(), vec![(MB, MB, 3)]);
+        assert!(BandwidthReport::from_samples(Vec::new()).to_string().contains("too quickly"));
+    }
+}
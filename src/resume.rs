@@ -1,35 +1,36 @@
 use serde::{Deserialize, Serialize};
-use std::fs;
+use std::collections::HashMap;
+use std::fs::{self, File, Metadata};
+use std::io::Write;
 use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use crate::config::Config;
+use crate::copy::atomic_temp_path;
 use crate::error::{Error, Result};
 
-/// Resume state for interrupted transfers
+/// Resume state for interrupted transfers.
+///
+/// A file is copied front to back, so what landed is always the prefix
+/// `0..bytes_done` of the target; there are no holes to track.
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct ResumeState {
     pub source: PathBuf,
     pub target: PathBuf,
     pub total_size: u64,
-    pub chunks_completed: Vec<ChunkInfo>,
+    pub bytes_done: u64,
     pub timestamp: String,
     pub version: String,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
-pub struct ChunkInfo {
-    pub offset: u64,
-    pub length: u64,
-    pub checksum: Option<String>,
-}
-
 impl ResumeState {
     pub fn new(source: PathBuf, target: PathBuf, total_size: u64) -> Self {
         Self {
             source,
             target,
             total_size,
-            chunks_completed: Vec::new(),
+            bytes_done: 0,
             timestamp: get_timestamp(),
-            version: "1.0".to_string(),
+            version: "2.0".to_string(),
         }
     }
 
@@ -47,71 +48,522 @@ impl ResumeState {
         state_path
     }
 
-    /// Save state to disk
-    pub fn save(&self) -> Result<()> {
+    /// Save state to disk, listing the transfer in the user's resume index
+    pub fn save(&mut self) -> Result<()> {
+        self.save_to(ResumeIndex::user().as_ref())
+    }
+
+    fn save_to(&mut self, index: Option<&ResumeIndex>) -> Result<()> {
+        self.timestamp = get_timestamp();
         let state_file = Self::state_file_path(&self.target);
         let json = serde_json::to_string_pretty(self)
             .map_err(|e| Error::Custom(format!("Failed to serialize state: {}", e)))?;
         fs::write(&state_file, json)
             .map_err(Error::Io)?;
+        // The state file alone is enough to resume; the index is a convenience
+        if let Some(index) = index {
+            index.record(self).ok();
+        }
         Ok(())
     }
 
-    /// Load state from disk
+    /// Load state from disk. States written before `bytes_done` existed
+    /// (version 1.0) don't parse and are reported as invalid.
     pub fn load(target: &Path) -> Result<Option<ResumeState>> {
         let state_file = Self::state_file_path(target);
         if !state_file.exists() {
             return Ok(None);
         }
+        Self::load_file(&state_file).map(Some)
+    }
 
-        let json = fs::read_to_string(&state_file)
+    /// Load the state saved in `state_file` itself
+    pub fn load_file(state_file: &Path) -> Result<ResumeState> {
+        let json = fs::read_to_string(state_file)
             .map_err(Error::Io)?;
-        let state: ResumeState = serde_json::from_str(&json)
-            .map_err(|_| Error::InvalidResumeState)?;
-        Ok(Some(state))
+        serde_json::from_str(&json)
+            .map_err(|_| Error::InvalidResumeState)
     }
 
     /// Delete state file
     pub fn cleanup(&self) -> Result<()> {
+        self.cleanup_from(ResumeIndex::user().as_ref())
+    }
+
+    fn cleanup_from(&self, index: Option<&ResumeIndex>) -> Result<()> {
         let state_file = Self::state_file_path(&self.target);
+        // Only a saved state was ever indexed
         if state_file.exists() {
             fs::remove_file(state_file).map_err(Error::Io)?;
+            if let Some(index) = index {
+                index.remove(&self.target).ok();
+            }
         }
         Ok(())
     }
 
-    /// Add a completed chunk
-    pub fn mark_chunk_done(&mut self, offset: u64, length: u64, checksum: Option<String>) {
-        self.chunks_completed.push(ChunkInfo {
-            offset,
-            length,
-            checksum,
-        });
+    /// Record that everything before `offset` has been written
+    pub fn advance_to(&mut self, offset: u64) {
+        self.bytes_done = offset;
     }
 
     /// Get total bytes completed
     pub fn bytes_completed(&self) -> u64 {
-        self.chunks_completed.iter().map(|c| c.length).sum()
+        self.bytes_done
+    }
+
+    pub fn percent_complete(&self) -> f64 {
+        if self.total_size == 0 {
+            100.0
+        } else {
+            self.bytes_done as f64 * 100.0 / self.total_size as f64
+        }
     }
 
     /// Validate resume state is coherent
     pub fn validate(&self) -> Result<()> {
-        let mut sorted_chunks = self.chunks_completed.clone();
-        sorted_chunks.sort_by_key(|c| c.offset);
-
-        // Check no overlaps and no gaps
-        let mut expected_offset = 0;
-        for chunk in &sorted_chunks {
-            if chunk.offset != expected_offset {
-                return Err(Error::InvalidResumeState);
+        if self.bytes_done > self.total_size {
+            return Err(Error::InvalidResumeState);
+        }
+        Ok(())
+    }
+
+    /// Check the state was saved for this same source at this same size, so
+    /// a partial target is never continued with another file's bytes
+    pub fn validate_source(&self, source: &Path, total_size: u64) -> Result<()> {
+        let same_path = self.source == source
+            || matches!(
+                (fs::canonicalize(&self.source), fs::canonicalize(source)),
+                (Ok(saved), Ok(live)) if saved == live
+            );
+        if same_path && self.total_size == total_size {
+            Ok(())
+        } else {
+            Err(Error::InvalidResumeState)
+        }
+    }
+}
+
+/// A resume state as `better-cp checkpoint` inspects it: the saved fields,
+/// plus whatever would stop the copy from resuming
+#[derive(Debug, Clone)]
+pub struct Checkpoint {
+    pub state: ResumeState,
+    pub state_file: PathBuf,
+    /// When the state file was last written
+    pub saved_at: Option<SystemTime>,
+    /// Why resuming from this state would fail; empty when it is coherent
+    pub problems: Vec<String>,
+}
+
+impl Checkpoint {
+    /// Load the state for `path`, a copy's target or its `.better-cp.state`
+    /// file, and check it against the source and the partial target
+    pub fn inspect(path: &Path) -> Result<Self> {
+        let is_state_file = path.to_string_lossy().ends_with(".better-cp.state");
+        let state_file = if is_state_file {
+            path.to_path_buf()
+        } else {
+            ResumeState::state_file_path(path)
+        };
+        if !state_file.exists() {
+            return Err(Error::Usage(format!("No resume state for {}", path.display())));
+        }
+        let state = ResumeState::load_file(&state_file)?;
+        let saved_at = fs::metadata(&state_file).and_then(|m| m.modified()).ok();
+
+        let mut problems = Vec::new();
+        if state.validate().is_err() {
+            problems.push(format!(
+                "{} bytes done is more than the {} bytes the source has",
+                state.bytes_done, state.total_size
+            ));
+        }
+        match fs::metadata(&state.source) {
+            Ok(metadata) if metadata.len() != state.total_size => problems.push(format!(
+                "the source is now {} bytes, not {}",
+                metadata.len(),
+                state.total_size
+            )),
+            Ok(_) => {}
+            Err(e) => problems.push(format!("the source can't be read: {}", e)),
+        }
+        // The bytes written so far sit in the target, or its `.tmp` file for an atomic copy
+        let written = [state.target.clone(), atomic_temp_path(&state.target)]
+            .iter()
+            .filter_map(|path| fs::metadata(path).ok())
+            .map(|metadata| metadata.len())
+            .max();
+        match written {
+            Some(len) if len < state.bytes_done => problems.push(format!(
+                "the partial target holds {} bytes, fewer than the {} recorded",
+                len, state.bytes_done
+            )),
+            Some(_) => {}
+            None => problems.push("the partial target is missing".to_string()),
+        }
+
+        Ok(Self { state, state_file, saved_at, problems })
+    }
+
+    /// Whether the copy could resume from this state
+    pub fn is_coherent(&self) -> bool {
+        self.problems.is_empty()
+    }
+
+    /// Time since the state was last saved
+    pub fn age(&self) -> Option<Duration> {
+        SystemTime::now().duration_since(self.saved_at?).ok()
+    }
+}
+
+/// How often a copy persists its resume state: once this many bytes have
+/// been written since the last save, or this much time has passed,
+/// whichever comes first
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ResumeInterval {
+    pub bytes: u64,
+    pub time: Duration,
+}
+
+impl Default for ResumeInterval {
+    fn default() -> Self {
+        Self {
+            bytes: 100 * 1024 * 1024,
+            time: Duration::from_secs(30),
+        }
+    }
+}
+
+impl ResumeInterval {
+    /// Parse `--resume-interval`: a size (`64M`), a time (`500ms`, `10s`,
+    /// `5min`, `1h`), or one of each separated by a comma (`16M,5s`). The
+    /// part not given keeps its default.
+    pub fn parse(value: &str) -> Result<Self> {
+        let mut interval = Self::default();
+        for part in value.split(',').map(str::trim) {
+            match parse_time(part) {
+                Some(time) => interval.time = time,
+                None => interval.bytes = Config::parse_size(part)?,
             }
-            expected_offset = chunk.offset + chunk.length;
         }
+        if interval.bytes == 0 || interval.time.is_zero() {
+            return Err(Error::Usage(format!("Resume interval must not be zero: {}", value)));
+        }
+        Ok(interval)
+    }
+
+    /// Whether a save is due, `bytes` and `elapsed` after the last one
+    pub fn is_due(&self, bytes: u64, elapsed: Duration) -> bool {
+        bytes >= self.bytes || elapsed >= self.time
+    }
+}
+
+/// `500ms`, `10s`, `5min` or `1h`; anything else is left to size parsing
+fn parse_time(value: &str) -> Option<Duration> {
+    let split = value.find(|c: char| !c.is_ascii_digit())?;
+    let (num, unit) = value.split_at(split);
+    let num: u64 = num.parse().ok()?;
+    match unit.trim() {
+        "ms" => Some(Duration::from_millis(num)),
+        "s" | "sec" => Some(Duration::from_secs(num)),
+        "min" => Some(Duration::from_secs(num * 60)),
+        "h" => Some(Duration::from_secs(num * 3600)),
+        _ => None,
+    }
+}
+
+/// One pending transfer in the resume index
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct IndexEntry {
+    pub source: PathBuf,
+    pub target: PathBuf,
+    pub total_size: u64,
+    pub bytes_completed: u64,
+    /// When the state was last saved, in seconds since the Unix epoch
+    pub saved_at: u64,
+}
+
+impl IndexEntry {
+    pub fn percent_complete(&self) -> f64 {
+        if self.total_size == 0 {
+            100.0
+        } else {
+            self.bytes_completed as f64 * 100.0 / self.total_size as f64
+        }
+    }
+
+    /// Time since the state was last saved
+    pub fn age(&self) -> Duration {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+        now.saturating_sub(Duration::from_secs(self.saved_at))
+    }
+}
+
+/// Central list of interrupted transfers, kept as `resume-index.json` under
+/// the config directory so `better-cp resume --list` can find the state
+/// files scattered next to their targets
+pub struct ResumeIndex {
+    path: PathBuf,
+}
+
+impl ResumeIndex {
+    pub fn at(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    /// The index in the user's config directory (`$XDG_CONFIG_HOME/better-cp`
+    /// or `~/.config/better-cp`), as the config file is looked up
+    pub fn user() -> Option<Self> {
+        // Tests must never write to the real config directory
+        if cfg!(test) {
+            return None;
+        }
+        let config_dir = match std::env::var("XDG_CONFIG_HOME") {
+            Ok(dir) => PathBuf::from(dir),
+            Err(_) => dirs::home_dir()?.join(".config"),
+        };
+        Some(Self::at(config_dir.join("better-cp/resume-index.json")))
+    }
+
+    /// Every transfer on record whose state file is still there, most
+    /// recently saved first
+    pub fn entries(&self) -> Result<Vec<IndexEntry>> {
+        let mut entries: Vec<IndexEntry> = self
+            .read()?
+            .into_iter()
+            .filter(|entry| ResumeState::state_file_path(&entry.target).exists())
+            .collect();
+        entries.sort_by(|a, b| b.saved_at.cmp(&a.saved_at).then_with(|| a.target.cmp(&b.target)));
+        Ok(entries)
+    }
+
+    /// Add or refresh the entry for `state`'s target
+    pub fn record(&self, state: &ResumeState) -> Result<()> {
+        let entry = IndexEntry {
+            source: canonical(&state.source),
+            target: canonical(&state.target),
+            total_size: state.total_size,
+            bytes_completed: state.bytes_completed(),
+            saved_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs(),
+        };
+        self.update(|entries| {
+            entries.retain(|e| e.target != entry.target);
+            entries.push(entry);
+        })
+    }
+
+    /// Drop the entry for `target`, once its transfer has completed
+    pub fn remove(&self, target: &Path) -> Result<()> {
+        let target = canonical(target);
+        self.update(|entries| entries.retain(|e| e.target != target))
+    }
+
+    fn read(&self) -> Result<Vec<IndexEntry>> {
+        match fs::read_to_string(&self.path) {
+            // A damaged index only loses the listing, never the transfers
+            Ok(json) => Ok(serde_json::from_str(&json).unwrap_or_default()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+            Err(e) => Err(Error::Io(e)),
+        }
+    }
 
+    /// Read, change and rewrite the index while holding a lock on
+    /// `resume-index.lock`, so concurrent copies don't lose each other's
+    /// entries. The new index is renamed into place, never half-written.
+    fn update(&self, change: impl FnOnce(&mut Vec<IndexEntry>)) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent).map_err(Error::Io)?;
+        }
+        let lock = File::options()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(self.path.with_extension("lock"))
+            .map_err(Error::Io)?;
+        lock.lock().map_err(Error::Io)?;
+
+        let mut entries = self.read()?;
+        change(&mut entries);
+        let json = serde_json::to_string_pretty(&entries)
+            .map_err(|e| Error::Custom(format!("Failed to serialize resume index: {}", e)))?;
+        let temp = self.path.with_extension("json.tmp");
+        fs::write(&temp, json).map_err(Error::Io)?;
+        fs::rename(&temp, &self.path).map_err(Error::Io)
+    }
+}
+
+/// `path` in its canonical directory, so a transfer is listed the same
+/// whichever directory it was started from. The file name is kept as is:
+/// the state file sits beside it, even when it is a symlink.
+fn canonical(path: &Path) -> PathBuf {
+    match (path.parent(), path.file_name()) {
+        (Some(parent), Some(name)) => {
+            let parent = if parent.as_os_str().is_empty() { Path::new(".") } else { parent };
+            match fs::canonicalize(parent) {
+                Ok(parent) => parent.join(name),
+                Err(_) => canonical(parent).join(name),
+            }
+        }
+        _ => std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf()),
+    }
+}
+
+/// One fully copied file in a directory manifest
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+struct CompletedFile {
+    source: PathBuf,
+    size: u64,
+    modified: Option<SystemTime>,
+}
+
+impl CompletedFile {
+    fn new(source: &Path, metadata: &Metadata) -> Self {
+        Self {
+            source: source.to_path_buf(),
+            size: metadata.len(),
+            modified: metadata.modified().ok(),
+        }
+    }
+}
+
+/// Resume state for a directory copy: a manifest of the files already
+/// copied, kept alongside the target as `<target>.better-cp.state`.
+///
+/// Entries are appended one JSON line at a time and flushed, so an
+/// interrupted run leaves every file it finished on record. A source file
+/// whose size or mtime changed since it was recorded is copied again.
+pub struct DirResumeState {
+    manifest_path: PathBuf,
+    completed: HashMap<PathBuf, CompletedFile>,
+    writer: Option<File>,
+}
+
+impl DirResumeState {
+    /// Start a fresh manifest for `target`, discarding any earlier one
+    pub fn new(target: &Path) -> Self {
+        let manifest_path = ResumeState::state_file_path(target);
+        fs::remove_file(&manifest_path).ok();
+        Self {
+            manifest_path,
+            completed: HashMap::new(),
+            writer: None,
+        }
+    }
+
+    /// Pick up the manifest of an earlier run into `target`, if there is one.
+    /// A line torn by a crash mid-write is ignored.
+    pub fn load(target: &Path) -> Result<Self> {
+        let manifest_path = ResumeState::state_file_path(target);
+        let content = match fs::read_to_string(&manifest_path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(Error::Io(e)),
+        };
+
+        let completed = content
+            .lines()
+            .filter_map(|line| serde_json::from_str::<CompletedFile>(line).ok())
+            .map(|file| (file.source.clone(), file))
+            .collect();
+        Ok(Self {
+            manifest_path,
+            completed,
+            writer: None,
+        })
+    }
+
+    /// Whether `source` was copied by an earlier run and hasn't changed since
+    pub fn is_complete(&self, source: &Path, metadata: &Metadata) -> bool {
+        self.completed.get(source) == Some(&CompletedFile::new(source, metadata))
+    }
+
+    /// Record that `source` has been fully copied
+    pub fn mark_complete(&mut self, source: &Path, metadata: &Metadata) -> Result<()> {
+        let file = CompletedFile::new(source, metadata);
+        let line = serde_json::to_string(&file)
+            .map_err(|e| Error::Custom(format!("Failed to serialize state: {}", e)))?;
+
+        if self.writer.is_none() {
+            let writer = File::options()
+                .create(true)
+                .append(true)
+                .open(&self.manifest_path)
+                .map_err(Error::Io)?;
+            self.writer = Some(writer);
+        }
+        if let Some(writer) = self.writer.as_mut() {
+            writeln!(writer, "{}", line).map_err(Error::Io)?;
+            writer.flush().map_err(Error::Io)?;
+        }
+
+        self.completed.insert(file.source.clone(), file);
+        Ok(())
+    }
+
+    /// Number of files on record
+    pub fn completed_count(&self) -> usize {
+        self.completed.len()
+    }
+
+    /// Delete the manifest once the whole tree has been copied
+    pub fn cleanup(&mut self) -> Result<()> {
+        self.writer = None;
+        if self.manifest_path.exists() {
+            fs::remove_file(&self.manifest_path).map_err(Error::Io)?;
+        }
         Ok(())
     }
 }
 
+/// Find leftovers of interrupted copies under `dir`: atomic-mode `.tmp` files
+/// (only those next to the target they stand in for, or with resume state;
+/// any other `.tmp` belongs to someone else), `.better-cp.partial` staging directories, `.better-cp.state` resume files
+/// and `.better-cp.verify` verification checkpoints
+pub fn find_stale_files(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut stale = Vec::new();
+    collect_stale_files(dir, &mut stale)?;
+    stale.sort();
+    Ok(stale)
+}
+
+fn collect_stale_files(dir: &Path, stale: &mut Vec<PathBuf>) -> Result<()> {
+    for entry in fs::read_dir(dir).map_err(Error::Io)? {
+        let entry = entry.map_err(Error::Io)?;
+        let file_type = entry.file_type().map_err(Error::Io)?;
+        let path = entry.path();
+        let name = entry.file_name().to_string_lossy().into_owned();
+
+        if file_type.is_dir() {
+            if name.ends_with(".better-cp.partial") {
+                stale.push(path);
+            } else {
+                collect_stale_files(&path, stale)?;
+            }
+        } else if file_type.is_file()
+            && (is_atomic_leftover(&path) || name.ends_with(".better-cp.state") || name.ends_with(".better-cp.verify"))
+        {
+            stale.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// Whether `path` is the `<target>.tmp` of an interrupted atomic copy: its
+/// target sits next to it, or a resume state names either of them
+fn is_atomic_leftover(path: &Path) -> bool {
+    let Some(target) = path.to_str().and_then(|p| p.strip_suffix(".tmp")) else {
+        return false;
+    };
+    let target = Path::new(target);
+    target.exists()
+        || ResumeState::state_file_path(target).exists()
+        || ResumeState::state_file_path(path).exists()
+}
+
 // Simple timestamp generation
 fn get_timestamp() -> String {
     // Simplified ISO 8601 format
@@ -124,6 +576,100 @@ mod tests {
     use super::*;
     use std::path::PathBuf;
 
+    #[test]
+    fn test_checkpoint_reports_progress_and_problems() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let source = dir.path().join("disk.img");
+        let target = dir.path().join("copy.img");
+        fs::write(&source, vec![0u8; 400]).unwrap();
+        fs::write(&target, vec![0u8; 100]).unwrap();
+
+        let mut state = ResumeState::new(source.clone(), target.clone(), 400);
+        state.advance_to(100);
+        state.save_to(None).unwrap();
+        // Either the target or its state file names the checkpoint
+        for path in [target.clone(), ResumeState::state_file_path(&target)] {
+            let checkpoint = Checkpoint::inspect(&path).unwrap();
+            assert_eq!(checkpoint.state.percent_complete(), 25.0);
+            assert!(checkpoint.is_coherent(), "{:?}", checkpoint.problems);
+        }
+
+        // More done than the source holds, and than the target has
+        state.advance_to(500);
+        state.save_to(None).unwrap();
+        let checkpoint = Checkpoint::inspect(&target).unwrap();
+        assert!(!checkpoint.is_coherent());
+        assert_eq!(checkpoint.problems.len(), 2, "{:?}", checkpoint.problems);
+
+        fs::write(ResumeState::state_file_path(&target), "{ not json").unwrap();
+        assert!(matches!(Checkpoint::inspect(&target), Err(Error::InvalidResumeState)));
+    }
+
+    #[test]
+    fn test_index_lists_partial_transfers() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let index = ResumeIndex::at(dir.path().join("config/resume-index.json"));
+
+        let mut first = ResumeState::new(dir.path().join("a.src"), dir.path().join("a.iso"), 400);
+        first.advance_to(100);
+        first.save_to(Some(&index)).unwrap();
+        let mut second = ResumeState::new(dir.path().join("b.src"), dir.path().join("b.iso"), 200);
+        second.advance_to(150);
+        second.save_to(Some(&index)).unwrap();
+        // Saving again refreshes the entry rather than adding another
+        first.advance_to(200);
+        first.save_to(Some(&index)).unwrap();
+
+        let mut entries = index.entries().unwrap();
+        entries.sort_by(|a, b| a.target.cmp(&b.target));
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].target, dir.path().canonicalize().unwrap().join("a.iso"));
+        assert_eq!(entries[0].percent_complete(), 50.0);
+        assert_eq!(entries[1].percent_complete(), 75.0);
+        assert!(entries[1].age() < Duration::from_secs(60));
+
+        first.cleanup_from(Some(&index)).unwrap();
+        assert_eq!(index.entries().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_bytes_done_survives_reload() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let mut state = ResumeState::new(dir.path().join("a.src"), dir.path().join("a.iso"), 300);
+        state.advance_to(120);
+        state.save_to(None).unwrap();
+
+        let loaded = ResumeState::load(&dir.path().join("a.iso")).unwrap().unwrap();
+        assert_eq!(loaded.bytes_completed(), 120);
+        assert!(loaded.validate().is_ok());
+
+        // More bytes than the source has can't be right
+        state.advance_to(301);
+        assert!(state.validate().is_err());
+
+        // A pre-2.0 chunk list is not trusted
+        fs::write(
+            ResumeState::state_file_path(&dir.path().join("a.iso")),
+            r#"{"source":"a.src","target":"a.iso","total_size":300,"chunks_completed":[],"timestamp":"0","version":"1.0"}"#,
+        )
+        .unwrap();
+        assert!(matches!(ResumeState::load(&dir.path().join("a.iso")), Err(Error::InvalidResumeState)));
+    }
+
+    #[test]
+    fn test_parse_resume_interval() {
+        let mib = 1024 * 1024;
+        assert_eq!(ResumeInterval::parse("64M").unwrap().bytes, 64 * mib);
+        assert_eq!(ResumeInterval::parse("64M").unwrap().time, Duration::from_secs(30));
+        assert_eq!(
+            ResumeInterval::parse("16M, 5s").unwrap(),
+            ResumeInterval { bytes: 16 * mib, time: Duration::from_secs(5) }
+        );
+        assert_eq!(ResumeInterval::parse("2min").unwrap().time, Duration::from_secs(120));
+        assert!(ResumeInterval::parse("0").is_err());
+        assert!(ResumeInterval::parse("soon").is_err());
+    }
+
     #[test]
     fn test_state_file_path() {
         let target = PathBuf::from("/backup/file.iso");
@@ -141,4 +687,23 @@ mod tests {
         assert_eq!(state.total_size, 1024 * 1024 * 1024);
         assert_eq!(state.bytes_completed(), 0);
     }
+
+    #[test]
+    fn test_dir_manifest_survives_reload_and_detects_changes() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let source = temp_dir.path().join("a.txt");
+        let target = temp_dir.path().join("dest");
+        fs::write(&source, b"first").unwrap();
+        let metadata = fs::metadata(&source).unwrap();
+
+        let mut state = DirResumeState::new(&target);
+        state.mark_complete(&source, &metadata).unwrap();
+
+        let reloaded = DirResumeState::load(&target).unwrap();
+        assert_eq!(reloaded.completed_count(), 1);
+        assert!(reloaded.is_complete(&source, &metadata));
+
+        fs::write(&source, b"changed contents").unwrap();
+        assert!(!reloaded.is_complete(&source, &fs::metadata(&source).unwrap()));
+    }
 }
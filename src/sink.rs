@@ -0,0 +1,128 @@
+use std::fs::{self, File};
+use std::io::{Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use crate::durability::{sync_file, sync_parent_dir};
+use crate::error::{Error, Result};
+
+/// Where a copy writes its bytes. The copy loop only ever talks to a sink,
+/// so a destination that isn't a local file (such as a chunked upload) can
+/// be plugged in by implementing this.
+///
+/// Writes arrive in order, each starting where the last one ended, but a
+/// resumed copy starts at the offset its resume state recorded. A sink
+/// dropped without `finalize` must leave nothing published at the target.
+pub trait CopySink: Send {
+    /// Write `data` at `offset`
+    fn write_at(&mut self, offset: u64, data: &[u8]) -> Result<()>;
+
+    /// Make everything written so far durable. The copy saves its resume
+    /// state right after, counting those bytes as done.
+    fn flush(&mut self) -> Result<()>;
+
+    /// Everything is written: publish the result at the target
+    fn finalize(self) -> Result<()>;
+}
+
+/// A local file, as copies have always written. With an atomic copy the
+/// file is a temporary one that `finalize` renames to the target.
+pub struct LocalFileSink {
+    file: File,
+    path: PathBuf,
+    target: PathBuf,
+    fsync: bool,
+}
+
+impl LocalFileSink {
+    /// Create (or truncate) `path` to write `target`'s contents into
+    pub fn create(path: &Path, target: &Path) -> Result<Self> {
+        let file = File::create(path).map_err(Error::Io)?;
+        Ok(Self::from_file(file, path, target))
+    }
+
+    /// Reopen the partial file at `path` to continue after its first
+    /// `offset` bytes, dropping anything past them. A file shorter than
+    /// `offset` has lost bytes the resume state counts as written, which
+    /// fails with `Error::InvalidResumeState`.
+    pub fn resume(path: &Path, target: &Path, offset: u64) -> Result<Self> {
+        let file = File::options()
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)
+            .map_err(Error::Io)?;
+        if file.metadata().map_err(Error::Io)?.len() < offset {
+            return Err(Error::InvalidResumeState);
+        }
+        file.set_len(offset).map_err(Error::Io)?;
+        Ok(Self::from_file(file, path, target))
+    }
+
+    fn from_file(file: File, path: &Path, target: &Path) -> Self {
+        Self {
+            file,
+            path: path.to_path_buf(),
+            target: target.to_path_buf(),
+            fsync: false,
+        }
+    }
+
+    /// Reserve the target's full `len` before anything is written
+    /// (`--preallocate`); see [`preallocate`]
+    pub fn preallocate(&self, len: u64) -> Result<()> {
+        preallocate(&self.file, len)
+    }
+
+    /// Flush the file to disk before it is renamed into place, and its
+    /// directory after (`--fsync`)
+    pub fn with_fsync(mut self, fsync: bool) -> Self {
+        self.fsync = fsync;
+        self
+    }
+}
+
+/// Give `file` a length of `len` with its blocks reserved up front, so the
+/// filesystem can lay it out contiguously and a full disk fails now rather
+/// than partway through. Linux reserves the blocks with `fallocate`; where
+/// that isn't available the file is only extended with `set_len`.
+pub fn preallocate(file: &File, len: u64) -> Result<()> {
+    #[cfg(target_os = "linux")]
+    if len > 0 {
+        use std::os::unix::io::AsRawFd;
+        // SAFETY: the descriptor belongs to `file`, open for the whole call
+        if unsafe { libc::fallocate(file.as_raw_fd(), 0, 0, len as libc::off_t) } == 0 {
+            return Ok(());
+        }
+        let err = std::io::Error::last_os_error();
+        if err.raw_os_error() != Some(libc::EOPNOTSUPP) {
+            return Err(Error::Io(err));
+        }
+    }
+    file.set_len(len).map_err(Error::Io)
+}
+
+impl CopySink for LocalFileSink {
+    fn write_at(&mut self, offset: u64, data: &[u8]) -> Result<()> {
+        self.file.seek(SeekFrom::Start(offset)).map_err(Error::Io)?;
+        self.file.write_all(data).map_err(Error::Io)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        self.file.sync_data().map_err(Error::Io)
+    }
+
+    fn finalize(self) -> Result<()> {
+        // --fsync: the data must be on disk before the rename publishes it
+        if self.fsync {
+            sync_file(&self.file, &self.path)?;
+        }
+        drop(self.file);
+
+        if self.path != self.target {
+            fs::rename(&self.path, &self.target).map_err(Error::Io)?;
+        }
+        if self.fsync {
+            sync_parent_dir(&self.target)?;
+        }
+        Ok(())
+    }
+}
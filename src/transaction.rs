@@ -0,0 +1,90 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use crate::error::{Error, Result};
+
+/// Destinations a run is about to create, so a failed `--transactional`
+/// copy can put the destination back the way it was.
+///
+/// Only paths that did not exist before the run are recorded; targets that
+/// were overwritten or merged into are left alone by a rollback.
+#[derive(Debug, Default)]
+pub struct RollbackLog {
+    created: Vec<PathBuf>,
+    created_dirs: Vec<PathBuf>,
+}
+
+impl RollbackLog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record `target` (and any missing parent directories) before copying to it
+    pub fn track(&mut self, target: &Path) {
+        if target.exists() {
+            return;
+        }
+        self.created.push(target.to_path_buf());
+
+        let mut parent = target.parent();
+        while let Some(dir) = parent {
+            if dir.as_os_str().is_empty() || dir.exists() {
+                break;
+            }
+            self.created_dirs.push(dir.to_path_buf());
+            parent = dir.parent();
+        }
+    }
+
+    /// Remove everything recorded, newest first, returning how many entries
+    /// were removed. Recorded parent directories go only if left empty.
+    pub fn rollback(&self) -> Result<usize> {
+        let mut removed = 0;
+
+        for path in self.created.iter().rev() {
+            let result = match fs::symlink_metadata(path) {
+                Ok(metadata) if metadata.is_dir() => fs::remove_dir_all(path),
+                Ok(_) => fs::remove_file(path),
+                Err(_) => continue,
+            };
+            result.map_err(|e| {
+                Error::Custom(format!("Rollback failed to remove {}: {}", path.display(), e))
+            })?;
+            removed += 1;
+        }
+
+        // Recorded deepest-first per target, so children go before parents
+        for dir in &self.created_dirs {
+            if fs::remove_dir(dir).is_ok() {
+                removed += 1;
+            }
+        }
+
+        Ok(removed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_rollback_removes_only_new_entries() {
+        let temp_dir = TempDir::new().unwrap();
+        let existing = temp_dir.path().join("existing.txt");
+        let fresh = temp_dir.path().join("new/deeper/fresh.txt");
+        fs::write(&existing, b"keep").unwrap();
+
+        let mut log = RollbackLog::new();
+        log.track(&existing);
+        log.track(&fresh);
+
+        fs::create_dir_all(fresh.parent().unwrap()).unwrap();
+        fs::write(&fresh, b"undo").unwrap();
+        fs::write(&existing, b"overwritten").unwrap();
+
+        assert_eq!(log.rollback().unwrap(), 3);
+        assert!(existing.exists());
+        assert!(!temp_dir.path().join("new").exists());
+    }
+}
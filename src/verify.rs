@@ -1,33 +1,553 @@
+use memmap2::Mmap;
 use sha2::{Sha256, Digest};
-use std::fs::File;
-use std::io::{Read, Result as IoResult};
-use std::path::Path;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::{self, File};
+use std::io::{Read, Result as IoResult, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+use walkdir::WalkDir;
+use crate::error::{Error, Result};
+use crate::filter::WalkFilter;
+use crate::interrupt::{is_interrupted, InterruptFlag};
+use crate::plan::{plan_tree, PlanKind};
 
 const CHUNK_SIZE: usize = 16 * 1024 * 1024; // 16 MB chunks for hashing
 
-/// Compute SHA-256 checksum of a file
-pub fn compute_checksum<P: AsRef<Path>>(path: P) -> IoResult<String> {
-    let mut file = File::open(path)?;
+/// Files up to this size are verified by comparing their bytes mapped into
+/// memory, which on fast disks beats hashing both
+pub const MMAP_COMPARE_LIMIT: u64 = 128 * 1024 * 1024;
+
+/// Slices compared at once before looking for the exact differing byte
+const COMPARE_BLOCK: usize = 64 * 1024;
+
+/// Compute SHA-256 checksum of a file. `progress`, if given, is called
+/// with the bytes hashed so far after every chunk, so a long re-read can
+/// show how far it got.
+pub fn compute_checksum<P: AsRef<Path>>(path: P, progress: Option<&dyn Fn(u64)>) -> IoResult<String> {
+    hash_reader(File::open(path)?, progress)
+}
+
+/// Compute the SHA-256 checksum of everything `reader` yields
+pub fn checksum_reader<R: Read>(reader: R) -> IoResult<String> {
+    hash_reader(reader, None)
+}
+
+fn hash_reader<R: Read>(mut reader: R, progress: Option<&dyn Fn(u64)>) -> IoResult<String> {
     let mut hasher = Sha256::new();
     let mut buffer = vec![0; CHUNK_SIZE];
+    let mut hashed = 0;
 
     loop {
-        let bytes_read = file.read(&mut buffer)?;
+        let bytes_read = reader.read(&mut buffer)?;
         if bytes_read == 0 {
             break;
         }
         hasher.update(&buffer[..bytes_read]);
+        hashed += bytes_read as u64;
+        if let Some(progress) = progress {
+            progress(hashed);
+        }
     }
 
     Ok(format!("{:x}", hasher.finalize()))
 }
 
+/// SHA-256 of data that arrives in pieces, such as the chunks of a copy,
+/// hex-encoded like `compute_checksum`'s
+#[derive(Default)]
+pub struct StreamingChecksum(Sha256);
+
+impl StreamingChecksum {
+    pub fn update(&mut self, data: &[u8]) {
+        self.0.update(data);
+    }
+
+    pub fn finish(self) -> String {
+        format!("{:x}", self.0.finalize())
+    }
+}
+
+impl Write for StreamingChecksum {
+    fn write(&mut self, buf: &[u8]) -> IoResult<usize> {
+        self.update(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> IoResult<()> {
+        Ok(())
+    }
+}
+
+/// Offset of the first byte where files `a` and `b` differ (the shorter
+/// one's length when it is a prefix of the other), or `None` if they are
+/// identical. Both are memory-mapped whole, so keep this to files below
+/// `MMAP_COMPARE_LIMIT`.
+pub fn first_difference(a: &Path, b: &Path) -> IoResult<Option<u64>> {
+    let (a, b) = (File::open(a)?, File::open(b)?);
+    let (a_len, b_len) = (a.metadata()?.len(), b.metadata()?.len());
+    if a_len == 0 || b_len == 0 {
+        // Nothing to map; an empty file differs from any other at the start
+        return Ok((a_len != b_len).then_some(0));
+    }
+
+    // SAFETY: the maps are only read, and only while both files stay open.
+    // A file truncated meanwhile by another process could fault the read,
+    // the same hazard any mmap reader takes on.
+    let (a, b) = unsafe { (Mmap::map(&a)?, Mmap::map(&b)?) };
+    let len = a.len().min(b.len());
+    let mut offset = 0;
+    for (a_block, b_block) in a[..len].chunks(COMPARE_BLOCK).zip(b[..len].chunks(COMPARE_BLOCK)) {
+        if a_block != b_block {
+            let within = a_block.iter().zip(b_block).position(|(x, y)| x != y).unwrap_or(0);
+            return Ok(Some((offset + within) as u64));
+        }
+        offset += a_block.len();
+    }
+    Ok((a.len() != b.len()).then_some(len as u64))
+}
+
+/// Bytes `BlockVerifier` compares per block, and so the most an interrupted
+/// verification reads again when it resumes
+pub const VERIFY_BLOCK_SIZE: u64 = 64 * 1024 * 1024;
+
+/// How far a `BlockVerifier` got, saved as `<target>.better-cp.verify`
+/// after every block so an interrupted verification picks up from there.
+///
+/// SHA-256's running state can't be saved, so the digest is a hash chain
+/// instead: each link is the SHA-256 of the previous link followed by the
+/// next block's SHA-256, both hex-encoded.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct VerifyCheckpoint {
+    pub source: PathBuf,
+    pub source_size: u64,
+    /// Source modification time in nanoseconds since the Unix epoch
+    pub source_mtime_ns: u128,
+    pub target_size: u64,
+    /// Target modification time, as for the source, and inode (0 where
+    /// there are none): a target rewritten since no longer continues
+    pub target_mtime_ns: u128,
+    pub target_inode: u64,
+    pub block_size: u64,
+    /// Bytes found identical so far, a whole number of blocks
+    pub offset: u64,
+    /// The hash chain over those blocks; empty before the first
+    pub chain: String,
+}
+
+impl VerifyCheckpoint {
+    /// Path of the checkpoint kept for `target`
+    pub fn path(target: &Path) -> PathBuf {
+        let mut path = target.to_path_buf();
+        let filename = format!(
+            "{}.better-cp.verify",
+            target
+                .file_name()
+                .map(|n| n.to_string_lossy())
+                .unwrap_or_default()
+        );
+        path.set_file_name(filename);
+        path
+    }
+
+    /// The checkpoint saved for `target`, if there is a readable one
+    pub fn load(target: &Path) -> Option<Self> {
+        let json = fs::read_to_string(Self::path(target)).ok()?;
+        serde_json::from_str(&json).ok()
+    }
+
+    /// Forget any verification progress saved for `target`, as once its
+    /// contents are rewritten
+    pub fn discard(target: &Path) {
+        fs::remove_file(Self::path(target)).ok();
+    }
+
+    fn save(&self, target: &Path) -> Result<()> {
+        let json = serde_json::to_string(self)
+            .map_err(|e| Error::Custom(format!("Failed to serialize verify checkpoint: {}", e)))?;
+        fs::write(Self::path(target), json).map_err(Error::Io)
+    }
+
+    /// Whether this checkpoint was saved comparing the same files as
+    /// `fresh` describes, with the same block size
+    fn continues(&self, fresh: &Self) -> bool {
+        self.source == fresh.source
+            && self.source_size == fresh.source_size
+            && self.source_mtime_ns == fresh.source_mtime_ns
+            && self.target_size == fresh.target_size
+            && self.target_mtime_ns == fresh.target_mtime_ns
+            && self.target_inode == fresh.target_inode
+            && self.block_size == fresh.block_size
+            && self.offset.is_multiple_of(self.block_size)
+    }
+}
+
+/// Modification time of `metadata`'s file in nanoseconds since the Unix epoch
+fn mtime_ns(metadata: &fs::Metadata) -> Result<u128> {
+    Ok(metadata
+        .modified()
+        .map_err(Error::Io)?
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or(0))
+}
+
+/// Inode number of `metadata`'s file, where there are inodes
+fn inode(metadata: &fs::Metadata) -> u64 {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        metadata.ino()
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = metadata;
+        0
+    }
+}
+
+/// The next link of a `VerifyCheckpoint` hash chain
+fn chain_link(chain: &str, block: &[u8]) -> String {
+    let block_digest = format!("{:x}", Sha256::digest(block));
+    let mut hasher = Sha256::new();
+    hasher.update(chain.as_bytes());
+    hasher.update(block_digest.as_bytes());
+    format!("{:x}", hasher.finalize())
+}
+
+/// What a `BlockVerifier` found
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BlockVerdict {
+    /// The files are identical; `digest` is the hash chain over all blocks
+    Match { digest: String },
+    /// The first differing byte, as `first_difference` reports it
+    Differs { offset: u64 },
+}
+
+/// Compares a target with its source one block at a time, saving a
+/// `VerifyCheckpoint` after each, so verifying a huge file that gets
+/// interrupted resumes where it stopped instead of reading it all again.
+/// Reads stay bounded by the block size, whatever the file size.
+pub struct BlockVerifier<'a> {
+    source: &'a Path,
+    target: &'a Path,
+    block_size: u64,
+    interrupt: Option<&'a InterruptFlag>,
+    progress: Option<&'a dyn Fn(u64)>,
+}
+
+impl<'a> BlockVerifier<'a> {
+    pub fn new(source: &'a Path, target: &'a Path) -> Self {
+        Self {
+            source,
+            target,
+            block_size: VERIFY_BLOCK_SIZE,
+            interrupt: None,
+            progress: None,
+        }
+    }
+
+    pub fn with_block_size(mut self, block_size: u64) -> Self {
+        self.block_size = block_size.max(1);
+        self
+    }
+
+    /// Stop after the current block once `interrupt` is raised, failing
+    /// with `Error::Interrupted` and leaving the checkpoint behind
+    pub fn with_interrupt(mut self, interrupt: &'a InterruptFlag) -> Self {
+        self.interrupt = Some(interrupt);
+        self
+    }
+
+    /// Call `progress` with the bytes verified so far, counting those an
+    /// earlier run checked, after every block
+    pub fn with_progress(mut self, progress: &'a dyn Fn(u64)) -> Self {
+        self.progress = Some(progress);
+        self
+    }
+
+    /// Compare the files, continuing from a checkpoint saved for the same
+    /// source, unchanged target and block size. The checkpoint is removed
+    /// once there is a verdict.
+    pub fn run(&self) -> Result<BlockVerdict> {
+        let src_metadata = fs::metadata(self.source).map_err(Error::Io)?;
+        let tgt_metadata = fs::metadata(self.target).map_err(Error::Io)?;
+        let fresh = VerifyCheckpoint {
+            source: self.source.to_path_buf(),
+            source_size: src_metadata.len(),
+            source_mtime_ns: mtime_ns(&src_metadata)?,
+            target_size: tgt_metadata.len(),
+            target_mtime_ns: mtime_ns(&tgt_metadata)?,
+            target_inode: inode(&tgt_metadata),
+            block_size: self.block_size,
+            offset: 0,
+            chain: String::new(),
+        };
+        let mut checkpoint = match VerifyCheckpoint::load(self.target) {
+            Some(saved) if saved.continues(&fresh) => saved,
+            _ => fresh,
+        };
+
+        let (mut src, mut tgt) = (
+            File::open(self.source).map_err(Error::Io)?,
+            File::open(self.target).map_err(Error::Io)?,
+        );
+        src.seek(SeekFrom::Start(checkpoint.offset)).map_err(Error::Io)?;
+        tgt.seek(SeekFrom::Start(checkpoint.offset)).map_err(Error::Io)?;
+
+        let len = checkpoint.source_size.min(checkpoint.target_size);
+        let mut src_block = vec![0; self.block_size.min(len) as usize];
+        let mut tgt_block = src_block.clone();
+        while checkpoint.offset < len {
+            let n = (len - checkpoint.offset).min(self.block_size) as usize;
+            src.read_exact(&mut src_block[..n]).map_err(Error::Io)?;
+            tgt.read_exact(&mut tgt_block[..n]).map_err(Error::Io)?;
+            if let Some(within) = src_block[..n].iter().zip(&tgt_block[..n]).position(|(a, b)| a != b) {
+                VerifyCheckpoint::discard(self.target);
+                return Ok(BlockVerdict::Differs { offset: checkpoint.offset + within as u64 });
+            }
+
+            checkpoint.chain = chain_link(&checkpoint.chain, &src_block[..n]);
+            checkpoint.offset += n as u64;
+            if let Some(progress) = self.progress {
+                progress(checkpoint.offset);
+            }
+            if checkpoint.offset < len {
+                checkpoint.save(self.target)?;
+                if self.interrupt.is_some_and(is_interrupted) {
+                    return Err(Error::Interrupted(self.target.to_string_lossy().to_string()));
+                }
+            }
+        }
+
+        VerifyCheckpoint::discard(self.target);
+        if checkpoint.source_size != checkpoint.target_size {
+            return Ok(BlockVerdict::Differs { offset: len });
+        }
+        Ok(BlockVerdict::Match { digest: checkpoint.chain })
+    }
+}
+
 /// Verify file matches expected checksum
 pub fn verify_checksum<P: AsRef<Path>>(path: P, expected: &str) -> IoResult<bool> {
-    let actual = compute_checksum(path)?;
+    let actual = compute_checksum(path, None)?;
     Ok(actual == expected)
 }
 
+/// What a file looked like when its checksum was taken
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+struct CachedChecksum {
+    size: u64,
+    /// Modification time in nanoseconds since the Unix epoch
+    mtime_ns: u128,
+    checksum: String,
+}
+
+/// Checksums of files verified earlier, trusted again as long as the file's
+/// size and modification time are unchanged.
+///
+/// Kept as a JSON file between runs, so repeated verification of a synced
+/// tree only re-reads the files that changed since.
+#[derive(Debug, Default)]
+pub struct ChecksumCache {
+    /// Where the cache is saved; `None` keeps it for this run only
+    path: Option<PathBuf>,
+    entries: BTreeMap<PathBuf, CachedChecksum>,
+    /// Bytes read to compute checksums the cache didn't have
+    bytes_hashed: u64,
+}
+
+impl ChecksumCache {
+    /// Load the cache at `path`. A missing or unreadable file starts an empty cache.
+    pub fn load(path: &Path) -> Self {
+        let entries = fs::read_to_string(path)
+            .ok()
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default();
+        Self {
+            path: Some(path.to_path_buf()),
+            entries,
+            bytes_hashed: 0,
+        }
+    }
+
+    /// Checksum of `file`, reusing the cached one when the file's size and
+    /// mtime haven't changed since it was taken
+    pub fn checksum(&mut self, file: &Path) -> IoResult<String> {
+        let metadata = fs::metadata(file)?;
+        let size = metadata.len();
+        let mtime_ns = metadata
+            .modified()?
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_nanos())
+            .unwrap_or(0);
+        let key = fs::canonicalize(file)?;
+
+        if let Some(cached) = self.entries.get(&key) {
+            if cached.size == size && cached.mtime_ns == mtime_ns {
+                return Ok(cached.checksum.clone());
+            }
+        }
+
+        let checksum = compute_checksum(file, None)?;
+        self.bytes_hashed += size;
+        self.entries.insert(key, CachedChecksum { size, mtime_ns, checksum: checksum.clone() });
+        Ok(checksum)
+    }
+
+    /// Bytes read this run to checksum files the cache couldn't vouch for
+    pub fn bytes_hashed(&self) -> u64 {
+        self.bytes_hashed
+    }
+
+    /// Write the cache back to its file, if it has one
+    pub fn save(&self) -> Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let json = serde_json::to_string(&self.entries)
+            .map_err(|e| Error::Custom(format!("Failed to serialize checksum cache: {}", e)))?;
+        fs::write(path, json).map_err(|e| {
+            Error::Custom(format!("Failed to write checksum cache {}: {}", path.display(), e))
+        })
+    }
+}
+
+/// How a destination tree differs from its source at one path
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum DiscrepancyKind {
+    /// Present in both trees with different contents
+    Mismatch,
+    /// In the source but not the destination
+    Missing,
+    /// In the destination but not the source
+    Extra,
+}
+
+/// One file that differs between two trees, by its path relative to the roots
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct Discrepancy {
+    pub path: PathBuf,
+    pub kind: DiscrepancyKind,
+}
+
+/// Outcome of comparing a destination tree against its source
+#[derive(Serialize, Debug, Default)]
+pub struct TreeReport {
+    /// Files present in both trees whose checksums were compared
+    pub files_checked: usize,
+    /// Every difference found, sorted by path
+    pub discrepancies: Vec<Discrepancy>,
+}
+
+impl TreeReport {
+    /// Whether the destination is an exact copy of the source
+    pub fn is_match(&self) -> bool {
+        self.discrepancies.is_empty()
+    }
+}
+
+/// Walk both trees and compare their files by checksum. Only regular files
+/// are compared; directories and symlinks are not reported.
+pub fn compare_trees(source: &Path, target: &Path) -> Result<TreeReport> {
+    compare_trees_cached(source, target, &mut ChecksumCache::default())
+}
+
+/// [`compare_trees`], taking checksums of unchanged files from `cache`
+pub fn compare_trees_cached(source: &Path, target: &Path, cache: &mut ChecksumCache) -> Result<TreeReport> {
+    let source_files = list_files(source)?;
+    let target_files = list_files(target)?;
+    let mut report = TreeReport::default();
+
+    for path in source_files.union(&target_files) {
+        let kind = match (source_files.contains(path), target_files.contains(path)) {
+            (true, false) => DiscrepancyKind::Missing,
+            (false, true) => DiscrepancyKind::Extra,
+            _ => {
+                report.files_checked += 1;
+                let expected = cache.checksum(&source.join(path)).map_err(Error::Io)?;
+                let actual = cache.checksum(&target.join(path)).map_err(Error::Io)?;
+                if expected == actual {
+                    continue;
+                }
+                DiscrepancyKind::Mismatch
+            }
+        };
+        report.discrepancies.push(Discrepancy { path: path.clone(), kind });
+    }
+
+    Ok(report)
+}
+
+/// Confirm a directory copy delivered everything: every file the walk of
+/// `source` (through `filter`) plans to copy must be a file in `target` of
+/// the same size, so the file count and total bytes agree. With `checksums`,
+/// the contents are compared as well. Files in `target` the source doesn't
+/// have are not counted, so merging into an existing directory passes.
+///
+/// Fails with `Error::TreeMismatch`, naming the files that fell short.
+pub fn verify_tree(source: &Path, target: &Path, filter: &WalkFilter, checksums: bool) -> Result<()> {
+    let (mut expected_files, mut expected_bytes) = (0, 0);
+    let (mut actual_files, mut actual_bytes) = (0, 0);
+    let mut missing = Vec::new();
+
+    for entry in plan_tree(source, target, filter)? {
+        let PlanKind::File(metadata) = &entry.kind else {
+            continue;
+        };
+        expected_files += 1;
+        expected_bytes += metadata.len();
+
+        let landed = fs::metadata(&entry.target)
+            .ok()
+            .filter(|target_metadata| target_metadata.is_file());
+        let intact = match &landed {
+            Some(target_metadata) if target_metadata.len() == metadata.len() => {
+                !checksums || compute_checksum(&entry.source, None).map_err(Error::Io)?
+                    == compute_checksum(&entry.target, None).map_err(Error::Io)?
+            }
+            _ => false,
+        };
+        if let Some(target_metadata) = landed {
+            actual_files += 1;
+            actual_bytes += target_metadata.len();
+        }
+        if !intact {
+            missing.push(entry.target.to_string_lossy().to_string());
+        }
+    }
+
+    if missing.is_empty() {
+        return Ok(());
+    }
+    Err(Error::TreeMismatch {
+        target: target.to_string_lossy().to_string(),
+        expected_files,
+        expected_bytes,
+        actual_files,
+        actual_bytes,
+        missing,
+    })
+}
+
+/// Regular files under `root`, relative to it. A single file is its own tree.
+fn list_files(root: &Path) -> Result<BTreeSet<PathBuf>> {
+    if !root.exists() {
+        return Err(Error::SourceNotFound(root.to_string_lossy().to_string()));
+    }
+
+    let mut files = BTreeSet::new();
+    for entry in WalkDir::new(root) {
+        let entry = entry.map_err(|e| Error::Custom(e.to_string()))?;
+        if entry.file_type().is_file() {
+            let relative = entry.path().strip_prefix(root).unwrap_or(entry.path());
+            files.insert(relative.to_path_buf());
+        }
+    }
+    Ok(files)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -40,7 +560,7 @@ mod tests {
         file.write_all(b"hello world").unwrap();
         file.flush().unwrap();
 
-        let checksum = compute_checksum(file.path()).unwrap();
+        let checksum = compute_checksum(file.path(), None).unwrap();
         assert!(!checksum.is_empty());
         assert_eq!(checksum.len(), 64); // SHA-256 hex is 64 chars
     }
@@ -51,8 +571,163 @@ mod tests {
         file.write_all(b"test data").unwrap();
         file.flush().unwrap();
 
-        let checksum = compute_checksum(file.path()).unwrap();
+        let checksum = compute_checksum(file.path(), None).unwrap();
         assert!(verify_checksum(file.path(), &checksum).unwrap());
         assert!(!verify_checksum(file.path(), "wronghash").unwrap());
     }
+
+    #[test]
+    fn test_checksum_progress_counts_up() {
+        let mut file = NamedTempFile::new().unwrap();
+        let data = vec![3u8; 2 * CHUNK_SIZE + 1];
+        file.write_all(&data).unwrap();
+        file.flush().unwrap();
+
+        let seen = std::cell::RefCell::new(Vec::new());
+        let checksum = compute_checksum(file.path(), Some(&|bytes| seen.borrow_mut().push(bytes))).unwrap();
+        assert_eq!(checksum, compute_checksum(file.path(), None).unwrap());
+
+        let seen = seen.into_inner();
+        assert!(seen.len() >= 3, "{:?}", seen);
+        assert!(seen.windows(2).all(|pair| pair[0] < pair[1]), "{:?}", seen);
+        assert_eq!(seen.last(), Some(&(data.len() as u64)));
+    }
+
+    #[test]
+    fn test_block_verify_resumes_after_interrupt() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let source = temp_dir.path().join("source.bin");
+        let target = temp_dir.path().join("target.bin");
+        let data: Vec<u8> = (0..10_000u32).map(|i| (i % 251) as u8).collect();
+        std::fs::write(&source, &data).unwrap();
+        std::fs::write(&target, &data).unwrap();
+
+        let expected = BlockVerifier::new(&source, &target).with_block_size(1024).run().unwrap();
+        assert!(VerifyCheckpoint::load(&target).is_none());
+
+        // Raised up front: the first block is checked, then it stops
+        let interrupt = InterruptFlag::default();
+        interrupt.store(true, std::sync::atomic::Ordering::SeqCst);
+        let verifier = BlockVerifier::new(&source, &target).with_block_size(1024).with_interrupt(&interrupt);
+        assert!(matches!(verifier.run(), Err(Error::Interrupted(_))));
+        assert_eq!(VerifyCheckpoint::load(&target).unwrap().offset, 1024);
+
+        // Resuming checks the other nine blocks into the same digest
+        interrupt.store(false, std::sync::atomic::Ordering::SeqCst);
+        assert_eq!(verifier.run().unwrap(), expected);
+        assert!(matches!(expected, BlockVerdict::Match { .. }));
+        assert!(VerifyCheckpoint::load(&target).is_none());
+
+        // A flipped byte past the checkpoint is still found
+        let mut corrupt = data.clone();
+        corrupt[5000] ^= 0xff;
+        std::fs::write(&target, &corrupt).unwrap();
+        interrupt.store(true, std::sync::atomic::Ordering::SeqCst);
+        assert!(verifier.run().is_err());
+        interrupt.store(false, std::sync::atomic::Ordering::SeqCst);
+        assert_eq!(verifier.run().unwrap(), BlockVerdict::Differs { offset: 5000 });
+
+        // So is one before it, written after the checkpoint was saved
+        std::fs::write(&target, &data).unwrap();
+        interrupt.store(true, std::sync::atomic::Ordering::SeqCst);
+        assert!(verifier.run().is_err());
+        assert_eq!(VerifyCheckpoint::load(&target).unwrap().offset, 1024);
+        let saved_mtime = std::fs::metadata(&target).unwrap().modified().unwrap();
+        let mut file = std::fs::OpenOptions::new().write(true).open(&target).unwrap();
+        file.seek(SeekFrom::Start(100)).unwrap();
+        file.write_all(&[data[100] ^ 0xff]).unwrap();
+        // A clock too coarse to tell the writes apart still moves on
+        file.set_modified(saved_mtime + std::time::Duration::from_secs(1)).unwrap();
+        drop(file);
+        interrupt.store(false, std::sync::atomic::Ordering::SeqCst);
+        assert_eq!(verifier.run().unwrap(), BlockVerdict::Differs { offset: 100 });
+    }
+
+    #[test]
+    fn test_compare_trees_reports_each_kind() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let source = temp_dir.path().join("source");
+        let target = temp_dir.path().join("target");
+        for root in [&source, &target] {
+            std::fs::create_dir_all(root.join("sub")).unwrap();
+            std::fs::write(root.join("same.txt"), b"same").unwrap();
+        }
+        std::fs::write(source.join("sub/changed.txt"), b"original").unwrap();
+        std::fs::write(target.join("sub/changed.txt"), b"corrupt!").unwrap();
+        std::fs::write(source.join("gone.txt"), b"gone").unwrap();
+        std::fs::write(target.join("stray.txt"), b"stray").unwrap();
+
+        let report = compare_trees(&source, &target).unwrap();
+        assert_eq!(report.files_checked, 2);
+        assert_eq!(
+            report.discrepancies,
+            vec![
+                Discrepancy { path: PathBuf::from("gone.txt"), kind: DiscrepancyKind::Missing },
+                Discrepancy { path: PathBuf::from("stray.txt"), kind: DiscrepancyKind::Extra },
+                Discrepancy { path: PathBuf::from("sub/changed.txt"), kind: DiscrepancyKind::Mismatch },
+            ]
+        );
+        assert!(!report.is_match());
+    }
+
+    #[test]
+    fn test_checksum_cache_skips_unchanged_files() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let source = temp_dir.path().join("source");
+        let target = temp_dir.path().join("target");
+        for root in [&source, &target] {
+            std::fs::create_dir_all(root).unwrap();
+            std::fs::write(root.join("big.bin"), vec![7u8; 64 * 1024]).unwrap();
+            std::fs::write(root.join("small.txt"), b"small").unwrap();
+        }
+        let cache_path = temp_dir.path().join("checksums.json");
+
+        let mut cache = ChecksumCache::load(&cache_path);
+        assert!(compare_trees_cached(&source, &target, &mut cache).unwrap().is_match());
+        let first_run = cache.bytes_hashed();
+        assert_eq!(first_run, 2 * (64 * 1024 + 5));
+        cache.save().unwrap();
+
+        // Nothing changed: every checksum comes from the saved cache
+        let mut cache = ChecksumCache::load(&cache_path);
+        assert!(compare_trees_cached(&source, &target, &mut cache).unwrap().is_match());
+        assert_eq!(cache.bytes_hashed(), 0);
+
+        // A changed size invalidates just that file's entry
+        std::fs::write(target.join("small.txt"), b"changed").unwrap();
+        let report = compare_trees_cached(&source, &target, &mut cache).unwrap();
+        assert_eq!(report.discrepancies.len(), 1);
+        assert_eq!(cache.bytes_hashed(), 7);
+    }
+
+    #[test]
+    fn test_verify_tree_catches_dropped_file() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let source = temp_dir.path().join("source");
+        let target = temp_dir.path().join("target");
+        for root in [&source, &target] {
+            std::fs::create_dir_all(root.join("sub")).unwrap();
+            std::fs::write(root.join("kept.txt"), b"kept").unwrap();
+        }
+        std::fs::write(source.join("sub/dropped.txt"), b"dropped").unwrap();
+        let filter = WalkFilter::new();
+
+        let err = verify_tree(&source, &target, &filter, false).unwrap_err();
+        match err {
+            Error::TreeMismatch { expected_files, actual_files, actual_bytes, ref missing, .. } => {
+                assert_eq!((expected_files, actual_files, actual_bytes), (2, 1, 4));
+                assert_eq!(missing, &vec![target.join("sub/dropped.txt").to_string_lossy().to_string()]);
+            }
+            other => panic!("expected TreeMismatch, got {:?}", other),
+        }
+        assert_eq!(err.exit_code(), 5);
+
+        std::fs::write(target.join("sub/dropped.txt"), b"dropped").unwrap();
+        verify_tree(&source, &target, &filter, false).unwrap();
+
+        // Same size, different bytes: only checksums notice
+        std::fs::write(target.join("kept.txt"), b"kep!").unwrap();
+        verify_tree(&source, &target, &filter, false).unwrap();
+        assert!(verify_tree(&source, &target, &filter, true).is_err());
+    }
 }
@@ -0,0 +1,72 @@
+use std::io;
+use std::path::Path;
+use std::time::Duration;
+use crate::error::{Error, Result};
+
+/// Run one blocking read on tokio's blocking pool, giving up with
+/// `Error::Timeout` if it hasn't returned within `timeout`. Without a
+/// timeout there is nothing to watch, so the read runs inline.
+///
+/// Each call gets a fresh window, so a copy that reads chunk by chunk only
+/// fails once a single chunk stalls. A read that never returns (a hung NFS
+/// mount) is left behind on its blocking thread.
+pub async fn read_within<T: Send + 'static>(
+    timeout: Option<Duration>,
+    path: &Path,
+    read: impl FnOnce() -> io::Result<T> + Send + 'static,
+) -> Result<T> {
+    let Some(limit) = timeout else {
+        return read().map_err(Error::Io);
+    };
+    let task = tokio::task::spawn_blocking(read);
+    let joined = tokio::time::timeout(limit, task).await.map_err(|_| Error::Timeout {
+        path: path.to_string_lossy().to_string(),
+        seconds: limit.as_secs(),
+    })?;
+    joined
+        .map_err(|e| Error::Custom(format!("Read task failed: {}", e)))?
+        .map_err(Error::Io)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+    use std::time::Instant;
+
+    /// A reader that takes far longer than the timeout to produce anything
+    struct SlowReader;
+
+    impl Read for SlowReader {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            std::thread::sleep(Duration::from_millis(500));
+            Ok(buf.len())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_stalled_read_times_out_promptly() {
+        let start = Instant::now();
+        let result = read_within(Some(Duration::from_millis(50)), Path::new("slow"), || {
+            let mut buffer = [0; 16];
+            SlowReader.read(&mut buffer)
+        })
+        .await;
+
+        assert!(matches!(result, Err(Error::Timeout { .. })));
+        assert!(start.elapsed() < Duration::from_millis(400));
+    }
+
+    #[tokio::test]
+    async fn test_read_within_window_succeeds() {
+        let bytes = read_within(Some(Duration::from_secs(5)), Path::new("fast"), || Ok(7)).await;
+        assert_eq!(bytes.unwrap(), 7);
+    }
+
+    #[tokio::test]
+    async fn test_untimed_read_runs_inline() {
+        let caller = std::thread::current().id();
+        let reader = read_within(None, Path::new("inline"), || Ok(std::thread::current().id())).await;
+        assert_eq!(reader.unwrap(), caller);
+    }
+}
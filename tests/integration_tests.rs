@@ -1,7 +1,23 @@
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::process::Command;
 use tempfile::TempDir;
 
+/// `better-cp`, with a config directory of the tests' own so runs never read
+/// the user's config or write to their resume index
+fn better_cp() -> Command {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_better-cp"));
+    command.env("XDG_CONFIG_HOME", PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("config"));
+    command
+}
+
+/// `better-mv`, set up as `better_cp` is
+fn better_mv() -> Command {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_better-mv"));
+    command.env("XDG_CONFIG_HOME", PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("config"));
+    command
+}
+
 /// Helper to create a test file with specific content
 fn create_test_file(path: &PathBuf, content: &[u8]) -> std::io::Result<()> {
     if let Some(parent) = path.parent() {
@@ -11,6 +27,19 @@ fn create_test_file(path: &PathBuf, content: &[u8]) -> std::io::Result<()> {
     Ok(())
 }
 
+/// Whether two existing paths live on the same filesystem
+fn same_device(a: &Path, b: &Path) -> bool {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        if let (Ok(a), Ok(b)) = (fs::metadata(a), fs::metadata(b)) {
+            return a.dev() == b.dev();
+        }
+    }
+    let _ = (a, b);
+    true
+}
+
 /// Helper to create a test directory structure
 fn create_test_structure(base: &std::path::Path) -> std::io::Result<()> {
     // Create directory structure:
@@ -326,21 +355,80 @@ fn test_copy_preserves_permissions() -> std::io::Result<()> {
     Ok(())
 }
 
-/// Test: Copying to a path with non-existent parent directory fails gracefully
+/// Test: Copying to a path with non-existent parent directories creates them,
+/// and a trailing-slash destination that doesn't exist becomes a directory
 #[test]
 fn test_copy_to_nonexistent_parent() -> std::io::Result<()> {
+    use std::process::Stdio;
+
     let temp_dir = TempDir::new()?;
     let source = temp_dir.path().join("source.txt");
+    let other = temp_dir.path().join("other.txt");
     let dest = temp_dir.path().join("nonexistent/parent/dest.txt");
-    
     fs::write(&source, b"content")?;
-    
-    // Attempting to copy without creating parent should fail
-    let content = fs::read(&source)?;
-    let result = fs::write(&dest, content);
-    
-    assert!(result.is_err());
-    
+    fs::write(&other, b"other")?;
+
+    let status = better_cp()
+        .args(["copy", "--quiet", "--no-progress", "--no-clobber"])
+        .arg(&source)
+        .arg(&dest)
+        .stdin(Stdio::null())
+        .status()?;
+    assert!(status.success());
+    assert_eq!(fs::read(&dest)?, b"content");
+
+    let dir = temp_dir.path().join("a/b/c/");
+    let status = better_cp()
+        .args(["copy", "--quiet", "--no-progress", "--no-clobber"])
+        .arg(&source)
+        .arg(&other)
+        .arg(&dir)
+        .stdin(Stdio::null())
+        .status()?;
+    assert!(status.success());
+    assert_eq!(fs::read(dir.join("source.txt"))?, b"content");
+    assert_eq!(fs::read(dir.join("other.txt"))?, b"other");
+
+    // Without the trailing slash several sources need an existing directory
+    let status = better_cp()
+        .args(["copy", "--quiet", "--no-progress", "--no-clobber"])
+        .arg(&source)
+        .arg(&other)
+        .arg(temp_dir.path().join("missing"))
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .status()?;
+    assert_eq!(status.code(), Some(2));
+
+    Ok(())
+}
+
+/// Test: Moving files and directories into deep non-existent destinations
+#[test]
+fn test_move_to_nonexistent_parent() -> std::io::Result<()> {
+    use std::process::Stdio;
+
+    let temp_dir = TempDir::new()?;
+    let file = temp_dir.path().join("file.txt");
+    let tree = temp_dir.path().join("tree");
+    create_test_file(&file, b"moved")?;
+    create_test_file(&tree.join("inner.txt"), b"inner")?;
+
+    let file_dest = temp_dir.path().join("x/y/z/file.txt");
+    let tree_dest = temp_dir.path().join("p/q/");
+    for (source, dest) in [(&file, &file_dest), (&tree, &tree_dest)] {
+        let status = better_mv()
+            .args(["move", "--quiet", "--no-progress", "--no-clobber"])
+            .arg(source)
+            .arg(dest)
+            .stdin(Stdio::null())
+            .status()?;
+        assert!(status.success());
+        assert!(!source.exists());
+    }
+    assert_eq!(fs::read(&file_dest)?, b"moved");
+    assert_eq!(fs::read(tree_dest.join("tree/inner.txt"))?, b"inner");
+
     Ok(())
 }
 
@@ -389,6 +477,76 @@ fn test_copy_preserves_metadata() -> std::io::Result<()> {
     Ok(())
 }
 
+/// Test: --preserve names every attribute kept, and without it the config's
+/// behavior settings decide
+#[test]
+fn test_preserve_list_and_config_decide_timestamps() -> std::io::Result<()> {
+    use std::process::Stdio;
+    use std::time::{Duration, SystemTime};
+
+    let temp_dir = TempDir::new()?;
+    let source = temp_dir.path().join("source.txt");
+    fs::write(&source, b"content")?;
+    let old = SystemTime::now() - Duration::from_secs(86_400);
+    fs::File::options().write(true).open(&source)?.set_modified(old)?;
+    let config_home = temp_dir.path().join("config");
+    create_test_file(&config_home.join("better-cp/config.toml"), CONFIG_WITHOUT_TIMES.as_bytes())?;
+
+    let copy = |dest: &Path, extra: &[&str], config: Option<&Path>| -> std::io::Result<SystemTime> {
+        let mut command = better_cp();
+        if let Some(config) = config {
+            command.env("XDG_CONFIG_HOME", config);
+        }
+        let status = command
+            .args(["copy", "--quiet", "--no-progress"])
+            .args(extra)
+            .arg(&source)
+            .arg(dest)
+            .stdin(Stdio::null())
+            .status()?;
+        assert!(status.success());
+        fs::metadata(dest)?.modified()
+    };
+
+    assert_eq!(copy(&temp_dir.path().join("default.txt"), &[], None)?, old);
+    assert_ne!(copy(&temp_dir.path().join("mode.txt"), &["--preserve=mode"], None)?, old);
+    assert_ne!(copy(&temp_dir.path().join("config.txt"), &[], Some(&config_home))?, old);
+    assert_eq!(
+        copy(&temp_dir.path().join("listed.txt"), &["--preserve=timestamps"], Some(&config_home))?,
+        old
+    );
+
+    Ok(())
+}
+
+/// A config file that keeps permissions but not timestamps
+const CONFIG_WITHOUT_TIMES: &str = r#"version = 1
+
+[defaults]
+overwrite = "prompt"
+resume = true
+verify = "fast"
+parallel = 4
+sparse = true
+reflink = "auto"
+
+[behavior]
+follow_symlinks = false
+preserve_times = false
+preserve_permissions = true
+atomic = true
+
+[performance]
+buffer_size = "64M"
+chunk_size = "100M"
+resume_threshold = "100M,30s"
+
+[ui]
+color = true
+progress_style = "bars"
+show_per_file = false
+"#;
+
 /// Test: Very nested directory structure copy
 #[test]
 fn test_copy_deeply_nested_structure() -> std::io::Result<()> {
@@ -405,3 +563,1075 @@ fn test_copy_deeply_nested_structure() -> std::io::Result<()> {
     
     Ok(())
 }
+
+/// Test: `-` reads the source from stdin and writes the destination to stdout
+#[test]
+fn test_copy_through_stdin_and_stdout() -> std::io::Result<()> {
+    use std::io::Write;
+    use std::process::Stdio;
+
+    let temp_dir = TempDir::new()?;
+    let dest = temp_dir.path().join("from_stdin.bin");
+    let payload: Vec<u8> = (0..64 * 1024).map(|i| (i % 251) as u8).collect();
+
+    // stdin -> file
+    let mut child = better_cp()
+        .args(["copy", "-"])
+        .arg(&dest)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .spawn()?;
+    child.stdin.take().unwrap().write_all(&payload)?;
+    assert!(child.wait()?.success());
+    assert_eq!(fs::read(&dest)?, payload);
+
+    // file -> stdout
+    let output = better_cp()
+        .arg("copy")
+        .arg(&dest)
+        .arg("-")
+        .output()?;
+    assert!(output.status.success());
+    assert_eq!(output.stdout, payload);
+
+    Ok(())
+}
+
+/// Test: --no-clobber skips existing targets, copies the rest and exits
+/// with the skip code
+#[test]
+fn test_no_clobber_skips_existing_and_copies_the_rest() -> std::io::Result<()> {
+    use std::process::Stdio;
+
+    let temp_dir = TempDir::new()?;
+    let source1 = temp_dir.path().join("keep.txt");
+    let source2 = temp_dir.path().join("new.txt");
+    let dest_dir = temp_dir.path().join("dest");
+
+    fs::create_dir(&dest_dir)?;
+    fs::write(&source1, b"incoming")?;
+    fs::write(&source2, b"fresh")?;
+    fs::write(dest_dir.join("keep.txt"), b"existing")?;
+
+    let output = better_cp()
+        .args(["copy", "--no-clobber", "--no-progress", "--quiet"])
+        .arg(&source1)
+        .arg(&source2)
+        .arg(&dest_dir)
+        .stdin(Stdio::null())
+        .output()?;
+
+    assert_eq!(output.status.code(), Some(4));
+    // Skipping was asked for, so it is no error to report
+    assert!(output.stderr.is_empty(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(fs::read(dest_dir.join("keep.txt"))?, b"existing");
+    assert_eq!(fs::read(dest_dir.join("new.txt"))?, b"fresh");
+
+    Ok(())
+}
+
+/// Test: better-mv refuses the commands only better-cp runs
+#[test]
+fn test_move_binary_rejects_copy_commands() -> std::io::Result<()> {
+    use std::process::Stdio;
+
+    let temp_dir = TempDir::new()?;
+    let source = temp_dir.path().join("source.txt");
+    fs::write(&source, b"content")?;
+
+    let status = better_mv()
+        .arg("copy")
+        .arg(&source)
+        .arg(temp_dir.path().join("dest.txt"))
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .status()?;
+    assert_eq!(status.code(), Some(2));
+    assert!(!temp_dir.path().join("dest.txt").exists());
+
+    Ok(())
+}
+
+/// Test: --json streams newline-delimited progress events ending with "done"
+#[test]
+fn test_json_progress_stream_ends_with_done() -> std::io::Result<()> {
+    use std::process::Stdio;
+
+    let temp_dir = TempDir::new()?;
+    let source = temp_dir.path().join("source.bin");
+    let dest = temp_dir.path().join("dest.bin");
+    fs::write(&source, vec![7u8; 256 * 1024])?;
+
+    let output = better_cp()
+        .args(["copy", "--json"])
+        .arg(&source)
+        .arg(&dest)
+        .stdin(Stdio::null())
+        .output()?;
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let events: Vec<serde_json::Value> = stdout
+        .lines()
+        .map(|line| serde_json::from_str(line).expect("every line is JSON"))
+        .collect();
+
+    let done = events.last().expect("at least one event");
+    assert_eq!(done["event"], "done");
+    assert_eq!(done["bytes"], 256 * 1024);
+    assert!(events[..events.len() - 1].iter().all(|e| e["event"] == "progress"));
+
+    Ok(())
+}
+
+/// Test: the final --json event lists each source with its outcome
+#[test]
+fn test_json_done_lists_per_file_results() -> std::io::Result<()> {
+    use std::process::Stdio;
+
+    let temp_dir = TempDir::new()?;
+    let fresh = temp_dir.path().join("fresh.txt");
+    let existing = temp_dir.path().join("existing.txt");
+    let dest_dir = temp_dir.path().join("dest");
+    fs::create_dir(&dest_dir)?;
+    fs::write(&fresh, b"new data")?;
+    fs::write(&existing, b"source")?;
+    fs::write(dest_dir.join("existing.txt"), b"keep")?;
+
+    let output = better_cp()
+        .args(["copy", "--json", "--no-clobber"])
+        .arg(&fresh)
+        .arg(&existing)
+        .arg(&dest_dir)
+        .stdin(Stdio::null())
+        .output()?;
+    // The skipped source still sets the exit code
+    assert_eq!(output.status.code(), Some(4));
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let done: serde_json::Value =
+        serde_json::from_str(stdout.lines().last().expect("a done event")).unwrap();
+    assert_eq!(done["event"], "done");
+    assert_eq!(done["files_skipped"], 1);
+    assert!(done["error"].is_null(), "{}", done["error"]);
+
+    let files = done["files"].as_array().expect("files list");
+    assert_eq!(files.len(), 2);
+    assert_eq!(files[0]["status"], "copied");
+    assert_eq!(files[0]["bytes"], 8);
+    assert_eq!(files[0]["destination"], dest_dir.join("fresh.txt").to_str().unwrap());
+    assert_eq!(files[1]["status"], "skipped");
+    assert_eq!(files[1]["bytes"], 0);
+    assert_eq!(fs::read(dest_dir.join("existing.txt"))?, b"keep");
+
+    Ok(())
+}
+
+/// Test: a directory source is listed file by file in the --json done event
+#[test]
+fn test_json_done_lists_each_file_of_a_directory() -> std::io::Result<()> {
+    use std::process::Stdio;
+
+    let temp_dir = TempDir::new()?;
+    let source = temp_dir.path().join("tree");
+    let dest = temp_dir.path().join("dest");
+    create_test_file(&source.join("a.txt"), b"aaaa")?;
+    create_test_file(&source.join("nested/b.txt"), b"bb")?;
+    create_test_file(&source.join("nested/deeper/c.txt"), b"c")?;
+    // The destination exists, so the tree is copied into it
+    create_test_file(&dest.join("tree/nested/b.txt"), b"keep")?;
+
+    let output = better_cp()
+        .args(["copy", "--json", "--no-clobber"])
+        .arg(&source)
+        .arg(&dest)
+        .stdin(Stdio::null())
+        .output()?;
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let done: serde_json::Value =
+        serde_json::from_str(stdout.lines().last().expect("a done event")).unwrap();
+    let files = done["files"].as_array().expect("files list");
+    assert_eq!(files.len(), 3, "{}", stdout);
+    let status_of = |path: &str| {
+        let destination = dest.join("tree").join(path);
+        files
+            .iter()
+            .find(|file| file["destination"] == destination.to_str().unwrap())
+            .map(|file| (file["status"].as_str().unwrap().to_string(), file["bytes"].as_u64().unwrap()))
+            .unwrap_or_else(|| panic!("no entry for {}", path))
+    };
+    assert_eq!(status_of("a.txt"), ("copied".to_string(), 4));
+    assert_eq!(status_of("nested/b.txt"), ("skipped".to_string(), 0));
+    assert_eq!(status_of("nested/deeper/c.txt"), ("copied".to_string(), 1));
+
+    Ok(())
+}
+
+/// Test: --verbose --tree draws the files of a copied directory, nested
+/// by directory and marked created or overwritten
+#[test]
+fn test_verbose_tree_shows_each_copied_file() -> std::io::Result<()> {
+    use std::process::Stdio;
+
+    let temp_dir = TempDir::new()?;
+    let source = temp_dir.path().join("site");
+    let dest = temp_dir.path().join("dest");
+    create_test_file(&source.join("index.html"), b"index")?;
+    create_test_file(&source.join("css/main.css"), b"body {}")?;
+    create_test_file(&source.join("css/vendor/reset.css"), b"* {}")?;
+    create_test_file(&dest.join("site/css/main.css"), b"old")?;
+
+    let output = better_cp()
+        .args(["copy", "--verbose", "--tree", "--no-progress", "--overwrite", "always"])
+        .arg(&source)
+        .arg(&dest)
+        .stdin(Stdio::null())
+        .output()?;
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let tree: Vec<&str> = stdout.lines().skip_while(|line| !line.ends_with("site")).collect();
+    assert_eq!(
+        tree,
+        [
+            dest.join("site").to_str().unwrap(),
+            "├─ css/",
+            "│  ├─ ~ main.css",
+            "│  └─ vendor/",
+            "│     └─ + reset.css",
+            "└─ + index.html",
+        ],
+        "{}",
+        stdout
+    );
+
+    Ok(())
+}
+
+/// Test: --verbose names each copied source next to its own stats, even
+/// after an earlier source was skipped
+#[test]
+fn test_verbose_summary_names_the_copied_source() -> std::io::Result<()> {
+    use std::process::Stdio;
+
+    let temp_dir = TempDir::new()?;
+    let kept = temp_dir.path().join("kept.txt");
+    let fresh = temp_dir.path().join("fresh.txt");
+    let dest_dir = temp_dir.path().join("dest");
+    fs::create_dir(&dest_dir)?;
+    fs::write(&kept, b"source")?;
+    fs::write(&fresh, b"twelve bytes")?;
+    fs::write(dest_dir.join("kept.txt"), b"keep")?;
+
+    let output = better_cp()
+        .args(["copy", "--verbose", "--no-progress", "--no-clobber"])
+        .arg(&kept)
+        .arg(&fresh)
+        .arg(&dest_dir)
+        .stdin(Stdio::null())
+        .output()?;
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let lines: Vec<&str> = stdout.lines().filter(|line| line.contains(" in ") && line.contains("/s)")).collect();
+    assert_eq!(lines.len(), 1, "{}", stdout);
+    assert!(lines[0].contains("fresh.txt") && lines[0].contains("12 B"), "{}", lines[0]);
+
+    Ok(())
+}
+
+/// Test: a failed --json copy prints one JSON document carrying the full
+/// error, and nothing meant for people
+#[test]
+fn test_json_failure_is_one_document() -> std::io::Result<()> {
+    use std::process::Stdio;
+
+    let temp_dir = TempDir::new()?;
+    let missing = temp_dir.path().join("missing.txt");
+    let dest = temp_dir.path().join("dest.txt");
+
+    let output = better_cp()
+        .args(["copy", "--json"])
+        .arg(&missing)
+        .arg(&dest)
+        .stdin(Stdio::null())
+        .output()?;
+    assert_eq!(output.status.code(), Some(3));
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.is_empty(), "stderr: {}", stderr);
+    assert_eq!(stdout.lines().count(), 1, "stdout: {}", stdout);
+    for text in [&stdout, &stderr] {
+        assert!(!text.contains('❌') && !text.contains('⚠'));
+    }
+
+    let done: serde_json::Value = serde_json::from_str(&stdout).expect("stdout is one JSON document");
+    assert_eq!(done["event"], "done");
+    let error = done["error"].as_str().expect("an error field");
+    assert!(error.contains("Source not found") && error.contains("Tip:"));
+
+    Ok(())
+}
+
+/// Test: --preallocate is refused with the copies that can't honour it
+#[test]
+fn test_preallocate_conflicts_with_sparse_and_transcoding() -> std::io::Result<()> {
+    use std::process::Stdio;
+
+    let temp_dir = TempDir::new()?;
+    let source = temp_dir.path().join("source.txt");
+    fs::write(&source, b"content")?;
+
+    for flag in ["--sparse", "--compress=zstd", "--decompress"] {
+        let status = better_cp()
+            .args(["copy", "--quiet", "--no-progress", "--preallocate", flag])
+            .arg(&source)
+            .arg(temp_dir.path().join("dest.txt"))
+            .stdin(Stdio::null())
+            .stderr(Stdio::null())
+            .status()?;
+        assert_eq!(status.code(), Some(2), "{}", flag);
+    }
+    assert!(!temp_dir.path().join("dest.txt").exists());
+
+    Ok(())
+}
+
+/// Test: --pipeline copies a tree of multi-chunk files byte for byte
+#[test]
+fn test_pipeline_copies_directory_intact() -> std::io::Result<()> {
+    use std::process::Stdio;
+
+    let temp_dir = TempDir::new()?;
+    let source = temp_dir.path().join("src");
+    let dest = temp_dir.path().join("dest");
+    let contents: Vec<u8> = (0..300_000u32).map(|i| (i % 251) as u8).collect();
+    create_test_file(&source.join("a.bin"), &contents)?;
+    create_test_file(&source.join("sub/b.bin"), &contents[..100_000])?;
+
+    let status = better_cp()
+        .args(["copy", "--quiet", "--no-progress", "--pipeline", "--buffer=64K"])
+        .arg(&source)
+        .arg(&dest)
+        .stdin(Stdio::null())
+        .status()?;
+    assert!(status.success());
+    assert_eq!(fs::read(dest.join("a.bin"))?, contents);
+    assert_eq!(fs::read(dest.join("sub/b.bin"))?, &contents[..100_000]);
+
+    Ok(())
+}
+
+/// Test: a failed dry run or verify ends --json output with the same done
+/// event as a failed copy, and the same exit code
+#[test]
+fn test_json_failures_share_the_done_event() -> std::io::Result<()> {
+    use std::process::Stdio;
+
+    let temp_dir = TempDir::new()?;
+    let missing = temp_dir.path().join("missing");
+    let dest = temp_dir.path().join("dest");
+
+    for args in [["copy", "--dry-run", "--json"], ["verify", "--json", "--"]] {
+        let output = better_cp()
+            .args(args)
+            .arg(&missing)
+            .arg(&dest)
+            .stdin(Stdio::null())
+            .output()?;
+        assert_eq!(output.status.code(), Some(3), "{:?}", args);
+        assert!(output.stderr.is_empty());
+
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        assert_eq!(stdout.lines().count(), 1, "stdout: {}", stdout);
+        let done: serde_json::Value = serde_json::from_str(&stdout).expect("stdout is one JSON document");
+        assert_eq!(done["event"], "done");
+        assert!(done["error"].as_str().expect("an error field").contains("Source not found"));
+    }
+
+    Ok(())
+}
+
+/// Test: --log appends one line per copied file
+#[test]
+fn test_log_records_each_copied_file() -> std::io::Result<()> {
+    use std::process::Stdio;
+
+    let temp_dir = TempDir::new()?;
+    let first = temp_dir.path().join("first.txt");
+    let second = temp_dir.path().join("second.txt");
+    let dest_dir = temp_dir.path().join("dest");
+    let log = temp_dir.path().join("copy.log");
+    fs::create_dir(&dest_dir)?;
+    fs::write(&first, b"12345")?;
+    fs::write(&second, b"abc")?;
+
+    let status = better_cp()
+        .args(["copy", "--quiet", "--no-progress", "--log"])
+        .arg(&log)
+        .arg(&first)
+        .arg(&second)
+        .arg(&dest_dir)
+        .stdin(Stdio::null())
+        .status()?;
+    assert!(status.success());
+
+    let content = fs::read_to_string(&log)?;
+    let lines: Vec<&str> = content.lines().collect();
+    assert_eq!(lines.len(), 2);
+    assert_eq!(
+        lines[0],
+        format!("COPY {} -> {} (5 bytes) OK", first.display(), dest_dir.join("first.txt").display())
+    );
+    assert!(lines[1].ends_with("(3 bytes) OK"));
+
+    Ok(())
+}
+
+/// Test: --log has a line for every file of a directory copy, not one for
+/// the directory
+#[test]
+fn test_log_records_each_file_of_a_directory() -> std::io::Result<()> {
+    use std::process::Stdio;
+
+    let temp_dir = TempDir::new()?;
+    let source = temp_dir.path().join("tree");
+    let log = temp_dir.path().join("copy.jsonl");
+    create_test_file(&source.join("top.txt"), b"top")?;
+    create_test_file(&source.join("sub/inner.txt"), b"inner")?;
+
+    // Sequential and parallel directory copies both log each file
+    for (dest, parallel) in [("seq", "1"), ("par", "2")] {
+        let status = better_cp()
+            .args(["copy", "--quiet", "--no-progress", "--log-format", "jsonl", "--parallel", parallel, "--log"])
+            .arg(&log)
+            .arg(&source)
+            .arg(temp_dir.path().join(dest))
+            .stdin(Stdio::null())
+            .status()?;
+        assert!(status.success());
+    }
+
+    let content = fs::read_to_string(&log)?;
+    let mut logged: Vec<(String, u64)> = content
+        .lines()
+        .map(|line| {
+            let entry: serde_json::Value = serde_json::from_str(line).unwrap();
+            assert_eq!(entry["result"], "ok");
+            (entry["destination"].as_str().unwrap().to_string(), entry["bytes"].as_u64().unwrap())
+        })
+        .collect();
+    logged.sort();
+    let expected = |dest: &str, path: &str| temp_dir.path().join(dest).join(path).to_str().unwrap().to_string();
+    assert_eq!(
+        logged,
+        vec![
+            (expected("par", "sub/inner.txt"), 5),
+            (expected("par", "top.txt"), 3),
+            (expected("seq", "sub/inner.txt"), 5),
+            (expected("seq", "top.txt"), 3),
+        ]
+    );
+
+    Ok(())
+}
+
+/// Test: --dry-run --verbose lists every planned file with its action
+#[test]
+fn test_dry_run_verbose_lists_each_file() -> std::io::Result<()> {
+    use std::process::Stdio;
+
+    let temp_dir = TempDir::new()?;
+    let source = temp_dir.path().join("source");
+    let dest = temp_dir.path().join("dest");
+    create_test_file(&source.join("nested/deep/file.txt"), b"nested")?;
+    create_test_file(&source.join("debug.log"), b"log")?;
+
+    let output = better_cp()
+        .args(["--color=never", "copy", "--dry-run", "--verbose", "--exclude", "*.log"])
+        .arg(&source)
+        .arg(&dest)
+        .stdin(Stdio::null())
+        .output()?;
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let expected = format!(
+        "create    {} -> {}",
+        source.join("nested/deep/file.txt").display(),
+        dest.join("nested/deep/file.txt").display()
+    );
+    assert!(stdout.lines().any(|line| line.trim() == expected), "{}", stdout);
+    assert!(stdout.lines().any(|line| line.trim().starts_with("excluded") && line.contains("debug.log")));
+    assert!(!dest.exists());
+
+    Ok(())
+}
+
+/// Test: --dry-run --json prints the plan as one JSON document and writes nothing
+#[test]
+fn test_dry_run_json_lists_planned_actions() -> std::io::Result<()> {
+    use better_cp::json_output::OperationResult;
+    use better_cp::plan::PlannedAction;
+    use std::process::Stdio;
+
+    let temp_dir = TempDir::new()?;
+    let source = temp_dir.path().join("source");
+    let dest = temp_dir.path().join("dest");
+    create_test_file(&source.join("new.txt"), b"new file")?;
+    create_test_file(&source.join("sub/old.txt"), b"replacement")?;
+    create_test_file(&source.join("debug.log"), b"log")?;
+    // dest exists, so the tree is copied into dest/source
+    create_test_file(&dest.join("source/sub/old.txt"), b"old")?;
+
+    let output = better_cp()
+        .args(["copy", "--dry-run", "--json", "--overwrite=always", "--exclude", "*.log"])
+        .arg(&source)
+        .arg(&dest)
+        .stdin(Stdio::null())
+        .output()?;
+    assert!(output.status.success());
+
+    let result: OperationResult = serde_json::from_slice(&output.stdout).expect("stdout is one JSON document");
+    assert!(result.planned);
+    assert_eq!(result.plan.len(), 3);
+    let action = |name: &str| result.plan.iter().find(|f| f.source == source.join(name)).unwrap().action;
+    assert_eq!(action("new.txt"), PlannedAction::Create);
+    assert_eq!(action("sub/old.txt"), PlannedAction::Overwrite);
+    assert_eq!(action("debug.log"), PlannedAction::Excluded);
+    assert_eq!(result.summary.files_copied, 2);
+    assert_eq!(result.summary.bytes_transferred, 19);
+    assert!(!dest.join("source/new.txt").exists());
+    assert_eq!(fs::read(dest.join("source/sub/old.txt"))?, b"old");
+
+    Ok(())
+}
+
+/// Test: --write-manifest lists every copied file with a digest that matches it
+#[test]
+fn test_write_manifest_matches_copied_files() -> std::io::Result<()> {
+    use better_cp::verify::compute_checksum;
+    use std::process::Stdio;
+
+    let temp_dir = TempDir::new()?;
+    let source = temp_dir.path().join("source");
+    let dest = temp_dir.path().join("dest");
+    let manifest = temp_dir.path().join("SHA256SUMS");
+    create_test_structure(&source)?;
+
+    let status = better_cp()
+        .args(["copy", "--quiet", "--no-progress", "--write-manifest"])
+        .arg(&manifest)
+        .arg(&source)
+        .arg(&dest)
+        .stdin(Stdio::null())
+        .status()?;
+    assert!(status.success());
+
+    let content = fs::read_to_string(&manifest)?;
+    let mut paths = Vec::new();
+    for line in content.lines() {
+        let (digest, path) = line.split_once("  ").expect("sha256sum line format");
+        assert_eq!(digest, compute_checksum(dest.join(path), None)?);
+        paths.push(path);
+    }
+    assert_eq!(paths, ["file1.txt", "subdir/file2.txt", "subdir/nested/file3.txt"]);
+
+    Ok(())
+}
+
+/// Test: --exclude-from reads patterns from a file and skips matching files
+#[test]
+fn test_exclude_from_file_omits_matches() -> std::io::Result<()> {
+    use std::process::Stdio;
+
+    let temp_dir = TempDir::new()?;
+    let source = temp_dir.path().join("source");
+    let dest = temp_dir.path().join("dest");
+    create_test_structure(&source)?;
+    create_test_file(&source.join("debug.log"), b"noise")?;
+    create_test_file(&source.join("subdir/nested/trace.log"), b"noise")?;
+
+    let patterns = temp_dir.path().join("ignore.txt");
+    fs::write(&patterns, "# build noise\n\n*.log\n")?;
+
+    let status = better_cp()
+        .args(["copy", "--no-progress", "--exclude-from"])
+        .arg(&patterns)
+        .arg(&source)
+        .arg(&dest)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .status()?;
+    assert!(status.success());
+
+    assert!(dest.join("file1.txt").exists());
+    assert!(dest.join("subdir/nested/file3.txt").exists());
+    assert!(!dest.join("debug.log").exists());
+    assert!(!dest.join("subdir/nested/trace.log").exists());
+
+    Ok(())
+}
+
+/// Test: --include re-admits files that a broader --exclude would drop
+#[test]
+fn test_include_overrides_exclude() -> std::io::Result<()> {
+    use std::process::Stdio;
+
+    let temp_dir = TempDir::new()?;
+    let source = temp_dir.path().join("source");
+    let dest = temp_dir.path().join("dest");
+    create_test_file(&source.join("a.txt"), b"keep")?;
+    create_test_file(&source.join("b.txt"), b"keep")?;
+    create_test_file(&source.join("build.log"), b"noise")?;
+    create_test_file(&source.join("main.rs"), b"noise")?;
+
+    let status = better_cp()
+        .args(["copy", "--no-progress", "--exclude", "*", "--include", "*.txt"])
+        .arg(&source)
+        .arg(&dest)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .status()?;
+    assert!(status.success());
+
+    let mut copied: Vec<_> = fs::read_dir(&dest)?
+        .map(|entry| entry.map(|e| e.file_name().to_string_lossy().into_owned()))
+        .collect::<std::io::Result<_>>()?;
+    copied.sort();
+    assert_eq!(copied, vec!["a.txt", "b.txt"]);
+
+    Ok(())
+}
+
+/// Test: `better-cp clean` removes leftovers of interrupted copies only
+#[test]
+fn test_clean_removes_stale_leftovers() -> std::io::Result<()> {
+    use std::process::Stdio;
+
+    let temp_dir = TempDir::new()?;
+    let root = temp_dir.path();
+    create_test_file(&root.join("keep.txt"), b"data")?;
+    create_test_file(&root.join("big.iso"), b"old")?;
+    create_test_file(&root.join("big.iso.tmp"), b"partial")?;
+    create_test_file(&root.join("nested/big.iso.better-cp.state"), b"{}")?;
+    // Someone else's temp file: no target or resume state beside it
+    create_test_file(&root.join("editor.swp.tmp"), b"mine")?;
+
+    let status = better_cp()
+        .args(["clean", "--dry-run"])
+        .arg(root)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .status()?;
+    assert!(status.success());
+    assert!(root.join("big.iso.tmp").exists());
+
+    let status = better_cp()
+        .arg("clean")
+        .arg(root)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .status()?;
+    assert!(status.success());
+
+    assert!(root.join("keep.txt").exists());
+    assert!(root.join("editor.swp.tmp").exists());
+    assert!(!root.join("big.iso.tmp").exists());
+    assert!(!root.join("nested/big.iso.better-cp.state").exists());
+
+    Ok(())
+}
+
+/// Test: --transactional undoes earlier copies when a later one fails
+#[test]
+fn test_transactional_rolls_back_on_failure() -> std::io::Result<()> {
+    use std::process::Stdio;
+
+    let temp_dir = TempDir::new()?;
+    let first = temp_dir.path().join("first.txt");
+    let missing = temp_dir.path().join("missing.txt");
+    let third = temp_dir.path().join("third.txt");
+    let dest = temp_dir.path().join("dest");
+    create_test_file(&first, b"one")?;
+    create_test_file(&third, b"three")?;
+    fs::create_dir(&dest)?;
+
+    let status = better_cp()
+        .args(["copy", "--no-progress", "--transactional"])
+        .arg(&first)
+        .arg(&missing)
+        .arg(&third)
+        .arg(&dest)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()?;
+    assert!(!status.success());
+
+    assert!(!dest.join("first.txt").exists());
+    assert!(!dest.join("third.txt").exists());
+    assert_eq!(fs::read_dir(&dest)?.count(), 0);
+
+    Ok(())
+}
+
+/// Test: --max-depth copies the boundary level but nothing below it
+#[test]
+fn test_max_depth_limits_deep_structure() -> std::io::Result<()> {
+    use std::process::Stdio;
+
+    let temp_dir = TempDir::new()?;
+    let base = temp_dir.path().join("source");
+    let dest = temp_dir.path().join("dest");
+
+    // Same shape as the deeply nested test: a/b/c/d/e/f/file.txt
+    let deep_path = base.join("a/b/c/d/e/f");
+    fs::create_dir_all(&deep_path)?;
+    fs::write(deep_path.join("file.txt"), b"deeply nested")?;
+    fs::write(base.join("a/b/b.txt"), b"boundary")?;
+    fs::write(base.join("a/b/c/c.txt"), b"too deep")?;
+
+    let status = better_cp()
+        .args(["copy", "--no-progress", "--max-depth", "2"])
+        .arg(&base)
+        .arg(&dest)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .status()?;
+    assert!(status.success());
+
+    assert!(dest.join("a/b").is_dir());
+    assert_eq!(fs::read(dest.join("a/b/b.txt"))?, b"boundary");
+    assert!(!dest.join("a/b/c/c.txt").exists());
+    assert!(!dest.join("a/b/c/d/e/f/file.txt").exists());
+
+    Ok(())
+}
+
+/// Test: --min-size skips small files and counts them as skipped
+#[test]
+fn test_min_size_skips_small_files() -> std::io::Result<()> {
+    use std::process::Stdio;
+
+    let temp_dir = TempDir::new()?;
+    let source = temp_dir.path().join("source");
+    let dest = temp_dir.path().join("dest");
+    create_test_file(&source.join("tiny.txt"), &[b'x'; 50])?;
+    create_test_file(&source.join("big.bin"), &[b'y'; 2048])?;
+
+    let output = better_cp()
+        .args(["copy", "--no-progress", "--min-size", "1K"])
+        .arg(&source)
+        .arg(&dest)
+        .stdin(Stdio::null())
+        .output()?;
+    assert!(output.status.success());
+
+    assert!(dest.join("big.bin").exists());
+    assert!(!dest.join("tiny.txt").exists());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("1 skipped"));
+
+    Ok(())
+}
+
+/// Test: --newer-than drops files older than the reference
+#[test]
+fn test_newer_than_drops_older_files() -> std::io::Result<()> {
+    use std::process::Stdio;
+    use std::time::{Duration, SystemTime};
+
+    let temp_dir = TempDir::new()?;
+    let source = temp_dir.path().join("source");
+    let dest = temp_dir.path().join("dest");
+    let reference = temp_dir.path().join("last-backup");
+    create_test_file(&source.join("old.txt"), b"old")?;
+    create_test_file(&source.join("new.txt"), b"new")?;
+    create_test_file(&reference, b"")?;
+
+    let now = SystemTime::now();
+    let stamp = |path: &std::path::Path, time| -> std::io::Result<()> {
+        fs::File::options().write(true).open(path)?.set_modified(time)
+    };
+    stamp(&source.join("old.txt"), now - Duration::from_secs(7200))?;
+    stamp(&reference, now - Duration::from_secs(3600))?;
+
+    let status = better_cp()
+        .args(["copy", "--no-progress", "--newer-than"])
+        .arg(&reference)
+        .arg(&source)
+        .arg(&dest)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .status()?;
+    assert!(status.success());
+
+    assert!(dest.join("new.txt").exists());
+    assert!(!dest.join("old.txt").exists());
+
+    Ok(())
+}
+
+/// Test: `better-cp verify` reports exactly the destination file that was corrupted
+#[test]
+fn test_verify_reports_corrupted_file() -> std::io::Result<()> {
+    use std::process::Stdio;
+
+    let temp_dir = TempDir::new()?;
+    let source = temp_dir.path().join("source");
+    let dest = temp_dir.path().join("dest");
+    create_test_structure(&source)?;
+
+    let status = better_cp()
+        .args(["copy", "--quiet", "--no-progress"])
+        .arg(&source)
+        .arg(&dest)
+        .stdin(Stdio::null())
+        .status()?;
+    assert!(status.success());
+
+    let verify = |dest: &std::path::Path| {
+        better_cp()
+            .args(["verify", "--json"])
+            .arg(&source)
+            .arg(dest)
+            .stdin(Stdio::null())
+            .output()
+    };
+
+    let clean = verify(&dest)?;
+    assert!(clean.status.success());
+
+    fs::write(dest.join("subdir/nested/file3.txt"), [b'x'; 300])?;
+    let output = verify(&dest)?;
+    assert_eq!(output.status.code(), Some(5));
+
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["files_checked"], 3);
+    let discrepancies = report["discrepancies"].as_array().unwrap();
+    assert_eq!(discrepancies.len(), 1);
+    assert_eq!(discrepancies[0]["path"], "subdir/nested/file3.txt");
+    assert_eq!(discrepancies[0]["kind"], "mismatch");
+
+    Ok(())
+}
+
+/// Test: --backup keeps the overwritten target as `file~` for both copy and move
+#[test]
+fn test_backup_keeps_overwritten_target() -> std::io::Result<()> {
+    use std::process::Stdio;
+
+    let temp_dir = TempDir::new()?;
+    let source = temp_dir.path().join("new.txt");
+    let target = temp_dir.path().join("file.txt");
+    let backup = temp_dir.path().join("file.txt~");
+    fs::write(&source, b"new contents")?;
+    fs::write(&target, b"old contents")?;
+
+    let status = better_cp()
+        .args(["copy", "--quiet", "--no-progress", "--overwrite=always", "--backup"])
+        .arg(&source)
+        .arg(&target)
+        .stdin(Stdio::null())
+        .status()?;
+    assert!(status.success());
+    assert_eq!(fs::read(&target)?, b"new contents");
+    assert_eq!(fs::read(&backup)?, b"old contents");
+
+    fs::write(&source, b"moved contents")?;
+    let status = better_mv()
+        .args(["move", "--quiet", "--no-progress", "--overwrite=always", "--backup=numbered"])
+        .arg(&source)
+        .arg(&target)
+        .stdin(Stdio::null())
+        .status()?;
+    assert!(status.success());
+    assert!(!source.exists());
+    assert_eq!(fs::read(&target)?, b"moved contents");
+    assert_eq!(fs::read(temp_dir.path().join("file.txt.~1~"))?, b"new contents");
+
+    Ok(())
+}
+
+/// Test: --reflink=always on a filesystem that can't clone explains why
+#[test]
+fn test_reflink_always_explains_unsupported_filesystem() -> std::io::Result<()> {
+    use std::process::Stdio;
+
+    let temp_dir = TempDir::new()?;
+    // No filesystem clones across filesystems, so a source on another one
+    // (tmpfs, usually) takes the unsupported path even where the temp dir clones
+    let source_dir = match TempDir::new_in("/dev/shm") {
+        Ok(dir) if !same_device(dir.path(), temp_dir.path()) => dir,
+        _ => TempDir::new()?,
+    };
+    let source = source_dir.path().join("source.txt");
+    let target = temp_dir.path().join("target.txt");
+    fs::write(&source, b"clone me")?;
+
+    let output = better_cp()
+        .args(["copy", "--quiet", "--no-progress", "--reflink=always"])
+        .arg(&source)
+        .arg(&target)
+        .stdin(Stdio::null())
+        .output()?;
+    if output.status.success() {
+        // The temp dir is on a filesystem that clones (Btrfs, XFS, APFS...)
+        assert_eq!(fs::read(&target)?, b"clone me");
+        return Ok(());
+    }
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Reflink (copy-on-write clone) not supported"), "{}", stderr);
+    assert!(stderr.contains("--reflink=auto"), "{}", stderr);
+    assert!(!target.exists());
+
+    Ok(())
+}
+
+/// Test: --overwrite=prompt without a terminal fails instead of waiting on stdin
+#[test]
+fn test_prompt_without_terminal_is_an_error() -> std::io::Result<()> {
+    use std::process::Stdio;
+
+    let temp_dir = TempDir::new()?;
+    let source = temp_dir.path().join("source.txt");
+    let target = temp_dir.path().join("target.txt");
+    fs::write(&source, b"new contents")?;
+    fs::write(&target, b"old contents")?;
+
+    let output = better_cp()
+        .args(["copy", "--no-progress", "--overwrite=prompt"])
+        .arg(&source)
+        .arg(&target)
+        .stdin(Stdio::null())
+        .output()?;
+    assert_eq!(output.status.code(), Some(2));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("prompt mode requires a terminal"), "{}", stderr);
+    assert_eq!(fs::read(&target)?, b"old contents");
+
+    Ok(())
+}
+
+/// Test: a symlink named as the source is followed (-H, the default) with the
+/// links inside it kept, unless --no-dereference-args copies the link itself
+#[cfg(unix)]
+#[test]
+fn test_dereference_args_follows_only_the_source_link() -> std::io::Result<()> {
+    use std::os::unix::fs::symlink;
+    use std::process::Stdio;
+
+    let temp_dir = TempDir::new()?;
+    let real = temp_dir.path().join("real");
+    fs::create_dir(&real)?;
+    fs::write(real.join("data.txt"), b"data")?;
+    symlink("data.txt", real.join("inner"))?;
+    let link = temp_dir.path().join("link");
+    symlink(&real, &link)?;
+
+    let copy = |extra: &[&str], dest: &PathBuf| {
+        better_cp()
+            .args(["copy", "--quiet", "--no-progress"])
+            .args(extra)
+            .arg(&link)
+            .arg(dest)
+            .stdin(Stdio::null())
+            .status()
+    };
+
+    // With or without -H, the directory behind the source link is copied
+    for (extra, name) in [(&[][..], "plain"), (&["-H"][..], "followed")] {
+        let followed = temp_dir.path().join(name);
+        assert!(copy(extra, &followed)?.success());
+        assert!(fs::symlink_metadata(&followed)?.is_dir());
+        assert_eq!(fs::read(followed.join("data.txt"))?, b"data");
+        assert_eq!(fs::read_link(followed.join("inner"))?, PathBuf::from("data.txt"));
+    }
+
+    // Like cp -r, --no-dereference-args copies the source link itself
+    let linked = temp_dir.path().join("linked");
+    assert!(copy(&["--no-dereference-args"], &linked)?.success());
+    assert_eq!(fs::read_link(&linked)?, real);
+
+    Ok(())
+}
+
+/// Test: --rename names each copy from a template and refuses collisions
+#[test]
+fn test_rename_template_names_targets() -> std::io::Result<()> {
+    use std::process::Stdio;
+
+    let temp_dir = TempDir::new()?;
+    fs::create_dir_all(temp_dir.path().join("a"))?;
+    fs::create_dir_all(temp_dir.path().join("b"))?;
+    let first = temp_dir.path().join("a/Notes.TXT");
+    let second = temp_dir.path().join("b/Notes.TXT");
+    fs::write(&first, b"first")?;
+    fs::write(&second, b"second")?;
+    let dest = temp_dir.path().join("dest/");
+
+    let status = better_cp()
+        .args(["copy", "--quiet", "--no-progress", "--rename", "{index}_{name:lower}.{ext:lower}"])
+        .arg(&first)
+        .arg(&second)
+        .arg(&dest)
+        .stdin(Stdio::null())
+        .status()?;
+    assert!(status.success());
+    assert_eq!(fs::read(dest.join("1_notes.txt"))?, b"first");
+    assert_eq!(fs::read(dest.join("2_notes.txt"))?, b"second");
+
+    // Without {index} both would land on backup_Notes.TXT: nothing is copied
+    let other = temp_dir.path().join("other/");
+    let output = better_cp()
+        .args(["copy", "--quiet", "--no-progress", "--rename", "backup_{name}.{ext}"])
+        .arg(&first)
+        .arg(&second)
+        .arg(&other)
+        .stdin(Stdio::null())
+        .output()?;
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("backup_Notes.TXT"));
+    assert!(!other.exists());
+
+    Ok(())
+}
+
+/// Test: --quiet writes nothing to stdout, and never stops to ask
+#[test]
+fn test_quiet_copy_prints_nothing() -> std::io::Result<()> {
+    use std::process::Stdio;
+
+    let temp_dir = TempDir::new()?;
+    let source = temp_dir.path().join("source.txt");
+    let dest = temp_dir.path().join("dest.txt");
+    fs::write(&source, b"cron job")?;
+
+    // Quiet wins over --verbose and --stats
+    let output = better_cp()
+        .args(["copy", "--quiet", "--verbose", "--stats"])
+        .arg(&source)
+        .arg(&dest)
+        .stdin(Stdio::null())
+        .output()?;
+    assert!(output.status.success());
+    assert!(output.stdout.is_empty(), "stdout: {}", String::from_utf8_lossy(&output.stdout));
+    assert!(output.stderr.is_empty());
+    assert_eq!(fs::read(&dest)?, b"cron job");
+
+    // An existing target isn't prompted about: the copy fails on stderr
+    let output = better_cp()
+        .args(["copy", "--quiet"])
+        .arg(&source)
+        .arg(&dest)
+        .stdin(Stdio::null())
+        .output()?;
+    assert!(!output.status.success());
+    assert!(output.stdout.is_empty(), "stdout: {}", String::from_utf8_lossy(&output.stdout));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("already exists"));
+
+    Ok(())
+}